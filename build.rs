@@ -3,67 +3,63 @@ use std::fs;
 use std::io::Read;
 use std::path::PathBuf;
 use std::process::Command;
-use std::time::Duration;
 
-// Add serde imports for JSON handling
-use serde::Deserialize;
 use std::path::Path;
 
-// JSON response structures for Edge Impulse API
-#[derive(Debug, Deserialize)]
-struct ProjectResponse {
-    success: bool,
-    #[allow(dead_code)]
-    project: Project,
-    #[serde(rename = "defaultImpulseId")]
-    default_impulse_id: Option<i32>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Project {
-    #[allow(dead_code)]
-    id: i32,
-    #[allow(dead_code)]
-    name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct BuildJobResponse {
-    success: bool,
-    id: i32,
-}
-
-#[derive(Debug, Deserialize)]
-struct JobStatusResponse {
-    success: bool,
-    job: JobStatus,
-}
+// Proxy selection shared with ei-fetch
+#[path = "ei-fetch/src/proxy.rs"]
+mod proxy;
 
-#[derive(Debug, Deserialize)]
-struct JobStatus {
-    #[allow(dead_code)]
-    id: i32,
-    category: String,
-    finished: Option<String>, // Can be a timestamp string when finished
-    #[serde(rename = "finishedSuccessful")]
-    finished_successful: Option<bool>,
-}
+/// Glue files in ffi_glue/, copied to the model folder and built with the SDK
+const FFI_GLUE_FILES: [&str; 4] = [
+    "edge_impulse_c_api.cpp",
+    "edge_impulse_wrapper.h",
+    "CMakeLists.txt",
+    "tflite_detection_postprocess_wrapper.cc",
+];
 
 /// Copy FFI glue files from ffi_glue/ to the selected model folder (e.g., cpp/ or cpp2/)
+///
+/// Files in `EI_FFI_GLUE_DIR` are copied afterwards, so they replace the default file with the
+/// same name or are added next to the defaults.
 fn copy_ffi_glue(model_dir: &str) {
-    let files = [
-        "edge_impulse_c_api.cpp",
-        "edge_impulse_wrapper.h",
-        "CMakeLists.txt",
-        "tflite_detection_postprocess_wrapper.cc",
-    ];
-    for file in &files {
+    for file in &FFI_GLUE_FILES {
         let src = format!("ffi_glue/{}", file);
         let dst = format!("{}/{}", model_dir, file);
         if std::path::Path::new(&src).exists() {
             fs::copy(&src, &dst).unwrap_or_else(|_| panic!("Failed to copy {} to {}", src, dst));
         }
     }
+    for src in custom_ffi_glue_files() {
+        let dst = Path::new(model_dir).join(src.file_name().unwrap());
+        fs::copy(&src, &dst)
+            .unwrap_or_else(|_| panic!("Failed to copy {} to {}", src.display(), dst.display()));
+        println!("cargo:info=Using custom FFI glue file {}", src.display());
+    }
+}
+
+/// Files of the glue directory set with `EI_FFI_GLUE_DIR`, sorted by name
+fn custom_ffi_glue_files() -> Vec<PathBuf> {
+    let Ok(dir) = env::var("EI_FFI_GLUE_DIR") else {
+        return Vec::new();
+    };
+    let entries = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Failed to read EI_FFI_GLUE_DIR {}: {}", dir, e));
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Names of the custom glue files with one of `extensions`
+fn custom_ffi_glue_names(extensions: &[&str]) -> Vec<String> {
+    custom_ffi_glue_files()
+        .iter()
+        .filter(|path| has_extension(path, extensions))
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
 }
 
 /// Copy model files from a custom directory specified by EI_MODEL environment variable
@@ -74,15 +70,20 @@ fn copy_model_from_custom_path() -> bool {
             model_path
         );
 
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        let model_dest = Path::new(&manifest_dir).join("model");
+
+        // A deployment ZIP (local file or URL) is extracted just like the API download
+        if is_zip_model_source(&model_path) {
+            return copy_model_from_zip(&model_path, &model_dest);
+        }
+
         let model_source = Path::new(&model_path);
         if !model_source.exists() {
             println!("cargo:error=EI_MODEL path does not exist: {}", model_path);
             return false;
         }
 
-        let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
-        let model_dest = Path::new(&manifest_dir).join("model");
-
         // Create model directory if it doesn't exist
         if !model_dest.exists() {
             std::fs::create_dir_all(&model_dest)
@@ -166,273 +167,173 @@ fn copy_model_from_custom_path() -> bool {
     false
 }
 
-/// Recursively copy a directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
-    if !dst.exists() {
-        std::fs::create_dir_all(dst)?;
-    }
-
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-
-        if ty.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
-        } else {
-            std::fs::copy(&src_path, &dst_path)?;
-        }
-    }
-    Ok(())
+/// Check whether EI_MODEL points at a deployment ZIP rather than an extracted directory
+fn is_zip_model_source(model_path: &str) -> bool {
+    let lower = model_path.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || is_local_zip(model_path)
 }
 
-/// Read Edge Impulse project configuration from environment variables
-fn read_edge_impulse_config() -> Option<(String, String)> {
-    // Check environment variables
-    let env_project_id = std::env::var("EI_PROJECT_ID").ok();
-    let env_api_key = std::env::var("EI_API_KEY").ok();
-    if let (Some(pid), Some(key)) = (env_project_id, env_api_key) {
-        return Some((pid, key));
+/// A local ZIP file, by extension or, for downloads saved without one, by its signature
+fn is_local_zip(model_path: &str) -> bool {
+    if model_path.to_ascii_lowercase().ends_with(".zip") {
+        return true;
     }
-
-    // No configuration found
-    None
+    let mut signature = [0u8; 4];
+    fs::File::open(model_path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok()
+        && signature == *b"PK\x03\x04"
 }
 
-/// Download Edge Impulse model from the REST API using curl
-///
-/// This function:
-/// 1. Gets project information to find the default impulse ID
-/// 2. Triggers a build job for the model
-/// 3. Polls the job status until completion
-/// 4. Downloads and extracts the model files
-/// 5. Returns true if successful, false otherwise
-fn download_model_from_edge_impulse(project_id: &str, api_key: &str) -> bool {
-    println!("cargo:info=Starting model download process...");
-    println!("cargo:info=Project ID: {}", project_id);
-    println!(
-        "cargo:info=API Key: {}...",
-        &api_key[..api_key.len().min(8)]
-    );
-
-    // Get the Edge Impulse Studio host from environment or use default
-    let studio_host = env::var("EDGE_IMPULSE_STUDIO_HOST")
-        .unwrap_or_else(|_| "https://studio.edgeimpulse.com".to_string());
-
-    let base_url = format!("{}/v1/api", studio_host);
+/// Stamp file in the model directory with the hash of the local EI_MODEL ZIP it was extracted from
+const MODEL_ZIP_STAMP: &str = ".ei-model-zip";
 
-    // Step 1: Get project information to find defaultImpulseId
-    println!("cargo:info=Step 1/5: Getting project information...");
-    let project_url = format!("{}/{}", base_url, project_id);
+/// Whether the model directory was extracted from a local EI_MODEL ZIP that changed since
+///
+/// Models placed in `model/` by other means (no stamp) keep priority over EI_MODEL.
+fn model_zip_changed(model_path: &str, model_dir: &Path) -> bool {
+    let Ok(stamp) = fs::read_to_string(model_dir.join(MODEL_ZIP_STAMP)) else {
+        return false;
+    };
+    fs::read(model_path).is_ok_and(|data| stamp.trim() != format!("{:016x}", fnv1a(&data)))
+}
 
-    let project_response: ProjectResponse =
-        match ureq::get(&project_url).set("x-api-key", api_key).call() {
-            Ok(response) => {
-                if response.status() != 200 {
-                    println!(
-                        "cargo:error=Failed to get project info: HTTP {}",
-                        response.status()
-                    );
+/// Extract a deployment ZIP given by EI_MODEL (a local path or an http(s) URL) into the model directory
+fn copy_model_from_zip(model_path: &str, model_dest: &Path) -> bool {
+    let lower = model_path.to_ascii_lowercase();
+    let is_url = lower.starts_with("http://") || lower.starts_with("https://");
+    let zip_data = if is_url {
+        println!("cargo:info=Downloading model ZIP from {}", model_path);
+        let mut agent = ureq::AgentBuilder::new();
+        if let Some(proxy) = download_proxy(model_path) {
+            match ureq::Proxy::new(&proxy) {
+                Ok(proxy) => agent = agent.proxy(proxy),
+                Err(e) => {
+                    println!("cargo:error=Invalid proxy {}: {}", proxy, e);
                     return false;
                 }
-                match response.into_json() {
-                    Ok(data) => data,
-                    Err(e) => {
-                        println!("cargo:error=Failed to parse project response: {}", e);
-                        return false;
-                    }
-                }
             }
+        }
+        let response = match agent.build().get(model_path).call() {
+            Ok(response) => response,
             Err(e) => {
-                println!("cargo:error=Failed to get project info: {}", e);
+                println!("cargo:error=Failed to download model ZIP: {}", e);
                 return false;
             }
         };
-
-    if !project_response.success {
-        println!("cargo:error=Project API call was not successful");
-        return false;
-    }
-
-    let default_impulse_id = match project_response.default_impulse_id {
-        Some(id) => id,
-        None => {
-            println!("cargo:error=No default impulse ID found in project");
+        let mut data = Vec::new();
+        if let Err(e) = response.into_reader().read_to_end(&mut data) {
+            println!("cargo:error=Failed to read model ZIP download: {}", e);
             return false;
         }
-    };
-
-    println!(
-        "cargo:info=Found default impulse ID: {}",
-        default_impulse_id
-    );
-
-    // Step 2: Trigger build job
-    println!("cargo:info=Step 2/5: Triggering model build job...");
-    let build_url = format!(
-        "{}/{}/jobs/build-ondevice-model?type=zip&impulse={}",
-        base_url, project_id, default_impulse_id
-    );
-
-    // Determine engine type from environment variable, default to tflite-eon
-    let engine = env::var("EI_ENGINE").unwrap_or_else(|_| "tflite-eon".to_string());
-    println!("cargo:info=Using engine: {}", engine);
-
-    let build_response: BuildJobResponse = match ureq::post(&build_url)
-        .set("x-api-key", api_key)
-        .set("content-type", "application/json")
-        .send_json(serde_json::json!({"engine": engine}))
-    {
-        Ok(response) => {
-            if response.status() != 200 {
-                println!(
-                    "cargo:error=Failed to trigger build: HTTP {}",
-                    response.status()
-                );
+        data
+    } else {
+        match fs::read(model_path) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("cargo:error=Failed to read model ZIP {}: {}", model_path, e);
                 return false;
             }
-            match response.into_json() {
-                Ok(data) => data,
-                Err(e) => {
-                    println!("cargo:error=Failed to parse build response: {}", e);
-                    return false;
-                }
-            }
-        }
-        Err(e) => {
-            println!("cargo:error=Failed to trigger build: {}", e);
-            return false;
         }
     };
 
-    if !build_response.success {
-        println!("cargo:error=Build job creation was not successful");
-        return false;
-    }
-
-    let job_id = build_response.id;
-    println!("cargo:info=Build job created with ID: {}", job_id);
-
-    // Step 3: Poll job status until completion
-    println!("cargo:info=Step 3/5: Waiting for model build to complete...");
-    println!("cargo:info=This step typically takes 2-5 minutes. Polling every 5 seconds...");
-    let status_url = format!("{}/{}/jobs/{}/status", base_url, project_id, job_id);
-
-    let mut attempts = 0;
-    const MAX_ATTEMPTS: u32 = 120; // 10 minutes with 5-second intervals
-
-    loop {
-        attempts += 1;
-        if attempts > MAX_ATTEMPTS {
-            println!(
-                "cargo:error=Build timed out after {} attempts ({} minutes)",
-                MAX_ATTEMPTS,
-                MAX_ATTEMPTS * 5 / 60
+    let sha256 = sha256_hex(&zip_data);
+    println!("cargo:info=Model ZIP SHA-256: {}", sha256);
+    if let Some(pinned) = pinned_model_sha256() {
+        if sha256 != pinned {
+            panic!(
+                "Model ZIP {} has SHA-256 {}, but EI_MODEL_SHA256 is {}; refusing to build from a \
+                 corrupted or different archive",
+                model_path, sha256, pinned
             );
-            println!("cargo:error=The model build is taking longer than expected. You can try again or check your Edge Impulse project.");
-            return false;
         }
+    }
 
-        // Wait 5 seconds between polls
-        std::thread::sleep(Duration::from_secs(5));
-
-        let status_response: JobStatusResponse =
-            match ureq::get(&status_url).set("x-api-key", api_key).call() {
-                Ok(response) => {
-                    if response.status() != 200 {
-                        println!(
-                            "cargo:error=Failed to get job status: HTTP {}",
-                            response.status()
-                        );
-                        return false;
-                    }
-                    match response.into_json() {
-                        Ok(data) => data,
-                        Err(e) => {
-                            println!("cargo:error=Failed to parse job status: {}", e);
-                            return false;
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("cargo:error=Failed to get job status: {}", e);
-                    return false;
-                }
-            };
-
-        if !status_response.success {
-            println!("cargo:error=Job status API call was not successful");
+    if !model_dest.exists() {
+        if let Err(e) = fs::create_dir_all(model_dest) {
+            println!("cargo:error=Failed to create model directory: {}", e);
             return false;
         }
-
-        let job = status_response.job;
-        println!(
-            "cargo:info=Build status: {} (polling attempt {}/{})",
-            job.category, attempts, MAX_ATTEMPTS
-        );
-
-        // Check if job is finished
-        if let Some(successful) = job.finished_successful {
-            if job.finished.is_some() {
-                if successful {
-                    println!("cargo:info=Build completed successfully!");
-                    break;
-                } else {
-                    println!("cargo:error=Build failed on Edge Impulse servers");
-                    return false;
-                }
-            }
-        }
     }
 
-    // Step 4: Download the model
-    println!("cargo:info=Step 4/5: Downloading built model...");
-    let download_url = format!(
-        "{}/{}/deployment/download?type=zip&impulse={}",
-        base_url, project_id, default_impulse_id
+    println!(
+        "cargo:info=Extracting model ZIP {} to {}",
+        model_path,
+        model_dest.display()
     );
-
-    // Create model directory if it doesn't exist
-    let model_dir = PathBuf::from("model");
-    if !model_dir.exists() {
-        if let Err(e) = fs::create_dir(&model_dir) {
-            println!("cargo:error=Failed to create model directory: {}", e);
-            return false;
+    // Lets a later build notice that a local archive was replaced
+    let stamp = (!is_url).then(|| format!("{:016x}\n", fnv1a(&zip_data)));
+    if !extract_model_zip(zip_data, model_dest) {
+        return false;
+    }
+    if let Err(e) = fs::write(model_dest.join(MODEL_SHA256_FILE), format!("{}\n", sha256)) {
+        println!("cargo:warning=Failed to write {}: {}", MODEL_SHA256_FILE, e);
+    }
+    // Written only once the archive passed the pin check and was extracted
+    if let Some(stamp) = stamp {
+        if let Err(e) = fs::write(model_dest.join(MODEL_ZIP_STAMP), stamp) {
+            println!("cargo:warning=Failed to write {}: {}", MODEL_ZIP_STAMP, e);
         }
     }
+    true
+}
 
-    // Download the model
-    let download_response = match ureq::get(&download_url).set("x-api-key", api_key).call() {
-        Ok(response) => {
-            if response.status() != 200 {
-                println!(
-                    "cargo:error=Failed to download model: HTTP {}",
-                    response.status()
-                );
-                return false;
-            }
-            response
-        }
-        Err(e) => {
-            println!("cargo:error=Failed to download model: {}", e);
-            return false;
-        }
-    };
+/// File in the model directory with the SHA-256 of the deployment ZIP it was extracted from,
+/// written by ei-fetch and by EI_MODEL ZIP extraction
+const MODEL_SHA256_FILE: &str = ".ei-model-sha256";
+
+/// SHA-256 as lowercase hex
+fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
 
-    // Step 5: Extract the model
-    println!("cargo:info=Step 5/5: Extracting model files...");
+/// SHA-256 the deployment ZIP must have, from EI_MODEL_SHA256
+fn pinned_model_sha256() -> Option<String> {
+    env::var("EI_MODEL_SHA256")
+        .ok()
+        .map(|sha256| sha256.trim().to_ascii_lowercase())
+        .filter(|sha256| !sha256.is_empty())
+}
 
-    // Read the ZIP data
-    let mut zip_data = Vec::new();
-    match download_response.into_reader().read_to_end(&mut zip_data) {
-        Ok(_) => {}
-        Err(e) => {
-            println!("cargo:error=Failed to read download data: {}", e);
-            return false;
+/// Proxy for downloading `url`: `EI_PROXY`, else the proxy environment variables, with the
+/// same rules as ei-fetch
+fn download_proxy(url: &str) -> Option<String> {
+    env::var("EI_PROXY")
+        .ok()
+        .filter(|proxy| !proxy.is_empty())
+        .or_else(|| proxy::proxy_from_env(url))
+}
+
+/// Recursively copy a directory
+fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if !dst.exists() {
+        std::fs::create_dir_all(dst)?;
+    }
+
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if ty.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path)?;
+        } else {
+            std::fs::copy(&src_path, &dst_path)?;
         }
     }
+    Ok(())
+}
 
+/// Extract a deployment ZIP archive into the model directory
+///
+/// Existing `.gitignore` and `README.md` files in the model directory are preserved.
+/// Returns true if the archive could be opened, false otherwise.
+fn extract_model_zip(zip_data: Vec<u8>, model_dir: &Path) -> bool {
     // Extract ZIP file
     let mut archive = match zip::ZipArchive::new(std::io::Cursor::new(zip_data)) {
         Ok(archive) => archive,
@@ -442,6 +343,15 @@ fn download_model_from_edge_impulse(project_id: &str, api_key: &str) -> bool {
         }
     };
 
+    // Archives repacked from an extracted deployment often wrap it in a directory
+    let root = archive_model_root(&archive);
+    if !root.as_os_str().is_empty() {
+        println!(
+            "cargo:info=Extracting the model from the {} directory of the archive",
+            root.display()
+        );
+    }
+
     // Preserve existing .gitignore and README.md if they exist
     let gitignore_content = fs::read_to_string(model_dir.join(".gitignore")).ok();
     let readme_content = fs::read_to_string(model_dir.join("README.md")).ok();
@@ -467,6 +377,12 @@ fn download_model_from_edge_impulse(project_id: &str, api_key: &str) -> bool {
             }
         };
 
+        let Ok(file_path) = file_path.strip_prefix(&root) else {
+            continue;
+        };
+        if file_path.as_os_str().is_empty() {
+            continue;
+        }
         let target_path = model_dir.join(file_path);
 
         if file.name().ends_with('/') {
@@ -516,12 +432,29 @@ fn download_model_from_edge_impulse(project_id: &str, api_key: &str) -> bool {
         }
     }
 
-    println!("cargo:info=Model downloaded and extracted successfully!");
-    println!("cargo:info=Model is now ready for use. Future builds will use the local copy.");
-
     true
 }
 
+/// Directory of a deployment archive that holds `model-parameters/`, empty for the archive root
+fn archive_model_root<R: std::io::Read + std::io::Seek>(archive: &zip::ZipArchive<R>) -> PathBuf {
+    let mut root = None;
+    for name in archive.file_names() {
+        let path = Path::new(name);
+        if path.starts_with("model-parameters") {
+            return PathBuf::new();
+        }
+        if let Some(index) = path.iter().position(|part| part == "model-parameters") {
+            let candidate: PathBuf = path.iter().take(index).collect();
+            if root.as_ref().is_none_or(|root: &PathBuf| {
+                candidate.components().count() < root.components().count()
+            }) {
+                root = Some(candidate);
+            }
+        }
+    }
+    root.unwrap_or_default()
+}
+
 fn clean_model_folder() {
     let model_dir = "model";
 
@@ -574,82 +507,1837 @@ fn clean_model_folder() {
     println!("Model folder cleaned successfully. Only README.md and .gitignore remain.");
 }
 
-/// Fix the header file path in the generated header file to point to the correct TFLite file location
-fn fix_header_file_path(build_dir: &Path) {
-    let tflite_model_dir = build_dir.join("tflite-model");
-
-    // Find the actual TFLite file (should be named tflite_learn_*.tflite)
-    let tflite_files: Vec<_> = std::fs::read_dir(&tflite_model_dir)
-        .expect("Failed to read tflite-model directory")
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let file_name_os = entry.file_name();
-            let file_name = file_name_os.to_str()?;
-            if file_name.ends_with(".tflite") && file_name.starts_with("tflite_learn_") {
-                Some((entry.path(), file_name.to_string()))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    if tflite_files.is_empty() {
-        println!("cargo:warning=No tflite_learn_*.tflite file found in build directory");
-        return;
-    }
-
-    // Fix all TFLite files and their corresponding headers
-    for (tflite_file, tflite_filename) in &tflite_files {
-        let base_name = tflite_filename.trim_end_matches(".tflite");
-        let header_filename = format!("{}.h", base_name);
-        let header_file = tflite_model_dir.join(&header_filename);
+/// Name of the stamp file recording which Cargo target a native build directory was built for
+const BUILD_TARGET_STAMP: &str = ".ei-build-target";
+
+/// Prebuilt full TensorFlow Lite libraries, in link order (matches the official Makefile)
+const TFLITE_PREBUILT_LIBS: [&str; 9] = [
+    "tensorflow-lite",
+    "cpuinfo",
+    "farmhash",
+    "fft2d_fftsg",
+    "fft2d_fftsg2d",
+    "ruy",
+    "XNNPACK",
+    "pthreadpool",
+    "flatbuffers",
+];
+
+/// Per-target, per-feature-set build directory for the C++ library
+///
+/// Host and cross builds (or TFLite Micro and full TFLite builds) must not share one CMake
+/// build directory, otherwise a static library for the wrong configuration gets linked.
+fn native_build_dir(model_dir: &Path, target: &str, variant: &str) -> PathBuf {
+    model_dir.join("build").join(target).join(variant)
+}
 
-        if header_file.exists() && tflite_file.exists() {
-            let content =
-                std::fs::read_to_string(&header_file).expect("Failed to read header file");
+/// Read the Cargo target a native build directory was built for, if recorded
+fn read_build_target_stamp(build_dir: &Path) -> Option<String> {
+    fs::read_to_string(build_dir.join(BUILD_TARGET_STAMP))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
 
-            // Create the INCBIN macro name from the base name
-            let incbin_name = format!("incbin_{}", base_name);
+/// Record the Cargo target a native build directory was built for
+fn write_build_target_stamp(build_dir: &Path, target: &str) {
+    fs::write(build_dir.join(BUILD_TARGET_STAMP), target)
+        .expect("Failed to write build target stamp");
+}
 
-            // Use relative path instead of absolute path to avoid Docker container path issues
-            let relative_path = format!("tflite-model/{}", tflite_filename);
+/// Name of the stamp file recording the hash of the inputs a native build directory was built from
+const BUILD_INPUTS_STAMP: &str = ".ei-build-inputs";
 
-            // Patch any INCBIN macro to use the relative path
-            let old_pattern = format!(
-                "INCBIN({}, \"tflite-model/{}\");",
-                incbin_name, tflite_filename
-            );
-            let new_pattern = format!("INCBIN({}, \"{}\");", incbin_name, relative_path);
+/// Hash of everything the SDK library in a build directory is built from: the model files in
+/// `model_dir`, the FFI glue copied next to them and the CMake arguments
+///
+/// Re-exporting a model into `model/` changes the hash, so the library is rebuilt instead of
+/// linking stale code. Model parameters, TFLite models and glue are hashed by contents. The SDK
+/// is large and only changes with a new export, so its files are hashed by path, size and
+/// modification time.
+fn build_inputs_hash(model_dir: &Path, cmake_args: &[String]) -> u64 {
+    let mut hash = fnv1a(cmake_args.join("\n").as_bytes());
+    let mut files = Vec::new();
+    // Glue and CMakeLists.txt at the top level, not the build directories below it
+    if let Ok(entries) = fs::read_dir(model_dir) {
+        files.extend(
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file()),
+        );
+    }
+    list_files(&model_dir.join("model-parameters"), &mut files);
+    list_files(&model_dir.join("tflite-model"), &mut files);
+    let sdk_start = files.len();
+    list_files(&model_dir.join("edge-impulse-sdk"), &mut files);
+    files[..sdk_start].sort();
+    files[sdk_start..].sort();
+
+    for (index, file) in files.iter().enumerate() {
+        let relative = file.strip_prefix(model_dir).unwrap_or(file);
+        hash = fnv1a_extend(hash, relative.to_string_lossy().as_bytes());
+        if index < sdk_start {
+            hash = fnv1a_extend(hash, &fs::read(file).unwrap_or_default());
+        } else if let Ok(metadata) = fs::metadata(file) {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_nanos());
+            hash = fnv1a_extend(hash, &metadata.len().to_le_bytes());
+            hash = fnv1a_extend(hash, &modified.to_le_bytes());
+        }
+    }
+    hash
+}
 
-            let fixed_content = content.replace(&old_pattern, &new_pattern);
-            std::fs::write(&header_file, fixed_content).expect("Failed to write fixed header file");
-            println!(
-                "cargo:info=Fixed header file path in {} for {}",
-                header_file.display(),
-                tflite_filename
-            );
+/// Append the files below `dir` to `files`, recursively
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            list_files(&path, files);
         } else {
-            println!(
-                "cargo:warning=Header file or tflite file not found: {} or {}",
-                header_file.display(),
-                tflite_file.display()
-            );
+            files.push(path);
         }
     }
 }
 
-fn extract_and_write_model_metadata() {
-    use std::collections::HashMap;
-    use std::fs;
-    let header_path = "model/model-parameters/model_metadata.h";
-    let out_path = "src/model_metadata.rs";
-    let header = fs::read_to_string(header_path).expect("Failed to read model_metadata.h");
+/// Read the inputs hash a native build directory was built from, if recorded
+fn read_build_inputs_stamp(build_dir: &Path) -> Option<u64> {
+    let stamp = fs::read_to_string(build_dir.join(BUILD_INPUTS_STAMP)).ok()?;
+    u64::from_str_radix(stamp.trim(), 16).ok()
+}
 
-    let mut out = String::from("// This file is @generated by build.rs. Do not edit manually.\n");
-    out.push_str("// Model metadata constants extracted from model_metadata.h\n\n");
+/// Record the inputs hash a native build directory was built from
+fn write_build_inputs_stamp(build_dir: &Path, hash: u64) {
+    fs::write(
+        build_dir.join(BUILD_INPUTS_STAMP),
+        format!("{:016x}\n", hash),
+    )
+    .expect("Failed to write build inputs stamp");
+}
 
-    let mut seen = HashMap::new();
-    let mut raw_defs = Vec::new();
+/// Detect the CPU architecture of the first object file inside a static library (`ar` archive)
+///
+/// Returns the architecture in `CARGO_CFG_TARGET_ARCH` spelling (e.g. `aarch64`, `x86_64`),
+/// or None if the archive could not be read or contains no recognizable ELF/Mach-O/WebAssembly/COFF objects.
+fn archive_object_arch(archive: &Path) -> Option<&'static str> {
+    let data = fs::read(archive).ok()?;
+    if !data.starts_with(b"!<arch>\n") {
+        // Shared libraries are a single object file
+        return object_arch(&data);
+    }
+
+    let mut offset = 8;
+    while offset + 60 <= data.len() {
+        let header = &data[offset..offset + 60];
+        let name = String::from_utf8_lossy(&header[0..16]).trim().to_string();
+        let size: usize = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .ok()?;
+        let mut member_start = offset + 60;
+        let member_end = (member_start + size).min(data.len());
+
+        // BSD archives store long member names at the start of the member data
+        if let Some(len) = name.strip_prefix("#1/") {
+            member_start += len.parse::<usize>().unwrap_or(0);
+        }
+
+        // Skip symbol tables and the GNU long-name table
+        let is_index = name == "/"
+            || name == "//"
+            || name.starts_with("/SYM64/")
+            || name.starts_with("__.SYMDEF");
+        if !is_index && member_start < member_end {
+            if let Some(arch) = object_arch(&data[member_start..member_end]) {
+                return Some(arch);
+            }
+        }
+
+        // Members are aligned to even offsets
+        offset = offset + 60 + size + (size % 2);
+    }
+    None
+}
+
+/// Architectures of a Mach-O universal ("fat") binary, or None for any other file
+fn fat_binary_archs(data: &[u8]) -> Option<Vec<&'static str>> {
+    let word = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if word(0)? != 0xcafebabe {
+        return None;
+    }
+    // Each fat_arch entry is 20 bytes and starts with the CPU type
+    let count = word(4)? as usize;
+    Some(
+        (0..count.min(MAX_FAT_ARCHS))
+            .filter_map(|index| word(8 + index * 20).and_then(mach_o_arch))
+            .collect(),
+    )
+}
+
+/// Most slices read from a universal binary's header
+const MAX_FAT_ARCHS: usize = 32;
+
+/// Architecture of a Mach-O CPU type
+fn mach_o_arch(cputype: u32) -> Option<&'static str> {
+    match cputype {
+        7 => Some("x86"),
+        0x0100_0007 => Some("x86_64"),
+        12 => Some("arm"),
+        0x0100_000c => Some("aarch64"),
+        _ => None,
+    }
+}
+
+/// Detect the CPU architecture of a single ELF, Mach-O, WebAssembly or COFF object file
+fn object_arch(object: &[u8]) -> Option<&'static str> {
+    if object.len() >= 20 && object.starts_with(b"\x7fELF") {
+        let little_endian = object[5] == 1;
+        let machine = if little_endian {
+            u16::from_le_bytes([object[18], object[19]])
+        } else {
+            u16::from_be_bytes([object[18], object[19]])
+        };
+        return match machine {
+            3 => Some("x86"),
+            8 => Some("mips"),
+            40 => Some("arm"),
+            62 => Some("x86_64"),
+            94 => Some("xtensa"),
+            183 => Some("aarch64"),
+            243 => Some("riscv"),
+            _ => None,
+        };
+    }
+
+    if object.starts_with(b"\0asm") {
+        return Some("wasm32");
+    }
+
+    if object.len() >= 8 {
+        let magic = u32::from_le_bytes([object[0], object[1], object[2], object[3]]);
+        if magic == 0xfeedfacf || magic == 0xfeedface {
+            let cputype = u32::from_le_bytes([object[4], object[5], object[6], object[7]]);
+            return mach_o_arch(cputype);
+        }
+    }
+
+    if object.len() >= 2 {
+        return match u16::from_le_bytes([object[0], object[1]]) {
+            0x014c => Some("x86"),
+            0x8664 => Some("x86_64"),
+            0x01c4 => Some("arm"),
+            0xaa64 => Some("aarch64"),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+/// Fail the build with a clear message if a static library doesn't match the Cargo target architecture
+///
+/// Mismatches would otherwise only show up as inscrutable linker errors. Universal macOS
+/// libraries pass if one of their slices matches.
+fn check_library_arch(library: &Path, target_arch: &str) {
+    let mut header = Vec::new();
+    let _ = fs::File::open(library).and_then(|file| {
+        file.take(8 + 20 * MAX_FAT_ARCHS as u64)
+            .read_to_end(&mut header)
+    });
+    if let Some(archs) = fat_binary_archs(&header) {
+        if !archs.contains(&target_arch) {
+            panic!(
+                "{} is a universal library for {}, but the Cargo target architecture is {}. \
+                 Rebuild the C++ library (FORCE_REBUILD=1).",
+                library.display(),
+                archs.join(", "),
+                target_arch
+            );
+        }
+        println!(
+            "cargo:info=Verified {} architectures: {}",
+            library.display(),
+            archs.join(", ")
+        );
+        return;
+    }
+
+    let Some(lib_arch) = archive_object_arch(library) else {
+        println!(
+            "cargo:info=Could not determine architecture of {}, skipping architecture check",
+            library.display()
+        );
+        return;
+    };
+
+    // riscv32/riscv64 share one ELF machine type
+    let matches =
+        lib_arch == target_arch || (lib_arch == "riscv" && target_arch.starts_with("riscv"));
+    if !matches {
+        panic!(
+            "{} contains {} objects, but the Cargo target architecture is {}. \
+             Rebuild the C++ library for this target (FORCE_REBUILD=1) or select the matching \
+             TARGET_* platform variable.",
+            library.display(),
+            lib_arch,
+            target_arch
+        );
+    }
+    println!(
+        "cargo:info=Verified {} architecture: {}",
+        library.display(),
+        lib_arch
+    );
+}
+
+/// Copy a shared SDK library next to the binaries in the Cargo target profile directory
+///
+/// Together with the `$ORIGIN` rpath this lets `cargo run` and copied binaries find the library.
+/// Use `install-shared-lib.sh` to install it elsewhere.
+fn install_shared_library(library: &Path) {
+    let Ok(out_dir) = env::var("OUT_DIR") else {
+        return;
+    };
+    // OUT_DIR is <target>/<profile>/build/<package>-<hash>/out
+    let Some(profile_dir) = Path::new(&out_dir).ancestors().nth(3) else {
+        return;
+    };
+    let Some(file_name) = library.file_name() else {
+        return;
+    };
+    for dir in [profile_dir.to_path_buf(), profile_dir.join("examples")] {
+        if fs::create_dir_all(&dir).is_err() {
+            continue;
+        }
+        match fs::copy(library, dir.join(file_name)) {
+            Ok(_) => println!(
+                "cargo:info=Installed {} to {}",
+                file_name.to_string_lossy(),
+                dir.display()
+            ),
+            Err(e) => println!(
+                "cargo:warning=Failed to install {} to {}: {}",
+                file_name.to_string_lossy(),
+                dir.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Check that a symbol prefix is a valid C identifier, so prefixed names stay linkable
+fn is_valid_symbol_prefix(prefix: &str) -> bool {
+    let mut chars = prefix.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Public items of generated bindings, keyed by name
+///
+/// Struct fields are keyed as `Struct::field`, and every value is the item's declaration with
+/// whitespace normalized, so bindings generated from different models can be compared.
+fn bindings_signatures(bindings: &str) -> std::collections::BTreeMap<String, String> {
+    let item = regex::Regex::new(r"^pub (?:struct|enum|union|type|const|static|fn) (\w+)").unwrap();
+    let field = regex::Regex::new(r"^pub (\w+): ").unwrap();
+
+    let mut signatures = std::collections::BTreeMap::new();
+    let mut current_struct: Option<String> = None;
+    let mut lines = bindings.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(captures) = item.captures(line) {
+            let name = captures[1].to_string();
+            let mut declaration = line.to_string();
+            // Function signatures may span several lines
+            if line.starts_with("pub fn ") {
+                while !declaration.ends_with(';') {
+                    match lines.next() {
+                        Some(next) => {
+                            declaration.push(' ');
+                            declaration.push_str(next);
+                        }
+                        None => break,
+                    }
+                }
+            }
+            current_struct = (line.starts_with("pub struct ") || line.starts_with("pub union "))
+                .then(|| name.clone());
+            let declaration = declaration.split_whitespace().collect::<Vec<_>>().join(" ");
+            signatures.insert(name, declaration);
+        } else if let (Some(struct_name), Some(captures)) = (&current_struct, field.captures(line))
+        {
+            let declaration = line.trim_start_matches("pub ").trim_end_matches(',');
+            signatures.insert(
+                format!("{}::{}", struct_name, &captures[1]),
+                format!("{}::{}", struct_name, declaration),
+            );
+        } else if line == "}" {
+            current_struct = None;
+        }
+    }
+    signatures
+}
+
+/// Warn about public items that changed since the bindings were last generated
+///
+/// Bindings follow the model, so switching models can silently change the raw API. With
+/// `EI_DENY_BINDINGS_CHANGES` set, any change fails the build instead (e.g. for CI).
+fn report_bindings_changes(previous: &str, current: &str) {
+    let before = bindings_signatures(previous);
+    let after = bindings_signatures(current);
+
+    let mut changes = Vec::new();
+    for (name, declaration) in &before {
+        match after.get(name) {
+            None => changes.push(format!("removed: {}", declaration)),
+            Some(new) if new != declaration => {
+                changes.push(format!("changed: {} -> {}", declaration, new))
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, declaration) in &after {
+        if !before.contains_key(name) {
+            changes.push(format!("added: {}", declaration));
+        }
+    }
+    if changes.is_empty() {
+        return;
+    }
+
+    println!(
+        "cargo:warning=Generated bindings changed ({} items). Code using `bindings` directly may need updating; the `stable` API is unaffected.",
+        changes.len()
+    );
+    for change in changes.iter().take(20) {
+        println!("cargo:warning=  {}", change);
+    }
+    if changes.len() > 20 {
+        println!("cargo:warning=  ... and {} more", changes.len() - 20);
+    }
+    if env::var("EI_DENY_BINDINGS_CHANGES").is_ok() {
+        panic!("Generated bindings changed and EI_DENY_BINDINGS_CHANGES is set");
+    }
+}
+
+/// Warn about SDK error codes `EdgeImpulseError` doesn't have a variant for
+///
+/// `EI_IMPULSE_ERROR` is generated as a newtype, so new codes are only new constants in the
+/// bindings; `EdgeImpulseError` reports them as `Unknown(code)` until it learns about them. The
+/// known codes are read from the match arms of `EdgeImpulseError::from_code` in `src/error.rs`.
+fn report_unknown_impulse_errors(bindings: &str) {
+    let Ok(error_rs) = fs::read_to_string("src/error.rs") else {
+        return;
+    };
+    let Some(from_code) = error_rs
+        .split("pub fn from_code")
+        .nth(1)
+        .and_then(|rest| rest.split("other => Self::Unknown").next())
+    else {
+        return;
+    };
+    let arm = regex::Regex::new(r"(-?\d+) =>").unwrap();
+    let known: Vec<i64> = arm
+        .captures_iter(from_code)
+        .filter_map(|c| c[1].parse().ok())
+        .collect();
+
+    let constant = regex::Regex::new(
+        r"pub const (EI_IMPULSE_\w+): EI_IMPULSE_ERROR\s*=\s*EI_IMPULSE_ERROR\(\s*(-?\d+)\s*\)",
+    )
+    .unwrap();
+    for c in constant.captures_iter(bindings) {
+        let Ok(code) = c[2].parse::<i64>() else {
+            continue;
+        };
+        if !known.contains(&code) {
+            println!(
+                "cargo:warning=SDK error {} ({}) has no EdgeImpulseError variant, it is reported as EdgeImpulseError::Unknown({})",
+                &c[1], code, code
+            );
+        }
+    }
+}
+
+/// Size of a model's learning block in the exported C++ sources
+struct ModelBlobSize {
+    file: String,
+    /// Size of the embedded .tflite flatbuffer, `None` for EON-compiled blocks
+    bytes: Option<u64>,
+    /// Tensor arena the block needs at runtime, if the sources declare it
+    arena: Option<u64>,
+}
+
+/// Report the size of the SDK library and the model, so binary growth can be tracked across
+/// model versions
+///
+/// Prints a one-line summary, and writes it as JSON to `EI_SIZE_REPORT` if set. The tensor
+/// arena is the largest RAM allocation of the impulse; the embedded models end up in flash.
+fn report_sizes(library: &Path, model_dir: &Path) {
+    let library_bytes = fs::metadata(library).map(|m| m.len()).unwrap_or(0);
+
+    let len = regex::Regex::new(r"unsigned int \w+_len\s*=\s*(\d+)").unwrap();
+    let arena = regex::Regex::new(r"kTensorArenaSize\s*=\s*(\d+)").unwrap();
+    let mut blobs = Vec::new();
+    if let Ok(entries) = fs::read_dir(model_dir.join("tflite-model")) {
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "cpp"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Ok(source) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let capture =
+                |re: &regex::Regex| re.captures(&source).and_then(|c| c[1].parse::<u64>().ok());
+            let blob = ModelBlobSize {
+                file: path.file_name().unwrap().to_string_lossy().into_owned(),
+                bytes: capture(&len),
+                arena: capture(&arena),
+            };
+            if blob.bytes.is_some() || blob.arena.is_some() {
+                blobs.push(blob);
+            }
+        }
+    }
+
+    let largest_arena = fs::read_to_string(model_dir.join("model-parameters/model_metadata.h"))
+        .ok()
+        .and_then(|header| {
+            regex::Regex::new(r"#define EI_CLASSIFIER_TFLITE_LARGEST_ARENA_SIZE\s+(\d+)")
+                .unwrap()
+                .captures(&header)
+                .and_then(|c| c[1].parse::<u64>().ok())
+        })
+        .or_else(|| blobs.iter().filter_map(|blob| blob.arena).max());
+    let model_bytes: u64 = blobs.iter().filter_map(|blob| blob.bytes).sum();
+
+    let kib = |bytes: u64| format!("{:.1} KiB", bytes as f64 / 1024.0);
+    println!(
+        "cargo:warning=Size report: {} {}, embedded models {}{}, tensor arena {}",
+        library.file_name().unwrap().to_string_lossy(),
+        kib(library_bytes),
+        kib(model_bytes),
+        if blobs.iter().any(|blob| blob.bytes.is_none()) {
+            " (+ EON-compiled blocks)"
+        } else {
+            ""
+        },
+        largest_arena
+            .map(kib)
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+
+    let Ok(report_path) = env::var("EI_SIZE_REPORT") else {
+        return;
+    };
+    let optional = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+    let blobs_json = blobs
+        .iter()
+        .map(|blob| {
+            format!(
+                "    {{ \"file\": {:?}, \"bytes\": {}, \"arena_bytes\": {} }}",
+                blob.file,
+                optional(blob.bytes),
+                optional(blob.arena)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let json = format!(
+        "{{\n  \"library\": {:?},\n  \"library_bytes\": {},\n  \"model_bytes\": {},\n  \"tensor_arena_bytes\": {},\n  \"models\": [\n{}\n  ]\n}}\n",
+        library.display().to_string(),
+        library_bytes,
+        model_bytes,
+        optional(largest_arena),
+        blobs_json
+    );
+    if let Err(e) = fs::write(&report_path, json) {
+        println!(
+            "cargo:warning=Failed to write size report to {}: {}",
+            report_path, e
+        );
+    }
+}
+
+/// Whether to skip the C++ SDK build because this is a check build (e.g. rust-analyzer)
+///
+/// `EI_SKIP_NATIVE_BUILD` forces the decision either way. Otherwise check builds are detected
+/// from the rustc wrapper: rust-analyzer runs build scripts with itself as `RUSTC_WRAPPER`, and
+/// `cargo clippy` sets `clippy-driver` as `RUSTC_WORKSPACE_WRAPPER`.
+fn skip_native_build() -> bool {
+    if let Ok(value) = env::var("EI_SKIP_NATIVE_BUILD") {
+        return !matches!(value.as_str(), "" | "0" | "false");
+    }
+    ["RUSTC_WRAPPER", "RUSTC_WORKSPACE_WRAPPER"]
+        .iter()
+        .any(|var| {
+            env::var(var).is_ok_and(|wrapper| {
+                Path::new(&wrapper)
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .is_some_and(|name| {
+                        name.contains("rust-analyzer") || name.contains("clippy-driver")
+                    })
+            })
+        })
+}
+
+/// Sanitizer combinations supported by `EI_SANITIZE`
+fn is_supported_sanitizer(sanitize: &str) -> bool {
+    matches!(
+        sanitize,
+        "address" | "undefined" | "address,undefined" | "undefined,address"
+    )
+}
+
+/// Cross-compilation settings for an armv7 musl target (e.g. Alpine-based gateways)
+struct Armv7MuslTarget {
+    /// Rust target triple
+    triple: &'static str,
+    /// Prefix of the default GCC cross toolchain (musl.cc naming)
+    toolchain: &'static str,
+    /// Architecture and floating point flags matching the target's float ABI
+    arch_flags: &'static str,
+}
+
+/// Detect an armv7 musl target from the Cargo target triple or `TARGET_LINUX_ARMV7_MUSL`
+///
+/// `musleabihf` uses the hard-float ABI. `musleabi` uses the soft-float calling convention, so
+/// the SDK is built with `softfp` to still use the FPU internally.
+fn armv7_musl_target() -> Option<Armv7MuslTarget> {
+    let target = env::var("TARGET").unwrap_or_default();
+    if target == "armv7-unknown-linux-musleabihf" || env::var("TARGET_LINUX_ARMV7_MUSL").is_ok() {
+        Some(Armv7MuslTarget {
+            triple: "armv7-unknown-linux-musleabihf",
+            toolchain: "armv7l-linux-musleabihf",
+            arch_flags: "-march=armv7-a -mfpu=neon-vfpv4 -mfloat-abi=hard",
+        })
+    } else if target == "armv7-unknown-linux-musleabi" {
+        Some(Armv7MuslTarget {
+            triple: "armv7-unknown-linux-musleabi",
+            toolchain: "armv7l-linux-musleabi",
+            arch_flags: "-march=armv7-a -mfpu=vfpv3-d16 -mfloat-abi=softfp",
+        })
+    } else {
+        None
+    }
+}
+
+impl Armv7MuslTarget {
+    /// The musl.cc compiler, unless one is set for the target (see [`target_compiler`])
+    fn compiler(&self, tool: &str) -> String {
+        let suffix = if tool == "CC" { "gcc" } else { "g++" };
+        target_compiler(tool, self.triple)
+            .unwrap_or_else(|| format!("{}-{}", self.toolchain, suffix))
+    }
+}
+
+/// Prebuilt TensorFlow Lite platform (`tflite/<platform>`) of the Cargo target, from
+/// `CARGO_CFG_TARGET_OS` and `CARGO_CFG_TARGET_ARCH`
+///
+/// The `TARGET_*` variables (e.g. `TARGET_MAC_ARM64`) override it.
+fn cargo_target_platform() -> Option<&'static str> {
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    match (os.as_str(), arch.as_str()) {
+        ("macos", "aarch64") => Some("mac-arm64"),
+        ("macos", "x86_64") => Some("mac-x86_64"),
+        ("linux", "aarch64") => Some("linux-aarch64"),
+        ("linux", "arm") => Some("linux-armv7"),
+        ("linux", "x86_64") => Some("linux-x86"),
+        _ => None,
+    }
+}
+
+/// Compiler (`tool` is `CC` or `CXX`) set for the Rust target `triple`, looked up like the cc
+/// crate does: `CC_<triple>`, `CC_<triple with underscores>`, `TARGET_CC`, and `CC` itself only
+/// when building for the host, so a host `CC` never compiles the SDK of a cross build
+fn target_compiler(tool: &str, triple: &str) -> Option<String> {
+    let native = env::var("HOST").is_ok_and(|host| host == triple);
+    [
+        format!("{}_{}", tool, triple),
+        format!("{}_{}", tool, triple.replace('-', "_")),
+        format!("TARGET_{}", tool),
+    ]
+    .into_iter()
+    .chain(native.then(|| tool.to_string()))
+    .find_map(|name| env::var(name).ok())
+}
+
+/// Cross-compiling for 64-bit Arm Linux with the GNU toolchain (`aarch64-linux-gnu-*`):
+/// `--target aarch64-unknown-linux-gnu` from another host, or `TARGET_LINUX_AARCH64`
+fn aarch64_linux_cross() -> bool {
+    let target = env::var("TARGET").unwrap_or_default();
+    env::var("TARGET_LINUX_AARCH64").is_ok()
+        || (target == "aarch64-unknown-linux-gnu"
+            && env::var("HOST").is_ok_and(|host| host != target))
+}
+
+/// Prefix of the Arm GNU Toolchain, which provides newlib and libstdc++ for bare-metal targets
+const CORTEX_M_TOOLCHAIN: &str = "arm-none-eabi";
+
+/// Cross-compilation settings for a bare-metal Cortex-M target
+struct CortexMTarget {
+    /// Rust target triple
+    triple: &'static str,
+    /// CPU and floating point flags; `EI_CORTEX_M_FLAGS` replaces them, e.g. for a Cortex-M7
+    arch_flags: String,
+}
+
+/// Detect a bare-metal Cortex-M4/M7 target (`thumbv7em-none-eabi` or `thumbv7em-none-eabihf`)
+fn cortex_m_target() -> Option<CortexMTarget> {
+    let (triple, default_flags) = match env::var("TARGET").as_deref() {
+        Ok("thumbv7em-none-eabihf") => (
+            "thumbv7em-none-eabihf",
+            "-mcpu=cortex-m4 -mthumb -mfloat-abi=hard -mfpu=fpv4-sp-d16",
+        ),
+        Ok("thumbv7em-none-eabi") => (
+            "thumbv7em-none-eabi",
+            "-mcpu=cortex-m4 -mthumb -mfloat-abi=soft",
+        ),
+        _ => return None,
+    };
+    println!("cargo:rerun-if-env-changed=EI_CORTEX_M_FLAGS");
+    let arch_flags = env::var("EI_CORTEX_M_FLAGS")
+        .ok()
+        .filter(|flags| !flags.is_empty())
+        .unwrap_or_else(|| default_flags.to_string());
+    Some(CortexMTarget { triple, arch_flags })
+}
+
+impl CortexMTarget {
+    /// Compiler flags; sections per function let the firmware's linker drop the SDK code it
+    /// doesn't call
+    fn flags(&self) -> String {
+        format!(
+            "{} -ffunction-sections -fdata-sections -fno-exceptions",
+            self.arch_flags
+        )
+    }
+
+    /// The toolchain's compiler, unless one is set for the target (see [`target_compiler`])
+    fn compiler(&self, tool: &str) -> String {
+        let suffix = if tool == "CC" { "gcc" } else { "g++" };
+        target_compiler(tool, self.triple)
+            .unwrap_or_else(|| format!("{}-{}", CORTEX_M_TOOLCHAIN, suffix))
+    }
+
+    /// newlib and libstdc++ include directories, for bindgen
+    fn system_include_dirs(&self) -> Vec<PathBuf> {
+        gcc_system_include_dirs(&self.compiler("CXX"), &self.arch_flags)
+    }
+
+    /// Directory of one of the toolchain's libraries, for the multilib matching the target flags
+    fn library_dir(&self, file_name: &str) -> Option<PathBuf> {
+        let output = query_gcc(
+            &self.compiler("CXX"),
+            &self.arch_flags,
+            &[&format!("-print-file-name={}", file_name)],
+        );
+        // GCC prints the bare name back when it doesn't have the library
+        let path = PathBuf::from(output.lines().next()?.trim());
+        if path.is_absolute() {
+            path.parent().map(Path::to_path_buf)
+        } else {
+            None
+        }
+    }
+}
+
+/// Run a GCC cross compiler with the target's `flags` and return its stdout and stderr
+fn query_gcc(compiler: &str, flags: &str, args: &[&str]) -> String {
+    let output = Command::new(compiler)
+        .args(flags.split_whitespace())
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to run {}, install the target's GCC toolchain or set CXX: {}",
+                compiler, e
+            )
+        });
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    text
+}
+
+/// C and C++ include directories of a GCC cross toolchain, which bindgen's clang doesn't know
+/// about
+fn gcc_system_include_dirs(compiler: &str, flags: &str) -> Vec<PathBuf> {
+    query_gcc(compiler, flags, &["-xc++", "-E", "-Wp,-v", "-"])
+        .lines()
+        .skip_while(|line| !line.starts_with("#include <...> search starts here:"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("End of search list."))
+        .map(|line| PathBuf::from(line.trim()))
+        .collect()
+}
+
+/// An Espressif chip running ESP-IDF (`*-espidf` targets, which have `std`)
+struct EspIdfTarget {
+    /// Rust target triple
+    triple: &'static str,
+    /// Chip, in ESP-IDF's `IDF_TARGET` spelling
+    chip: &'static str,
+}
+
+/// Detect an Xtensa ESP-IDF target
+fn esp_idf_target() -> Option<EspIdfTarget> {
+    let (triple, chip) = match env::var("TARGET").as_deref() {
+        Ok("xtensa-esp32s3-espidf") => ("xtensa-esp32s3-espidf", "esp32s3"),
+        Ok("xtensa-esp32-espidf") => ("xtensa-esp32-espidf", "esp32"),
+        _ => return None,
+    };
+    Some(EspIdfTarget { triple, chip })
+}
+
+impl EspIdfTarget {
+    /// Prefix of the chip's GCC toolchain, as installed by ESP-IDF or `espup`
+    fn toolchain(&self) -> String {
+        format!("xtensa-{}-elf", self.chip)
+    }
+
+    /// The toolchain's compiler, unless one is set for the target (see [`target_compiler`])
+    fn compiler(&self, tool: &str) -> String {
+        let suffix = if tool == "CC" { "gcc" } else { "g++" };
+        target_compiler(tool, self.triple)
+            .unwrap_or_else(|| format!("{}-{}", self.toolchain(), suffix))
+    }
+
+    /// Code generation flags ESP-IDF builds with; `-mlongcalls` lets calls reach across the
+    /// chip's separate instruction memories
+    fn flags(&self) -> &'static str {
+        "-mlongcalls -ffunction-sections -fdata-sections"
+    }
+}
+
+/// CMake settings for a Windows MSVC target
+struct MsvcTarget {
+    /// `EI_CMAKE_GENERATOR`, else Ninja if it's installed, else `None` for CMake's default
+    /// (the newest Visual Studio), see [`cmake_generator`]
+    generator: Option<String>,
+    /// Link the static C runtime (`-C target-feature=+crt-static`) instead of `msvcrt`
+    static_crt: bool,
+    /// Environment of `cl.exe` (`INCLUDE`, `LIB`, `PATH`), so Ninja builds work outside a
+    /// Visual Studio developer prompt
+    compiler_env: Vec<(std::ffi::OsString, std::ffi::OsString)>,
+}
+
+/// Detect a Windows MSVC target from `CARGO_CFG_TARGET_ENV`
+fn msvc_target() -> Option<MsvcTarget> {
+    if env::var("CARGO_CFG_TARGET_ENV").as_deref() != Ok("msvc") {
+        return None;
+    }
+    let generator = cmake_generator(true);
+    let target = env::var("TARGET").unwrap_or_default();
+    Some(MsvcTarget {
+        compiler_env: cc::windows_registry::find_tool(&target, "cl.exe")
+            .map(|tool| {
+                tool.get_envs()
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        generator,
+        static_crt: env::var("CARGO_CFG_TARGET_FEATURE")
+            .unwrap_or_default()
+            .split(',')
+            .any(|feature| feature == "crt-static"),
+    })
+}
+
+impl MsvcTarget {
+    fn is_visual_studio(&self) -> bool {
+        self.generator
+            .as_deref()
+            .is_none_or(|generator| generator.starts_with("Visual Studio"))
+    }
+
+    /// Generator, architecture, C runtime and output directory arguments for CMake
+    fn cmake_args(&self, build_dir: &Path) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(generator) = &self.generator {
+            args.push(format!("-G{}", generator));
+        }
+        if self.is_visual_studio() {
+            let platform = match env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+                Ok("x86") => "Win32",
+                Ok("aarch64") => "ARM64",
+                _ => "x64",
+            };
+            args.push(format!("-A{}", platform));
+        } else {
+            args.push("-DCMAKE_C_COMPILER=cl".to_string());
+            args.push("-DCMAKE_CXX_COMPILER=cl".to_string());
+        }
+        // Rust always links the release CRT, also in debug builds
+        args.push("-DCMAKE_POLICY_DEFAULT_CMP0091=NEW".to_string());
+        args.push(format!(
+            "-DCMAKE_MSVC_RUNTIME_LIBRARY={}",
+            if self.static_crt {
+                "MultiThreaded"
+            } else {
+                "MultiThreadedDLL"
+            }
+        ));
+        // `$<0:>` stops Visual Studio from adding a Release/ subdirectory
+        let output_dir = format!("{}/$<0:>", cmake_path(build_dir));
+        for kind in ["ARCHIVE", "LIBRARY", "RUNTIME"] {
+            args.push(format!("-DCMAKE_{}_OUTPUT_DIRECTORY={}", kind, output_dir));
+        }
+        args
+    }
+}
+
+/// Emscripten SDK paths for a `wasm32-unknown-emscripten` target
+struct EmscriptenTarget {
+    /// Emscripten's CMake toolchain file, which selects emcc/em++ and the wasm32 sysroot
+    toolchain_file: PathBuf,
+    /// Emscripten's libc and libc++ headers, for bindgen
+    sysroot: PathBuf,
+}
+
+/// Detect an Emscripten target and find the SDK through `EMSCRIPTEN` or `EMSDK` (set by
+/// `emsdk_env.sh`)
+fn emscripten_target() -> Option<EmscriptenTarget> {
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("emscripten") {
+        return None;
+    }
+    println!("cargo:rerun-if-env-changed=EMSCRIPTEN");
+    println!("cargo:rerun-if-env-changed=EMSDK");
+    println!("cargo:rerun-if-env-changed=EM_CACHE");
+    let root = env::var_os("EMSCRIPTEN")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("EMSDK").map(|emsdk| PathBuf::from(emsdk).join("upstream/emscripten")))
+        .unwrap_or_else(|| {
+            panic!("Building for Emscripten needs the Emscripten SDK: run `source <emsdk>/emsdk_env.sh` or set EMSCRIPTEN to its emscripten directory")
+        });
+    let toolchain_file = root.join("cmake/Modules/Platform/Emscripten.cmake");
+    if !toolchain_file.exists() {
+        panic!(
+            "Emscripten CMake toolchain file not found at {}",
+            toolchain_file.display()
+        );
+    }
+    let cache = env::var_os("EM_CACHE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("cache"));
+    Some(EmscriptenTarget {
+        toolchain_file,
+        sysroot: cache.join("sysroot"),
+    })
+}
+
+/// A path in CMake's spelling, with forward slashes
+fn cmake_path(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
+
+/// Absolute path of a directory for linker arguments
+///
+/// Windows canonical paths are verbatim (`\\?\C:\...`), which link.exe doesn't understand, so
+/// the prefix is dropped.
+fn absolute_dir(dir: &Path) -> PathBuf {
+    let absolute = dir.canonicalize().expect("Failed to get absolute path");
+    match absolute
+        .to_str()
+        .and_then(|path| path.strip_prefix(r"\\?\"))
+    {
+        Some(path) if !path.starts_with("UNC") => PathBuf::from(path),
+        _ => absolute,
+    }
+}
+
+/// Whether `program --version` runs
+fn has_program(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// CMake generator: `EI_CMAKE_GENERATOR`, else Ninja if it's installed and preferred or there is
+/// no `make`, else Unix Makefiles if there is a `make`
+///
+/// `None` leaves the choice to CMake, which is the newest Visual Studio on Windows.
+fn cmake_generator(prefer_ninja: bool) -> Option<String> {
+    println!("cargo:rerun-if-env-changed=EI_CMAKE_GENERATOR");
+    if let Some(generator) = env::var("EI_CMAKE_GENERATOR")
+        .ok()
+        .filter(|generator| !generator.is_empty())
+    {
+        return Some(generator);
+    }
+    let has_make = !prefer_ninja && has_program("make");
+    if !has_make && has_program("ninja") {
+        Some("Ninja".to_string())
+    } else if has_make {
+        Some("Unix Makefiles".to_string())
+    } else {
+        None
+    }
+}
+
+/// Drop the CMake cache of `build_dir` if it was configured with another generator, which CMake
+/// refuses to switch in place
+fn clear_cmake_cache_of_other_generator(build_dir: &Path, generator: Option<&str>) {
+    let Ok(cache) = fs::read_to_string(build_dir.join("CMakeCache.txt")) else {
+        return;
+    };
+    let Some(cached) = cache
+        .lines()
+        .find_map(|line| line.strip_prefix("CMAKE_GENERATOR:INTERNAL="))
+    else {
+        return;
+    };
+    let matches = match generator {
+        Some(generator) => cached == generator,
+        None => cached.starts_with("Visual Studio"),
+    };
+    if !matches {
+        println!(
+            "cargo:warning=CMake generator changed from {} to {}, configuring from scratch",
+            cached,
+            generator.unwrap_or("CMake's default")
+        );
+        let _ = fs::remove_file(build_dir.join("CMakeCache.txt"));
+        let _ = fs::remove_dir_all(build_dir.join("CMakeFiles"));
+    }
+}
+
+/// Configure and build a CMake project in `build_dir`
+///
+/// The build goes through `cmake --build --parallel`, which drives Make, Ninja or MSBuild.
+/// Windows MSVC builds get the compiler's environment and their own generator choice (see
+/// [`MsvcTarget`]); other targets use `EI_CMAKE_GENERATOR`, else Unix Makefiles, else Ninja.
+fn run_cmake_build(
+    build_dir: &Path,
+    cmake_args: &[String],
+    build_type: &str,
+    msvc: Option<&MsvcTarget>,
+    what: &str,
+) {
+    let jobs = env::var("NUM_JOBS").unwrap_or_else(|_| "4".to_string());
+    let command = |program: &str| {
+        let mut command = Command::new(program);
+        command.current_dir(build_dir);
+        if let Some(msvc) = msvc {
+            command.envs(msvc.compiler_env.iter().map(|(key, value)| (key, value)));
+        }
+        command
+    };
+
+    let mut configure = command("cmake");
+    configure.args(cmake_args);
+    match msvc {
+        Some(msvc) => {
+            clear_cmake_cache_of_other_generator(build_dir, msvc.generator.as_deref());
+            configure.args(msvc.cmake_args(build_dir));
+        }
+        None => {
+            let generator = cmake_generator(false);
+            clear_cmake_cache_of_other_generator(build_dir, generator.as_deref());
+            if let Some(generator) = generator {
+                configure.arg(format!("-G{}", generator));
+            }
+        }
+    }
+    let cmake_status = configure.status().expect("Failed to run cmake configure");
+    if !cmake_status.success() {
+        panic!("CMake configuration of {} failed", what);
+    }
+
+    // `--config` only matters to multi-configuration generators such as Visual Studio
+    let build_status = command("cmake")
+        .args(["--build", ".", "--config", build_type, "--parallel", &jobs])
+        .status()
+        .expect("Failed to run cmake --build");
+    if !build_status.success() {
+        panic!("Build of {} failed", what);
+    }
+}
+
+/// Build the SDK with CMake (the default) or with the `cc` crate alone, from `EI_BUILD_SYSTEM`
+fn build_with_cc() -> bool {
+    match env::var("EI_BUILD_SYSTEM").as_deref() {
+        Ok("cc") => true,
+        Ok("cmake") | Ok("") | Err(_) => false,
+        Ok(other) => panic!("EI_BUILD_SYSTEM must be 'cmake' or 'cc', got '{}'", other),
+    }
+}
+
+/// A static SDK library built by the `cc` crate instead of CMake (`EI_BUILD_SYSTEM=cc`)
+///
+/// Compiles the same sources with the same definitions as `ffi_glue/CMakeLists.txt`. The `cc`
+/// crate picks the compiler, archiver and target flags from the Cargo target and the usual
+/// `CC`/`CXX`/`CFLAGS`/`CXXFLAGS` variables, so no CMake or make is needed.
+struct CcSdkBuild<'a> {
+    /// Model directory with the SDK and the FFI glue
+    model_dir: PathBuf,
+    /// Output directory of the objects and the library, with the TFLite model files copied in
+    build_dir: &'a Path,
+    use_full_tflite: bool,
+    sanitize: Option<&'a str>,
+    msvc: bool,
+    /// C compiler, C++ compiler and flags for the targets whose GCC toolchain the `cc` crate
+    /// doesn't pick by itself (armv7 musl, Cortex-M, ESP-IDF)
+    toolchain: Option<(String, String, String)>,
+    /// Position-independent code, off for firmware
+    pic: bool,
+    bare_metal: bool,
+    /// Espressif chip to build the ESP-NN kernels for
+    esp_nn: Option<&'a str>,
+}
+
+impl CcSdkBuild<'_> {
+    /// Compile the sources into `libedge-impulse-sdk.a` (`edge-impulse-sdk.lib` with MSVC)
+    fn compile(&self) {
+        let model = &self.model_dir;
+        let sdk = model.join("edge-impulse-sdk");
+        let mut files = Vec::new();
+        list_files(&model.join("tflite-model"), &mut files);
+        list_files(&model.join("model-parameters"), &mut files);
+        let mut cpp_sources: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|file| has_extension(file, &["cpp"]))
+            .collect();
+        let mut files = Vec::new();
+        list_files(&sdk, &mut files);
+        let tensorflow = sdk.join("tensorflow");
+        cpp_sources.extend(files.into_iter().filter(|file| {
+            if has_extension(file, &["cpp"]) {
+                return true;
+            }
+            if !has_extension(file, &["cc"]) || !file.starts_with(&tensorflow) {
+                return false;
+            }
+            // Full TensorFlow Lite is prebuilt, only Edge Impulse's kernels are compiled
+            !self.use_full_tflite
+                || file.ends_with("lite/micro/kernels/detection_postprocess.cc")
+                || file.starts_with(tensorflow.join("lite/kernels/custom"))
+        }));
+        let mut c_sources = Vec::new();
+        for name in [
+            "edge_impulse_c_api.cpp",
+            "tflite_detection_postprocess_wrapper.cc",
+        ]
+        .into_iter()
+        .map(String::from)
+        .chain(
+            custom_ffi_glue_names(&["c", "cc", "cpp"])
+                .into_iter()
+                .filter(|source| !FFI_GLUE_FILES.contains(&source.as_str())),
+        ) {
+            let source = model.join(&name);
+            if has_extension(&source, &["c"]) {
+                c_sources.push(source);
+            } else {
+                cpp_sources.push(source);
+            }
+        }
+        if self.use_full_tflite {
+            let implementation = model.join("../ffi_glue/tflite_full_impl.cpp");
+            if implementation.exists() {
+                cpp_sources.push(implementation);
+            }
+        }
+        let esp_nn = sdk.join("porting/espressif/ESP-NN");
+        if let Some(chip) = self.esp_nn {
+            if !esp_nn.exists() {
+                panic!(
+                    "ESP-NN not found in {}, export the model with a newer Edge Impulse SDK",
+                    esp_nn.display()
+                );
+            }
+            let mut files = Vec::new();
+            list_files(&esp_nn.join("src"), &mut files);
+            // Assembly kernels for the ESP32-S3's SIMD instructions
+            let extensions: &[&str] = if chip == "esp32s3" {
+                &["c", "S"]
+            } else {
+                &["c"]
+            };
+            c_sources.extend(
+                files
+                    .into_iter()
+                    .filter(|file| has_extension(file, extensions)),
+            );
+        }
+        cpp_sources.sort();
+        cpp_sources.dedup();
+        c_sources.sort();
+
+        let objects = if c_sources.is_empty() {
+            Vec::new()
+        } else {
+            self.build(false).files(&c_sources).compile_intermediates()
+        };
+        println!(
+            "cargo:info=Compiling {} SDK sources with the cc crate",
+            cpp_sources.len() + c_sources.len()
+        );
+        self.build(true)
+            .files(&cpp_sources)
+            .objects(&objects)
+            .compile("edge-impulse-sdk");
+    }
+
+    /// Compiler settings shared by the C and C++ sources
+    fn build(&self, cpp: bool) -> cc::Build {
+        let model = &self.model_dir;
+        let mut build = cc::Build::new();
+        build
+            .cpp(cpp)
+            .out_dir(self.build_dir)
+            .cargo_metadata(false)
+            .cargo_warnings(false)
+            .warnings(false)
+            .pic(self.pic)
+            .define("EIDSP_SIGNAL_C_FN_POINTER", "0")
+            .define("EI_C_LINKAGE", "1")
+            .define("INCBIN_SILENCE_BITCODE_WARNING", None)
+            .define("NDEBUG", None)
+            .define(
+                "EI_FFI_MODEL_HASH",
+                format!("{:#018x}ULL", model_hash()).as_str(),
+            );
+        if cpp {
+            build.std("c++17");
+        }
+        for dir in [
+            "",
+            "edge-impulse-sdk",
+            "model-parameters",
+            "tflite-model",
+            "edge-impulse-sdk/tensorflow",
+            "edge-impulse-sdk/tensorflow/lite",
+            "edge-impulse-sdk/tensorflow/lite/micro",
+            "edge-impulse-sdk/tensorflow/lite/micro/kernels",
+            "..",
+            "../tensorflow-lite",
+        ] {
+            build.include(model.join(dir));
+        }
+        build
+            .include(self.build_dir)
+            .include(self.build_dir.join("tflite-model"));
+
+        if let Some((cc, cxx, flags)) = &self.toolchain {
+            build.compiler(if cpp { cxx } else { cc });
+            for flag in flags.split_whitespace() {
+                build.flag(flag);
+            }
+        }
+        if self.use_full_tflite {
+            for define in [
+                "EI_CLASSIFIER_USE_FULL_TFLITE",
+                "TFLITE_KERNEL_USE_XNNPACK",
+                "TFLITE_USE_XNNPACK",
+                "EI_CLASSIFIER_ENABLE_DETECTION_POSTPROCESS_OP",
+            ] {
+                build.define(define, "1");
+            }
+            build.define("DISABLEFLOAT16", None);
+        }
+        if self.bare_metal {
+            build.define("EI_FFI_BARE_METAL", None);
+        }
+        if let Some(chip) = self.esp_nn {
+            let esp_nn = model.join("edge-impulse-sdk/porting/espressif/ESP-NN");
+            build
+                .include(esp_nn.join("include"))
+                .include(esp_nn.join("src/common"))
+                .define("EI_PORTING_ESPRESSIF", "0")
+                .define("EI_CLASSIFIER_TFLITE_ENABLE_ESP_NN", "1");
+            if chip == "esp32s3" {
+                build
+                    .define("EI_CLASSIFIER_TFLITE_ENABLE_ESP_NN_S3", "1")
+                    .define("CONFIG_IDF_TARGET_ESP32S3", "1");
+            } else {
+                build.define("CONFIG_IDF_TARGET_ESP32", "1");
+            }
+        }
+
+        if self.msvc {
+            build
+                .opt_level(2)
+                .debug(false)
+                .define("_CRT_SECURE_NO_WARNINGS", None)
+                .define("NOMINMAX", None);
+            for flag in ["/fp:fast", "/utf-8", "/bigobj", "/Zc:__cplusplus"] {
+                build.flag(flag);
+            }
+        } else if let Some(sanitize) = self.sanitize {
+            build.opt_level(1).debug(true);
+            build.flag(format!("-fsanitize={}", sanitize));
+            for flag in [
+                "-fno-omit-frame-pointer",
+                "-fno-sanitize-recover=all",
+                "-fno-lto",
+            ] {
+                build.flag(flag);
+            }
+        } else {
+            build.opt_level(3).debug(false);
+            for flag in ["-fno-lto", "-ffast-math", "-funroll-loops"] {
+                build.flag(flag);
+            }
+        }
+        if !self.msvc {
+            build.flag("-fvisibility=hidden");
+        }
+        build
+    }
+}
+
+/// Whether `path` has one of `extensions`
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext))
+}
+
+/// Pick a binutils program (`nm`, `objcopy`, `ld`, `ar`, `lipo`) for the target
+///
+/// `EI_NM`, `EI_OBJCOPY`, `EI_LD`, `EI_AR` and `EI_LIPO` override the defaults. GNU objcopy
+/// cannot rewrite Mach-O objects, so the LLVM tools are used on Apple targets, along with the
+/// system `ld`/`ar`/`lipo`.
+fn binutil(tool: &str, target_os: &str) -> String {
+    if let Ok(path) = env::var(format!("EI_{}", tool.to_uppercase())) {
+        return path;
+    }
+    if target_os == "macos" || target_os == "ios" {
+        match tool {
+            "ld" | "ar" | "lipo" => tool.to_string(),
+            _ => format!("llvm-{}", tool),
+        }
+    } else if aarch64_linux_cross() {
+        format!("aarch64-linux-gnu-{}", tool)
+    } else if let Some(musl) = armv7_musl_target() {
+        format!("{}-{}", musl.toolchain, tool)
+    } else if cortex_m_target().is_some() {
+        format!("{}-{}", CORTEX_M_TOOLCHAIN, tool)
+    } else if let Some(esp_idf) = esp_idf_target() {
+        format!("{}-{}", esp_idf.toolchain(), tool)
+    } else {
+        tool.to_string()
+    }
+}
+
+/// Architectures of a universal macOS build, in `-arch` spelling, with their full TensorFlow Lite
+/// platform
+const MACOS_UNIVERSAL_ARCHS: [(&str, &str); 2] = [("arm64", "mac-arm64"), ("x86_64", "mac-x86_64")];
+
+/// Merge single-architecture macOS libraries into one universal library with `lipo`
+fn create_universal_library(slices: &[PathBuf], output: &Path) {
+    let lipo = binutil("lipo", "macos");
+    let status = Command::new(&lipo)
+        .arg("-create")
+        .args(slices)
+        .arg("-output")
+        .arg(output)
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run {}: {}", lipo, e));
+    if !status.success() {
+        panic!("{} failed to create {}", lipo, output.display());
+    }
+    println!("cargo:info=Created universal library {}", output.display());
+}
+
+/// Prefix every global symbol defined in the static SDK library
+///
+/// This lets the crate's copy of the SDK coexist with another Edge Impulse export linked into
+/// the same application. The `ei_ffi_*` entry points called from Rust keep their names. Symbols
+/// that already carry the prefix are left alone, so running this twice is harmless.
+fn prefix_library_symbols(library: &Path, prefix: &str, target_os: &str) {
+    let renamed = rename_library_symbols(library, target_os, |name| {
+        (!name.starts_with("ei_ffi_") && !name.starts_with(prefix))
+            .then(|| format!("{}{}", prefix, name))
+    });
+    if renamed == 0 {
+        println!("cargo:info=No symbols to prefix in {}", library.display());
+    } else {
+        println!(
+            "cargo:info=Prefixed {} symbols in {} with '{}'",
+            renamed,
+            library.display(),
+            prefix
+        );
+    }
+}
+
+/// Rename the `ei_ffi_*` entry points of an extra model's library to `ei_ffi_<name>_*`
+///
+/// The library must be freshly built and isolated, so that the entry points are its only
+/// global symbols and none of them is renamed yet.
+fn rename_entry_points(library: &Path, name: &str, target_os: &str) {
+    let prefix = format!("ei_ffi_{}_", name);
+    let renamed = rename_library_symbols(library, target_os, |symbol| {
+        let entry_point = symbol.strip_prefix("ei_ffi_")?;
+        Some(format!("{}{}", prefix, entry_point))
+    });
+    println!(
+        "cargo:info=Renamed {} entry points in {} to {}*",
+        renamed,
+        library.display(),
+        prefix
+    );
+}
+
+/// Rename global symbols defined in a static library with objcopy
+///
+/// `rename` gets each symbol name (without the Mach-O underscore) and returns its new name, or
+/// `None` to keep it. Returns the number of renamed symbols.
+fn rename_library_symbols<F>(library: &Path, target_os: &str, rename: F) -> usize
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let nm = binutil("nm", target_os);
+    let objcopy = binutil("objcopy", target_os);
+    // Mach-O prepends an underscore to every C symbol name
+    let underscore = if target_os == "macos" || target_os == "ios" {
+        "_"
+    } else {
+        ""
+    };
+
+    let output = Command::new(&nm)
+        .arg("-g")
+        .arg("--defined-only")
+        .arg(library)
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to run {} (set EI_NM to override): {}", nm, e));
+    if !output.status.success() {
+        panic!(
+            "{} failed on {}: {}",
+            nm,
+            library.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let mut renames = std::collections::BTreeMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // "<address> <type> <name>"; archive member headers and blank lines have fewer fields
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            continue;
+        }
+        let symbol = fields[fields.len() - 1];
+        let Some(name) = symbol.strip_prefix(underscore) else {
+            continue;
+        };
+        if let Some(new_name) = rename(name) {
+            renames.insert(symbol.to_string(), format!("{}{}", underscore, new_name));
+        }
+    }
+
+    if renames.is_empty() {
+        return 0;
+    }
+
+    let map_path = library.with_file_name("symbol-renames.map");
+    let map: String = renames
+        .iter()
+        .map(|(old, new)| format!("{} {}\n", old, new))
+        .collect();
+    fs::write(&map_path, map).expect("Failed to write symbol rename map");
+
+    let status = Command::new(&objcopy)
+        .arg(format!("--redefine-syms={}", map_path.display()))
+        .arg(library)
+        .status()
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to run {} (set EI_OBJCOPY to override): {}",
+                objcopy, e
+            )
+        });
+    if !status.success() {
+        panic!(
+            "{} failed to rename symbols in {}",
+            objcopy,
+            library.display()
+        );
+    }
+    renames.len()
+}
+
+/// Hide every symbol of the static SDK library except the `ei_ffi_*` entry points
+///
+/// The library and the prebuilt archives it depends on are partially linked (`ld -r`) into one
+/// relocatable object in which all other symbols are local. Its TensorFlow Lite copy then can't
+/// clash with OpenCV's or the application's own TensorFlow Lite at link time.
+fn isolate_library_symbols(library: &Path, extra_archives: &[PathBuf], target_os: &str) {
+    let apple = target_os == "macos" || target_os == "ios";
+    let ld = binutil("ld", target_os);
+    let object = library.with_file_name("edge-impulse-sdk-isolated.o");
+
+    let mut link = Command::new(&ld);
+    link.arg("-r").arg("-o").arg(&object);
+    if apple {
+        // ld64 makes every symbol that isn't exported local when partially linking
+        link.arg("-force_load")
+            .arg(library)
+            .args(extra_archives)
+            .arg("-exported_symbol")
+            .arg("_ei_ffi_*");
+    } else {
+        link.arg("--whole-archive")
+            .arg(library)
+            .arg("--no-whole-archive")
+            .args(extra_archives);
+    }
+    let status = link
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run {} (set EI_LD to override): {}", ld, e));
+    if !status.success() {
+        panic!("{} failed to partially link {}", ld, library.display());
+    }
+
+    if !apple {
+        let objcopy = binutil("objcopy", target_os);
+        let status = Command::new(&objcopy)
+            .arg("--wildcard")
+            .arg("--keep-global-symbol=ei_ffi_*")
+            .arg(&object)
+            .status()
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Failed to run {} (set EI_OBJCOPY to override): {}",
+                    objcopy, e
+                )
+            });
+        if !status.success() {
+            panic!(
+                "{} failed to localize symbols in {}",
+                objcopy,
+                object.display()
+            );
+        }
+    }
+
+    // Replace the library with an archive holding just the isolated object
+    let ar = binutil("ar", target_os);
+    fs::remove_file(library).expect("Failed to remove SDK library before isolating symbols");
+    let status = Command::new(&ar)
+        .arg("rcs")
+        .arg(library)
+        .arg(&object)
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run {} (set EI_AR to override): {}", ar, e));
+    if !status.success() {
+        panic!("{} failed to create {}", ar, library.display());
+    }
+    println!(
+        "cargo:info=Isolated symbols in {} (only ei_ffi_* remain global)",
+        library.display()
+    );
+}
+
+/// Copy the model's TFLite files (or compiled model sources) and headers to the build directory
+///
+/// INCBIN embeds the `.tflite` files relative to the build directory, so they have to be next
+/// to the build rather than in the model directory.
+fn copy_tflite_model_files(tflite_model_dir: &Path, build_dir: &Path) {
+    let tflite_build_dir = build_dir.join("tflite-model");
+
+    // Debug: Print the tflite-model directory contents
+    println!(
+        "cargo:info=DEBUG: Checking tflite-model directory: {}",
+        tflite_model_dir.display()
+    );
+    if !tflite_model_dir.exists() {
+        println!("cargo:error=DEBUG: tflite-model directory does not exist!");
+        std::process::exit(1);
+    }
+
+    // List all files in the directory
+    match std::fs::read_dir(tflite_model_dir) {
+        Ok(entries) => {
+            println!("cargo:info=DEBUG: Contents of tflite-model directory:");
+            for entry in entries {
+                match entry {
+                    Ok(entry) => {
+                        let file_name = entry.file_name();
+                        let file_name_str = file_name.to_string_lossy();
+                        let file_type = if entry.file_type().unwrap().is_dir() {
+                            "DIR"
+                        } else {
+                            "FILE"
+                        };
+                        println!("cargo:info=DEBUG:   {}: {}", file_type, file_name_str);
+                    }
+                    Err(e) => {
+                        println!("cargo:warning=DEBUG: Failed to read directory entry: {}", e)
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            println!(
+                "cargo:error=DEBUG: Failed to read tflite-model directory: {}",
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // Check if this is a raw TFLite model or a compiled model
+    let mut tflite_files: Vec<_> = std::fs::read_dir(tflite_model_dir)
+        .expect("Failed to read tflite-model directory")
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name_os = entry.file_name();
+            let file_name = file_name_os.to_str()?;
+            println!("cargo:info=DEBUG: Checking file: {} (ends_with .tflite: {}, starts_with tflite_learn_: {})",
+                file_name,
+                file_name.ends_with(".tflite"),
+                file_name.starts_with("tflite_learn_"));
+            if file_name.ends_with(".tflite") && file_name.starts_with("tflite_learn_") {
+                Some((entry.path(), file_name.to_string(), "raw"))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // If no raw TFLite files found, check for compiled model files
+    if tflite_files.is_empty() {
+        let compiled_files: Vec<_> = std::fs::read_dir(tflite_model_dir)
+            .expect("Failed to read tflite-model directory")
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let file_name_os = entry.file_name();
+                let file_name = file_name_os.to_str()?;
+                println!("cargo:info=DEBUG: Checking compiled file: {} (ends_with .cpp: {}, starts_with tflite_learn_: {})",
+                    file_name,
+                    file_name.ends_with(".cpp"),
+                    file_name.starts_with("tflite_learn_"));
+                if file_name.ends_with(".cpp") && file_name.starts_with("tflite_learn_") {
+                    let base_name = file_name.trim_end_matches("_compiled.cpp");
+                    Some((entry.path(), format!("{}.tflite", base_name), "compiled"))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if compiled_files.is_empty() {
+            println!("cargo:error=No tflite_learn_*.tflite or tflite_learn_*_compiled.cpp files found in model/tflite-model/");
+            std::process::exit(1);
+        }
+
+        println!(
+            "cargo:info=Detected compiled model with {} compiled files",
+            compiled_files.len()
+        );
+        tflite_files = compiled_files;
+    } else {
+        println!(
+            "cargo:info=Detected raw TFLite model with {} TFLite files",
+            tflite_files.len()
+        );
+    }
+
+    std::fs::create_dir_all(&tflite_build_dir).expect("Failed to create tflite-model build dir");
+
+    // Copy all model files and their corresponding headers
+    for (source_path, tflite_filename, model_type) in &tflite_files {
+        let base_name = tflite_filename.trim_end_matches(".tflite");
+        let header_filename = if *model_type == "compiled" {
+            format!("{}_compiled.h", base_name)
+        } else {
+            format!("{}.h", base_name)
+        };
+        let header_source = tflite_model_dir.join(&header_filename);
+
+        if !header_source.exists() {
+            println!(
+                "cargo:error=Header file {} not found for {} file {}",
+                header_filename, model_type, tflite_filename
+            );
+            std::process::exit(1);
+        }
+
+        let tflite_dest = tflite_build_dir.join(tflite_filename);
+        let header_dest = tflite_build_dir.join(&header_filename);
+
+        if *model_type == "raw" {
+            // For raw TFLite models, copy the actual .tflite file
+            if tflite_dest.exists() {
+                std::fs::remove_file(&tflite_dest).expect("Failed to remove old TFLite file");
+            }
+            std::fs::copy(source_path, &tflite_dest)
+                .expect("Failed to copy TFLite file to build directory");
+        } else {
+            // For compiled models, create a dummy .tflite file (the actual model is in the .cpp file)
+            if tflite_dest.exists() {
+                std::fs::remove_file(&tflite_dest).expect("Failed to remove old TFLite file");
+            }
+            // Create an empty file as placeholder - the actual model is in the compiled .cpp
+            std::fs::write(&tflite_dest, "").expect("Failed to create dummy TFLite file");
+            println!(
+                "cargo:info=Created dummy TFLite file for compiled model: {}",
+                tflite_filename
+            );
+        }
+
+        // Copy header file
+        if header_dest.exists() {
+            std::fs::remove_file(&header_dest).expect("Failed to remove old header file");
+        }
+        std::fs::copy(&header_source, &header_dest)
+            .expect("Failed to copy header file to build directory");
+
+        println!(
+            "cargo:info=Copied {} model files to build directory: {} -> {}",
+            model_type,
+            tflite_filename,
+            tflite_dest.display()
+        );
+
+        // For compiled models, also copy the .cpp files
+        if *model_type == "compiled" {
+            let cpp_filename = format!("{}_compiled.cpp", base_name);
+            let cpp_source = tflite_model_dir.join(&cpp_filename);
+            let cpp_dest = tflite_build_dir.join(&cpp_filename);
+
+            if cpp_source.exists() {
+                if cpp_dest.exists() {
+                    std::fs::remove_file(&cpp_dest).expect("Failed to remove old CPP file");
+                }
+                std::fs::copy(&cpp_source, &cpp_dest)
+                    .expect("Failed to copy CPP file to build directory");
+                println!(
+                    "cargo:info=Copied compiled CPP file: {} -> {}",
+                    cpp_filename,
+                    cpp_dest.display()
+                );
+            } else {
+                println!(
+                    "cargo:warning=CPP file {} not found for compiled model",
+                    cpp_filename
+                );
+            }
+        }
+    }
+
+    // Fix the header file paths in all copied header files
+    fix_header_file_path(build_dir);
+
+    // Also overwrite the original headers to ensure C++ build uses the correct paths
+    for (_, tflite_filename, model_type) in &tflite_files {
+        let base_name = tflite_filename.trim_end_matches(".tflite");
+        let header_filename = if *model_type == "compiled" {
+            format!("{}_compiled.h", base_name)
+        } else {
+            format!("{}.h", base_name)
+        };
+        let header_source = tflite_model_dir.join(&header_filename);
+        let header_dest = tflite_build_dir.join(&header_filename);
+        std::fs::copy(&header_dest, &header_source)
+            .expect("Failed to overwrite original header file with fixed path");
+    }
+}
+
+/// Fix the header file path in the generated header file to point to the correct TFLite file location
+fn fix_header_file_path(build_dir: &Path) {
+    let tflite_model_dir = build_dir.join("tflite-model");
+
+    // Find the actual TFLite file (should be named tflite_learn_*.tflite)
+    let tflite_files: Vec<_> = std::fs::read_dir(&tflite_model_dir)
+        .expect("Failed to read tflite-model directory")
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name_os = entry.file_name();
+            let file_name = file_name_os.to_str()?;
+            if file_name.ends_with(".tflite") && file_name.starts_with("tflite_learn_") {
+                Some((entry.path(), file_name.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if tflite_files.is_empty() {
+        println!("cargo:warning=No tflite_learn_*.tflite file found in build directory");
+        return;
+    }
+
+    // Fix all TFLite files and their corresponding headers
+    for (tflite_file, tflite_filename) in &tflite_files {
+        let base_name = tflite_filename.trim_end_matches(".tflite");
+        let header_filename = format!("{}.h", base_name);
+        let header_file = tflite_model_dir.join(&header_filename);
+
+        if header_file.exists() && tflite_file.exists() {
+            let content =
+                std::fs::read_to_string(&header_file).expect("Failed to read header file");
+
+            // Create the INCBIN macro name from the base name
+            let incbin_name = format!("incbin_{}", base_name);
+
+            // Use relative path instead of absolute path to avoid Docker container path issues
+            let relative_path = format!("tflite-model/{}", tflite_filename);
+
+            // Patch any INCBIN macro to use the relative path
+            let old_pattern = format!(
+                "INCBIN({}, \"tflite-model/{}\");",
+                incbin_name, tflite_filename
+            );
+            let new_pattern = format!("INCBIN({}, \"{}\");", incbin_name, relative_path);
+
+            let fixed_content = content.replace(&old_pattern, &new_pattern);
+            std::fs::write(&header_file, fixed_content).expect("Failed to write fixed header file");
+            println!(
+                "cargo:info=Fixed header file path in {} for {}",
+                header_file.display(),
+                tflite_filename
+            );
+        } else {
+            println!(
+                "cargo:warning=Header file or tflite file not found: {} or {}",
+                header_file.display(),
+                tflite_file.display()
+            );
+        }
+    }
+}
+
+/// Path of a generated source file, included by src/lib.rs
+///
+/// Generated files go to OUT_DIR rather than src/, so read-only checkouts, `cargo publish`
+/// verification and builds of several models from one checkout all work.
+fn generated_path(name: &str) -> PathBuf {
+    PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set")).join(name)
+}
+
+fn extract_and_write_model_metadata() {
+    write_model_metadata(Path::new("model"), &generated_path("model_metadata.rs"));
+}
+
+/// Generate the metadata module of the model in `model_dir`
+fn write_model_metadata(model_dir: &Path, out_path: &Path) {
+    use std::collections::HashMap;
+    use std::fs;
+    let header_path = model_dir.join("model-parameters/model_metadata.h");
+    let header = fs::read_to_string(header_path).expect("Failed to read model_metadata.h");
+
+    let mut out = String::from("// This file is @generated by build.rs. Do not edit manually.\n");
+    out.push_str("// Model metadata constants extracted from model_metadata.h\n\n");
+
+    let mut seen = HashMap::new();
+    let mut raw_defs = Vec::new();
 
     // First pass: collect all constants and their raw values
     for line in header.lines() {
@@ -730,6 +2418,24 @@ fn extract_and_write_model_metadata() {
             }
             continue;
         }
+        // Quantization parameters keep the same type whatever their value (e.g. a zero point of 0)
+        if let Some(ty) = quantization_constant_type(name) {
+            let resolved = resolve(val, &emitted).unwrap_or_else(|| val.clone());
+            match resolved.parse::<f32>() {
+                Ok(num) if ty == "f32" => {
+                    out.push_str(&format!("pub const {}: f32 = {:?};\n", name, num));
+                }
+                Ok(num) => {
+                    out.push_str(&format!("pub const {}: i32 = {};\n", name, num as i32));
+                }
+                Err(_) => {
+                    out.push_str(&format!("// Could not resolve: {} = {}\n", name, val));
+                    continue;
+                }
+            }
+            emitted.insert(name.clone(), resolved);
+            continue;
+        }
         // String constants
         if val.starts_with('"') && val.ends_with('"') {
             out.push_str(&format!(
@@ -852,10 +2558,369 @@ fn extract_and_write_model_metadata() {
     if !emitted.contains_key("EI_CLASSIFIER_LAST_LAYER_YOLOV5") {
         out.push_str("pub const EI_CLASSIFIER_LAST_LAYER_YOLOV5: usize = 0;\n");
     }
+    if !emitted.contains_key("EI_CLASSIFIER_OBJECT_TRACKING_ENABLED") {
+        out.push_str("pub const EI_CLASSIFIER_OBJECT_TRACKING_ENABLED: usize = 0;\n");
+    }
+    if !emitted.contains_key("EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW") {
+        out.push_str("pub const EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW: usize = 4;\n");
+    }
+    // Project info is missing from hand-written or very old headers
+    for (name, default) in [
+        ("EI_CLASSIFIER_PROJECT_ID", "usize = 0"),
+        ("EI_CLASSIFIER_PROJECT_OWNER", "&str = \"\""),
+        ("EI_CLASSIFIER_PROJECT_NAME", "&str = \"\""),
+        ("EI_CLASSIFIER_PROJECT_DEPLOY_VERSION", "usize = 0"),
+    ] {
+        if !emitted.contains_key(name) {
+            out.push_str(&format!("pub const {}: {};\n", name, default));
+        }
+    }
+    // License recorded by ei-fetch, only trusted if it belongs to the same project
+    let project_info = project_info(&model_dir.join("ei-project.txt"));
+    let project_id = emitted
+        .get("EI_CLASSIFIER_PROJECT_ID")
+        .map(String::as_str)
+        .unwrap_or("0");
+    let same_project = project_info.get("id").map(String::as_str) == Some(project_id);
+    let license = project_info.get("license").filter(|_| same_project);
+    out.push_str(
+        "\n/// License of the Studio project, as recorded by ei-fetch (`None` if unknown)\n",
+    );
+    out.push_str(&format!(
+        "pub const EI_CLASSIFIER_PROJECT_LICENSE: Option<&str> = {};\n",
+        match license {
+            Some(license) => format!("Some({})", license),
+            None => "None".to_string(),
+        }
+    ));
+
+    // Newer SDKs read quantization from the model at runtime; default to a float model
+    for tensor in ["INPUT", "OUTPUT"] {
+        for (field, default) in [
+            ("DATATYPE", "usize = 1"),
+            ("QUANTIZED", "usize = 0"),
+            ("SCALE", "f32 = 1.0"),
+            ("ZEROPOINT", "i32 = 0"),
+        ] {
+            let name = format!("EI_CLASSIFIER_TFLITE_{}_{}", tensor, field);
+            if !emitted.contains_key(&name) {
+                out.push_str(&format!("pub const {}: {};\n", name, default));
+            }
+        }
+    }
+
+    // Quantization of the input/output tensors, with stable types whatever the header's values are
+    for tensor in ["INPUT", "OUTPUT"] {
+        let raw = |field: &str| {
+            let name = format!("EI_CLASSIFIER_TFLITE_{}_{}", tensor, field);
+            resolve(&name, &emitted)
+        };
+        let quantized = raw("QUANTIZED").is_some_and(|v| v.parse::<i32>().is_ok_and(|v| v != 0));
+        let scale = raw("SCALE")
+            .and_then(|v| v.parse::<f32>().ok())
+            .filter(|_| quantized)
+            .unwrap_or(1.0);
+        let zero_point = raw("ZEROPOINT")
+            .and_then(|v| v.parse::<f32>().ok())
+            .filter(|_| quantized)
+            .unwrap_or(0.0) as i32;
+        let tensor_name = tensor.to_lowercase();
+        out.push_str(&format!(
+            "\n/// Whether the model's {} tensor is quantized\n",
+            tensor_name
+        ));
+        out.push_str(&format!(
+            "pub const EI_CLASSIFIER_{}_QUANTIZED: bool = {};\n",
+            tensor, quantized
+        ));
+        out.push_str(&format!(
+            "/// Scale of the {} tensor (1.0 when it isn't quantized)\n",
+            tensor_name
+        ));
+        out.push_str(&format!(
+            "pub const EI_CLASSIFIER_{}_SCALE: f32 = {:?};\n",
+            tensor, scale
+        ));
+        out.push_str(&format!(
+            "/// Zero point of the {} tensor (0 when it isn't quantized)\n",
+            tensor_name
+        ));
+        out.push_str(&format!(
+            "pub const EI_CLASSIFIER_{}_ZERO_POINT: i32 = {};\n",
+            tensor, zero_point
+        ));
+    }
+
+    // Class labels from model_variables.h
+    let variables = fs::read_to_string(model_dir.join("model-parameters/model_variables.h"))
+        .unwrap_or_default();
+    let labels = extract_labels(&variables);
+    if labels.is_empty() {
+        println!("cargo:warning=No class labels found in model_variables.h");
+    }
+    out.push_str("\n/// Class labels, in the order of the model's classification output\n");
+    out.push_str("pub const EI_CLASSIFIER_LABELS: &[&str] = &[");
+    out.push_str(
+        &labels
+            .iter()
+            .map(|label| format!("\"{}\"", label))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    out.push_str("];\n");
+
+    // Names of the raw input axes, e.g. "accX + accY + accZ" for sensor fusion models
+    let axes: Vec<String> = emitted
+        .get("EI_CLASSIFIER_FUSION_AXES_STRING")
+        .map(|axes| {
+            axes.trim_matches('"')
+                .split('+')
+                .map(|axis| axis.trim().to_string())
+                .filter(|axis| !axis.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    out.push_str("\n/// Names of the raw input axes, in the order the model expects them\n");
+    out.push_str("pub const EI_CLASSIFIER_AXIS_NAMES: &[&str] = &[");
+    out.push_str(
+        &axes
+            .iter()
+            .map(|axis| format!("{:?}", axis))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    out.push_str("];\n");
+
+    // DSP blocks and the input scaling the first one expects
+    let dsp_blocks = extract_dsp_block_types(&variables);
+    let is_microphone = matches!(
+        (
+            emitted.get("EI_CLASSIFIER_SENSOR"),
+            emitted.get("EI_CLASSIFIER_SENSOR_MICROPHONE"),
+        ),
+        (Some(sensor), Some(microphone)) if sensor == microphone
+    );
+    out.push_str("\n/// Types of the impulse's DSP blocks (e.g. \"image\", \"mfcc\"), in order\n");
+    out.push_str("pub const EI_CLASSIFIER_DSP_BLOCKS: &[&str] = &[");
+    out.push_str(
+        &dsp_blocks
+            .iter()
+            .map(|block| format!("{:?}", block))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    out.push_str("];\n");
+    out.push_str(
+        "\n/// Scale of the raw features the first DSP block expects: \"packed_rgb\", \"raw_i16\" or \"raw\"\n",
+    );
+    out.push_str(&format!(
+        "pub const EI_CLASSIFIER_INPUT_SCALING: &str = {:?};\n",
+        input_scaling(dsp_blocks.first().map(String::as_str), is_microphone)
+    ));
+
+    // Regression blocks output a single value; SDKs that don't name the classification mode
+    // in the learning block config label it "value"
+    let is_regression = regex::Regex::new(
+        r"classification_mode\s*=\s*EI_CLASSIFIER_CLASSIFICATION_MODE_REGRESSION\b",
+    )
+    .unwrap()
+    .is_match(&variables)
+        || (labels == ["value"]
+            && emitted
+                .get("EI_CLASSIFIER_OBJECT_DETECTION")
+                .is_none_or(|value| value == "0"));
+    out.push_str("\n/// Whether the model's learning block is a regression block with a single output value\n");
+    out.push_str(&format!(
+        "pub const EI_CLASSIFIER_IS_REGRESSION: bool = {};\n",
+        is_regression
+    ));
+
+    // EI_CLASSIFIER_HAS_VISUAL_ANOMALY is patched to 1 for every model, so detect visual
+    // anomaly (FOMO-AD) models from the anomaly block type instead
+    let is_visual_anomaly = match (
+        emitted.get("EI_CLASSIFIER_HAS_ANOMALY"),
+        emitted.get("EI_ANOMALY_TYPE_VISUAL_GMM"),
+    ) {
+        (Some(has_anomaly), Some(visual_gmm)) => has_anomaly == visual_gmm,
+        _ => false,
+    };
+    out.push_str("\n/// Whether the model's anomaly block is a visual anomaly (FOMO-AD) block\n");
+    out.push_str(&format!(
+        "pub const EI_CLASSIFIER_IS_VISUAL_ANOMALY: bool = {};\n",
+        is_visual_anomaly
+    ));
+
+    out.push_str(
+        "\n/// Hash of the model this metadata was generated from, see `EI_FFI_MODEL_HASH`\n",
+    );
+    out.push_str(&format!(
+        "pub const EI_CLASSIFIER_MODEL_HASH: u64 = {:#018x};\n",
+        model_hash_of(model_dir)
+    ));
 
     fs::write(out_path, out).expect("Failed to write model_metadata.rs");
 }
 
+/// Hash of the model's parameter headers (FNV-1a, which unlike `DefaultHasher` is stable across
+/// Rust versions)
+///
+/// It's embedded in the bindings, the metadata and the native library, so that files left over
+/// from a previous model are caught at compile time (bindings vs. metadata) or when the
+/// classifier starts (library vs. metadata) instead of silently misreading results.
+fn model_hash() -> u64 {
+    model_hash_of(Path::new("model"))
+}
+
+/// Hash of the parameter headers of the model in `model_dir`, see [`model_hash`]
+fn model_hash_of(model_dir: &Path) -> u64 {
+    let mut data = Vec::new();
+    for file in ["model_metadata.h", "model_variables.h"] {
+        data.extend(fs::read(model_dir.join("model-parameters").join(file)).unwrap_or_default());
+    }
+    fnv1a(&data)
+}
+
+/// 64-bit FNV-1a hash
+fn fnv1a(data: &[u8]) -> u64 {
+    fnv1a_extend(0xcbf2_9ce4_8422_2325, data)
+}
+
+/// Continue an FNV-1a hash with more data
+fn fnv1a_extend(mut hash: u64, data: &[u8]) -> u64 {
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Write the dummy bindings, metadata and thresholds in place of the generated ones
+///
+/// The dummy functions are plain Rust, so nothing needs to be linked and every inference fails
+/// with `EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE`.
+fn write_dummy_model() {
+    println!("cargo:warning=Building against the dummy model (`dummy` feature or docs.rs), inference will always fail");
+    for (dummy, generated) in [
+        ("src/bindings_dummy.rs", "bindings.rs"),
+        ("src/model_metadata_dummy.rs", "model_metadata.rs"),
+        ("src/thresholds_dummy.rs", "thresholds.rs"),
+    ] {
+        println!("cargo:rerun-if-changed={}", dummy);
+        fs::copy(dummy, generated_path(generated))
+            .unwrap_or_else(|e| panic!("Failed to copy {}: {}", dummy, e));
+    }
+
+    // A stand-in extra model, so the generated `models` API type-checks and is documented
+    let dummy_dir = generated_path("models").join("dummy");
+    fs::create_dir_all(&dummy_dir).expect("Failed to create the dummy extra model directory");
+    for (dummy, generated) in [
+        ("src/bindings_dummy.rs", "bindings.rs"),
+        ("src/model_metadata_dummy.rs", "model_metadata.rs"),
+    ] {
+        fs::copy(dummy, dummy_dir.join(generated))
+            .unwrap_or_else(|e| panic!("Failed to copy {}: {}", dummy, e));
+    }
+    write_models_module(&["dummy".to_string()]);
+}
+
+/// Project info written by ei-fetch next to the model
+const PROJECT_INFO_PATH: &str = "model/ei-project.txt";
+
+/// [`MODEL_SHA256_FILE`] of the model directory
+const MODEL_SHA256_PATH: &str = "model/.ei-model-sha256";
+
+/// Read the `key = value` lines of the project info file
+///
+/// Strings are kept as the quoted, escaped Rust literals ei-fetch writes, ready to be pasted
+/// into the generated source.
+fn project_info(path: &Path) -> std::collections::HashMap<String, String> {
+    let line = regex::Regex::new(r#"^(\w+) = (\d+|".*")$"#).unwrap();
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| line.captures(l.trim_end()))
+        .map(|c| (c[1].to_string(), c[2].to_string()))
+        .collect()
+}
+
+/// Rust type of the `EI_CLASSIFIER_TFLITE_{INPUT,OUTPUT}_{SCALE,ZEROPOINT}` constants
+///
+/// The header writes these as plain numbers, so without this a zero point of `0` would become a
+/// `usize` and `-128` an `i32`, and code using them would only compile for some models.
+fn quantization_constant_type(name: &str) -> Option<&'static str> {
+    let field = name
+        .strip_prefix("EI_CLASSIFIER_TFLITE_INPUT_")
+        .or_else(|| name.strip_prefix("EI_CLASSIFIER_TFLITE_OUTPUT_"))?;
+    match field {
+        "SCALE" => Some("f32"),
+        "ZEROPOINT" => Some("i32"),
+        _ => None,
+    }
+}
+
+/// DSP block types of the impulse, from the `ei_dsp_config_<type>_t` configs in model_variables.h
+fn extract_dsp_block_types(variables: &str) -> Vec<String> {
+    let config = regex::Regex::new(r"\bei_dsp_config_(\w+?)_t\s+ei_dsp_config_").unwrap();
+    config
+        .captures_iter(variables)
+        .map(|capture| capture[1].to_string())
+        .collect()
+}
+
+/// Scale of the raw features expected by a DSP block
+///
+/// Image blocks take pixels packed as `0xRRGGBB` and audio blocks take raw 16-bit samples; all
+/// other blocks work on the values as they come from the sensor.
+fn input_scaling(dsp_block: Option<&str>, is_microphone: bool) -> &'static str {
+    match dsp_block {
+        Some("image") => "packed_rgb",
+        Some(block) if block.starts_with("mfcc") || block.starts_with("mfe") => "raw_i16",
+        Some(block) if block.starts_with("audio") => "raw_i16",
+        Some("spectrogram") if is_microphone => "raw_i16",
+        _ => "raw",
+    }
+}
+
+/// Extract the class labels from the `ei_classifier_inferencing_categories` array in model_variables.h
+///
+/// Labels are returned as the raw contents of the C string literals, which use the same
+/// escape sequences as Rust for anything that can appear in a label.
+fn extract_labels(variables: &str) -> Vec<String> {
+    let Some(start) = variables.find("ei_classifier_inferencing_categories") else {
+        return Vec::new();
+    };
+    let rest = &variables[start..];
+    let (Some(open), Some(close)) = (rest.find('{'), rest.find('}')) else {
+        return Vec::new();
+    };
+    if close < open {
+        return Vec::new();
+    }
+
+    let mut labels = Vec::new();
+    let mut chars = rest[open + 1..close].chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut label = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    label.push(c);
+                    if let Some(escaped) = chars.next() {
+                        label.push(escaped);
+                    }
+                }
+                '"' => break,
+                _ => label.push(c),
+            }
+        }
+        labels.push(label);
+    }
+    labels
+}
+
 /// Patch model metadata to always include visual anomaly detection fields
 fn patch_model_metadata_for_visual_anomaly(model_dir: &Path) {
     let metadata_header = model_dir.join("model-parameters/model_metadata.h");
@@ -909,7 +2974,7 @@ fn patch_model_for_full_tflite(model_dir: &Path, use_full_tflite: bool) {
 fn extract_and_write_thresholds() {
     use std::fs;
     let header_path = "model/model-parameters/model_variables.h";
-    let out_path = "src/thresholds.rs";
+    let out_path = generated_path("thresholds.rs");
 
     println!("cargo:info=EXTRACT_THRESHOLDS FUNCTION CALLED");
     println!(
@@ -1030,7 +3095,7 @@ impl Thresholds {
         ));
     }
 
-    fs::write(out_path, out).expect("Failed to write thresholds.rs");
+    fs::write(&out_path, out).expect("Failed to write thresholds.rs");
 }
 
 fn extract_block_id_from_config(_header: &str, config_line: &str) -> Option<usize> {
@@ -1154,6 +3219,345 @@ fn extract_threshold_from_config(header: &str, config_line: &str) -> Option<f32>
     None
 }
 
+/// Extra models from the `EI_MODEL_<NAME>` variables, as (module name, source) sorted by name
+///
+/// The source is a model directory or deployment ZIP, like `EI_MODEL`.
+fn extra_models() -> Vec<(String, String)> {
+    let mut models: Vec<(String, String)> = env::vars()
+        .filter_map(|(key, source)| {
+            let name = key.strip_prefix("EI_MODEL_")?.to_ascii_lowercase();
+            Some((name, source))
+        })
+        .collect();
+    models.sort();
+    for (name, _) in &models {
+        println!(
+            "cargo:rerun-if-env-changed=EI_MODEL_{}",
+            name.to_ascii_uppercase()
+        );
+        if !is_valid_model_name(name) {
+            panic!(
+                "EI_MODEL_{} must be named with letters, digits and underscores, starting with a letter, and not be a Rust keyword",
+                name.to_ascii_uppercase()
+            );
+        }
+    }
+    models
+}
+
+/// Whether an extra model name makes a valid module name
+fn is_valid_model_name(name: &str) -> bool {
+    const KEYWORDS: [&str; 52] = [
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+        "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+        "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+        "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type",
+        "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield", "union",
+    ];
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Stamp file in a staged extra model with the source it was copied from
+const EXTRA_MODEL_STAMP: &str = ".ei-model-source";
+
+/// Copy or extract an extra model to `OUT_DIR/models/<name>/model`, returning that directory
+///
+/// The staged copy is reused while its source is unchanged, so the SDK isn't rebuilt.
+fn stage_extra_model(name: &str, source: &str) -> PathBuf {
+    let model_dir = generated_path("models").join(name).join("model");
+    let is_zip = is_zip_model_source(source);
+    let fingerprint = if is_local_zip(source) {
+        println!("cargo:rerun-if-changed={}", source);
+        fs::read(source).map(|data| fnv1a(&data)).unwrap_or(0)
+    } else if is_zip {
+        // Downloads are only fetched again when the URL changes
+        0
+    } else {
+        println!("cargo:rerun-if-changed={}/model-parameters", source);
+        model_hash_of(Path::new(source))
+    };
+    let stamp = format!("{}\n{:016x}\n", source, fingerprint);
+    if model_dir.join("model-parameters").exists()
+        && fs::read_to_string(model_dir.join(EXTRA_MODEL_STAMP)).ok() == Some(stamp.clone())
+    {
+        return model_dir;
+    }
+
+    println!("cargo:info=Staging extra model '{}' from {}", name, source);
+    let _ = fs::remove_dir_all(&model_dir);
+    fs::create_dir_all(&model_dir).expect("Failed to create extra model directory");
+    let copied = if is_zip {
+        copy_model_from_zip(source, &model_dir)
+    } else {
+        ["edge-impulse-sdk", "model-parameters", "tflite-model"]
+            .iter()
+            .all(|dir| {
+                copy_dir_recursive(&Path::new(source).join(dir), &model_dir.join(dir))
+                    .inspect_err(|e| {
+                        println!("cargo:warning=Failed to copy {}/{}: {}", source, dir, e)
+                    })
+                    .is_ok()
+            })
+    };
+    if !copied || !model_dir.join("model-parameters").exists() {
+        panic!(
+            "EI_MODEL_{} ({}) is not an Edge Impulse C++ library deployment",
+            name.to_ascii_uppercase(),
+            source
+        );
+    }
+
+    copy_ffi_glue(model_dir.to_str().expect("OUT_DIR is not valid UTF-8"));
+    patch_model_metadata_for_visual_anomaly(&model_dir);
+    fs::write(model_dir.join(EXTRA_MODEL_STAMP), stamp).expect("Failed to write extra model stamp");
+    model_dir
+}
+
+/// Links the functions of an extra model's bindings to its renamed entry points
+#[derive(Debug)]
+struct ExtraModelLinkNames {
+    prefix: String,
+}
+
+impl bindgen::callbacks::ParseCallbacks for ExtraModelLinkNames {
+    fn generated_link_name_override(
+        &self,
+        item: bindgen::callbacks::ItemInfo<'_>,
+    ) -> Option<String> {
+        let entry_point = item.name.strip_prefix("ei_ffi_")?;
+        Some(format!("{}{}", self.prefix, entry_point))
+    }
+}
+
+/// Generate the bindings and metadata of a staged extra model next to its model directory
+fn write_extra_model_sources(name: &str, model_dir: &Path) {
+    let out_dir = model_dir
+        .parent()
+        .expect("extra model directory has a parent");
+    let dir = model_dir.to_str().expect("OUT_DIR is not valid UTF-8");
+    let bindings_path = out_dir.join("bindings.rs");
+    configure_bindings(
+        bindgen::Builder::default().header(format!("{}/edge_impulse_wrapper.h", dir)),
+        dir,
+    )
+    .parse_callbacks(Box::new(ExtraModelLinkNames {
+        prefix: format!("ei_ffi_{}_", name),
+    }))
+    .generate()
+    .unwrap_or_else(|e| {
+        panic!(
+            "Unable to generate bindings for extra model '{}': {}",
+            name, e
+        )
+    })
+    .write_to_file(&bindings_path)
+    .expect("Couldn't write bindings!");
+
+    // Checked against EI_CLASSIFIER_MODEL_HASH by a const assertion in src/models.rs
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&bindings_path)
+        .and_then(|mut file| {
+            use std::io::Write;
+            write!(
+                file,
+                "\n/// Hash of the model these bindings were generated from\npub const EI_FFI_MODEL_HASH: u64 = {:#018x};\n",
+                model_hash_of(model_dir)
+            )
+        })
+        .expect("Couldn't write bindings!");
+
+    write_model_metadata(model_dir, &out_dir.join("model_metadata.rs"));
+}
+
+/// Write the `models` module, with one submodule per extra model (see src/models.rs)
+fn write_models_module(names: &[String]) {
+    let mut out = String::from("// This file is @generated by build.rs. Do not edit manually.\n");
+    for name in names {
+        out.push_str(&format!(
+            "\n/// Extra model `{}`\npub mod {} {{\n    extra_model!({:?});\n}}\n",
+            name, name, name
+        ));
+    }
+    fs::write(generated_path("models.rs"), out).expect("Failed to write models.rs");
+}
+
+/// Build the SDK library of a staged extra model and link it
+///
+/// Extra models use TensorFlow Lite Micro and are always isolated, so their SDK copies can't
+/// clash with each other or with the main model. Their entry points are then renamed to
+/// `ei_ffi_<name>_*`, which the bindings link to. Compilers and cross-compilation flags are
+/// the main model's.
+fn build_extra_model(
+    name: &str,
+    model_dir: &Path,
+    main_cmake_args: &[String],
+    cargo_target: &str,
+    target_os: &str,
+) {
+    let build_dir = native_build_dir(model_dir, cargo_target, "tflite-micro+isolated");
+    let library = build_dir.join(format!("libedge-impulse-sdk-{}.a", name));
+    let mut cmake_args = vec![
+        model_dir.display().to_string(),
+        "-DCMAKE_BUILD_TYPE=Release".to_string(),
+        "-DEIDSP_SIGNAL_C_FN_POINTER=1".to_string(),
+        "-DEI_C_LINKAGE=1".to_string(),
+        "-DEI_BUILD_SHARED=OFF".to_string(),
+        "-DBUILD_SHARED_LIBS=OFF".to_string(),
+        format!("-DEI_FFI_MODEL_HASH={:#018x}", model_hash_of(model_dir)),
+    ];
+    cmake_args.extend(
+        main_cmake_args
+            .iter()
+            .filter(|arg| {
+                [
+                    "-DCMAKE_C_",
+                    "-DCMAKE_CXX_",
+                    "-DCMAKE_ASM_",
+                    "-DCMAKE_SYSTEM_",
+                    "-DCMAKE_TRY_COMPILE_",
+                    "-DEI_FFI_EXTRA_SOURCES=",
+                    "-DEI_FFI_ESP_NN=",
+                ]
+                .iter()
+                .any(|prefix| arg.starts_with(prefix))
+            })
+            .cloned(),
+    );
+    let inputs_hash = build_inputs_hash(model_dir, &cmake_args);
+    let should_rebuild = !library.exists()
+        || read_build_target_stamp(&build_dir).as_deref() != Some(cargo_target)
+        || read_build_inputs_stamp(&build_dir) != Some(inputs_hash)
+        || env::var("FORCE_REBUILD").is_ok();
+
+    if should_rebuild {
+        println!(
+            "cargo:warning=Building C++ library of extra model '{}'...",
+            name
+        );
+        fs::create_dir_all(&build_dir).expect("Failed to create build directory");
+        copy_tflite_model_files(&model_dir.join("tflite-model"), &build_dir);
+        let _ = fs::remove_file(&library);
+
+        run_cmake_build(
+            &build_dir,
+            &cmake_args,
+            "Release",
+            None,
+            &format!("extra model '{}'", name),
+        );
+
+        // Renamed, so the main model's `edge-impulse-sdk` can't resolve to this library
+        let built = build_dir.join("libedge-impulse-sdk.a");
+        isolate_library_symbols(&built, &[], target_os);
+        rename_entry_points(&built, name, target_os);
+        fs::rename(&built, &library).expect("Failed to rename extra model library");
+        write_build_target_stamp(&build_dir, cargo_target);
+        write_build_inputs_stamp(&build_dir, inputs_hash);
+    }
+
+    check_library_arch(
+        &library,
+        &env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default(),
+    );
+    println!(
+        "cargo:rustc-link-search=native={}",
+        absolute_dir(&build_dir).display()
+    );
+    println!("cargo:rustc-link-lib=static=edge-impulse-sdk-{}", name);
+}
+
+/// Clang flags, type and function allowlist shared by the bindings of every model
+fn configure_bindings(builder: bindgen::Builder, model_dir: &str) -> bindgen::Builder {
+    builder
+        .clang_arg("-xc++")
+        .clang_arg("-std=c++17")
+        .clang_arg(format!("-I{}", model_dir))
+        .clang_arg(format!("-I{}/edge-impulse-sdk", model_dir))
+        .clang_arg("-O3")
+        .clang_arg("-flto")
+        .clang_arg("-ffast-math")
+        .clang_arg("-funroll-loops")
+        // Force inclusion of visual anomaly detection fields for consistent bindings
+        .clang_arg("-DEI_CLASSIFIER_HAS_VISUAL_ANOMALY=1")
+        // Error codes are a newtype so codes added by newer SDKs are new constants rather than
+        // invalid enum values, see `report_unknown_impulse_errors`
+        .newtype_enum("EI_IMPULSE_ERROR")
+        .rustified_enum(".*")
+        .default_enum_style(bindgen::EnumVariation::Rust {
+            non_exhaustive: false,
+        })
+        .prepend_enum_name(false)
+        .translate_enum_integer_types(true)
+        .derive_copy(true)
+        .derive_debug(true)
+        .derive_default(true)
+        // Do NOT derive Eq, PartialEq, Hash, Ord, PartialOrd to avoid function pointer comparison warnings
+        .derive_eq(false)
+        .derive_hash(false)
+        .derive_partialeq(false)
+        .derive_partialord(false)
+        .derive_ord(false)
+        // Disable problematic traits for structs with function pointers
+        .disable_name_namespacing()
+        // Raw C types from core, so the bindings also build with the `no_std` feature
+        .use_core()
+        .ctypes_prefix("::core::ffi")
+        .disable_untagged_union()
+        // Ignore INCBIN macro to avoid processing .tflite files
+        .blocklist_item("INCBIN")
+        .blocklist_item("incbin_.*")
+        .blocklist_item("gincbin_.*")
+        .allowlist_type("ei_impulse_handle_t")
+        .allowlist_type("ei_impulse_result_t")
+        .allowlist_type("ei_feature_t")
+        .allowlist_type("ei_signal_t")
+        .allowlist_type("EI_IMPULSE_ERROR")
+        .allowlist_type("ei_impulse_result_classification_t")
+        .allowlist_type("ei_impulse_result_bounding_box_t")
+        .allowlist_type("ei_impulse_result_timing_t")
+        .allowlist_type("ei_impulse_visual_ad_result_t")
+        .allowlist_type("ei_ffi_tracked_object_t")
+        .allowlist_type("ei_ffi_log_callback_t")
+        .allowlist_type("ei_ffi_porting_hooks_t")
+        .allowlist_type("ei_ffi_alloc_failure_callback_t")
+        .allowlist_function("ei_ffi_run_classifier_init")
+        .allowlist_function("ei_ffi_run_classifier_deinit")
+        .allowlist_function("ei_ffi_init_impulse")
+        .allowlist_function("ei_ffi_run_classifier")
+        .allowlist_function("ei_ffi_run_classifier_continuous")
+        .allowlist_function("ei_ffi_run_inference")
+        .allowlist_function("ei_ffi_run_inference_features")
+        .allowlist_function("ei_ffi_impulse_handle_new")
+        .allowlist_function("ei_ffi_impulse_handle_free")
+        .allowlist_function("ei_ffi_run_classifier_with_handle")
+        .allowlist_function("ei_ffi_signal_from_buffer")
+        .allowlist_function("ei_ffi_set_object_detection_threshold")
+        .allowlist_function("ei_ffi_set_anomaly_threshold")
+        .allowlist_function("ei_ffi_set_object_tracking_threshold")
+        .allowlist_function("ei_ffi_set_learn_block_threshold")
+        .allowlist_function("ei_ffi_get_learn_block_threshold")
+        .allowlist_function("ei_ffi_set_nms_iou_threshold")
+        .allowlist_function("ei_ffi_get_nms_iou_threshold")
+        .allowlist_function("ei_ffi_set_max_detections")
+        .allowlist_function("ei_ffi_get_max_detections")
+        .allowlist_function("ei_ffi_object_tracking_count")
+        .allowlist_function("ei_ffi_object_tracking_get")
+        .allowlist_function("ei_ffi_freeform_output_count")
+        .allowlist_function("ei_ffi_freeform_output_get")
+        .allowlist_function("ei_ffi_set_log_callback")
+        .allowlist_function("ei_ffi_set_porting_hooks")
+        .allowlist_function("ei_ffi_set_timer_hook")
+        .allowlist_function("ei_ffi_clock_source")
+        .allowlist_function("ei_ffi_set_alloc_failure_callback")
+        .allowlist_function("ei_ffi_set_cancel_flag")
+        .allowlist_function("ei_ffi_last_dsp_error")
+        .allowlist_function("ei_ffi_model_hash")
+}
+
 fn main() {
     println!("cargo:warning=DEBUG: Build script starting...");
     println!(
@@ -1163,15 +3567,48 @@ fn main() {
 
     // Force rerun on every build
     println!("cargo:rerun-if-changed=build.rs");
-    println!("cargo:rerun-if-changed=src/thresholds.rs");
+    println!("cargo:rerun-if-env-changed=EI_SYMBOL_PREFIX");
+    println!("cargo:rerun-if-env-changed=EI_SANITIZE");
+    println!("cargo:rerun-if-env-changed=EI_ISOLATE_SYMBOLS");
+    println!("cargo:rerun-if-env-changed=TARGET_LINUX_ARMV7_MUSL");
+    println!("cargo:rerun-if-env-changed=EI_PROJECT_ID");
+    println!("cargo:rerun-if-env-changed=EI_API_KEY");
+    println!("cargo:rerun-if-env-changed=EI_DEPLOY_VERSION");
+    println!("cargo:rerun-if-env-changed=EI_MODEL_SHA256");
+    println!("cargo:rerun-if-env-changed=EI_DENY_BINDINGS_CHANGES");
+    println!("cargo:rerun-if-env-changed=EI_SIZE_REPORT");
+    println!("cargo:rerun-if-env-changed=EI_FFI_GLUE_DIR");
+    println!("cargo:rerun-if-env-changed=EI_MACOS_UNIVERSAL");
+    println!("cargo:rerun-if-env-changed=EI_BUILD_SYSTEM");
+    // A missing file would rerun the script on every build; ei-fetch writes it together with
+    // model-parameters/, which is watched anyway
+    for path in [PROJECT_INFO_PATH, MODEL_SHA256_PATH] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    if let Ok(dir) = env::var("EI_FFI_GLUE_DIR") {
+        println!("cargo:rerun-if-changed={}", dir);
+    }
+    // A check build skips the native library, so a later real build must rerun this script
+    println!("cargo:rerun-if-env-changed=EI_SKIP_NATIVE_BUILD");
+    println!("cargo:rerun-if-env-changed=RUSTC_WRAPPER");
+    println!("cargo:rerun-if-env-changed=RUSTC_WORKSPACE_WRAPPER");
+
+    // docs.rs and CI jobs without a model only need the types: use the dummy model and skip
+    // the model lookup and the C++ build
+    println!("cargo:rerun-if-env-changed=DOCS_RS");
+    if env::var_os("CARGO_FEATURE_DUMMY").is_some() || env::var_os("DOCS_RS").is_some() {
+        write_dummy_model();
+        return;
+    }
 
     // Get the current working directory and construct absolute paths
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
     let manifest_path = PathBuf::from(manifest_dir);
 
     let model_header = manifest_path.join("model/model-parameters/model_metadata.h");
-    let out_bindings = manifest_path.join("src/bindings.rs");
-    let _out_metadata = manifest_path.join("src/model_metadata.rs");
+    let out_bindings = generated_path("bindings.rs");
 
     // Check if we have a valid model structure - only look for actual model components
     let sdk_dir = manifest_path.join("model/edge-impulse-sdk");
@@ -1182,6 +3619,30 @@ fn main() {
     let mut has_valid_model =
         sdk_dir.exists() && model_parameters_dir.exists() && tflite_model_dir.exists();
 
+    // A local ZIP in EI_MODEL is extracted again whenever the archive is replaced
+    println!("cargo:rerun-if-env-changed=EI_MODEL");
+    if let Ok(model_path) = env::var("EI_MODEL") {
+        if is_local_zip(&model_path) {
+            println!("cargo:rerun-if-changed={}", model_path);
+            let model_dir = manifest_path.join("model");
+            if has_valid_model && model_zip_changed(&model_path, &model_dir) {
+                println!(
+                    "cargo:info=EI_MODEL archive {} changed, extracting it again",
+                    model_path
+                );
+                for dir in [
+                    "edge-impulse-sdk",
+                    "model-parameters",
+                    "tflite-model",
+                    "tensorflow-lite",
+                ] {
+                    let _ = fs::remove_dir_all(model_dir.join(dir));
+                }
+                has_valid_model = false;
+            }
+        }
+    }
+
     // If no valid model found, try to copy from EI_MODEL path first
     if !has_valid_model {
         println!("cargo:info=No valid model found locally, checking for EI_MODEL environment variable...");
@@ -1201,35 +3662,51 @@ fn main() {
         }
     }
 
-    // If still no valid model found, try to download from Edge Impulse API
-    if !has_valid_model {
-        println!("cargo:info=No valid model found locally, checking for Edge Impulse API configuration...");
-
-        if let Some((project_id, api_key)) = read_edge_impulse_config() {
-            println!("cargo:info=Found Edge Impulse configuration in environment variables");
+    // Downloading from Studio takes minutes, so it's done ahead of time by ei-fetch rather than
+    // here, where it would stall IDEs and rust-analyzer
+    if !has_valid_model && env::var("EI_PROJECT_ID").is_ok() && env::var("EI_API_KEY").is_ok() {
+        eprintln!(
+            "cargo:error=EI_PROJECT_ID and EI_API_KEY are set, but no model has been downloaded"
+        );
+        eprintln!("cargo:error=The build no longer downloads models from Edge Impulse Studio. Fetch the model first:");
+        eprintln!("cargo:error=     cargo run -p ei-fetch");
+        eprintln!("cargo:error=then run the build again.");
+        std::process::exit(1);
+    }
 
-            // Attempt to download the model
-            if download_model_from_edge_impulse(&project_id, &api_key) {
-                // Re-check if we now have a valid model
-                has_valid_model =
-                    sdk_dir.exists() && model_parameters_dir.exists() && tflite_model_dir.exists();
+    // A pinned deployment version makes the build reproducible only if it's the model in use
+    if let (true, Ok(pinned)) = (has_valid_model, env::var("EI_DEPLOY_VERSION")) {
+        let header = fs::read_to_string(&model_header).unwrap_or_default();
+        let version = regex::Regex::new(r"#define\s+EI_CLASSIFIER_PROJECT_DEPLOY_VERSION\s+(\d+)")
+            .unwrap()
+            .captures(&header)
+            .map(|captures| captures[1].to_string());
+        if version.as_deref() != Some(pinned.trim()) {
+            eprintln!(
+                "cargo:error=EI_DEPLOY_VERSION is {}, but the model in model/ is deployment version {}",
+                pinned,
+                version.as_deref().unwrap_or("unknown")
+            );
+            eprintln!("cargo:error=Fetch the pinned version first:");
+            eprintln!("cargo:error=     cargo run -p ei-fetch");
+            std::process::exit(1);
+        }
+    }
 
-                if has_valid_model {
-                    println!(
-                        "cargo:warning=Model was downloaded from Edge Impulse Studio project ID {}",
-                        project_id
-                    );
-                } else {
-                    println!("cargo:warning=Model download completed but model structure is still invalid");
+    // A pinned archive hash only holds if model/ was extracted from that archive
+    if let (true, Some(pinned)) = (has_valid_model, pinned_model_sha256()) {
+        let recorded = fs::read_to_string(MODEL_SHA256_PATH).ok();
+        let recorded = recorded.as_deref().map(str::trim);
+        if recorded != Some(pinned.as_str()) {
+            panic!(
+                "EI_MODEL_SHA256 is {}, but the model in model/ was extracted from {}; fetch the \
+                 pinned archive again with ei-fetch or EI_MODEL",
+                pinned,
+                match recorded {
+                    Some(sha256) => format!("an archive with SHA-256 {}", sha256),
+                    None => "an unknown archive".to_string(),
                 }
-            } else {
-                println!("cargo:warning=Failed to download model from Edge Impulse API");
-            }
-        } else {
-            println!("cargo:info=No Edge Impulse configuration found in environment variables");
-            println!("cargo:info=To enable automatic model download, set the following environment variables:");
-            println!("cargo:info=EI_PROJECT_ID=your-project-id");
-            println!("cargo:info=EI_API_KEY=your-api-key");
+            );
         }
     }
 
@@ -1246,74 +3723,62 @@ fn main() {
 
         // Generate real bindings using bindgen
         let wrapper_header = manifest_path.join("model/edge_impulse_wrapper.h");
-        let bindings = bindgen::Builder::default()
-            .header(wrapper_header.to_str().unwrap())
-            .clang_arg("-xc++")
-            .clang_arg("-std=c++17")
-            .clang_arg("-Imodel")
-            .clang_arg("-Imodel/edge-impulse-sdk")
-            .clang_arg("-O3")
-            .clang_arg("-flto")
-            .clang_arg("-ffast-math")
-            .clang_arg("-funroll-loops")
-            // Force inclusion of visual anomaly detection fields for consistent bindings
-            .clang_arg("-DEI_CLASSIFIER_HAS_VISUAL_ANOMALY=1")
-            .rustified_enum(".*")
-            .default_enum_style(bindgen::EnumVariation::Rust {
-                non_exhaustive: false,
-            })
-            .prepend_enum_name(false)
-            .translate_enum_integer_types(true)
-            .derive_copy(true)
-            .derive_debug(true)
-            .derive_default(true)
-            // Do NOT derive Eq, PartialEq, Hash, Ord, PartialOrd to avoid function pointer comparison warnings
-            .derive_eq(false)
-            .derive_hash(false)
-            .derive_partialeq(false)
-            .derive_partialord(false)
-            .derive_ord(false)
-            // Disable problematic traits for structs with function pointers
-            .disable_name_namespacing()
-            .disable_untagged_union()
-            // Ignore INCBIN macro to avoid processing .tflite files
-            .blocklist_item("INCBIN")
-            .blocklist_item("incbin_.*")
-            .blocklist_item("gincbin_.*")
-            .allowlist_type("ei_impulse_handle_t")
-            .allowlist_type("ei_impulse_result_t")
-            .allowlist_type("ei_feature_t")
-            .allowlist_type("ei_signal_t")
-            .allowlist_type("EI_IMPULSE_ERROR")
-            .allowlist_type("ei_impulse_result_classification_t")
-            .allowlist_type("ei_impulse_result_bounding_box_t")
-            .allowlist_type("ei_impulse_result_timing_t")
-            .allowlist_type("ei_impulse_visual_ad_result_t")
-            .allowlist_function("ei_ffi_run_classifier_init")
-            .allowlist_function("ei_ffi_run_classifier_deinit")
-            .allowlist_function("ei_ffi_init_impulse")
-            .allowlist_function("ei_ffi_run_classifier")
-            .allowlist_function("ei_ffi_run_classifier_continuous")
-            .allowlist_function("ei_ffi_run_inference")
-            .allowlist_function("ei_ffi_signal_from_buffer")
-            .allowlist_function("ei_ffi_set_object_detection_threshold")
-            .allowlist_function("ei_ffi_set_anomaly_threshold")
-            .allowlist_function("ei_ffi_set_object_tracking_threshold")
+        let mut bindings = bindgen::Builder::default().header(wrapper_header.to_str().unwrap());
+        // Headers from EI_FFI_GLUE_DIR are parsed as well, and everything they declare is exported
+        for header in custom_ffi_glue_names(&["h", "hpp"]) {
+            if header != "edge_impulse_wrapper.h" {
+                bindings =
+                    bindings.header(manifest_path.join("model").join(&header).to_str().unwrap());
+            }
+            bindings = bindings.allowlist_file(format!(".*/model/{}", regex::escape(&header)));
+        }
+        // The target's headers come from its SDK, not the host: the Emscripten sysroot, the Arm
+        // GNU Toolchain for Cortex-M or the ESP-IDF toolchain
+        if let Some(emscripten) = emscripten_target() {
+            bindings = bindings.clang_arg(format!("--sysroot={}", emscripten.sysroot.display()));
+        }
+        if let Some(cortex_m) = cortex_m_target() {
+            for dir in cortex_m.system_include_dirs() {
+                bindings = bindings.clang_arg(format!("-isystem{}", dir.display()));
+            }
+        }
+        if let Some(esp_idf) = esp_idf_target() {
+            for dir in gcc_system_include_dirs(&esp_idf.compiler("CXX"), esp_idf.flags()) {
+                bindings = bindings.clang_arg(format!("-isystem{}", dir.display()));
+            }
+        }
+        let bindings = configure_bindings(bindings, "model")
             .generate()
             .expect("Unable to generate bindings");
 
+        let previous_bindings = fs::read_to_string(&out_bindings).ok();
         bindings
             .write_to_file(&out_bindings)
             .expect("Couldn't write bindings!");
 
-        // Add allow attributes to suppress warnings in generated bindings
+        // Checked against EI_CLASSIFIER_MODEL_HASH by a const assertion in src/lib.rs
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&out_bindings)
+            .and_then(|mut file| {
+                use std::io::Write;
+                write!(
+                    file,
+                    "\n/// Hash of the model these bindings were generated from\npub const EI_FFI_MODEL_HASH: u64 = {:#018x};\n",
+                    model_hash()
+                )
+            })
+            .expect("Couldn't write bindings!");
+
+        // The naming lints are allowed on the `bindings` module in src/lib.rs, since an included
+        // file can't carry inner attributes
         let bindings_content =
             std::fs::read_to_string(&out_bindings).expect("Failed to read generated bindings");
-        let modified_content = format!(
-            "#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]\n{}",
-            bindings_content
-        );
-        std::fs::write(&out_bindings, modified_content).expect("Failed to write modified bindings");
+
+        if let Some(previous) = previous_bindings {
+            report_bindings_changes(&previous, &bindings_content);
+        }
+        report_unknown_impulse_errors(&bindings_content);
 
         // Generate model metadata
         if model_header.exists() {
@@ -1329,256 +3794,210 @@ fn main() {
         eprintln!("cargo:error=  1. Ensure model files exist in the model/ directory");
         eprintln!("cargo:error=  2. Set EI_MODEL environment variable to copy from a custom path:");
         eprintln!("cargo:error=     export EI_MODEL=/path/to/your/model");
-        eprintln!("cargo:error=  3. Set environment variables to download a model:");
-        eprintln!("cargo:error=     export EI_PROJECT_ID=your-project-id");
-        eprintln!("cargo:error=     export EI_API_KEY=your-api-key");
+        eprintln!("cargo:error=  3. Download a model from Edge Impulse Studio:");
+        eprintln!("cargo:error=     cargo run -p ei-fetch -- --project-id your-project-id --api-key your-api-key");
         std::process::exit(1);
     }
 
+    // Extra models get their bindings and metadata here too, so check builds see them
+    let extra_models: Vec<(String, PathBuf)> = extra_models()
+        .into_iter()
+        .map(|(name, source)| {
+            let model_dir = stage_extra_model(&name, &source);
+            write_extra_model_sources(&name, &model_dir);
+            (name, model_dir)
+        })
+        .collect();
+    write_models_module(
+        &extra_models
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>(),
+    );
+
     // Check if we should clean the model folder
     if env::var("CLEAN_MODEL").is_ok() {
         clean_model_folder();
         return;
     }
 
-    // Define model directory and build directory for use throughout the function
-    let model_dir = "model";
-    let cpp_dir = PathBuf::from(model_dir);
-    let build_dir = cpp_dir.join("build");
-
-    // If we have a valid model, we need to build the C++ library
-    if has_valid_model {
-        copy_ffi_glue(model_dir);
-
-        // Create build directory if it doesn't exist
-        std::fs::create_dir_all(&build_dir).expect("Failed to create build directory");
-
-        // --- Dynamically find and copy TFLite file and header to build directory for INCBIN ---
-        let tflite_model_dir = manifest_path.join("model/tflite-model");
-        let tflite_build_dir = build_dir.join("tflite-model");
+    // Check builds only need the bindings and metadata; building the SDK takes minutes and
+    // nothing gets linked
+    if skip_native_build() {
+        println!("cargo:warning=Check build detected, skipping the C++ SDK build (set EI_SKIP_NATIVE_BUILD=0 to build it)");
+        extract_and_write_thresholds();
+        return;
+    }
 
-        // Debug: Print the tflite-model directory contents
-        println!(
-            "cargo:info=DEBUG: Checking tflite-model directory: {}",
-            tflite_model_dir.display()
-        );
-        if !tflite_model_dir.exists() {
-            println!("cargo:error=DEBUG: tflite-model directory does not exist!");
-            std::process::exit(1);
+    // Check if we need full TensorFlow Lite
+    // Only USE_FULL_TFLITE is supported
+    let armv7_musl = armv7_musl_target();
+    let msvc = msvc_target();
+    let emscripten = emscripten_target();
+    let cortex_m = cortex_m_target();
+    let esp_idf = esp_idf_target();
+    if cortex_m.is_some() && env::var_os("CARGO_FEATURE_NO_STD").is_none() {
+        panic!("Cortex-M targets have no standard library, enable the `no_std` feature");
+    }
+    // The prebuilt full TensorFlow Lite libraries link against glibc (or libc++ on macOS), so
+    // musl, MSVC, Emscripten and microcontroller targets use TFLite Micro
+    let unsupported_full_tflite = match (&armv7_musl, &msvc) {
+        (Some(musl), _) => Some(musl.triple.to_string()),
+        (None, Some(_)) => env::var("TARGET").ok(),
+        (None, None) if emscripten.is_some() || cortex_m.is_some() || esp_idf.is_some() => {
+            env::var("TARGET").ok()
         }
-
-        // List all files in the directory
-        match std::fs::read_dir(&tflite_model_dir) {
-            Ok(entries) => {
-                println!("cargo:info=DEBUG: Contents of tflite-model directory:");
-                for entry in entries {
-                    match entry {
-                        Ok(entry) => {
-                            let file_name = entry.file_name();
-                            let file_name_str = file_name.to_string_lossy();
-                            let file_type = if entry.file_type().unwrap().is_dir() {
-                                "DIR"
-                            } else {
-                                "FILE"
-                            };
-                            println!("cargo:info=DEBUG:   {}: {}", file_type, file_name_str);
-                        }
-                        Err(e) => {
-                            println!("cargo:warning=DEBUG: Failed to read directory entry: {}", e)
-                        }
-                    }
-                }
-            }
-            Err(e) => {
+        (None, None) => None,
+    };
+    let use_full_tflite = env::var("USE_FULL_TFLITE").is_ok()
+        && match &unsupported_full_tflite {
+            Some(target) => {
                 println!(
-                    "cargo:error=DEBUG: Failed to read tflite-model directory: {}",
-                    e
+                    "cargo:warning=USE_FULL_TFLITE is not supported for {}, building with TensorFlow Lite Micro",
+                    target
                 );
-                std::process::exit(1);
+                false
             }
-        }
-
-        // Check if this is a raw TFLite model or a compiled model
-        let mut tflite_files: Vec<_> = std::fs::read_dir(&tflite_model_dir)
-            .expect("Failed to read tflite-model directory")
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let file_name_os = entry.file_name();
-                let file_name = file_name_os.to_str()?;
-                println!("cargo:info=DEBUG: Checking file: {} (ends_with .tflite: {}, starts_with tflite_learn_: {})",
-                    file_name,
-                    file_name.ends_with(".tflite"),
-                    file_name.starts_with("tflite_learn_"));
-                if file_name.ends_with(".tflite") && file_name.starts_with("tflite_learn_") {
-                    Some((entry.path(), file_name.to_string(), "raw"))
-                } else {
-                    None
-                }
-            })
-            .collect();
+            None => true,
+        };
 
-        // If no raw TFLite files found, check for compiled model files
-        if tflite_files.is_empty() {
-            let compiled_files: Vec<_> = std::fs::read_dir(&tflite_model_dir)
-                .expect("Failed to read tflite-model directory")
-                .filter_map(|entry| {
-                    let entry = entry.ok()?;
-                    let file_name_os = entry.file_name();
-                    let file_name = file_name_os.to_str()?;
-                    println!("cargo:info=DEBUG: Checking compiled file: {} (ends_with .cpp: {}, starts_with tflite_learn_: {})",
-                        file_name,
-                        file_name.ends_with(".cpp"),
-                        file_name.starts_with("tflite_learn_"));
-                    if file_name.ends_with(".cpp") && file_name.starts_with("tflite_learn_") {
-                        let base_name = file_name.trim_end_matches("_compiled.cpp");
-                        Some((entry.path(), format!("{}.tflite", base_name), "compiled"))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+    // Build and link the SDK as a shared library instead of a static one
+    let build_shared = env::var("EI_BUILD_SHARED").is_ok();
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let lib_file_name = match (build_shared, target_os.as_str()) {
+        // link.exe links the DLL through its import library, edge-impulse-sdk.lib
+        (true, "windows") if msvc.is_some() => "edge-impulse-sdk.dll",
+        (true, "macos" | "ios") => "libedge-impulse-sdk.dylib",
+        (true, _) => "libedge-impulse-sdk.so",
+        (false, _) if msvc.is_some() => "edge-impulse-sdk.lib",
+        (false, _) => "libedge-impulse-sdk.a",
+    };
 
-            if compiled_files.is_empty() {
-                println!("cargo:error=No tflite_learn_*.tflite or tflite_learn_*_compiled.cpp files found in model/tflite-model/");
-                std::process::exit(1);
-            }
+    if build_shared && !extra_models.is_empty() {
+        panic!("EI_MODEL_<NAME> extra models are only supported for static builds (unset EI_BUILD_SHARED)");
+    }
 
-            println!(
-                "cargo:info=Detected compiled model with {} compiled files",
-                compiled_files.len()
-            );
-            tflite_files = compiled_files;
-        } else {
-            println!(
-                "cargo:info=Detected raw TFLite model with {} TFLite files",
-                tflite_files.len()
+    // Build the SDK with sanitizers for debugging crashes across the FFI boundary
+    let sanitize = env::var("EI_SANITIZE")
+        .ok()
+        .filter(|sanitize| !sanitize.is_empty());
+    if let Some(sanitize) = &sanitize {
+        if !is_supported_sanitizer(sanitize) {
+            panic!(
+                "EI_SANITIZE must be 'address', 'undefined' or 'address,undefined', got '{}'",
+                sanitize
             );
         }
+    }
 
-        std::fs::create_dir_all(&tflite_build_dir)
-            .expect("Failed to create tflite-model build dir");
-
-        // Copy all model files and their corresponding headers
-        for (source_path, tflite_filename, model_type) in &tflite_files {
-            let base_name = tflite_filename.trim_end_matches(".tflite");
-            let header_filename = if *model_type == "compiled" {
-                format!("{}_compiled.h", base_name)
-            } else {
-                format!("{}.h", base_name)
-            };
-            let header_source = tflite_model_dir.join(&header_filename);
+    // Hide the SDK's (and TensorFlow Lite's) symbols so other TFLite copies can be linked alongside
+    let isolate_symbols = env::var("EI_ISOLATE_SYMBOLS").is_ok();
+
+    // Prefix the SDK's symbols so it can coexist with another Edge Impulse export
+    let symbol_prefix = env::var("EI_SYMBOL_PREFIX")
+        .ok()
+        .filter(|prefix| !prefix.is_empty());
+    if let Some(prefix) = &symbol_prefix {
+        if !is_valid_symbol_prefix(prefix) {
+            panic!(
+                "EI_SYMBOL_PREFIX must be a valid C identifier, got '{}'",
+                prefix
+            );
+        }
+        if build_shared {
+            panic!("EI_SYMBOL_PREFIX is only supported for static builds (unset EI_BUILD_SHARED)");
+        }
+        if isolate_symbols {
+            panic!("EI_ISOLATE_SYMBOLS already hides every symbol EI_SYMBOL_PREFIX would rename, set only one of them");
+        }
+    }
 
-            if !header_source.exists() {
-                println!(
-                    "cargo:error=Header file {} not found for {} file {}",
-                    header_filename, model_type, tflite_filename
-                );
-                std::process::exit(1);
+    // Symbol rewriting needs GNU/LLVM binutils and ELF or Mach-O objects, and the sanitizer flags
+    // are Clang/GCC ones
+    if msvc.is_some() {
+        for (enabled, setting) in [
+            (sanitize.is_some(), "EI_SANITIZE"),
+            (symbol_prefix.is_some(), "EI_SYMBOL_PREFIX"),
+            (isolate_symbols, "EI_ISOLATE_SYMBOLS"),
+            (!extra_models.is_empty(), "EI_MODEL_<NAME> (extra models)"),
+        ] {
+            if enabled {
+                panic!("{} is not supported for Windows MSVC targets", setting);
             }
+        }
+    }
 
-            let tflite_dest = tflite_build_dir.join(tflite_filename);
-            let header_dest = tflite_build_dir.join(&header_filename);
-
-            if *model_type == "raw" {
-                // For raw TFLite models, copy the actual .tflite file
-                if tflite_dest.exists() {
-                    std::fs::remove_file(&tflite_dest).expect("Failed to remove old TFLite file");
-                }
-                std::fs::copy(source_path, &tflite_dest)
-                    .expect("Failed to copy TFLite file to build directory");
-            } else {
-                // For compiled models, create a dummy .tflite file (the actual model is in the .cpp file)
-                if tflite_dest.exists() {
-                    std::fs::remove_file(&tflite_dest).expect("Failed to remove old TFLite file");
-                }
-                // Create an empty file as placeholder - the actual model is in the compiled .cpp
-                std::fs::write(&tflite_dest, "").expect("Failed to create dummy TFLite file");
-                println!(
-                    "cargo:info=Created dummy TFLite file for compiled model: {}",
-                    tflite_filename
-                );
+    // Emscripten builds one static wasm32 library without threads, and objcopy can't rewrite
+    // wasm objects
+    if emscripten.is_some() {
+        for (enabled, setting) in [
+            (build_shared, "EI_BUILD_SHARED"),
+            (sanitize.is_some(), "EI_SANITIZE"),
+            (symbol_prefix.is_some(), "EI_SYMBOL_PREFIX"),
+            (isolate_symbols, "EI_ISOLATE_SYMBOLS"),
+            (!extra_models.is_empty(), "EI_MODEL_<NAME> (extra models)"),
+        ] {
+            if enabled {
+                panic!("{} is not supported for Emscripten targets", setting);
             }
+        }
+    }
 
-            // Copy header file
-            if header_dest.exists() {
-                std::fs::remove_file(&header_dest).expect("Failed to remove old header file");
+    // Bare-metal firmware links one static SDK library, and has no sanitizer runtimes
+    if cortex_m.is_some() {
+        for (enabled, setting) in [
+            (build_shared, "EI_BUILD_SHARED"),
+            (sanitize.is_some(), "EI_SANITIZE"),
+            (!extra_models.is_empty(), "EI_MODEL_<NAME> (extra models)"),
+        ] {
+            if enabled {
+                panic!("{} is not supported for Cortex-M targets", setting);
             }
-            std::fs::copy(&header_source, &header_dest)
-                .expect("Failed to copy header file to build directory");
-
-            println!(
-                "cargo:info=Copied {} model files to build directory: {} -> {}",
-                model_type,
-                tflite_filename,
-                tflite_dest.display()
-            );
-
-            // For compiled models, also copy the .cpp files
-            if *model_type == "compiled" {
-                let cpp_filename = format!("{}_compiled.cpp", base_name);
-                let cpp_source = tflite_model_dir.join(&cpp_filename);
-                let cpp_dest = tflite_build_dir.join(&cpp_filename);
+        }
+    }
 
-                if cpp_source.exists() {
-                    if cpp_dest.exists() {
-                        std::fs::remove_file(&cpp_dest).expect("Failed to remove old CPP file");
-                    }
-                    std::fs::copy(&cpp_source, &cpp_dest)
-                        .expect("Failed to copy CPP file to build directory");
-                    println!(
-                        "cargo:info=Copied compiled CPP file: {} -> {}",
-                        cpp_filename,
-                        cpp_dest.display()
-                    );
-                } else {
-                    println!(
-                        "cargo:warning=CPP file {} not found for compiled model",
-                        cpp_filename
-                    );
-                }
+    // ESP-IDF applications link everything statically into one firmware image
+    if esp_idf.is_some() {
+        for (enabled, setting) in [
+            (build_shared, "EI_BUILD_SHARED"),
+            (sanitize.is_some(), "EI_SANITIZE"),
+        ] {
+            if enabled {
+                panic!("{} is not supported for ESP-IDF targets", setting);
             }
         }
+    }
 
-        // Fix the header file paths in all copied header files
-        fix_header_file_path(&build_dir);
-
-        // Also overwrite the original headers to ensure C++ build uses the correct paths
-        for (_, tflite_filename, model_type) in &tflite_files {
-            let base_name = tflite_filename.trim_end_matches(".tflite");
-            let header_filename = if *model_type == "compiled" {
-                format!("{}_compiled.h", base_name)
-            } else {
-                format!("{}.h", base_name)
-            };
-            let header_source = tflite_model_dir.join(&header_filename);
-            let header_dest = tflite_build_dir.join(&header_filename);
-            std::fs::copy(&header_dest, &header_source)
-                .expect("Failed to overwrite original header file with fixed path");
-        }
+    // Build one arm64 + x86_64 library that both macOS targets link
+    let macos_universal = env::var("EI_MACOS_UNIVERSAL").is_ok();
+    if macos_universal && target_os != "macos" {
+        panic!("EI_MACOS_UNIVERSAL is only supported for macOS targets");
+    }
 
-        // Remove the static library and CMake cache if FORCE_REBUILD is set
-        let lib_path = build_dir.join("libedge-impulse-sdk.a");
-        if env::var("FORCE_REBUILD").is_ok() {
-            if lib_path.exists() {
-                std::fs::remove_file(&lib_path).expect("Failed to remove old static library");
-                println!("cargo:warning=Removed old static library to force C++ rebuild");
-            }
-            // Also clean CMake cache to avoid path conflicts
-            let cmake_cache = build_dir.join("CMakeCache.txt");
-            if cmake_cache.exists() {
-                std::fs::remove_file(&cmake_cache).expect("Failed to remove CMake cache");
-                println!("cargo:warning=Removed CMake cache to avoid path conflicts");
+    // The cc crate builds one static library for the Cargo target
+    let use_cc = build_with_cc();
+    if use_cc {
+        for (enabled, setting) in [
+            (build_shared, "EI_BUILD_SHARED"),
+            (macos_universal, "EI_MACOS_UNIVERSAL"),
+            (!extra_models.is_empty(), "EI_MODEL_<NAME> (extra models)"),
+        ] {
+            if enabled {
+                panic!("{} is not supported with EI_BUILD_SYSTEM=cc", setting);
             }
         }
     }
-    // --- End TFLite copy logic ---
-
-    // Check if we need full TensorFlow Lite
-    // Only USE_FULL_TFLITE is supported
-    let use_full_tflite = env::var("USE_FULL_TFLITE").is_ok();
 
-    // Detect platform target
-    let target_platform = if env::var("TARGET_MAC_ARM64").is_ok() {
+    // Platform of the prebuilt TensorFlow Lite libraries: a TARGET_* override, else the Cargo
+    // target's
+    let target_platform = if macos_universal {
+        // The universal library has both slices; link the TensorFlow Lite libraries for this one
+        if env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("aarch64") {
+            "mac-arm64"
+        } else {
+            "mac-x86_64"
+        }
+    } else if env::var("TARGET_MAC_ARM64").is_ok() {
         "mac-arm64"
     } else if env::var("TARGET_MAC_X86_64").is_ok() {
         "mac-x86_64"
@@ -1586,6 +4005,8 @@ fn main() {
         "linux-x86"
     } else if env::var("TARGET_LINUX_AARCH64").is_ok() {
         "linux-aarch64"
+    } else if armv7_musl.is_some() {
+        "linux-armv7-musl"
     } else if env::var("TARGET_LINUX_ARMV7").is_ok() {
         "linux-armv7"
     } else if env::var("TARGET_JETSON_NANO").is_ok() {
@@ -1599,25 +4020,16 @@ fn main() {
         || env::var("TARGET_TDA4VM").is_ok()
     {
         "linux-aarch64"
+    } else if let Some(platform) = cargo_target_platform() {
+        platform
     } else {
-        // Auto-detect based on current system
-        if cfg!(target_os = "macos") {
-            if cfg!(target_arch = "aarch64") {
-                "mac-arm64"
-            } else {
-                "mac-x86_64"
-            }
-        } else if cfg!(target_os = "linux") {
-            if cfg!(target_arch = "aarch64") {
-                "linux-aarch64"
-            } else if cfg!(target_arch = "arm") {
-                "linux-armv7"
-            } else {
-                "linux-x86"
-            }
-        } else {
-            "linux-x86" // default fallback
+        if use_full_tflite {
+            println!(
+                "cargo:warning=No prebuilt TensorFlow Lite platform for {}, using linux-x86; set a TARGET_* variable to pick one",
+                env::var("TARGET").unwrap_or_default()
+            );
         }
+        "linux-x86"
     };
 
     // Detect additional backend/accelerator support
@@ -1630,48 +4042,129 @@ fn main() {
     let link_tflite_flex = env::var("LINK_TFLITE_FLEX_LIBRARY").is_ok();
     let use_memryx_software = env::var("EI_CLASSIFIER_USE_MEMRYX_SOFTWARE").is_ok();
 
+    // Describe the native build configuration so each target/feature set gets its own build dir
+    let cargo_target = env::var("TARGET").unwrap_or_else(|_| "host".to_string());
+    // Both macOS targets share one universal build
+    let build_target = if macos_universal {
+        "universal-apple-darwin".to_string()
+    } else {
+        cargo_target.clone()
+    };
+    let mut build_variant = vec![if use_full_tflite && macos_universal {
+        "tflite-full-mac-universal".to_string()
+    } else if use_full_tflite {
+        format!("tflite-full-{}", target_platform)
+    } else {
+        "tflite-micro".to_string()
+    }];
+    for (enabled, name) in [
+        (use_tvm, "tvm"),
+        (use_onnx, "onnx"),
+        (use_qualcomm_qnn, "qnn"),
+        (use_ethos, "ethos"),
+        (use_akida, "akida"),
+        (use_memryx, "memryx"),
+        (link_tflite_flex, "flex"),
+        (use_memryx_software, "memryx-software"),
+        (build_shared, "shared"),
+        (
+            msvc.as_ref().is_some_and(|msvc| msvc.static_crt),
+            "crt-static",
+        ),
+    ] {
+        if enabled {
+            build_variant.push(name.to_string());
+        }
+    }
+    if let Some(prefix) = &symbol_prefix {
+        build_variant.push(format!("prefix-{}", prefix));
+    }
+    if let Some(sanitize) = &sanitize {
+        build_variant.push(format!("sanitize-{}", sanitize.replace(',', "-")));
+    }
+    if isolate_symbols {
+        build_variant.push("isolated".to_string());
+    }
+    if use_cc {
+        build_variant.push("cc".to_string());
+    }
+    let build_variant = build_variant.join("+");
+
+    // Define model directory and build directory for use throughout the function
+    let model_dir = "model";
+    let cpp_dir = PathBuf::from(model_dir);
+    let build_dir = native_build_dir(&cpp_dir, &build_target, &build_variant);
+    println!(
+        "cargo:info=Using native build directory {} for target {}",
+        build_dir.display(),
+        build_target
+    );
+
+    // If we have a valid model, we need to build the C++ library
+    if has_valid_model {
+        copy_ffi_glue(model_dir);
+
+        // Create build directory if it doesn't exist
+        std::fs::create_dir_all(&build_dir).expect("Failed to create build directory");
+
+        // --- Dynamically find and copy TFLite file and header to build directory for INCBIN ---
+        copy_tflite_model_files(&manifest_path.join("model/tflite-model"), &build_dir);
+    }
+    // --- End TFLite copy logic ---
+
     // Get TensorRT version for Jetson builds
     let tensorrt_version = env::var("TENSORRT_VERSION").unwrap_or_else(|_| "8.5.2".to_string());
 
     // Get Python cross path for cross-compilation
     let python_cross_path = env::var("PYTHON_CROSS_PATH").ok();
 
+    // Sanitizer builds keep debug info and skip the aggressive Release flags
+    let build_type = if sanitize.is_some() {
+        "RelWithDebInfo"
+    } else {
+        "Release"
+    };
+
     // Configure CMake with the required macros for C linkage
     let mut cmake_args = vec![
-        "..".to_string(),
-        "-DCMAKE_BUILD_TYPE=Release".to_string(),
+        manifest_path.join(model_dir).display().to_string(),
+        format!("-DCMAKE_BUILD_TYPE={}", build_type),
+        format!("-DEI_SANITIZE={}", sanitize.as_deref().unwrap_or("")),
+        format!(
+            "-DEI_ISOLATE_SYMBOLS={}",
+            if isolate_symbols { "ON" } else { "OFF" }
+        ),
         "-DEIDSP_SIGNAL_C_FN_POINTER=1".to_string(),
         "-DEI_C_LINKAGE=1".to_string(),
-        "-DBUILD_SHARED_LIBS=OFF".to_string(), // Build static library
+        // Build static library unless EI_BUILD_SHARED is set
+        format!(
+            "-DEI_BUILD_SHARED={}",
+            if build_shared { "ON" } else { "OFF" }
+        ),
+        "-DBUILD_SHARED_LIBS=OFF".to_string(),
+        // Returned by ei_ffi_model_hash, checked against the metadata when the classifier starts
+        format!("-DEI_FFI_MODEL_HASH={:#018x}", model_hash()),
+        // Extra sources from EI_FFI_GLUE_DIR
+        format!(
+            "-DEI_FFI_EXTRA_SOURCES={}",
+            custom_ffi_glue_names(&["c", "cc", "cpp"])
+                .into_iter()
+                .filter(|source| !FFI_GLUE_FILES.contains(&source.as_str()))
+                .collect::<Vec<_>>()
+                .join(";")
+        ),
     ];
 
     // Set up cross-compilation for aarch64
-    if env::var("TARGET_LINUX_AARCH64").is_ok() {
-        // Check if cross-compilers are available in the environment
-        if let Ok(cc) = env::var("CC") {
-            cmake_args.push(format!("-DCMAKE_C_COMPILER={}", cc));
-            println!("cargo:info=Using cross-compiler CC: {}", cc);
-        } else if let Ok(cc) = env::var("CC_aarch64_unknown_linux_gnu") {
-            cmake_args.push(format!("-DCMAKE_C_COMPILER={}", cc));
-            println!("cargo:info=Using Rust target cross-compiler CC: {}", cc);
-        } else {
-            // Try to find the cross-compiler
-            let cross_cc = "aarch64-linux-gnu-gcc";
-            cmake_args.push(format!("-DCMAKE_C_COMPILER={}", cross_cc));
-            println!("cargo:info=Using default cross-compiler CC: {}", cross_cc);
-        }
-
-        if let Ok(cxx) = env::var("CXX") {
-            cmake_args.push(format!("-DCMAKE_CXX_COMPILER={}", cxx));
-            println!("cargo:info=Using cross-compiler CXX: {}", cxx);
-        } else if let Ok(cxx) = env::var("CXX_aarch64_unknown_linux_gnu") {
-            cmake_args.push(format!("-DCMAKE_CXX_COMPILER={}", cxx));
-            println!("cargo:info=Using Rust target cross-compiler CXX: {}", cxx);
-        } else {
-            // Try to find the cross-compiler
-            let cross_cxx = "aarch64-linux-gnu-g++";
-            cmake_args.push(format!("-DCMAKE_CXX_COMPILER={}", cross_cxx));
-            println!("cargo:info=Using default cross-compiler CXX: {}", cross_cxx);
+    if aarch64_linux_cross() {
+        for (tool, variable, default) in [
+            ("CC", "CMAKE_C_COMPILER", "aarch64-linux-gnu-gcc"),
+            ("CXX", "CMAKE_CXX_COMPILER", "aarch64-linux-gnu-g++"),
+        ] {
+            let compiler = target_compiler(tool, "aarch64-unknown-linux-gnu")
+                .unwrap_or_else(|| default.to_string());
+            cmake_args.push(format!("-D{}={}", variable, compiler));
+            println!("cargo:info=Using cross-compiler {}: {}", tool, compiler);
         }
 
         // Set system name for cross-compilation
@@ -1683,6 +4176,75 @@ fn main() {
         cmake_args.push("-DCMAKE_CXX_FLAGS=-fPIC -fno-lto".to_string());
 
         println!("cargo:info=Configured for aarch64 cross-compilation with PIC");
+    } else if let Some(musl) = &armv7_musl {
+        // Set up cross-compilation for armv7 musl (Alpine)
+        for (tool, cmake_var) in [("CC", "CMAKE_C_COMPILER"), ("CXX", "CMAKE_CXX_COMPILER")] {
+            let compiler = musl.compiler(tool);
+            cmake_args.push(format!("-D{}={}", cmake_var, compiler));
+            println!("cargo:info=Using cross-compiler {}: {}", tool, compiler);
+        }
+
+        cmake_args.push("-DCMAKE_SYSTEM_NAME=Linux".to_string());
+        cmake_args.push("-DCMAKE_SYSTEM_PROCESSOR=armv7".to_string());
+
+        let flags = format!("-fPIC -fno-lto {}", musl.arch_flags);
+        cmake_args.push(format!("-DCMAKE_C_FLAGS={}", flags));
+        cmake_args.push(format!("-DCMAKE_CXX_FLAGS={}", flags));
+
+        println!(
+            "cargo:info=Configured for {} cross-compilation",
+            musl.triple
+        );
+    } else if let Some(cortex_m) = &cortex_m {
+        for (tool, cmake_var) in [("CC", "CMAKE_C_COMPILER"), ("CXX", "CMAKE_CXX_COMPILER")] {
+            let compiler = cortex_m.compiler(tool);
+            cmake_args.push(format!("-D{}={}", cmake_var, compiler));
+            println!("cargo:info=Using cross-compiler {}: {}", tool, compiler);
+        }
+
+        // No OS: CMake can't link test executables without the firmware's startup code and
+        // linker script
+        cmake_args.push("-DCMAKE_SYSTEM_NAME=Generic".to_string());
+        cmake_args.push("-DCMAKE_SYSTEM_PROCESSOR=arm".to_string());
+        cmake_args.push("-DCMAKE_TRY_COMPILE_TARGET_TYPE=STATIC_LIBRARY".to_string());
+        cmake_args.push("-DEI_FFI_BARE_METAL=ON".to_string());
+
+        cmake_args.push(format!("-DCMAKE_C_FLAGS={}", cortex_m.flags()));
+        cmake_args.push(format!("-DCMAKE_CXX_FLAGS={}", cortex_m.flags()));
+
+        println!(
+            "cargo:info=Configured for {} with {}",
+            cortex_m.triple, cortex_m.arch_flags
+        );
+    } else if let Some(esp_idf) = &esp_idf {
+        for (tool, cmake_var) in [("CC", "CMAKE_C_COMPILER"), ("CXX", "CMAKE_CXX_COMPILER")] {
+            let compiler = esp_idf.compiler(tool);
+            cmake_args.push(format!("-D{}={}", cmake_var, compiler));
+            println!("cargo:info=Using cross-compiler {}: {}", tool, compiler);
+        }
+
+        // Built as a plain static library, outside of an ESP-IDF project: the SDK's own ESP-IDF
+        // porting layer isn't used, so no ESP-IDF headers are needed
+        cmake_args.push("-DCMAKE_SYSTEM_NAME=Generic".to_string());
+        cmake_args.push("-DCMAKE_SYSTEM_PROCESSOR=xtensa".to_string());
+        cmake_args.push("-DCMAKE_TRY_COMPILE_TARGET_TYPE=STATIC_LIBRARY".to_string());
+        cmake_args.push(format!("-DCMAKE_C_FLAGS={}", esp_idf.flags()));
+        cmake_args.push(format!("-DCMAKE_CXX_FLAGS={}", esp_idf.flags()));
+        cmake_args.push(format!("-DCMAKE_ASM_FLAGS={}", esp_idf.flags()));
+        // Espressif's optimized TFLite Micro kernels
+        cmake_args.push(format!("-DEI_FFI_ESP_NN={}", esp_idf.chip));
+
+        println!("cargo:info=Configured for {} with ESP-NN", esp_idf.triple);
+    } else if let Some(emscripten) = &emscripten {
+        // The toolchain file picks emcc/em++ and sets EMSCRIPTEN for CMakeLists.txt
+        cmake_args.push(format!(
+            "-DCMAKE_TOOLCHAIN_FILE={}",
+            cmake_path(&emscripten.toolchain_file)
+        ));
+        println!(
+            "cargo:info=Configured for Emscripten with {}",
+            emscripten.toolchain_file.display()
+        );
     }
 
     if use_full_tflite {
@@ -1740,41 +4302,148 @@ fn main() {
 
     // If we have a valid model, check if we need to build the C++ library
     if has_valid_model {
-        // Check if the library already exists
-        let lib_path = build_dir.join("libedge-impulse-sdk.a");
-        let should_rebuild = !lib_path.exists() || env::var("FORCE_REBUILD").is_ok();
+        // Rebuild unless the library was built for this target from the same model, glue and
+        // CMake arguments
+        let lib_path = build_dir.join(lib_file_name);
+        let target_matches = read_build_target_stamp(&build_dir).as_deref() == Some(&build_target);
+        let inputs_hash = build_inputs_hash(&cpp_dir, &cmake_args);
+        let inputs_match = read_build_inputs_stamp(&build_dir) == Some(inputs_hash);
+        let force_rebuild = env::var("FORCE_REBUILD").is_ok();
+        let should_rebuild =
+            !lib_path.exists() || !target_matches || !inputs_match || force_rebuild;
 
         if should_rebuild {
             if !lib_path.exists() {
                 println!("cargo:warning=Library not found, building C++ library...");
+            } else if !target_matches {
+                println!(
+                    "cargo:warning=Library in {} was not built for {}, rebuilding C++ library...",
+                    build_dir.display(),
+                    build_target
+                );
+            } else if !inputs_match {
+                println!(
+                    "cargo:warning=Model files or build settings changed since {} was built, rebuilding C++ library...",
+                    lib_path.display()
+                );
             } else {
                 println!("cargo:warning=Force rebuild requested, rebuilding C++ library...");
             }
 
-            println!("cargo:warning=CMake args: {:?}", cmake_args);
-            let cmake_status = Command::new("cmake")
-                .args(&cmake_args)
-                .current_dir(&build_dir)
-                .status()
-                .expect("Failed to run cmake configure");
+            // A failed build must not leave the previous library behind to be linked
+            let _ = fs::remove_file(&lib_path);
+            let _ = fs::remove_file(build_dir.join(BUILD_INPUTS_STAMP));
+            // FORCE_REBUILD also starts CMake over, e.g. after the checkout moved
+            if force_rebuild {
+                let _ = fs::remove_file(build_dir.join("CMakeCache.txt"));
+            }
 
-            if !cmake_status.success() {
-                panic!("CMake configuration failed");
+            if !use_cc {
+                println!("cargo:warning=CMake args: {:?}", cmake_args);
             }
 
-            // Build the library
-            let make_status = Command::new("make")
-                .arg("-j")
-                .arg(env::var("NUM_JOBS").unwrap_or_else(|_| "4".to_string()))
-                .current_dir(&build_dir)
-                .status()
-                .expect("Failed to run make");
+            // Rename or hide the symbols of a freshly built single-architecture library
+            let post_process = |library: &Path, platform: &str| {
+                if let Some(prefix) = &symbol_prefix {
+                    prefix_library_symbols(library, prefix, &target_os);
+                }
+
+                // Shared libraries are isolated by CMake at link time
+                if isolate_symbols && !build_shared {
+                    let tflite_archives: Vec<PathBuf> = if use_full_tflite {
+                        let tflite_lib_dir = manifest_path.join("tflite").join(platform);
+                        TFLITE_PREBUILT_LIBS
+                            .iter()
+                            .map(|lib| tflite_lib_dir.join(format!("lib{}.a", lib)))
+                            .filter(|archive| archive.exists())
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    isolate_library_symbols(library, &tflite_archives, &target_os);
+                }
+            };
 
-            if !make_status.success() {
-                panic!("Make build failed");
+            if use_cc {
+                let toolchain = if let Some(musl) = &armv7_musl {
+                    Some((
+                        musl.compiler("CC"),
+                        musl.compiler("CXX"),
+                        format!("-fno-lto {}", musl.arch_flags),
+                    ))
+                } else if let Some(cortex_m) = &cortex_m {
+                    Some((
+                        cortex_m.compiler("CC"),
+                        cortex_m.compiler("CXX"),
+                        cortex_m.flags(),
+                    ))
+                } else {
+                    esp_idf.as_ref().map(|esp_idf| {
+                        (
+                            esp_idf.compiler("CC"),
+                            esp_idf.compiler("CXX"),
+                            esp_idf.flags().to_string(),
+                        )
+                    })
+                };
+                CcSdkBuild {
+                    model_dir: manifest_path.join(model_dir),
+                    build_dir: &build_dir,
+                    use_full_tflite,
+                    sanitize: sanitize.as_deref(),
+                    msvc: msvc.is_some(),
+                    toolchain,
+                    pic: cortex_m.is_none() && esp_idf.is_none() && emscripten.is_none(),
+                    bare_metal: cortex_m.is_some(),
+                    esp_nn: esp_idf.as_ref().map(|esp_idf| esp_idf.chip),
+                }
+                .compile();
+                post_process(&lib_path, target_platform);
+            } else if macos_universal {
+                // One build per architecture, so the symbol tools only ever see thin objects
+                let slices: Vec<PathBuf> = MACOS_UNIVERSAL_ARCHS
+                    .iter()
+                    .map(|&(arch, platform)| {
+                        let slice_dir = build_dir.join(arch);
+                        std::fs::create_dir_all(&slice_dir)
+                            .expect("Failed to create build directory");
+                        copy_tflite_model_files(
+                            &manifest_path.join("model/tflite-model"),
+                            &slice_dir,
+                        );
+                        let mut slice_args = cmake_args.clone();
+                        slice_args.push(format!("-DCMAKE_OSX_ARCHITECTURES={}", arch));
+                        if use_full_tflite {
+                            slice_args.push(format!("-DTARGET_PLATFORM={}", platform));
+                        }
+                        run_cmake_build(
+                            &slice_dir,
+                            &slice_args,
+                            build_type,
+                            None,
+                            &format!("the {} C++ library", arch),
+                        );
+                        let slice = slice_dir.join(lib_file_name);
+                        post_process(&slice, platform);
+                        slice
+                    })
+                    .collect();
+                create_universal_library(&slices, &lib_path);
+            } else {
+                run_cmake_build(
+                    &build_dir,
+                    &cmake_args,
+                    build_type,
+                    msvc.as_ref(),
+                    "the C++ library",
+                );
+                post_process(&lib_path, target_platform);
             }
+
+            write_build_target_stamp(&build_dir, &build_target);
+            write_build_inputs_stamp(&build_dir, inputs_hash);
         } else {
-            println!("cargo:warning=Library already exists, skipping build");
+            println!("cargo:warning=Library is up to date, skipping build");
         }
 
         // Diagnostic: print contents of build directory
@@ -1791,10 +4460,30 @@ fn main() {
         println!("cargo:info=Setting up library linking for valid model");
         println!("cargo:info=Build directory: {}", build_dir.display());
 
+        // Never link a library that was built for a different target
+        match read_build_target_stamp(&build_dir) {
+            Some(built_for) if built_for == build_target => {}
+            Some(built_for) => panic!(
+                "{} in {} was built for {} but the current target is {}. Rebuild with FORCE_REBUILD=1.",
+                lib_file_name,
+                build_dir.display(),
+                built_for,
+                build_target
+            ),
+            None => panic!(
+                "{} in {} has no target stamp. Rebuild with FORCE_REBUILD=1.",
+                lib_file_name,
+                build_dir.display()
+            ),
+        }
+
+        // Make sure the library actually contains code for the architecture we're linking
+        let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+        check_library_arch(&build_dir.join(lib_file_name), &target_arch);
+        report_sizes(&build_dir.join(lib_file_name), Path::new(model_dir));
+
         // Tell Cargo where to find the built library - use absolute path
-        let absolute_build_dir = build_dir
-            .canonicalize()
-            .expect("Failed to get absolute path");
+        let absolute_build_dir = absolute_dir(&build_dir);
         println!(
             "cargo:rustc-link-search=native={}",
             absolute_build_dir.display()
@@ -1802,19 +4491,82 @@ fn main() {
 
         // Link against the Edge Impulse SDK library
         // The library name will depend on what CMake generates, typically something like "edge-impulse-sdk"
-        println!("cargo:rustc-link-lib=static=edge-impulse-sdk");
+        if build_shared {
+            println!("cargo:rustc-link-lib=dylib=edge-impulse-sdk");
+
+            // Find the library next to the executable when deployed, and in the build dir during development
+            if target_os != "windows" {
+                let origin = if target_os == "macos" || target_os == "ios" {
+                    "@loader_path"
+                } else {
+                    "$ORIGIN"
+                };
+                println!("cargo:rustc-link-arg=-Wl,-rpath,{}", origin);
+                println!(
+                    "cargo:rustc-link-arg=-Wl,-rpath,{}",
+                    absolute_build_dir.display()
+                );
+            }
+
+            install_shared_library(&absolute_build_dir.join(lib_file_name));
+        } else {
+            println!("cargo:rustc-link-lib=static=edge-impulse-sdk");
+        }
+
+        // Extra models are linked before the C++ standard library they depend on
+        for (name, extra_model_dir) in &extra_models {
+            build_extra_model(
+                name,
+                extra_model_dir,
+                &cmake_args,
+                &cargo_target,
+                &target_os,
+            );
+        }
+
+        // Pull in the sanitizer runtimes the instrumented SDK code calls into
+        if let Some(sanitize) = &sanitize {
+            println!("cargo:rustc-link-arg=-fsanitize={}", sanitize);
+        }
 
         // Link against C++ standard library
-        if env::var("TARGET_LINUX_AARCH64").is_ok() {
+        if msvc.is_some() {
+            // cl.exe objects request the C++ runtime matching their CRT (msvcprt or libcpmt)
+            // themselves
+        } else if aarch64_linux_cross() {
             // For aarch64 cross-compilation, use the ARM64 C++ standard library (dynamic)
             println!("cargo:rustc-link-lib=dylib=stdc++");
+        } else if armv7_musl.is_some() {
+            // musl binaries are linked statically, so use the toolchain's static libstdc++
+            println!("cargo:rustc-link-lib=static=stdc++");
+        } else if esp_idf.is_some() {
+            // GCC's libstdc++ from the ESP-IDF toolchain, which the firmware link already knows
+            println!("cargo:rustc-link-lib=stdc++");
+        } else if let Some(cortex_m) = &cortex_m {
+            // The firmware is linked by rust-lld or arm-none-eabi-gcc, neither of which knows
+            // the toolchain's multilib directories, so point at the ones for the target flags.
+            // libnosys stubs the syscalls newlib needs unless the firmware defines them.
+            for library in ["libstdc++.a", "libc.a", "libgcc.a"] {
+                match cortex_m.library_dir(library) {
+                    Some(dir) => println!("cargo:rustc-link-search=native={}", dir.display()),
+                    None => println!(
+                        "cargo:warning={} not found in the {} toolchain",
+                        library, CORTEX_M_TOOLCHAIN
+                    ),
+                }
+            }
+            // Not bundled into the rlib: they belong to the toolchain, not to this crate
+            for library in ["stdc++", "m", "c", "gcc", "nosys"] {
+                println!("cargo:rustc-link-lib=static:-bundle={}", library);
+            }
         } else {
             // Use c++ for macOS and other platforms
             println!("cargo:rustc-link-lib=c++");
         }
 
         // Link against prebuilt TensorFlow Lite libraries when using full TensorFlow Lite
-        if use_full_tflite {
+        // (a shared SDK library already has them linked in by CMake)
+        if use_full_tflite && !build_shared {
             let tflite_lib_dir = format!("tflite/{}", target_platform);
             let tflite_lib_path = Path::new(&tflite_lib_dir);
             let cwd = std::env::current_dir().unwrap();
@@ -1833,19 +4585,23 @@ fn main() {
             );
             // Check if TensorFlow Lite libraries exist (they might not when building from git)
             if tflite_lib_path.exists() {
-                println!("cargo:rustc-link-search=native={}", tflite_lib_dir);
-
-                // Link against prebuilt TensorFlow Lite and XNNPACK libraries in the correct order
-                // This matches the official Makefile: -ltensorflow-lite -lcpuinfo -lfarmhash -lfft2d_fftsg -lfft2d_fftsg2d -lruy -lXNNPACK -lpthreadpool
-                println!("cargo:rustc-link-lib=static=tensorflow-lite");
-                println!("cargo:rustc-link-lib=static=cpuinfo");
-                println!("cargo:rustc-link-lib=static=farmhash");
-                println!("cargo:rustc-link-lib=static=fft2d_fftsg");
-                println!("cargo:rustc-link-lib=static=fft2d_fftsg2d");
-                println!("cargo:rustc-link-lib=static=ruy");
-                println!("cargo:rustc-link-lib=static=XNNPACK");
-                println!("cargo:rustc-link-lib=static=pthreadpool");
-                println!("cargo:rustc-link-lib=static=flatbuffers");
+                for lib in TFLITE_PREBUILT_LIBS {
+                    let archive = tflite_lib_path.join(format!("lib{}.a", lib));
+                    if archive.exists() {
+                        check_library_arch(&archive, &target_arch);
+                    }
+                }
+
+                // Isolated builds already contain TensorFlow Lite inside the SDK library
+                if !isolate_symbols {
+                    println!("cargo:rustc-link-search=native={}", tflite_lib_dir);
+
+                    // Link against prebuilt TensorFlow Lite and XNNPACK libraries in the correct order
+                    // This matches the official Makefile: -ltensorflow-lite -lcpuinfo -lfarmhash -lfft2d_fftsg -lfft2d_fftsg2d -lruy -lXNNPACK -lpthreadpool
+                    for lib in TFLITE_PREBUILT_LIBS {
+                        println!("cargo:rustc-link-lib=static={}", lib);
+                    }
+                }
 
                 // Add system libraries that TensorFlow Lite depends on
                 println!("cargo:rustc-link-lib=dl");
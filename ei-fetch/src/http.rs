@@ -0,0 +1,567 @@
+//! HTTP client for the Studio API
+//!
+//! Requests are plain [`Request`] values sent through a client trait: [`HttpClient`] is
+//! blocking and implemented with ureq, [`AsyncHttpClient`] (`async` feature) with reqwest.
+//! Both are configured from one [`ClientConfig`] and retried by the same [`RetryPolicy`], so
+//! the two paths behave the same and only the retry logic needs tests. Downloads that break
+//! off are resumed with range requests, see [`ResponseHead::resume_offset`]. The proxy is
+//! resolved once by [`proxy_from_env`](crate::proxy::proxy_from_env), so both clients honor the same variables.
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+/// Request method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// An HTTP request, independent of the client that sends it
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    /// Limit on the whole request, including reading the response body
+    pub timeout: Option<Duration>,
+}
+
+impl Request {
+    pub fn get(url: &str) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    /// A POST request with a JSON body
+    pub fn post_json(url: &str, body: &serde_json::Value) -> Self {
+        Self {
+            method: Method::Post,
+            url: url.to_string(),
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: Some(body.to_string().into_bytes()),
+            timeout: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Ask for the rest of a resource whose first `offset` bytes were already received
+    pub fn range_from(self, offset: usize) -> Self {
+        if offset == 0 {
+            return self;
+        }
+        self.header("range", &format!("bytes={}-", offset))
+    }
+}
+
+/// Status and headers of a response
+#[derive(Debug, Clone, Default)]
+pub struct ResponseHead {
+    pub status: u16,
+    /// Header names in lower case
+    pub headers: Vec<(String, String)>,
+}
+
+impl ResponseHead {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// `Content-Length`, if the server sent it
+    pub fn content_length(&self) -> Option<usize> {
+        self.header("content-length")?.trim().parse().ok()
+    }
+
+    /// Delay requested by a `Retry-After` header in seconds (HTTP dates aren't supported)
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.header("retry-after")
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs)
+    }
+
+    /// Where the body of a response to [`Request::range_from`]`(offset)` starts: `offset` if the
+    /// server sent the requested range, 0 if it ignored the range and sent everything
+    pub fn resume_offset(&self, offset: usize) -> Result<usize, String> {
+        match self.status {
+            206 => match self.content_range() {
+                Some((start, _)) if start == offset => Ok(offset),
+                _ => Err(format!(
+                    "Server resumed the download at the wrong position (Content-Range: {})",
+                    self.header("content-range").unwrap_or("missing")
+                )),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    /// Size of the whole resource: the total of a `Content-Range`, else `Content-Length`
+    pub fn total_length(&self) -> Option<usize> {
+        match self.status {
+            206 => self.content_range().and_then(|(_, total)| total),
+            _ => self.content_length(),
+        }
+    }
+
+    /// Start and total size of a `Content-Range: bytes <start>-<end>/<total>` header
+    fn content_range(&self) -> Option<(usize, Option<usize>)> {
+        let range = self
+            .header("content-range")?
+            .trim()
+            .strip_prefix("bytes ")?;
+        let (span, total) = range.split_once('/')?;
+        let start = span.split_once('-')?.0.trim().parse().ok()?;
+        Some((start, total.trim().parse().ok()))
+    }
+}
+
+/// A response of a blocking client, with the body still to be read
+pub struct Response {
+    pub head: ResponseHead,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl Response {
+    pub fn into_json<T: serde::de::DeserializeOwned>(self) -> Result<T, String> {
+        serde_json::from_reader(self.body).map_err(|e| e.to_string())
+    }
+}
+
+/// Proxy, timeout and TLS settings shared by every client
+///
+/// Both clients verify certificates with rustls against the bundled webpki roots.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Proxy URL for every request, e.g. `http://proxy:3128`; `None` connects directly
+    ///
+    /// The clients don't read proxy variables themselves, see
+    /// [`proxy_from_env`](crate::proxy::proxy_from_env).
+    pub proxy: Option<String>,
+    /// Timeout for connecting to the server
+    pub connect_timeout: Duration,
+    /// Longest wait for the next data from the server, which ends stalled downloads
+    pub read_timeout: Duration,
+    pub user_agent: String,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(60),
+            user_agent: concat!("ei-fetch/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+}
+
+/// When to retry failed requests: rate limits (HTTP 429), server errors (HTTP 5xx) and
+/// transport errors such as dropped connections and timeouts
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Longest wait between retries
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (starting at 1) of a request answered with `head`,
+    /// `None` if it shouldn't be retried
+    ///
+    /// Waits for as long as the `Retry-After` header asks, or backs off exponentially if it's
+    /// missing.
+    pub fn delay(&self, attempt: u32, head: &ResponseHead) -> Option<Duration> {
+        if !is_transient(head.status) || attempt > self.max_retries {
+            return None;
+        }
+        let delay = head.retry_after().unwrap_or_else(|| Self::backoff(attempt));
+        Some(delay.min(self.max_delay))
+    }
+
+    /// Delay before retry number `attempt` of a request that failed without a response, or of
+    /// a download that broke off, `None` once the retries are used up
+    pub fn error_delay(&self, attempt: u32) -> Option<Duration> {
+        (attempt <= self.max_retries).then(|| Self::backoff(attempt).min(self.max_delay))
+    }
+
+    fn backoff(attempt: u32) -> Duration {
+        Duration::from_secs(1 << attempt.min(6))
+    }
+
+    pub fn report(&self, attempt: u32, delay: Duration, reason: &str) {
+        eprintln!(
+            "\n      {}, retrying in {}s ({}/{})",
+            reason,
+            delay.as_secs(),
+            attempt,
+            self.max_retries
+        );
+    }
+}
+
+/// Statuses worth retrying: rate limits, timeouts and server errors that usually pass
+fn is_transient(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Why a request with a `head` response is retried
+fn retry_reason(head: &ResponseHead) -> String {
+    match head.status {
+        429 => "Rate limited by Studio".to_string(),
+        status => format!("Studio responded with status code {}", status),
+    }
+}
+
+/// Error for a response that wasn't successful, after any retries
+fn status_error(what: &str, request: &Request, head: &ResponseHead) -> String {
+    format!(
+        "Failed to {}: {}: status code {}",
+        what, request.url, head.status
+    )
+}
+
+/// A blocking HTTP client
+pub trait HttpClient {
+    /// Send a request; every status, including errors, is a response
+    fn send(&self, request: &Request) -> Result<Response, String>;
+
+    /// Send a request, retrying it under `policy`, and fail unless the final status is 2xx
+    fn send_with_retry(
+        &self,
+        request: &Request,
+        policy: &RetryPolicy,
+        what: &str,
+    ) -> Result<Response, String> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let (delay, reason) = match self.send(request) {
+                Ok(response) if response.head.is_success() => return Ok(response),
+                Ok(response) => match policy.delay(attempt, &response.head) {
+                    Some(delay) => (delay, retry_reason(&response.head)),
+                    None => return Err(status_error(what, request, &response.head)),
+                },
+                Err(e) => match policy.error_delay(attempt) {
+                    Some(delay) => (delay, format!("Request failed: {}", e)),
+                    None => return Err(format!("Failed to {}: {}", what, e)),
+                },
+            };
+            policy.report(attempt, delay, &reason);
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// Blocking client using ureq
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl UreqClient {
+    pub fn new(config: &ClientConfig) -> Result<Self, String> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout_connect(config.connect_timeout)
+            .timeout_read(config.read_timeout)
+            .user_agent(&config.user_agent);
+        if let Some(proxy) = &config.proxy {
+            let proxy =
+                ureq::Proxy::new(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(Self {
+            agent: builder.build(),
+        })
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn send(&self, request: &Request) -> Result<Response, String> {
+        let method = match request.method {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        };
+        let mut call = self.agent.request(method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        if let Some(timeout) = request.timeout {
+            call = call.timeout(timeout);
+        }
+        let result = match &request.body {
+            Some(body) => call.send_bytes(body),
+            None => call.call(),
+        };
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.to_string()),
+        };
+        let head = ResponseHead {
+            status: response.status(),
+            headers: response
+                .headers_names()
+                .into_iter()
+                .filter_map(|name| {
+                    let value = response.header(&name)?.to_string();
+                    Some((name.to_ascii_lowercase(), value))
+                })
+                .collect(),
+        };
+        Ok(Response {
+            head,
+            body: Box::new(response.into_reader()),
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::nonblocking::*;
+
+#[cfg(feature = "async")]
+mod nonblocking {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use futures_util::{Stream, StreamExt};
+
+    use super::*;
+
+    /// Chunks of a response body
+    pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, String>> + Send>>;
+
+    /// A response of an async client, with the body still to be read
+    pub struct AsyncResponse {
+        pub head: ResponseHead,
+        pub body: BodyStream,
+    }
+
+    /// An async HTTP client
+    pub trait AsyncHttpClient: Sync {
+        /// Send a request; every status, including errors, is a response
+        fn send(
+            &self,
+            request: &Request,
+        ) -> impl Future<Output = Result<AsyncResponse, String>> + Send;
+
+        /// Send a request, retrying it under `policy`, and fail unless the final status is 2xx
+        fn send_with_retry(
+            &self,
+            request: &Request,
+            policy: &RetryPolicy,
+            what: &str,
+        ) -> impl Future<Output = Result<AsyncResponse, String>> + Send {
+            async move {
+                let mut attempt = 0;
+                loop {
+                    attempt += 1;
+                    let (delay, reason) = match self.send(request).await {
+                        Ok(response) if response.head.is_success() => return Ok(response),
+                        Ok(response) => match policy.delay(attempt, &response.head) {
+                            Some(delay) => (delay, retry_reason(&response.head)),
+                            None => return Err(status_error(what, request, &response.head)),
+                        },
+                        Err(e) => match policy.error_delay(attempt) {
+                            Some(delay) => (delay, format!("Request failed: {}", e)),
+                            None => return Err(format!("Failed to {}: {}", what, e)),
+                        },
+                    };
+                    policy.report(attempt, delay, &reason);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Async client using reqwest
+    pub struct ReqwestClient {
+        client: reqwest::Client,
+    }
+
+    impl ReqwestClient {
+        pub fn new(config: &ClientConfig) -> Result<Self, String> {
+            let mut builder = reqwest::Client::builder()
+                .use_rustls_tls()
+                .connect_timeout(config.connect_timeout)
+                .read_timeout(config.read_timeout)
+                .user_agent(&config.user_agent);
+            builder = match &config.proxy {
+                Some(proxy) => builder.proxy(
+                    reqwest::Proxy::all(proxy)
+                        .map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?,
+                ),
+                // reqwest would read the proxy variables again, without EI_PROXY
+                None => builder.no_proxy(),
+            };
+            Ok(Self {
+                client: builder.build().map_err(|e| e.to_string())?,
+            })
+        }
+    }
+
+    impl AsyncHttpClient for ReqwestClient {
+        async fn send(&self, request: &Request) -> Result<AsyncResponse, String> {
+            let method = match request.method {
+                Method::Get => reqwest::Method::GET,
+                Method::Post => reqwest::Method::POST,
+            };
+            let mut call = self.client.request(method, &request.url);
+            for (name, value) in &request.headers {
+                call = call.header(name, value);
+            }
+            if let Some(body) = &request.body {
+                call = call.body(body.clone());
+            }
+            if let Some(timeout) = request.timeout {
+                call = call.timeout(timeout);
+            }
+            let response = call.send().await.map_err(|e| e.to_string())?;
+            let head = ResponseHead {
+                status: response.status().as_u16(),
+                headers: response
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+            };
+            let body = response
+                .bytes_stream()
+                .map(|chunk| chunk.map(|bytes| bytes.to_vec()).map_err(|e| e.to_string()));
+            Ok(AsyncResponse {
+                head,
+                body: Box::pin(body),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io;
+
+    /// Answers with the given statuses in order, failing without a response for status 0
+    struct Scripted(RefCell<Vec<u16>>);
+
+    impl HttpClient for Scripted {
+        fn send(&self, _request: &Request) -> Result<Response, String> {
+            let status = self.0.borrow_mut().remove(0);
+            if status == 0 {
+                return Err("connection reset".to_string());
+            }
+            Ok(Response {
+                head: ResponseHead {
+                    status,
+                    headers: vec![("retry-after".to_string(), "0".to_string())],
+                },
+                body: Box::new(io::empty()),
+            })
+        }
+    }
+
+    #[test]
+    fn retries_rate_limited_requests() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            max_delay: Duration::from_secs(120),
+        };
+        let request = Request::get("https://studio.example/v1/api/1");
+
+        let client = Scripted(RefCell::new(vec![429, 429, 200]));
+        let response = client.send_with_retry(&request, &policy, "get").unwrap();
+        assert_eq!(response.head.status, 200);
+
+        let client = Scripted(RefCell::new(vec![429, 429, 429]));
+        let error = client.send_with_retry(&request, &policy, "get").err();
+        assert_eq!(
+            error.as_deref(),
+            Some("Failed to get: https://studio.example/v1/api/1: status code 429")
+        );
+
+        // Other errors aren't retried
+        let client = Scripted(RefCell::new(vec![404, 200]));
+        assert!(client.send_with_retry(&request, &policy, "get").is_err());
+
+        let head = ResponseHead {
+            status: 429,
+            headers: Vec::new(),
+        };
+        assert_eq!(policy.delay(1, &head), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(3, &head), None);
+    }
+
+    #[test]
+    fn retries_server_and_transport_errors() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            max_delay: Duration::ZERO,
+        };
+        let request = Request::get("https://studio.example/v1/api/1");
+
+        let client = Scripted(RefCell::new(vec![503, 0, 502, 200]));
+        let response = client.send_with_retry(&request, &policy, "get").unwrap();
+        assert_eq!(response.head.status, 200);
+
+        let client = Scripted(RefCell::new(vec![0, 0, 0, 0]));
+        let error = client.send_with_retry(&request, &policy, "get").err();
+        assert_eq!(error.as_deref(), Some("Failed to get: connection reset"));
+
+        let policy = RetryPolicy {
+            max_delay: Duration::from_secs(5),
+            ..policy
+        };
+        assert_eq!(policy.error_delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(policy.error_delay(3), Some(Duration::from_secs(5)));
+        assert_eq!(policy.error_delay(4), None);
+    }
+
+    #[test]
+    fn resumes_at_the_content_range() {
+        let head = |status, range: &str| ResponseHead {
+            status,
+            headers: vec![
+                ("content-range".to_string(), range.to_string()),
+                ("content-length".to_string(), "400".to_string()),
+            ],
+        };
+        let partial = head(206, "bytes 600-999/1000");
+        assert_eq!(partial.resume_offset(600), Ok(600));
+        assert_eq!(partial.total_length(), Some(1000));
+        assert!(partial.resume_offset(500).is_err());
+
+        // The server ignored the range and sent everything again
+        let full = head(200, "");
+        assert_eq!(full.resume_offset(600), Ok(0));
+        assert_eq!(full.total_length(), Some(400));
+
+        let request = Request::get("https://studio.example/export.zip");
+        assert!(request.clone().range_from(0).headers.is_empty());
+        assert_eq!(
+            request.range_from(600).headers,
+            vec![("range".to_string(), "bytes=600-".to_string())]
+        );
+    }
+}
@@ -0,0 +1,979 @@
+//! Download an Edge Impulse model from Studio into `model/`
+//!
+//! Building a model in Studio takes several minutes, which is too long to run inside
+//! `cargo build` (IDEs and rust-analyzer run build scripts in the background). This tool does
+//! the download up front; afterwards `build.rs` only uses the extracted files.
+//!
+//! ```sh
+//! cargo run -p ei-fetch -- --project-id 12345 --api-key ei_xxx
+//! ```
+
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::http::{
+    ClientConfig, HttpClient, Request, Response, ResponseHead, RetryPolicy, UreqClient,
+};
+use crate::proxy::proxy_from_env;
+
+mod http;
+mod proxy;
+
+const USAGE: &str = "\
+Download an Edge Impulse model into model/ ahead of `cargo build`
+
+Usage: cargo run -p ei-fetch -- [OPTIONS]
+
+Options:
+  --project-id <ID>   Edge Impulse project ID [env: EI_PROJECT_ID]
+  --api-key <KEY>     Project API key [env: EI_API_KEY]
+  --impulse-id <ID>   Impulse to build, for projects with several impulses
+                      [env: EI_IMPULSE_ID, default: the project's default impulse]
+  --engine <ENGINE>   Inference engine, tflite-eon or tflite [env: EI_ENGINE, default: tflite-eon]
+  --host <URL>        Studio host [env: EDGE_IMPULSE_STUDIO_HOST, default: https://studio.edgeimpulse.com]
+  --out <DIR>         Model directory to extract into [default: model/ of the workspace]
+  --max-concurrent <N>
+                      Fetches of the same project allowed at once, across processes
+                      [env: EI_FETCH_MAX_CONCURRENT, default: 1]
+  --max-retries <N>   Retries of requests failing with rate limits, server errors or dropped
+                      connections, and resumes of broken downloads
+                      [env: EI_FETCH_MAX_RETRIES, default: 5]
+  --proxy <URL>       Proxy for all requests to Studio [env: EI_PROXY, default: HTTPS_PROXY,
+                      HTTP_PROXY or ALL_PROXY, unless NO_PROXY lists the host]
+  --timeout <SECS>    Timeout of each API call, and the longest a download may stall
+                      [env: EI_FETCH_TIMEOUT, default: 60]
+  --cache-dir <DIR>   Cache of downloaded deployments [env: EI_FETCH_CACHE_DIR,
+                      default: ~/.cache/edge-impulse-ffi-rs]
+  --no-cache          Always build and download, without reading or writing the cache
+  --deploy-version <N>
+                      Use this deployment version instead of building, from the cache or
+                      Studio's last deployment [env: EI_DEPLOY_VERSION]
+  --sha256 <HEX>      SHA-256 the deployment ZIP must have [env: EI_MODEL_SHA256]
+  -h, --help          Print this help
+";
+
+/// Interval between job status polls
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Give up waiting for the build after this long
+const BUILD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Interval between attempts to get a fetch slot held by another process
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Longest wait between retries of a failed request
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+/// Name of the cache directory inside the user's cache directory
+const CACHE_NAME: &str = "edge-impulse-ffi-rs";
+
+// JSON response structures for Edge Impulse API
+#[derive(Debug, Deserialize)]
+struct ProjectResponse {
+    success: bool,
+    #[serde(rename = "defaultImpulseId")]
+    default_impulse_id: Option<i32>,
+    project: Option<Project>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    id: u32,
+    name: Option<String>,
+    owner: Option<String>,
+    #[serde(alias = "publicProjectLicense")]
+    license: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImpulsesResponse {
+    success: bool,
+    #[serde(default)]
+    impulses: Vec<Impulse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Impulse {
+    id: i32,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeploymentResponse {
+    success: bool,
+    #[serde(rename = "hasDeployment", default)]
+    has_deployment: bool,
+    version: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildJobResponse {
+    success: bool,
+    id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobStatusResponse {
+    success: bool,
+    job: JobStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobStatus {
+    category: String,
+    finished: Option<String>, // Can be a timestamp string when finished
+    #[serde(rename = "finishedSuccessful")]
+    finished_successful: Option<bool>,
+}
+
+struct Options {
+    project_id: String,
+    api_key: String,
+    /// `None` for the project's default impulse
+    impulse_id: Option<i32>,
+    engine: String,
+    host: String,
+    out: PathBuf,
+    max_concurrent: usize,
+    max_retries: u32,
+    timeout: Duration,
+    /// Explicit proxy, `None` uses the proxy environment variables
+    proxy: Option<String>,
+    /// `None` when caching is disabled
+    cache_dir: Option<PathBuf>,
+    /// Pinned deployment version, `None` builds the current impulse
+    deploy_version: Option<u32>,
+    /// Pinned SHA-256 of the deployment ZIP, as lowercase hex
+    sha256: Option<String>,
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            exit(2);
+        }
+    };
+
+    if let Err(message) = fetch(&options) {
+        eprintln!("error: {}", message);
+        exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut project_id = env::var("EI_PROJECT_ID").ok();
+    let mut api_key = env::var("EI_API_KEY").ok();
+    let mut impulse_id = env::var("EI_IMPULSE_ID").ok();
+    let mut engine = env::var("EI_ENGINE").ok();
+    let mut host = env::var("EDGE_IMPULSE_STUDIO_HOST").ok();
+    let mut out = None;
+    let mut max_concurrent = env::var("EI_FETCH_MAX_CONCURRENT").ok();
+    let mut max_retries = env::var("EI_FETCH_MAX_RETRIES").ok();
+    let mut timeout = env::var("EI_FETCH_TIMEOUT").ok();
+    let mut proxy = env::var("EI_PROXY").ok().filter(|proxy| !proxy.is_empty());
+    let mut cache_dir = env::var_os("EI_FETCH_CACHE_DIR").map(PathBuf::from);
+    let mut no_cache = false;
+    let mut deploy_version = env::var("EI_DEPLOY_VERSION").ok();
+    let mut sha256 = env::var("EI_MODEL_SHA256")
+        .ok()
+        .filter(|sha256| !sha256.is_empty());
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", arg))
+        };
+        match arg.as_str() {
+            "--project-id" => project_id = Some(value()?),
+            "--api-key" => api_key = Some(value()?),
+            "--impulse-id" => impulse_id = Some(value()?),
+            "--engine" => engine = Some(value()?),
+            "--host" => host = Some(value()?),
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--max-concurrent" => max_concurrent = Some(value()?),
+            "--max-retries" => max_retries = Some(value()?),
+            "--timeout" => timeout = Some(value()?),
+            "--proxy" => proxy = Some(value()?),
+            "--cache-dir" => cache_dir = Some(PathBuf::from(value()?)),
+            "--no-cache" => no_cache = true,
+            "--deploy-version" => deploy_version = Some(value()?),
+            "--sha256" => sha256 = Some(value()?),
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                exit(0);
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(Options {
+        project_id: project_id.ok_or("Missing --project-id (or EI_PROJECT_ID)")?,
+        api_key: api_key.ok_or("Missing --api-key (or EI_API_KEY)")?,
+        impulse_id: match impulse_id {
+            Some(value) => Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid --impulse-id: {}", value))?,
+            ),
+            None => None,
+        },
+        engine: engine.unwrap_or_else(|| "tflite-eon".to_string()),
+        host: host.unwrap_or_else(|| "https://studio.edgeimpulse.com".to_string()),
+        // The tool lives in a subdirectory of the edge-impulse-ffi-rs workspace
+        out: out.unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../model")),
+        max_concurrent: match max_concurrent {
+            Some(value) => match value.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(format!("Invalid --max-concurrent: {}", value)),
+            },
+            None => 1,
+        },
+        max_retries: match max_retries {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("Invalid --max-retries: {}", value))?,
+            None => 5,
+        },
+        timeout: match timeout {
+            Some(value) => match value.parse() {
+                Ok(secs) if secs > 0 => Duration::from_secs(secs),
+                _ => return Err(format!("Invalid --timeout: {}", value)),
+            },
+            None => Duration::from_secs(60),
+        },
+        proxy,
+        cache_dir: if no_cache {
+            None
+        } else {
+            cache_dir.or_else(default_cache_dir)
+        },
+        deploy_version: match deploy_version {
+            Some(value) => Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid --deploy-version: {}", value))?,
+            ),
+            None => None,
+        },
+        sha256: match sha256 {
+            Some(value) => {
+                let sha256 = value.trim().to_ascii_lowercase();
+                if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(format!("Invalid --sha256: {}", value));
+                }
+                Some(sha256)
+            }
+            None => None,
+        },
+    })
+}
+
+/// Build the model in Studio, download the deployment and extract it
+fn fetch(options: &Options) -> Result<(), String> {
+    let base_url = format!("{}/v1/api/{}", options.host, options.project_id);
+    let api_key = options.api_key.as_str();
+
+    eprintln!("Project ID: {}", options.project_id);
+    eprintln!("API Key: {}...", &api_key[..api_key.len().min(8)]);
+    eprintln!("Engine: {}", options.engine);
+
+    // Held until the model is extracted, so parallel builds don't start duplicate jobs
+    let _slot = acquire_slot(&options.project_id, options.max_concurrent)?;
+    let api = Api::new(options)?;
+
+    // Step 1: Get project information to find the impulse to build
+    eprintln!("[1/5] Getting project information...");
+    let project: ProjectResponse = api.get_json(&base_url, "get project info")?;
+    if !project.success {
+        return Err("Project API call was not successful".to_string());
+    }
+    let impulse_id = match options.impulse_id {
+        Some(impulse_id) => {
+            check_impulse(&api, &base_url, impulse_id)?;
+            impulse_id
+        }
+        None => {
+            let impulse_id = project
+                .default_impulse_id
+                .ok_or("No default impulse ID found in project")?;
+            eprintln!("      Default impulse ID: {}", impulse_id);
+            impulse_id
+        }
+    };
+
+    if let Some(version) = options.deploy_version {
+        return fetch_pinned(&api, &base_url, impulse_id, version, &project, options);
+    }
+
+    // Reuse the last deployment Studio built if it's already in the cache
+    if let Some(cache_dir) = &options.cache_dir {
+        if let Some(version) = last_deployment(&api, &base_url, impulse_id, &options.engine)? {
+            let cached = cache_path(cache_dir, options, version);
+            if let Some(zip_data) = read_cached(&cached) {
+                eprintln!(
+                    "      Deployment version {} found in {}, skipping the build",
+                    version,
+                    cached.display()
+                );
+                return install(zip_data, project.project.as_ref(), options);
+            }
+        }
+    }
+
+    // Step 2: Trigger build job
+    eprintln!("[2/5] Triggering model build job...");
+    let build_url = format!(
+        "{}/jobs/build-ondevice-model?type=zip&impulse={}",
+        base_url, impulse_id
+    );
+    let build: BuildJobResponse = api
+        .send(
+            Request::post_json(&build_url, &serde_json::json!({"engine": options.engine})),
+            "trigger build",
+        )?
+        .into_json()
+        .map_err(|e| format!("Failed to parse build response: {}", e))?;
+    if !build.success {
+        return Err("Build job creation was not successful".to_string());
+    }
+    eprintln!("      Build job ID: {}", build.id);
+
+    // Step 3: Poll job status until completion
+    eprintln!("[3/5] Waiting for the model build (typically 2-5 minutes)...");
+    wait_for_job(&api, &format!("{}/jobs/{}/status", base_url, build.id))?;
+
+    // Step 4: Download the model
+    eprintln!("[4/5] Downloading built model...");
+    let download_url = format!(
+        "{}/deployment/download?type=zip&impulse={}",
+        base_url, impulse_id
+    );
+    let zip_data = download(&api, &download_url)?;
+    cache_deployment(options, &zip_data);
+
+    // Step 5: Extract the model
+    install(zip_data, project.project.as_ref(), options)
+}
+
+/// Get a pinned deployment version without building: from the cache, or downloaded if it's
+/// still the last deployment Studio built
+fn fetch_pinned(
+    api: &Api,
+    base_url: &str,
+    impulse_id: i32,
+    version: u32,
+    project: &ProjectResponse,
+    options: &Options,
+) -> Result<(), String> {
+    eprintln!("      Pinned deployment version: {}", version);
+    if let Some(cache_dir) = &options.cache_dir {
+        let cached = cache_path(cache_dir, options, version);
+        if let Some(zip_data) = read_cached(&cached) {
+            eprintln!(
+                "      Deployment version {} found in {}",
+                version,
+                cached.display()
+            );
+            return install(zip_data, project.project.as_ref(), options);
+        }
+    }
+
+    // Studio only serves the last deployment it built
+    match last_deployment(api, base_url, impulse_id, &options.engine)? {
+        Some(last) if last == version => {}
+        Some(last) => {
+            return Err(format!(
+            "Deployment version {} is not cached and Studio's last {} deployment is version {}; \
+                 restore a cache containing it, or unset EI_DEPLOY_VERSION to build the impulse",
+            version, options.engine, last
+        ))
+        }
+        None => {
+            return Err(format!(
+                "Deployment version {} is not cached and Studio has no {} deployment of impulse {}",
+                version, options.engine, impulse_id
+            ))
+        }
+    }
+
+    eprintln!("[2/2] Downloading deployment version {}...", version);
+    let download_url = format!(
+        "{}/deployment/download?type=zip&impulse={}",
+        base_url, impulse_id
+    );
+    let zip_data = download(api, &download_url)?;
+    // Studio may have finished another build since it was asked for the version
+    match deploy_version(&zip_data) {
+        Some(downloaded) if downloaded == version => {}
+        downloaded => {
+            return Err(format!(
+                "Downloaded deployment version {} instead of the pinned version {}",
+                downloaded.map_or("unknown".to_string(), |v| v.to_string()),
+                version
+            ))
+        }
+    }
+    cache_deployment(options, &zip_data);
+    install(zip_data, project.project.as_ref(), options)
+}
+
+/// Version of the last deployment Studio built for the impulse and engine, if any
+fn last_deployment(
+    api: &Api,
+    base_url: &str,
+    impulse_id: i32,
+    engine: &str,
+) -> Result<Option<u32>, String> {
+    let deployment_url = format!(
+        "{}/deployment?type=zip&impulse={}&engine={}",
+        base_url, impulse_id, engine
+    );
+    let deployment: DeploymentResponse = api.get_json(&deployment_url, "get deployment info")?;
+    Ok(match deployment {
+        DeploymentResponse {
+            success: true,
+            has_deployment: true,
+            version,
+        } => version,
+        _ => None,
+    })
+}
+
+/// Store a downloaded deployment ZIP in the cache, if enabled
+fn cache_deployment(options: &Options, zip_data: &[u8]) {
+    let Some(cache_dir) = &options.cache_dir else {
+        return;
+    };
+    match deploy_version(zip_data) {
+        Some(version) => {
+            let cached = cache_path(cache_dir, options, version);
+            let written = write_atomically(&cached, zip_data).and_then(|()| {
+                fs::write(sha256_path(&cached), format!("{}\n", sha256_hex(zip_data)))
+            });
+            match written {
+                Ok(()) => eprintln!(
+                    "      Cached deployment version {} in {}",
+                    version,
+                    cached.display()
+                ),
+                Err(e) => eprintln!(
+                    "warning: failed to cache the deployment in {}: {}",
+                    cached.display(),
+                    e
+                ),
+            }
+        }
+        None => eprintln!("warning: no deploy version in the downloaded model, not caching it"),
+    }
+}
+
+/// A cached deployment ZIP, unless it doesn't match the SHA-256 recorded when it was cached
+///
+/// Caches written before hashes were recorded have no hash and are used as they are.
+fn read_cached(cached: &Path) -> Option<Vec<u8>> {
+    let zip_data = fs::read(cached).ok()?;
+    if let Ok(recorded) = fs::read_to_string(sha256_path(cached)) {
+        let sha256 = sha256_hex(&zip_data);
+        if recorded.trim() != sha256 {
+            eprintln!(
+                "warning: {} has SHA-256 {} instead of the recorded {}, ignoring the cached copy",
+                cached.display(),
+                sha256,
+                recorded.trim()
+            );
+            return None;
+        }
+    }
+    Some(zip_data)
+}
+
+/// Extract the model and record the project info and the ZIP's SHA-256 next to it
+fn install(zip_data: Vec<u8>, project: Option<&Project>, options: &Options) -> Result<(), String> {
+    let out = &options.out;
+    let sha256 = sha256_hex(&zip_data);
+    eprintln!("      Deployment SHA-256: {}", sha256);
+    if let Some(pinned) = &options.sha256 {
+        if &sha256 != pinned {
+            return Err(format!(
+                "Deployment has SHA-256 {}, but the pinned SHA-256 is {}; \
+                 refusing to extract a corrupted or different archive",
+                sha256, pinned
+            ));
+        }
+    }
+
+    eprintln!("[5/5] Extracting model files to {}...", out.display());
+    extract_model_zip(zip_data, out)?;
+    // Read by build.rs to check EI_MODEL_SHA256
+    let sha256_file = out.join(".ei-model-sha256");
+    fs::write(&sha256_file, format!("{}\n", sha256))
+        .map_err(|e| format!("Failed to write {}: {}", sha256_file.display(), e))?;
+    let info_path = out.join("ei-project.txt");
+    match project {
+        Some(project) => fs::write(&info_path, project_info(project))
+            .map_err(|e| format!("Failed to write {}: {}", info_path.display(), e))?,
+        // Don't leave the info of a previously fetched project behind
+        None => {
+            let _ = fs::remove_file(&info_path);
+        }
+    }
+
+    eprintln!("Model is ready. Run `cargo build` to build edge-impulse-ffi-rs against it.");
+    Ok(())
+}
+
+/// `key = value` lines read by `build.rs`, with strings as escaped Rust literals
+fn project_info(project: &Project) -> String {
+    let mut info = format!("id = {}\n", project.id);
+    for (key, value) in [
+        ("name", &project.name),
+        ("owner", &project.owner),
+        ("license", &project.license),
+    ] {
+        if let Some(value) = value {
+            info.push_str(&format!("{} = {:?}\n", key, value));
+        }
+    }
+    info
+}
+
+/// Make sure the project has the impulse, listing the ones it has otherwise
+fn check_impulse(api: &Api, base_url: &str, impulse_id: i32) -> Result<(), String> {
+    let list: ImpulsesResponse =
+        api.get_json(&format!("{}/impulses", base_url), "list impulses")?;
+    if !list.success {
+        return Err("Impulse list API call was not successful".to_string());
+    }
+    let name = |impulse: &Impulse| impulse.name.clone().unwrap_or_default();
+    match list
+        .impulses
+        .iter()
+        .find(|impulse| impulse.id == impulse_id)
+    {
+        Some(impulse) => {
+            eprintln!("      Impulse ID: {} ({})", impulse_id, name(impulse));
+            Ok(())
+        }
+        None => Err(format!(
+            "Project has no impulse {}, available impulses: {}",
+            impulse_id,
+            list.impulses
+                .iter()
+                .map(|impulse| format!("{} ({})", impulse.id, name(impulse)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Studio API client that waits out rate limits and retries transient failures
+struct Api<'a> {
+    api_key: &'a str,
+    /// Configuration of the async client used for downloads
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    config: ClientConfig,
+    client: UreqClient,
+    retry: RetryPolicy,
+    /// Timeout of each API call
+    timeout: Duration,
+}
+
+impl<'a> Api<'a> {
+    fn new(options: &'a Options) -> Result<Self, String> {
+        let config = ClientConfig {
+            proxy: options
+                .proxy
+                .clone()
+                .or_else(|| proxy_from_env(&options.host)),
+            read_timeout: options.timeout,
+            ..ClientConfig::default()
+        };
+        Ok(Self {
+            api_key: &options.api_key,
+            client: UreqClient::new(&config)?,
+            config,
+            retry: RetryPolicy {
+                max_retries: options.max_retries,
+                max_delay: MAX_RETRY_DELAY,
+            },
+            timeout: options.timeout,
+        })
+    }
+
+    /// `request` authenticated with the project's API key
+    fn authenticated(&self, request: Request) -> Request {
+        request.header("x-api-key", self.api_key)
+    }
+
+    /// Send an API call, retrying it on rate limits and transient failures
+    fn send(&self, request: Request, what: &str) -> Result<Response, String> {
+        let request = self.authenticated(request).timeout(self.timeout);
+        self.client.send_with_retry(&request, &self.retry, what)
+    }
+
+    fn get(&self, url: &str, what: &str) -> Result<Response, String> {
+        self.send(Request::get(url), what)
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str, what: &str) -> Result<T, String> {
+        self.get(url, what)?
+            .into_json()
+            .map_err(|e| format!("Failed to {}: invalid response: {}", what, e))
+    }
+}
+
+/// Take one of `max_concurrent` fetch slots of a project, waiting for another process to
+/// release one if needed
+///
+/// Slots are lock files in the temp directory; the OS releases them when the process exits, so
+/// an interrupted fetch never leaves a stale lock behind.
+fn acquire_slot(project_id: &str, max_concurrent: usize) -> Result<File, String> {
+    let mut waiting = false;
+    loop {
+        for slot in 0..max_concurrent {
+            let path = env::temp_dir().join(format!("ei-fetch-{}-{}.lock", project_id, slot));
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open lock file {}: {}", path.display(), e))?;
+            match file.try_lock() {
+                Ok(()) => {
+                    if waiting {
+                        eprintln!();
+                    }
+                    return Ok(file);
+                }
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => {
+                    return Err(format!("Failed to lock {}: {}", path.display(), e))
+                }
+            }
+        }
+        if !waiting {
+            eprint!(
+                "      Waiting for another ei-fetch of project {} to finish...",
+                project_id
+            );
+            let _ = io::stderr().flush();
+            waiting = true;
+        }
+        thread::sleep(LOCK_POLL_INTERVAL);
+    }
+}
+
+/// Poll a Studio job until it finishes, showing its status and the elapsed time
+fn wait_for_job(api: &Api, status_url: &str) -> Result<(), String> {
+    let started = Instant::now();
+    loop {
+        if started.elapsed() > BUILD_TIMEOUT {
+            eprintln!();
+            return Err(format!(
+                "Build timed out after {} minutes. Check the job in Edge Impulse Studio and try again.",
+                BUILD_TIMEOUT.as_secs() / 60
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+
+        let status: JobStatusResponse = api.get_json(status_url, "get job status")?;
+        if !status.success {
+            eprintln!();
+            return Err("Job status API call was not successful".to_string());
+        }
+
+        let elapsed = started.elapsed().as_secs();
+        eprint!(
+            "\r      {:<12} {:>2}m{:02}s elapsed",
+            status.job.category,
+            elapsed / 60,
+            elapsed % 60
+        );
+        let _ = io::stderr().flush();
+
+        if let (Some(successful), Some(_)) = (status.job.finished_successful, status.job.finished) {
+            eprintln!();
+            return if successful {
+                Ok(())
+            } else {
+                Err("Build failed on Edge Impulse servers".to_string())
+            };
+        }
+    }
+}
+
+/// The part of a download received so far
+///
+/// When the connection breaks off, the rest is requested with a range request; downloads
+/// aren't bound by the per-call timeout, only by the read timeout when they stall.
+#[derive(Default)]
+struct PartialDownload {
+    data: Vec<u8>,
+    total: Option<usize>,
+    /// Times the download broke off
+    breaks: u32,
+}
+
+impl PartialDownload {
+    /// Request for the rest of the file
+    fn request(&self, api: &Api, url: &str) -> Request {
+        api.authenticated(Request::get(url).range_from(self.data.len()))
+    }
+
+    /// Start receiving a response to [`request`](Self::request)
+    fn start(&mut self, head: &ResponseHead) -> Result<(), String> {
+        let offset = head.resume_offset(self.data.len())?;
+        if offset < self.data.len() {
+            eprintln!("\n      Studio can't resume the download, starting over");
+        }
+        self.data.truncate(offset);
+        self.total = head.total_length();
+        let remaining = self.total.unwrap_or(0).saturating_sub(offset);
+        self.data.reserve(remaining);
+        Ok(())
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.data.extend_from_slice(chunk);
+        show_progress(self.data.len(), self.total);
+    }
+
+    /// Delay before resuming after a response ended with `error`, or `None` if the file is
+    /// complete
+    fn resume_delay(
+        &mut self,
+        error: Option<String>,
+        policy: &RetryPolicy,
+    ) -> Result<Option<Duration>, String> {
+        let error = match (error, self.total) {
+            (Some(error), _) => error,
+            (None, Some(total)) if self.data.len() < total => format!(
+                "connection closed after {} of {} bytes",
+                self.data.len(),
+                total
+            ),
+            (None, _) => {
+                eprintln!();
+                return Ok(None);
+            }
+        };
+        self.breaks += 1;
+        match policy.error_delay(self.breaks) {
+            Some(delay) => {
+                let reason = format!("Download broke off ({})", error);
+                policy.report(self.breaks, delay, &reason);
+                Ok(Some(delay))
+            }
+            None => {
+                eprintln!();
+                Err(format!("Failed to read download data: {}", error))
+            }
+        }
+    }
+}
+
+/// Download a file into memory, showing the number of bytes received
+#[cfg(not(feature = "async"))]
+fn download(api: &Api, url: &str) -> Result<Vec<u8>, String> {
+    let mut download = PartialDownload::default();
+    let mut chunk = [0u8; 64 * 1024];
+    loop {
+        let response = api.client.send_with_retry(
+            &download.request(api, url),
+            &api.retry,
+            "download model",
+        )?;
+        download.start(&response.head)?;
+
+        let mut reader = response.body;
+        let error = loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break None,
+                Ok(read) => download.push(&chunk[..read]),
+                Err(e) => break Some(e.to_string()),
+            }
+        };
+        match download.resume_delay(error, &api.retry)? {
+            Some(delay) => thread::sleep(delay),
+            None => return Ok(download.data),
+        }
+    }
+}
+
+/// Download a file into memory with the async client, showing the number of bytes received
+#[cfg(feature = "async")]
+fn download(api: &Api, url: &str) -> Result<Vec<u8>, String> {
+    use crate::http::{AsyncHttpClient, ReqwestClient};
+    use futures_util::StreamExt;
+
+    let client = ReqwestClient::new(&api.config)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start the async runtime: {}", e))?;
+    runtime.block_on(async {
+        let mut download = PartialDownload::default();
+        loop {
+            let mut response = client
+                .send_with_retry(&download.request(api, url), &api.retry, "download model")
+                .await?;
+            download.start(&response.head)?;
+
+            let mut error = None;
+            while let Some(chunk) = response.body.next().await {
+                match chunk {
+                    Ok(chunk) => download.push(&chunk),
+                    Err(e) => {
+                        error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+            match download.resume_delay(error, &api.retry)? {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Ok(download.data),
+            }
+        }
+    })
+}
+
+/// Overwrite the progress line with the bytes received so far
+fn show_progress(received: usize, total: Option<usize>) {
+    let received = received as f64 / (1024.0 * 1024.0);
+    match total {
+        Some(total) => eprint!(
+            "\r      {:.1} / {:.1} MiB",
+            received,
+            total as f64 / (1024.0 * 1024.0)
+        ),
+        None => eprint!("\r      {:.1} MiB", received),
+    }
+    let _ = io::stderr().flush();
+}
+
+/// Per-user cache directory: `$XDG_CACHE_HOME`, `%LOCALAPPDATA%` on Windows,
+/// `~/Library/Caches` on macOS and `~/.cache` elsewhere
+fn default_cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("LOCALAPPDATA")?),
+        None if cfg!(target_os = "macos") => {
+            PathBuf::from(env::var_os("HOME")?).join("Library/Caches")
+        }
+        None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join(CACHE_NAME))
+}
+
+/// Cached ZIP of a deployment: `<cache>/<project>/<version>/<engine>.zip`
+fn cache_path(cache_dir: &Path, options: &Options, version: u32) -> PathBuf {
+    cache_dir
+        .join(&options.project_id)
+        .join(version.to_string())
+        .join(format!("{}.zip", options.engine))
+}
+
+/// SHA-256 of a cached ZIP, recorded next to it in `<engine>.zip.sha256`
+fn sha256_path(cached: &Path) -> PathBuf {
+    cached.with_extension("zip.sha256")
+}
+
+/// SHA-256 as lowercase hex
+fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Write a file through a temporary file, so concurrent readers never see a partial ZIP
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension(format!("zip.{}.tmp", std::process::id()));
+    fs::write(&temp, data)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// `EI_CLASSIFIER_PROJECT_DEPLOY_VERSION` from the `model_metadata.h` in a deployment ZIP
+fn deploy_version(zip_data: &[u8]) -> Option<u32> {
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(zip_data)).ok()?;
+    let mut header = String::new();
+    archive
+        .by_name("model-parameters/model_metadata.h")
+        .ok()?
+        .read_to_string(&mut header)
+        .ok()?;
+    header.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("#define"), Some("EI_CLASSIFIER_PROJECT_DEPLOY_VERSION"), Some(value)) => {
+                value.parse().ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Extract a deployment ZIP archive into the model directory
+///
+/// Existing `.gitignore` and `README.md` files in the model directory are preserved.
+fn extract_model_zip(zip_data: Vec<u8>, model_dir: &Path) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(zip_data))
+        .map_err(|e| format!("Failed to read ZIP archive: {}", e))?;
+
+    fs::create_dir_all(model_dir)
+        .map_err(|e| format!("Failed to create {}: {}", model_dir.display(), e))?;
+
+    // Preserve existing .gitignore and README.md if they exist
+    let gitignore_content = fs::read_to_string(model_dir.join(".gitignore")).ok();
+    let readme_content = fs::read_to_string(model_dir.join("README.md")).ok();
+
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to access file {} in ZIP: {}", i, e))?;
+        let target_path = match file.enclosed_name() {
+            Some(path) => model_dir.join(path),
+            None => {
+                eprintln!("warning: skipping file with invalid path: {}", file.name());
+                continue;
+            }
+        };
+
+        if file.name().ends_with('/') {
+            fs::create_dir_all(&target_path)
+                .map_err(|e| format!("Failed to create directory {:?}: {}", target_path, e))?;
+        } else {
+            if let Some(parent) = target_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+            }
+            let mut target_file = fs::File::create(&target_path)
+                .map_err(|e| format!("Failed to create file {:?}: {}", target_path, e))?;
+            io::copy(&mut file, &mut target_file)
+                .map_err(|e| format!("Failed to write file {:?}: {}", target_path, e))?;
+        }
+    }
+
+    // Restore .gitignore and README.md if they existed before
+    if let Some(content) = gitignore_content {
+        fs::write(model_dir.join(".gitignore"), content)
+            .map_err(|e| format!("Failed to restore .gitignore: {}", e))?;
+    }
+    if let Some(content) = readme_content {
+        fs::write(model_dir.join("README.md"), content)
+            .map_err(|e| format!("Failed to restore README.md: {}", e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Proxy selection from the standard proxy environment variables
+//!
+//! Shared by ei-fetch and the main crate's build script, which includes this file with
+//! `#[path]` for `EI_MODEL` URL downloads, so both follow the same rules.
+
+use std::env;
+
+/// Proxy for requests to `url` set by the standard environment variables
+///
+/// `HTTPS_PROXY` applies to https URLs and `HTTP_PROXY` to http URLs, with `ALL_PROXY` as the
+/// fallback for both, unless `NO_PROXY` lists the host. Lowercase names take precedence, as
+/// with curl.
+pub fn proxy_from_env(url: &str) -> Option<String> {
+    select_proxy(url, |name| {
+        env::var(name).ok().filter(|value| !value.trim().is_empty())
+    })
+}
+
+fn select_proxy(url: &str, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let lookup = |name: &str| var(&name.to_lowercase()).or_else(|| var(name));
+    let host = url_host(url)?;
+    if lookup("NO_PROXY").is_some_and(|no_proxy| bypasses_proxy(&no_proxy, host)) {
+        return None;
+    }
+    let scheme_proxy = if url.starts_with("https://") {
+        "HTTPS_PROXY"
+    } else {
+        "HTTP_PROXY"
+    };
+    lookup(scheme_proxy).or_else(|| lookup("ALL_PROXY"))
+}
+
+/// Host of a URL, without user info and port
+fn url_host(url: &str) -> Option<&str> {
+    let authority = url.split_once("://")?.1.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next(),
+        None => host.split(':').next(),
+    }
+}
+
+/// Whether a `NO_PROXY` list covers `host`: comma-separated host names, which also match
+/// their subdomains, optionally with a leading `.` or `*.`, or `*` for every host
+fn bypasses_proxy(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let entry = entry.trim_start_matches('*').trim_start_matches('.');
+            // Ports don't matter, all requests of a fetch go to the same host
+            let entry = match entry.rsplit_once(':') {
+                Some((name, port)) if !name.contains(':') && port.parse::<u16>().is_ok() => name,
+                _ => entry,
+            };
+            host == entry || host.ends_with(&format!(".{}", entry))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_proxy_from_environment() {
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let studio = "https://user@studio.edgeimpulse.com:443/v1/api/1";
+        let env = vars(&[
+            ("HTTPS_PROXY", "http://secure-proxy:3128"),
+            ("http_proxy", "http://proxy:3128"),
+        ]);
+        assert_eq!(
+            select_proxy(studio, env).as_deref(),
+            Some("http://secure-proxy:3128")
+        );
+        assert_eq!(
+            select_proxy("http://localhost:4800/v1", env).as_deref(),
+            Some("http://proxy:3128")
+        );
+
+        let env = vars(&[
+            ("ALL_PROXY", "http://proxy:3128"),
+            ("no_proxy", "localhost, .edgeimpulse.com:443"),
+        ]);
+        assert_eq!(select_proxy(studio, env), None);
+        assert_eq!(
+            select_proxy("https://edgeimpulse.com.example/", env).as_deref(),
+            Some("http://proxy:3128")
+        );
+        assert_eq!(url_host("http://[::1]:4800/v1"), Some("::1"));
+        assert!(bypasses_proxy("*", "studio.edgeimpulse.com"));
+        assert!(bypasses_proxy("::1, localhost", "::1"));
+    }
+}
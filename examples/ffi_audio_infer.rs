@@ -7,12 +7,15 @@
 //! 1. Load audio data from a WAV file
 //! 2. Convert it to the format expected by Edge Impulse models
 //! 3. Run inference using the FFI bindings
-//! 4. Display classification results
+//! 4. Hand the raw result over to the safe, owned result types and display it
+
+// The metadata constants' types follow the model, so some casts are no-ops for some models
+#![allow(clippy::unnecessary_cast)]
 
 use clap::Parser;
 use edge_impulse_ffi_rs::bindings::*;
 use edge_impulse_ffi_rs::model_metadata::*;
-use hound;
+use edge_impulse_ffi_rs::{Classification, InferenceOutput, Timing};
 use std::error::Error;
 
 /// Command line parameters for the audio classification example
@@ -115,21 +118,17 @@ fn resample_audio(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
     resampled
 }
 
-/// Print classification results from raw C struct
-fn print_classification_results(result: &ei_impulse_result_t, label_count: u16) {
-    if label_count > 0 {
+/// Print classification results
+fn print_classification_results(entries: &[Classification]) {
+    if !entries.is_empty() {
         println!("\n🎵 Audio Classification Results:");
         println!("┌─────────────────────────────────────────────────────────────┐");
         println!("│ Label                    │ Confidence │ Value              │");
         println!("├─────────────────────────────────────────────────────────────┤");
 
-        for i in 0..label_count {
-            let label = unsafe {
-                std::ffi::CStr::from_ptr(result.classification[i as usize].label)
-                    .to_string_lossy()
-                    .to_string()
-            };
-            let value = result.classification[i as usize].value;
+        for classification in entries {
+            let label = &classification.label;
+            let value = classification.value;
             let confidence = if value > 0.5 {
                 "HIGH"
             } else if value > 0.2 {
@@ -148,15 +147,15 @@ fn print_classification_results(result: &ei_impulse_result_t, label_count: u16)
 }
 
 /// Print timing information
-fn print_timing(timing: &ei_impulse_result_timing_t) {
+fn print_timing(timing: &Timing) {
     println!("\n⏱️  Timing Information:");
-    println!("  DSP: {:.2} ms", timing.dsp);
-    println!("  Classification: {:.2} ms", timing.classification);
-    println!("  Anomaly: {:.2} ms", timing.anomaly);
+    println!("  DSP: {:.2} ms", timing.dsp.as_secs_f64() * 1000.0);
     println!(
-        "  Total: {:.2} ms",
-        timing.dsp + timing.classification + timing.anomaly
+        "  Classification: {:.2} ms",
+        timing.classification.as_secs_f64() * 1000.0
     );
+    println!("  Anomaly: {:.2} ms", timing.anomaly.as_secs_f64() * 1000.0);
+    println!("  Total: {:.2} ms", timing.total().as_secs_f64() * 1000.0);
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -165,7 +164,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Get model metadata from the generated constants
     let input_frames = EI_CLASSIFIER_INPUT_FRAMES as usize;
     let input_frequency = EI_CLASSIFIER_FREQUENCY as u32;
-    let label_count = EI_CLASSIFIER_LABEL_COUNT as u16;
 
     println!("🎵 Audio Classification Example");
     println!("=================================");
@@ -294,20 +292,21 @@ fn main() -> Result<(), Box<dyn Error>> {
     let debug_int = if args.debug { 1 } else { 0 };
     let result_code = unsafe { ei_ffi_run_classifier(&mut signal, &mut result, debug_int) };
 
-    match result_code {
-        EI_IMPULSE_ERROR::EI_IMPULSE_OK => {
+    // One call checks the code and copies the results out of the C struct
+    match InferenceOutput::from_raw_result(result_code, &result) {
+        Ok(output) => {
             println!("✅ Inference completed successfully!");
 
             // Print classification results
-            print_classification_results(&result, label_count);
+            print_classification_results(&output.classification);
 
             // Print timing info
-            print_timing(&result.timing);
+            print_timing(&output.timing);
         }
-        error_code => {
+        Err(error) => {
             eprintln!(
-                "❌ Error running inference: {:?} (code: {})",
-                error_code, error_code as i32
+                "❌ Error running inference: {} (code: {})",
+                error, result_code.0
             );
             println!("This might be expected if:");
             println!("1. No model is loaded/initialized");
@@ -3,9 +3,14 @@
 //! Usage:
 //!   cargo run --example ffi_image_infer -- --image <path_to_image> [--debug]
 
+// The metadata constants follow the model, so some comparisons are constant for some models
+#![allow(clippy::absurd_extreme_comparisons)]
+
 use clap::Parser;
 use edge_impulse_ffi_rs::bindings::*;
+use edge_impulse_ffi_rs::inference::classification_entries;
 use edge_impulse_ffi_rs::model_metadata::*;
+use edge_impulse_ffi_rs::Timing;
 use image::{self, GenericImageView};
 use image::{imageops::FilterType, DynamicImage, RgbImage};
 use std::error::Error;
@@ -36,10 +41,10 @@ fn resize_and_crop(
 ) -> RgbImage {
     let (w, h) = img.dimensions();
     match resize_mode {
-        x if x == 0 => img // EI_CLASSIFIER_RESIZE_SQUASH
+        0 => img // EI_CLASSIFIER_RESIZE_SQUASH
             .resize_exact(input_width, input_height, FilterType::Triangle)
             .to_rgb8(),
-        x if x == 1 => {
+        1 => {
             // EI_CLASSIFIER_RESIZE_FIT_SHORTEST
             let factor = (input_width as f32 / w as f32).min(input_height as f32 / h as f32);
             let resize_w = (w as f32 * factor).round() as u32;
@@ -61,7 +66,7 @@ fn resize_and_crop(
             )
             .to_rgb8()
         }
-        x if x == 2 => {
+        2 => {
             // EI_CLASSIFIER_RESIZE_FIT_LONGEST
             let factor = (input_width as f32 / w as f32).max(input_height as f32 / h as f32);
             let resize_w = (w as f32 * factor).round() as u32;
@@ -96,31 +101,24 @@ fn resize_and_crop(
 }
 
 /// Print classification results from raw C struct
-fn print_classification_results(result: &ei_impulse_result_t, label_count: u16) {
-    if label_count > 0 {
-        println!("Classification results:");
-        // The classification array is fixed size with only 1 element
-        // In a real implementation, you'd need to get the actual results differently
-        let classification = &result.classification[0];
-        if !classification.label.is_null() {
-            let label = unsafe {
-                std::ffi::CStr::from_ptr(classification.label)
-                    .to_string_lossy()
-                    .to_string()
-            };
-            println!("  {}: {:.3}", label, classification.value);
-        } else {
-            println!("  No classification result available");
-        }
+fn print_classification_results(result: &ei_impulse_result_t) {
+    // Only the first EI_CLASSIFIER_LABEL_COUNT entries of the fixed-size array are filled in
+    let entries = classification_entries(result);
+    if entries.is_empty() {
+        return;
+    }
 
-        // Note: For multiple labels, you'd typically need to access the results
-        // through a different mechanism or pointer provided by the C library
-        if label_count > 1 {
-            println!(
-                "  Note: Model has {} labels but only first result shown",
-                label_count
-            );
+    println!("Classification results:");
+    for classification in entries {
+        if classification.label.is_null() {
+            continue;
         }
+        let label = unsafe {
+            std::ffi::CStr::from_ptr(classification.label)
+                .to_string_lossy()
+                .to_string()
+        };
+        println!("  {}: {:.3}", label, classification.value);
     }
 }
 
@@ -184,17 +182,15 @@ fn print_visual_anomaly_results(result: &ei_impulse_result_t) {
 
 /// Print timing information from raw C struct
 fn print_timing(timing: &ei_impulse_result_timing_t) {
+    let timing = Timing::from(timing);
     println!("Timing:");
-    println!("  DSP: {} ms ({} μs)", timing.dsp, timing.dsp_us);
-    println!(
-        "  Classification: {} ms ({} μs)",
-        timing.classification, timing.classification_us
-    );
-    if timing.anomaly > 0 {
-        println!(
-            "  Anomaly: {} ms ({} μs)",
-            timing.anomaly, timing.anomaly_us
-        );
+    println!("  DSP: {:?}", timing.dsp);
+    println!("  Classification: {:?}", timing.classification);
+    if !timing.anomaly.is_zero() {
+        println!("  Anomaly: {:?}", timing.anomaly);
+    }
+    if let Some(fps) = timing.fps_estimate() {
+        println!("  Total: {:?} (~{:.1} inferences/s)", timing.total(), fps);
     }
 }
 
@@ -205,7 +201,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     let input_width = EI_CLASSIFIER_INPUT_WIDTH as u32;
     let input_height = EI_CLASSIFIER_INPUT_HEIGHT as u32;
     let resize_mode = EI_CLASSIFIER_RESIZE_MODE;
-    let label_count = EI_CLASSIFIER_LABEL_COUNT as u16;
 
     println!("Using input dimensions: {}x{}", input_width, input_height);
     println!(
@@ -349,7 +344,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 error_code => {
                     eprintln!(
                         "Failed to set threshold: {:?} (code: {})",
-                        error_code, error_code as i32
+                        error_code, error_code.0
                     );
                     println!("This might be expected if:");
                     println!("1. The model doesn't have object detection");
@@ -384,7 +379,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             println!("Inference ran successfully!");
 
             // Print classification results
-            print_classification_results(&result, label_count);
+            print_classification_results(&result);
 
             // Print bounding boxes for object detection
             print_bounding_boxes(&result);
@@ -398,7 +393,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         error_code => {
             eprintln!(
                 "Error running inference: {:?} (code: {})",
-                error_code, error_code as i32
+                error_code, error_code.0
             );
             println!("This might be expected if:");
             println!("1. No model is loaded/initialized");
@@ -0,0 +1,350 @@
+//! Live Threshold Tuning Example
+//!
+//! Usage:
+//!   mkfifo /tmp/mic
+//!   arecord -f S16_LE -c 1 -r 16000 -t raw > /tmp/mic &
+//!   cargo run --example tune_thresholds --features toml -- --audio /tmp/mic [--config deployment.toml]
+//!
+//!   ffmpeg -f v4l2 -i /dev/video0 -vf scale=96:96 -pix_fmt rgb24 -f rawvideo /tmp/cam &
+//!   cargo run --example tune_thresholds --features toml -- --video /tmp/cam
+//!
+//! This example demonstrates how to:
+//! 1. Classify a live audio stream (raw 16-bit mono PCM at the model frequency) continuously, or
+//!    a camera stream (raw RGB24 frames at the model input size) frame by frame
+//! 2. Change thresholds, non-max suppression, the moving average filter and the result rate
+//!    from stdin while the stream keeps running
+//! 3. Reload a deployment config file into the running model
+//!
+//! Type `help` for the list of commands.
+
+// The metadata constants' types follow the model, so some casts are no-ops for some models
+#![allow(clippy::unnecessary_cast)]
+
+use clap::Parser;
+use edge_impulse_ffi_rs::model_metadata::*;
+use edge_impulse_ffi_rs::runner_api::{EimModel, InferenceResponse};
+use edge_impulse_ffi_rs::{features_from_rgb_bytes, DeploymentConfig};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Command line parameters for the threshold tuning example
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Raw 16-bit little-endian mono PCM at the model frequency (a file or a FIFO)
+    #[arg(long, conflicts_with = "video", required_unless_present = "video")]
+    audio: Option<PathBuf>,
+
+    /// Raw RGB24 frames at the model input size (a file or a FIFO)
+    #[arg(long)]
+    video: Option<PathBuf>,
+
+    /// Deployment config applied at startup and by the `reload` command
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print at most this many results per second
+    #[arg(long)]
+    rate: Option<f64>,
+}
+
+const HELP: &str = "\
+Commands:
+  threshold <block> <value>   Set the threshold of a learning block
+  nms <block> <iou>           Set the NMS IoU threshold of an object detection block
+  max <n|all>                 Keep only the n highest scoring boxes
+  maf <n|sdk|off>             Moving average filter length (audio only)
+  rate <per second|off>       Limit the printed results
+  debug <on|off>              Print SDK debug output
+  reload                      Apply the --config file again
+  show                        Print the current settings
+  help                        Print this help
+  quit                        Exit";
+
+/// Where the samples come from, and how much of them makes one inference
+enum Stream {
+    Audio {
+        reader: File,
+        slice_size: usize,
+    },
+    Video {
+        reader: File,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl Stream {
+    /// Read and classify the next slice or frame
+    ///
+    /// Returns `None` at the end of the stream, and `Some(None)` for audio slices that don't
+    /// complete a window yet.
+    fn classify(
+        &mut self,
+        model: &mut EimModel,
+    ) -> Result<Option<Option<InferenceResponse>>, Box<dyn Error>> {
+        match self {
+            Stream::Audio { reader, slice_size } => {
+                let mut bytes = vec![0u8; *slice_size * 2];
+                if !read_exact_or_eof(reader, &mut bytes)? {
+                    return Ok(None);
+                }
+                let slice: Vec<f32> = bytes
+                    .chunks_exact(2)
+                    .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f32)
+                    .collect();
+                Ok(Some(model.classify_continuous(&slice)?))
+            }
+            Stream::Video {
+                reader,
+                width,
+                height,
+            } => {
+                let mut frame = vec![0u8; *width as usize * *height as usize * 3];
+                if !read_exact_or_eof(reader, &mut frame)? {
+                    return Ok(None);
+                }
+                let features =
+                    features_from_rgb_bytes(&frame, *width, *height, *width as usize * 3)?;
+                Ok(Some(Some(model.infer(features, None)?)))
+            }
+        }
+    }
+}
+
+/// Fill `buffer`, returning false if the stream ended first
+fn read_exact_or_eof(reader: &mut File, buffer: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Settings of the example itself, next to those of the model
+struct Tuning {
+    config: Option<PathBuf>,
+    audio: bool,
+    min_interval: Option<Duration>,
+    last_print: Option<Instant>,
+    debug: bool,
+}
+
+impl Tuning {
+    fn should_print(&mut self) -> bool {
+        let now = Instant::now();
+        let due = match (self.min_interval, self.last_print) {
+            (Some(interval), Some(last)) => now.duration_since(last) >= interval,
+            _ => true,
+        };
+        if due {
+            self.last_print = Some(now);
+        }
+        due
+    }
+}
+
+/// Apply one command line, returning false to quit
+fn run_command(
+    line: &str,
+    model: &mut EimModel,
+    tuning: &mut Tuning,
+) -> Result<bool, Box<dyn Error>> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [] => {}
+        ["threshold", block, value] => {
+            model.set_learn_block_threshold(block.parse()?, value.parse()?)?;
+            println!("✅ Block {} threshold set to {}", block, value);
+        }
+        ["nms", block, iou] => {
+            model.set_nms_iou_threshold(block.parse()?, iou.parse()?)?;
+            println!("✅ Block {} NMS IoU threshold set to {}", block, iou);
+        }
+        ["max", "all"] => model.set_max_detections(None),
+        ["max", n] => model.set_max_detections(Some(n.parse()?)),
+        ["maf", window] => {
+            let continuous = model
+                .continuous_mut()
+                .ok_or("the moving average filter only applies to audio streams")?;
+            continuous.set_maf_window(match *window {
+                "sdk" => None,
+                "off" => Some(1),
+                n => Some(n.parse()?),
+            });
+            println!("✅ Moving average filter: {:?}", continuous.maf_window());
+        }
+        ["rate", "off"] => tuning.min_interval = None,
+        ["rate", per_second] => {
+            let per_second: f64 = per_second.parse()?;
+            if per_second <= 0.0 {
+                return Err("the rate must be positive".into());
+            }
+            tuning.min_interval = Some(Duration::from_secs_f64(1.0 / per_second));
+        }
+        ["debug", on_off] => {
+            tuning.debug = *on_off == "on";
+            model.set_debug(tuning.debug);
+        }
+        ["reload"] => {
+            let path = tuning.config.as_ref().ok_or("no --config file given")?;
+            let config = stream_config(DeploymentConfig::load(path)?, tuning.audio);
+            config.apply(model)?;
+            tuning.debug = config.debug;
+            println!("🔄 Reloaded {}", path.display());
+        }
+        ["show"] => show(model, tuning)?,
+        ["help"] => println!("{}", HELP),
+        ["quit" | "exit"] => return Ok(false),
+        _ => println!("❓ Unknown command: {} (type `help`)", line.trim()),
+    }
+    Ok(true)
+}
+
+fn show(model: &mut EimModel, tuning: &Tuning) -> Result<(), Box<dyn Error>> {
+    println!("\n🎯 Current settings:");
+    for threshold in model.classifier().thresholds()?.thresholds {
+        println!(
+            "  Block {}: {} (threshold: {})",
+            threshold.id, threshold.threshold_type, threshold.min_score
+        );
+    }
+    println!(
+        "  Max detections: {:?}",
+        model.classifier().max_detections()
+    );
+    if let Some(continuous) = model.continuous_mut() {
+        println!("  Moving average filter: {:?}", continuous.maf_window());
+    }
+    match tuning.min_interval {
+        Some(interval) => println!("  Rate: {:.2} results/s", 1.0 / interval.as_secs_f64()),
+        None => println!("  Rate: unlimited"),
+    }
+    println!("  Debug: {}", tuning.debug);
+    Ok(())
+}
+
+/// `config` with continuous mode on for audio streams and off for video streams, which a
+/// running model can't switch
+fn stream_config(mut config: DeploymentConfig, audio: bool) -> DeploymentConfig {
+    if !audio {
+        config.continuous = None;
+    } else if config.continuous.is_none() {
+        config.continuous = Some(Default::default());
+    }
+    config
+}
+
+/// Read commands on a separate thread, so the stream never waits for the keyboard
+fn spawn_command_reader() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    println!("🎛️  Live Threshold Tuning Example");
+    println!("=================================");
+    println!(
+        "Model: {} (v{})",
+        EI_CLASSIFIER_PROJECT_NAME, EI_CLASSIFIER_PROJECT_DEPLOY_VERSION
+    );
+
+    let audio = args.audio.is_some();
+    let config = match &args.config {
+        Some(path) => DeploymentConfig::load(path)?,
+        None => DeploymentConfig::default(),
+    };
+    let config = stream_config(config, audio);
+    let mut model = config.build()?;
+
+    let mut stream = match (&args.audio, &args.video) {
+        (Some(path), _) => {
+            let slice_size = model
+                .continuous_mut()
+                .map(|continuous| continuous.slice_size())
+                .ok_or("audio streams need a continuous model")?;
+            println!(
+                "📁 Audio: {} ({} samples per slice at {} Hz)",
+                path.display(),
+                slice_size,
+                EI_CLASSIFIER_FREQUENCY
+            );
+            Stream::Audio {
+                reader: File::open(path)?,
+                slice_size,
+            }
+        }
+        (None, Some(path)) => {
+            let (width, height) = (
+                EI_CLASSIFIER_INPUT_WIDTH as u32,
+                EI_CLASSIFIER_INPUT_HEIGHT as u32,
+            );
+            println!(
+                "📁 Video: {} ({}x{} RGB24 frames)",
+                path.display(),
+                width,
+                height
+            );
+            Stream::Video {
+                reader: File::open(path)?,
+                width,
+                height,
+            }
+        }
+        (None, None) => unreachable!("clap requires --audio or --video"),
+    };
+
+    let mut tuning = Tuning {
+        config: args.config,
+        audio,
+        min_interval: match args.rate {
+            Some(rate) if rate > 0.0 => Some(Duration::from_secs_f64(1.0 / rate)),
+            Some(_) => return Err("--rate must be positive".into()),
+            None => None,
+        },
+        last_print: None,
+        debug: config.debug,
+    };
+    println!("{}\n", HELP);
+
+    let commands = spawn_command_reader();
+    loop {
+        // Apply pending commands between inferences
+        loop {
+            match commands.try_recv() {
+                Ok(line) => match run_command(&line, &mut model, &mut tuning) {
+                    Ok(true) => {}
+                    Ok(false) => return Ok(()),
+                    Err(e) => println!("❌ {}", e),
+                },
+                Err(TryRecvError::Empty) => break,
+                // stdin closed: keep streaming with the current settings
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        match stream.classify(&mut model)? {
+            Some(Some(response)) if tuning.should_print() => println!("🔍 {:?}", response.result),
+            Some(_) => {}
+            None => {
+                println!("✅ End of stream");
+                return Ok(());
+            }
+        }
+    }
+}
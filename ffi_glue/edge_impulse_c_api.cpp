@@ -3,43 +3,215 @@
 #include "edge-impulse-sdk/classifier/ei_run_classifier.h"
 #include "edge-impulse-sdk/classifier/postprocessing/ei_postprocessing_common.h"
 #include "edge-impulse-sdk/dsp/numpy.hpp"
+#include <atomic>
+#include <cstdarg>
+#include <cstdio>
+#include <cstdlib>
+#include <cstring>
+#include <algorithm>
+#include <memory>
+#include <new>
+#include <type_traits>
+#include <vector>
+#if !defined(EI_FFI_BARE_METAL)
+#include <chrono>
+#include <thread>
+#endif
+
+// Entry points called from Rust stay visible when the SDK is built with -fvisibility=hidden, and
+// are exported from the DLL of a shared Windows build
+#if defined(_MSC_VER)
+#if defined(EI_FFI_SHARED)
+#define EI_FFI_EXPORT __declspec(dllexport)
+#else
+#define EI_FFI_EXPORT
+#endif
+#else
+#define EI_FFI_EXPORT __attribute__((visibility("default")))
+#endif
+
+// Bare-metal targets run a single thread and newlib has no thread-local storage, so per-thread
+// state is global there
+#if defined(EI_FFI_BARE_METAL)
+#define EI_FFI_THREAD_LOCAL
+#else
+#define EI_FFI_THREAD_LOCAL thread_local
+#endif
 
 // Forward declaration of the default impulse (C++ linkage)
 extern ei_impulse_handle_t& ei_default_impulse;
 
+// Callback receiving ei_printf output, set from Rust (nullptr prints to stdout)
+static std::atomic<ei_ffi_log_callback_t> ei_ffi_log_callback{nullptr};
+
+// The SDK reports DSP failures as EI_IMPULSE_DSP_ERROR and only prints the EIDSP_* code,
+// so ei_printf records it here for the last classifier call on this thread
+static EI_FFI_THREAD_LOCAL int ei_ffi_dsp_error = 0;
+static const char ei_ffi_dsp_error_message[] = "ERR: Failed to run DSP process (%d)";
+
+// Cancellation flag of the classifier calls made on this thread (nonzero cancels), set from Rust
+static EI_FFI_THREAD_LOCAL const int32_t* ei_ffi_cancel_flag = nullptr;
+
+// Porting hooks set from Rust, nullptr uses the POSIX implementation below
+static std::atomic<uint64_t (*)(void)> ei_ffi_read_timer_us_hook{nullptr};
+static std::atomic<void (*)(int32_t)> ei_ffi_sleep_hook{nullptr};
+static std::atomic<void* (*)(size_t)> ei_ffi_malloc_hook{nullptr};
+static std::atomic<void* (*)(size_t, size_t)> ei_ffi_calloc_hook{nullptr};
+static std::atomic<void (*)(void*)> ei_ffi_free_hook{nullptr};
+
+// Callback for failed ei_malloc/ei_calloc calls, set from Rust (nullptr fails them right away)
+static std::atomic<ei_ffi_alloc_failure_callback_t> ei_ffi_alloc_failure_callback{nullptr};
+
+// Classifier call running on this thread, reported with allocation failures
+enum {
+    EI_FFI_ALLOC_STAGE_OTHER = 0,
+    EI_FFI_ALLOC_STAGE_INIT = 1,
+    EI_FFI_ALLOC_STAGE_CLASSIFY = 2,
+    EI_FFI_ALLOC_STAGE_INFERENCE = 3,
+};
+static EI_FFI_THREAD_LOCAL int ei_ffi_alloc_stage = EI_FFI_ALLOC_STAGE_OTHER;
+
+// Sets ei_ffi_alloc_stage for the duration of a classifier call
+class ei_ffi_alloc_stage_scope {
+public:
+    explicit ei_ffi_alloc_stage_scope(int stage) : previous(ei_ffi_alloc_stage) {
+        ei_ffi_alloc_stage = stage;
+    }
+    ~ei_ffi_alloc_stage_scope() {
+        ei_ffi_alloc_stage = previous;
+    }
+private:
+    int previous;
+};
+
+// Whether a failed allocation of `size` bytes should be attempted again
+static bool ei_ffi_retry_alloc(size_t size, uint32_t attempt) {
+    ei_ffi_alloc_failure_callback_t callback = ei_ffi_alloc_failure_callback.load();
+    return callback != nullptr && size != 0 && callback(size, ei_ffi_alloc_stage, attempt) != 0;
+}
+
+// Maximum number of bounding boxes returned per inference (0 keeps all of them)
+static std::atomic<uint32_t> ei_ffi_max_detections{0};
+
+// Older SDKs have no NMS settings in the graph config: the overloads taking `int` only exist
+// when the config has an `nms_config` member, otherwise the `long` fallback is picked
+template <typename T>
+static auto ei_ffi_nms_iou_threshold(T* config, int) -> decltype(&config->nms_config.iou_threshold) {
+    return &config->nms_config.iou_threshold;
+}
+
+template <typename T>
+static float* ei_ffi_nms_iou_threshold(T*, long) {
+    return nullptr;
+}
+
+// Keep the ei_ffi_max_detections boxes with the highest scores
+static EI_IMPULSE_ERROR ei_ffi_limit_detections(EI_IMPULSE_ERROR res, ei_impulse_result_t* result) {
+    const uint32_t max_detections = ei_ffi_max_detections.load();
+    if (res != EI_IMPULSE_OK || result == nullptr || max_detections == 0 ||
+        result->bounding_boxes == nullptr || result->bounding_boxes_count <= max_detections) {
+        return res;
+    }
+    std::stable_sort(result->bounding_boxes, result->bounding_boxes + result->bounding_boxes_count,
+        [](const ei_impulse_result_bounding_box_t& a, const ei_impulse_result_bounding_box_t& b) {
+            return a.value > b.value;
+        });
+    result->bounding_boxes_count = max_detections;
+    return res;
+}
+
 extern "C" {
 
-__attribute__((visibility("default"))) void ei_ffi_run_classifier_init(void) {
+EI_FFI_EXPORT void ei_ffi_run_classifier_init(void) {
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_INIT);
     ::run_classifier_init();
 }
 
-__attribute__((visibility("default"))) void ei_ffi_run_classifier_deinit(void) {
+EI_FFI_EXPORT void ei_ffi_run_classifier_deinit(void) {
     ::run_classifier_deinit();
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_init_impulse(ei_impulse_handle_t* handle) {
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_init_impulse(ei_impulse_handle_t* handle) {
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_INIT);
     return ::init_impulse(handle);
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_run_classifier(signal_t* signal, ei_impulse_result_t* result, int debug) {
-    return ::run_classifier(signal, result, debug);
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_run_classifier(signal_t* signal, ei_impulse_result_t* result, int debug) {
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_CLASSIFY);
+    ei_ffi_dsp_error = 0;
+    return ei_ffi_limit_detections(::run_classifier(signal, result, debug), result);
+}
+
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_run_classifier_continuous(signal_t* signal, ei_impulse_result_t* result, int debug, int enable_maf) {
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_CLASSIFY);
+    ei_ffi_dsp_error = 0;
+    return ei_ffi_limit_detections(::run_classifier_continuous(signal, result, debug, enable_maf), result);
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_run_classifier_continuous(signal_t* signal, ei_impulse_result_t* result, int debug, int enable_maf_unused) {
-    return ::run_classifier_continuous(signal, result, debug, enable_maf_unused);
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_run_inference(ei_impulse_handle_t* handle, ei_feature_t* fmatrix, ei_impulse_result_t* result, int debug) {
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_INFERENCE);
+    return ei_ffi_limit_detections(::run_inference(handle, fmatrix, result, debug), result);
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_run_inference(ei_impulse_handle_t* handle, ei_feature_t* fmatrix, ei_impulse_result_t* result, int debug) {
-    return ::run_inference(handle, fmatrix, result, debug);
+// Run the learning blocks on precomputed DSP features, skipping the SDK's DSP. features holds the
+// output of every DSP block, concatenated in block order; handle is NULL for the global classifier
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_run_inference_features(ei_impulse_handle_t* handle, const float* features, size_t features_size, ei_impulse_result_t* result, int debug) {
+    if (handle == nullptr) {
+        handle = &ei_default_impulse;
+    }
+    const ei_impulse_t* impulse = handle->impulse;
+    size_t expected = 0;
+    for (size_t ix = 0; ix < impulse->dsp_blocks_size; ix++) {
+        expected += impulse->dsp_blocks[ix].n_output_features;
+    }
+    if (features == nullptr || features_size != expected) {
+        return EI_IMPULSE_INVALID_SIZE;
+    }
+
+    // The matrices borrow the caller's buffer; learning blocks only read their input
+    std::vector<std::unique_ptr<ei::matrix_t>> matrices;
+    std::vector<ei_feature_t> fmatrix(impulse->dsp_blocks_size);
+    size_t offset = 0;
+    for (size_t ix = 0; ix < impulse->dsp_blocks_size; ix++) {
+        const ei_model_dsp_t& block = impulse->dsp_blocks[ix];
+        matrices.emplace_back(new (std::nothrow) ei::matrix_t(1, block.n_output_features, const_cast<float*>(features) + offset));
+        if (!matrices.back()) {
+            return EI_IMPULSE_OUT_OF_MEMORY;
+        }
+        fmatrix[ix].matrix = matrices.back().get();
+        fmatrix[ix].blockId = block.blockId;
+        offset += block.n_output_features;
+    }
+
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_INFERENCE);
+    return ei_ffi_limit_detections(::run_inference(handle, fmatrix.data(), result, debug), result);
+}
+
+// Create a handle with its own state for the default impulse; must be freed with ei_ffi_impulse_handle_free
+EI_FFI_EXPORT ei_impulse_handle_t* ei_ffi_impulse_handle_new(void) {
+    return new (std::nothrow) ei_impulse_handle_t(ei_default_impulse.impulse);
+}
+
+EI_FFI_EXPORT void ei_ffi_impulse_handle_free(ei_impulse_handle_t* handle) {
+    delete handle;
+}
+
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_run_classifier_with_handle(ei_impulse_handle_t* handle, signal_t* signal, ei_impulse_result_t* result, int debug) {
+    if (handle == nullptr) {
+        return EI_IMPULSE_OUT_OF_MEMORY;
+    }
+    ei_ffi_alloc_stage_scope stage(EI_FFI_ALLOC_STAGE_CLASSIFY);
+    ei_ffi_dsp_error = 0;
+    return ei_ffi_limit_detections(::run_classifier(handle, signal, result, debug), result);
 }
 
 // Helper function to create signal from buffer (like EIM binary)
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_signal_from_buffer(const float* data, size_t data_size, signal_t* signal) {
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_signal_from_buffer(const float* data, size_t data_size, signal_t* signal) {
     return static_cast<EI_IMPULSE_ERROR>(ei::numpy::signal_from_buffer(data, data_size, signal));
 }
 
 // Threshold setting functions - Updated for current SDK structure
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_object_detection_threshold(uint32_t block_id, float min_score) {
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_set_object_detection_threshold(uint32_t block_id, float min_score) {
     // Find the postprocessing block with the specified block_id
     for (size_t i = 0; i < ei_default_impulse.impulse->postprocessing_blocks_size; i++) {
         const ei_postprocessing_block_t& block = ei_default_impulse.impulse->postprocessing_blocks[i];
@@ -56,7 +228,7 @@ __attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_object_detect
     return EI_IMPULSE_INFERENCE_ERROR;
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_anomaly_threshold(uint32_t block_id, float min_anomaly_score) {
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_set_anomaly_threshold(uint32_t block_id, float min_anomaly_score) {
     // Find the postprocessing block with the specified block_id
     for (size_t i = 0; i < ei_default_impulse.impulse->postprocessing_blocks_size; i++) {
         const ei_postprocessing_block_t& block = ei_default_impulse.impulse->postprocessing_blocks[i];
@@ -74,7 +246,7 @@ __attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_anomaly_thres
     return EI_IMPULSE_INFERENCE_ERROR;
 }
 
-__attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_object_tracking_threshold(uint32_t block_id, float threshold, uint32_t keep_grace, uint16_t max_observations) {
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_set_object_tracking_threshold(uint32_t block_id, float threshold, uint32_t keep_grace, uint16_t max_observations) {
     // Find the postprocessing block with the specified block_id
     for (size_t i = 0; i < ei_default_impulse.impulse->postprocessing_blocks_size; i++) {
         const ei_postprocessing_block_t& block = ei_default_impulse.impulse->postprocessing_blocks[i];
@@ -95,4 +267,385 @@ __attribute__((visibility("default"))) EI_IMPULSE_ERROR ei_ffi_set_object_tracki
     return EI_IMPULSE_INFERENCE_ERROR;
 }
 
+// Graph config of a neural network learning block, nullptr for blocks of other types
+static ei_learning_block_config_tflite_graph_t* ei_ffi_tflite_graph_config(const ei_learning_block_t& block) {
+#if EI_CLASSIFIER_INFERENCING_ENGINE != EI_CLASSIFIER_NONE
+    if (block.infer_fn == run_nn_inference && block.config != nullptr) {
+        return static_cast<ei_learning_block_config_tflite_graph_t*>(block.config);
+    }
+#endif
+    return nullptr;
+}
+
+// Threshold of a learning block: the min score of neural network blocks (object detection), or
+// the anomaly threshold of GMM blocks. The config type is picked from the block's infer_fn;
+// nullptr for blocks without a threshold (K-means) or whose config this glue doesn't know.
+static float* ei_ffi_learn_block_threshold(const ei_learning_block_t& block) {
+    if (block.config == nullptr) {
+        return nullptr;
+    }
+#if (EI_CLASSIFIER_HAS_ANOMALY == EI_ANOMALY_TYPE_GMM) || (EI_CLASSIFIER_HAS_ANOMALY == EI_ANOMALY_TYPE_VISUAL_GMM)
+    if (block.infer_fn == run_gmm_anomaly) {
+        return &static_cast<ei_learning_block_config_anomaly_gmm_t*>(block.config)->anomaly_threshold;
+    }
+#endif
+#if (EI_CLASSIFIER_HAS_ANOMALY == EI_ANOMALY_TYPE_KMEANS)
+    // K-means blocks report the distance to the nearest cluster and have no threshold
+    if (block.infer_fn == run_kmeans_anomaly) {
+        return nullptr;
+    }
+#endif
+    ei_learning_block_config_tflite_graph_t* config = ei_ffi_tflite_graph_config(block);
+    return config != nullptr ? &config->threshold : nullptr;
+}
+
+// Set the threshold of a learning block at runtime (object detection min score, or anomaly threshold for GMM blocks)
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_set_learn_block_threshold(uint32_t block_id, float threshold) {
+    // Find the learning block with the specified block_id
+    for (size_t i = 0; i < ei_default_impulse.impulse->learning_blocks_size; i++) {
+        const ei_learning_block_t& block = ei_default_impulse.impulse->learning_blocks[i];
+        if (block.blockId != block_id) {
+            continue;
+        }
+
+        float* config_threshold = ei_ffi_learn_block_threshold(block);
+        if (config_threshold == nullptr) {
+            return EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE;
+        }
+        *config_threshold = threshold;
+        return EI_IMPULSE_OK;
+    }
+    return EI_IMPULSE_INFERENCE_ERROR;
+}
+
+// Read the current threshold of a learning block (reflects changes made at runtime)
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_get_learn_block_threshold(uint32_t block_id, float* threshold) {
+    if (threshold == nullptr) {
+        return EI_IMPULSE_INFERENCE_ERROR;
+    }
+
+    // Find the learning block with the specified block_id
+    for (size_t i = 0; i < ei_default_impulse.impulse->learning_blocks_size; i++) {
+        const ei_learning_block_t& block = ei_default_impulse.impulse->learning_blocks[i];
+        if (block.blockId != block_id) {
+            continue;
+        }
+
+        const float* config_threshold = ei_ffi_learn_block_threshold(block);
+        if (config_threshold == nullptr) {
+            return EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE;
+        }
+        *threshold = *config_threshold;
+        return EI_IMPULSE_OK;
+    }
+    return EI_IMPULSE_INFERENCE_ERROR;
+}
+
+// Set the IoU above which non-max suppression merges overlapping boxes of a learning block
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_set_nms_iou_threshold(uint32_t block_id, float iou_threshold) {
+    for (size_t i = 0; i < ei_default_impulse.impulse->learning_blocks_size; i++) {
+        const ei_learning_block_t& block = ei_default_impulse.impulse->learning_blocks[i];
+        if (block.blockId != block_id) {
+            continue;
+        }
+
+        // Only neural network blocks have a graph config with NMS settings
+        ei_learning_block_config_tflite_graph_t* config = ei_ffi_tflite_graph_config(block);
+        float* iou = config != nullptr ? ei_ffi_nms_iou_threshold(config, 0) : nullptr;
+        if (iou == nullptr) {
+            return EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE;
+        }
+        *iou = iou_threshold;
+        return EI_IMPULSE_OK;
+    }
+    return EI_IMPULSE_INFERENCE_ERROR;
+}
+
+// Read the current NMS IoU threshold of a learning block
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_get_nms_iou_threshold(uint32_t block_id, float* iou_threshold) {
+    if (iou_threshold == nullptr) {
+        return EI_IMPULSE_INFERENCE_ERROR;
+    }
+
+    for (size_t i = 0; i < ei_default_impulse.impulse->learning_blocks_size; i++) {
+        const ei_learning_block_t& block = ei_default_impulse.impulse->learning_blocks[i];
+        if (block.blockId != block_id) {
+            continue;
+        }
+
+        // Only neural network blocks have a graph config with NMS settings
+        ei_learning_block_config_tflite_graph_t* config = ei_ffi_tflite_graph_config(block);
+        const float* iou = config != nullptr ? ei_ffi_nms_iou_threshold(config, 0) : nullptr;
+        if (iou == nullptr) {
+            return EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE;
+        }
+        *iou_threshold = *iou;
+        return EI_IMPULSE_OK;
+    }
+    return EI_IMPULSE_INFERENCE_ERROR;
+}
+
+EI_FFI_EXPORT void ei_ffi_set_max_detections(uint32_t max_detections) {
+    ei_ffi_max_detections.store(max_detections);
+}
+
+EI_FFI_EXPORT uint32_t ei_ffi_get_max_detections(void) {
+    return ei_ffi_max_detections.load();
+}
+
+// Number of objects currently tracked by the object tracking postprocessing block
+EI_FFI_EXPORT uint32_t ei_ffi_object_tracking_count(const ei_impulse_result_t* result) {
+#if EI_CLASSIFIER_OBJECT_TRACKING_ENABLED == 1
+    if (result != nullptr) {
+        return result->postprocessed_output.object_tracking_output.open_traces_count;
+    }
+#else
+    (void)result;
+#endif
+    return 0;
+}
+
+// Copy a tracked object out of the result (the SDK's traces are only valid until the next inference)
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_object_tracking_get(const ei_impulse_result_t* result, uint32_t index, ei_ffi_tracked_object_t* object) {
+#if EI_CLASSIFIER_OBJECT_TRACKING_ENABLED == 1
+    if (result == nullptr || object == nullptr ||
+        index >= result->postprocessed_output.object_tracking_output.open_traces_count) {
+        return EI_IMPULSE_INFERENCE_ERROR;
+    }
+    const ei_object_tracking_trace_t& trace = result->postprocessed_output.object_tracking_output.open_traces[index];
+    object->id = trace.id;
+    object->label = trace.label;
+    object->value = trace.value;
+    object->x = trace.x;
+    object->y = trace.y;
+    object->width = trace.width;
+    object->height = trace.height;
+    return EI_IMPULSE_OK;
+#else
+    (void)result;
+    (void)index;
+    (void)object;
+    return EI_IMPULSE_INFERENCE_ERROR;
+#endif
+}
+
+// Number of output tensors of freeform learning blocks (e.g. embedding models)
+EI_FFI_EXPORT uint32_t ei_ffi_freeform_output_count(const ei_impulse_result_t* result) {
+#if defined(EI_CLASSIFIER_FREEFORM_OUTPUT) && EI_CLASSIFIER_FREEFORM_OUTPUT == 1
+    if (result != nullptr) {
+        return static_cast<uint32_t>(result->freeform_outputs.size());
+    }
+#else
+    (void)result;
+#endif
+    return 0;
+}
+
+// Borrow a freeform output tensor (dequantized floats, only valid until the next inference)
+EI_FFI_EXPORT EI_IMPULSE_ERROR ei_ffi_freeform_output_get(const ei_impulse_result_t* result, uint32_t index, const float** data, size_t* len) {
+#if defined(EI_CLASSIFIER_FREEFORM_OUTPUT) && EI_CLASSIFIER_FREEFORM_OUTPUT == 1
+    if (result == nullptr || data == nullptr || len == nullptr ||
+        index >= result->freeform_outputs.size()) {
+        return EI_IMPULSE_INFERENCE_ERROR;
+    }
+    const matrix_t& output = result->freeform_outputs[index];
+    *data = output.buffer;
+    *len = output.rows * output.cols;
+    return EI_IMPULSE_OK;
+#else
+    (void)result;
+    (void)index;
+    (void)data;
+    (void)len;
+    return EI_IMPULSE_INFERENCE_ERROR;
+#endif
+}
+
+// EIDSP_* code of the DSP failure in the last classifier call on this thread, or 0
+EI_FFI_EXPORT int ei_ffi_last_dsp_error(void) {
+    return ei_ffi_dsp_error;
+}
+
+#ifndef EI_FFI_MODEL_HASH
+#define EI_FFI_MODEL_HASH 0ULL
+#endif
+
+// Hash of the model the library was built from (computed by build.rs from the model headers)
+EI_FFI_EXPORT uint64_t ei_ffi_model_hash(void) {
+    return EI_FFI_MODEL_HASH;
+}
+
+// Redirect ei_printf output to a callback
+EI_FFI_EXPORT void ei_ffi_set_log_callback(ei_ffi_log_callback_t callback) {
+    ei_ffi_log_callback.store(callback);
+}
+
+EI_FFI_EXPORT void ei_ffi_set_porting_hooks(const ei_ffi_porting_hooks_t* hooks) {
+    ei_ffi_read_timer_us_hook.store(hooks != nullptr ? hooks->read_timer_us : nullptr);
+    ei_ffi_sleep_hook.store(hooks != nullptr ? hooks->sleep_ms : nullptr);
+    ei_ffi_malloc_hook.store(hooks != nullptr ? hooks->malloc_fn : nullptr);
+    ei_ffi_calloc_hook.store(hooks != nullptr ? hooks->calloc_fn : nullptr);
+    ei_ffi_free_hook.store(hooks != nullptr ? hooks->free_fn : nullptr);
+}
+
+EI_FFI_EXPORT void ei_ffi_set_timer_hook(uint64_t (*read_timer_us)(void)) {
+    ei_ffi_read_timer_us_hook.store(read_timer_us);
+}
+
+EI_FFI_EXPORT int ei_ffi_clock_source(void) {
+    if (ei_ffi_read_timer_us_hook.load() != nullptr) {
+        return 1;
+    }
+#if defined(EI_FFI_BARE_METAL)
+    return 2;
+#else
+    return 0;
+#endif
+}
+
+EI_FFI_EXPORT void ei_ffi_set_alloc_failure_callback(ei_ffi_alloc_failure_callback_t callback) {
+    ei_ffi_alloc_failure_callback.store(callback);
+}
+
+EI_FFI_EXPORT void ei_ffi_set_cancel_flag(const int32_t* flag) {
+    ei_ffi_cancel_flag = flag;
+}
+
+// The functions below override the SDK's weak POSIX porting layer, falling back to the same
+// behaviour when no hook is set
+
+// Relaxed atomic load of a flag owned by Rust (an AtomicI32)
+static inline int32_t ei_ffi_load_relaxed(const int32_t* flag) {
+#if defined(_MSC_VER)
+    // Aligned 32-bit volatile reads are single loads on every MSVC target
+    return *static_cast<const volatile int32_t*>(flag);
+#else
+    return __atomic_load_n(flag, __ATOMIC_RELAXED);
+#endif
+}
+
+// Polled by the SDK between processing steps; EI_IMPULSE_CANCELED aborts the classifier call
+EI_IMPULSE_ERROR ei_run_impulse_check_canceled() {
+    const int32_t* flag = ei_ffi_cancel_flag;
+    if (flag != nullptr && ei_ffi_load_relaxed(flag) != 0) {
+        return EI_IMPULSE_CANCELED;
+    }
+    return EI_IMPULSE_OK;
+}
+
+uint64_t ei_read_timer_us() {
+    uint64_t (*hook)(void) = ei_ffi_read_timer_us_hook.load();
+    if (hook != nullptr) {
+        return hook();
+    }
+#if defined(EI_FFI_BARE_METAL)
+    // No OS clock: timings are 0 unless the firmware sets a timer hook
+    return 0;
+#else
+    return std::chrono::duration_cast<std::chrono::microseconds>(
+        std::chrono::steady_clock::now().time_since_epoch()).count();
+#endif
+}
+
+uint64_t ei_read_timer_ms() {
+    return ei_read_timer_us() / 1000;
+}
+
+EI_IMPULSE_ERROR ei_sleep(int32_t time_ms) {
+    void (*hook)(int32_t) = ei_ffi_sleep_hook.load();
+    if (hook != nullptr) {
+        hook(time_ms);
+    }
+#if !defined(EI_FFI_BARE_METAL)
+    else if (time_ms > 0) {
+        std::this_thread::sleep_for(std::chrono::milliseconds(time_ms));
+    }
+#endif
+    return EI_IMPULSE_OK;
+}
+
+void* ei_malloc(size_t size) {
+    for (uint32_t attempt = 0;; attempt++) {
+        void* (*hook)(size_t) = ei_ffi_malloc_hook.load();
+        void* ptr = hook != nullptr ? hook(size) : malloc(size);
+        if (ptr != nullptr || !ei_ffi_retry_alloc(size, attempt)) {
+            return ptr;
+        }
+    }
+}
+
+static void* ei_ffi_calloc(size_t nitems, size_t size) {
+    void* (*hook)(size_t, size_t) = ei_ffi_calloc_hook.load();
+    if (hook != nullptr) {
+        return hook(nitems, size);
+    }
+    void* (*malloc_hook)(size_t) = ei_ffi_malloc_hook.load();
+    if (malloc_hook == nullptr) {
+        return calloc(nitems, size);
+    }
+    // Only malloc is hooked: zero the memory ourselves
+    void* ptr = malloc_hook(nitems * size);
+    if (ptr != nullptr) {
+        memset(ptr, 0, nitems * size);
+    }
+    return ptr;
+}
+
+void* ei_calloc(size_t nitems, size_t size) {
+    if (size != 0 && nitems > SIZE_MAX / size) {
+        return nullptr;
+    }
+    for (uint32_t attempt = 0;; attempt++) {
+        void* ptr = ei_ffi_calloc(nitems, size);
+        if (ptr != nullptr || !ei_ffi_retry_alloc(nitems * size, attempt)) {
+            return ptr;
+        }
+    }
+}
+
+void ei_free(void* ptr) {
+    void (*hook)(void*) = ei_ffi_free_hook.load();
+    if (hook != nullptr) {
+        hook(ptr);
+    } else {
+        free(ptr);
+    }
+}
+
+// Overrides the SDK's weak POSIX ei_printf so debug output can be routed to Rust
+void ei_printf(const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+
+    if (strncmp(format, ei_ffi_dsp_error_message, sizeof(ei_ffi_dsp_error_message) - 1) == 0) {
+        va_list args_copy;
+        va_copy(args_copy, args);
+        ei_ffi_dsp_error = va_arg(args_copy, int);
+        va_end(args_copy);
+    }
+
+    ei_ffi_log_callback_t callback = ei_ffi_log_callback.load();
+    if (callback == nullptr) {
+        vprintf(format, args);
+        va_end(args);
+        return;
+    }
+
+    char buffer[256];
+    va_list args_copy;
+    va_copy(args_copy, args);
+    int length = vsnprintf(buffer, sizeof(buffer), format, args_copy);
+    va_end(args_copy);
+
+    if (length >= 0 && static_cast<size_t>(length) < sizeof(buffer)) {
+        callback(buffer);
+    } else if (length >= 0) {
+        // Message didn't fit on the stack, format it again into a large enough buffer
+        std::vector<char> large(static_cast<size_t>(length) + 1);
+        vsnprintf(large.data(), large.size(), format, args);
+        callback(large.data());
+    }
+    va_end(args);
+}
+
 } // extern "C"
@@ -18,13 +18,48 @@
 extern "C" {
 #endif
 
+// A tracked object from the object tracking postprocessing block
+typedef struct {
+    uint32_t id;
+    const char* label;
+    float value;
+    uint32_t x;
+    uint32_t y;
+    uint32_t width;
+    uint32_t height;
+} ei_ffi_tracked_object_t;
+
+// Receives the SDK's debug output (ei_printf), one formatted chunk at a time
+typedef void (*ei_ffi_log_callback_t)(const char* message);
+
+// Replacements for the SDK's porting functions; NULL members keep the POSIX implementation
+typedef struct {
+    uint64_t (*read_timer_us)(void);
+    void (*sleep_ms)(int32_t time_ms);
+    void* (*malloc_fn)(size_t size);
+    void* (*calloc_fn)(size_t nitems, size_t size);
+    void (*free_fn)(void* ptr);
+} ei_ffi_porting_hooks_t;
+
+// Called when ei_malloc/ei_calloc returns NULL, with the requested size, the classifier call the
+// allocation was made from (0: other, 1: init, 2: run_classifier, 3: run_inference) and the number
+// of failed attempts before this one; returning nonzero retries the allocation
+typedef int (*ei_ffi_alloc_failure_callback_t)(size_t size, int stage, uint32_t attempt);
+
 // Function declarations (no type redefinitions!)
 void ei_ffi_run_classifier_init(void);
 void ei_ffi_run_classifier_deinit(void);
 EI_IMPULSE_ERROR ei_ffi_init_impulse(ei_impulse_handle_t* handle);
 EI_IMPULSE_ERROR ei_ffi_run_classifier(signal_t* signal, ei_impulse_result_t* result, int debug);
-EI_IMPULSE_ERROR ei_ffi_run_classifier_continuous(signal_t* signal, ei_impulse_result_t* result, int debug, int enable_maf_unused);
+EI_IMPULSE_ERROR ei_ffi_run_classifier_continuous(signal_t* signal, ei_impulse_result_t* result, int debug, int enable_maf);
 EI_IMPULSE_ERROR ei_ffi_run_inference(ei_impulse_handle_t* handle, ei_feature_t* fmatrix, ei_impulse_result_t* result, int debug);
+// Learning blocks on precomputed DSP features (every DSP block's output in block order); NULL handle
+// for the global classifier
+EI_IMPULSE_ERROR ei_ffi_run_inference_features(ei_impulse_handle_t* handle, const float* features, size_t features_size, ei_impulse_result_t* result, int debug);
+// Independent impulse handles (own classifier state, shared model)
+ei_impulse_handle_t* ei_ffi_impulse_handle_new(void);
+void ei_ffi_impulse_handle_free(ei_impulse_handle_t* handle);
+EI_IMPULSE_ERROR ei_ffi_run_classifier_with_handle(ei_impulse_handle_t* handle, signal_t* signal, ei_impulse_result_t* result, int debug);
 // Helper function to create signal from buffer (like EIM binary)
 EI_IMPULSE_ERROR ei_ffi_signal_from_buffer(const float* data, size_t data_size, signal_t* signal);
 
@@ -32,6 +67,49 @@ EI_IMPULSE_ERROR ei_ffi_signal_from_buffer(const float* data, size_t data_size,
 EI_IMPULSE_ERROR ei_ffi_set_object_detection_threshold(uint32_t block_id, float min_score);
 EI_IMPULSE_ERROR ei_ffi_set_anomaly_threshold(uint32_t block_id, float min_anomaly_score);
 EI_IMPULSE_ERROR ei_ffi_set_object_tracking_threshold(uint32_t block_id, float threshold, uint32_t keep_grace, uint16_t max_observations);
+// Min score of neural network blocks or anomaly threshold of GMM blocks
+// (EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE for K-means and unknown blocks)
+EI_IMPULSE_ERROR ei_ffi_set_learn_block_threshold(uint32_t block_id, float threshold);
+EI_IMPULSE_ERROR ei_ffi_get_learn_block_threshold(uint32_t block_id, float* threshold);
+
+// Non-max suppression of object detection blocks (EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE if the
+// SDK has no NMS settings) and a cap on the boxes returned per inference (0 keeps all of them)
+EI_IMPULSE_ERROR ei_ffi_set_nms_iou_threshold(uint32_t block_id, float iou_threshold);
+EI_IMPULSE_ERROR ei_ffi_get_nms_iou_threshold(uint32_t block_id, float* iou_threshold);
+void ei_ffi_set_max_detections(uint32_t max_detections);
+uint32_t ei_ffi_get_max_detections(void);
+
+// Object tracking results (0 objects if the model has no object tracking)
+uint32_t ei_ffi_object_tracking_count(const ei_impulse_result_t* result);
+EI_IMPULSE_ERROR ei_ffi_object_tracking_get(const ei_impulse_result_t* result, uint32_t index, ei_ffi_tracked_object_t* object);
+
+// Output tensors of freeform learning blocks, e.g. embeddings (0 outputs if the model has none)
+uint32_t ei_ffi_freeform_output_count(const ei_impulse_result_t* result);
+EI_IMPULSE_ERROR ei_ffi_freeform_output_get(const ei_impulse_result_t* result, uint32_t index, const float** data, size_t* len);
+
+// Redirect ei_printf output to a callback (NULL restores printing to stdout)
+void ei_ffi_set_log_callback(ei_ffi_log_callback_t callback);
+
+// Cancel the classifier calls made on this thread once *flag is nonzero (NULL never cancels)
+void ei_ffi_set_cancel_flag(const int32_t* flag);
+
+// Route ei_read_timer_ms/us, ei_sleep and ei_malloc/calloc/free to the hooks (NULL restores all)
+void ei_ffi_set_porting_hooks(const ei_ffi_porting_hooks_t* hooks);
+
+// Route only ei_read_timer_ms/us to a hook, keeping the other hooks (NULL restores the default clock)
+void ei_ffi_set_timer_hook(uint64_t (*read_timer_us)(void));
+
+// Clock behind ei_read_timer_us: 0 std::chrono::steady_clock, 1 a timer hook, 2 none (bare metal)
+int ei_ffi_clock_source(void);
+
+// Report failed porting layer allocations to a callback (NULL fails them right away)
+void ei_ffi_set_alloc_failure_callback(ei_ffi_alloc_failure_callback_t callback);
+
+// EIDSP_* code of the DSP failure in the last classifier call on this thread, or 0
+int ei_ffi_last_dsp_error(void);
+
+// Hash of the model the library was built from (computed by build.rs from the model headers)
+uint64_t ei_ffi_model_hash(void);
 
 #ifdef __cplusplus
 }
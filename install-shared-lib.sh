@@ -0,0 +1,35 @@
+#!/bin/bash
+set -e
+
+# Install the shared Edge Impulse SDK library built with EI_BUILD_SHARED=1
+# Usage: ./install-shared-lib.sh <destination-dir> [target-triple]
+
+DEST_DIR="$1"
+TARGET="$2"
+
+if [ -z "$DEST_DIR" ]; then
+  echo "Usage: $0 <destination-dir> [target-triple]"
+  exit 1
+fi
+
+SEARCH_DIR="model/build"
+if [ -n "$TARGET" ]; then
+  SEARCH_DIR="model/build/$TARGET"
+fi
+
+LIBS=$(find "$SEARCH_DIR" -path '*shared*' \( -name 'libedge-impulse-sdk.so' -o -name 'libedge-impulse-sdk.dylib' \) 2>/dev/null)
+
+if [ -z "$LIBS" ]; then
+  echo "No shared library found in $SEARCH_DIR. Build with EI_BUILD_SHARED=1 first."
+  exit 1
+fi
+
+if [ "$(echo "$LIBS" | wc -l)" -gt 1 ]; then
+  echo "Multiple shared libraries found, pass the target triple to pick one:"
+  echo "$LIBS"
+  exit 1
+fi
+
+mkdir -p "$DEST_DIR"
+cp "$LIBS" "$DEST_DIR/"
+echo "Installed $LIBS to $DEST_DIR"
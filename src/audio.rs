@@ -0,0 +1,253 @@
+//! Audio preprocessing
+//!
+//! Audio models take `EI_CLASSIFIER_RAW_SAMPLE_COUNT` mono samples recorded at
+//! `EI_CLASSIFIER_FREQUENCY`, as raw 16-bit PCM values converted to `f32` (not normalized).
+//! [`audio_features`] converts interleaved PCM from any sample rate and channel count into that
+//! format, and [`EimModel::infer_audio`](crate::EimModel::infer_audio) runs inference on it.
+
+use std::fmt;
+
+use crate::model_metadata::{EI_CLASSIFIER_FREQUENCY, EI_CLASSIFIER_RAW_SAMPLE_COUNT};
+use crate::runner_api::EimError;
+
+/// Average interleaved channels into a single channel
+pub fn to_mono(samples: &[i16], channels: u16) -> Vec<i16> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels as usize)
+        .map(|frame| {
+            let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+            (sum / channels as i32) as i16
+        })
+        .collect()
+}
+
+/// Resample mono audio from `from_rate` to `to_rate` Hz using linear interpolation
+pub fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let len = (samples.len() as f64 / ratio).round() as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = (position.floor() as usize).min(samples.len() - 1);
+            let next = (index + 1).min(samples.len() - 1);
+            let fraction = position - index as f64;
+            let a = samples[index] as f64;
+            let b = samples[next] as f64;
+            (a + (b - a) * fraction).round() as i16
+        })
+        .collect()
+}
+
+/// How audio shorter than the model window is padded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AudioPadding {
+    /// Pad with silence
+    #[default]
+    Zero,
+    /// Pad with low-level noise at the recording's noise floor, so the padding doesn't look like
+    /// digital silence to the model
+    NoiseFloor,
+    /// Repeat the last sample
+    RepeatEdge,
+}
+
+/// How recordings are fitted to the model window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioOptions {
+    /// Padding of recordings shorter than the window
+    pub padding: AudioPadding,
+    /// Trim leading and trailing samples whose magnitude is at most this value before fitting
+    /// the recording to the window (`None` keeps them)
+    pub trim_silence: Option<i16>,
+}
+
+/// What [`audio_features_with`] did to fit a recording to the model window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioReport {
+    /// Mono samples after resampling, before trimming
+    pub input_samples: usize,
+    /// Silent samples removed from the start
+    pub trimmed_leading: usize,
+    /// Silent samples removed from the end
+    pub trimmed_trailing: usize,
+    /// Samples dropped because the recording is longer than the window
+    pub truncated: usize,
+    /// Samples added because the recording is shorter than the window
+    pub padded: usize,
+    /// Padding strategy used
+    pub padding: AudioPadding,
+}
+
+impl fmt::Display for AudioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "audio: {} samples, trimmed {} leading and {} trailing, truncated {}, padded {} ({:?})",
+            self.input_samples,
+            self.trimmed_leading,
+            self.trimmed_trailing,
+            self.truncated,
+            self.padded,
+            self.padding
+        )
+    }
+}
+
+/// Trim, truncate and pad mono samples to exactly `len` features
+///
+/// `frame` is the number of samples the noise floor is measured over (e.g. 10 ms of audio).
+pub fn fit_window(
+    samples: &[i16],
+    len: usize,
+    frame: usize,
+    options: &AudioOptions,
+) -> (Vec<f32>, AudioReport) {
+    let mut start = 0;
+    let mut end = samples.len();
+    if let Some(threshold) = options.trim_silence {
+        let loud = |s: &i16| s.unsigned_abs() > threshold.unsigned_abs();
+        start = samples.iter().position(loud).unwrap_or(samples.len());
+        end = samples.iter().rposition(loud).map_or(start, |i| i + 1);
+    }
+    let kept = &samples[start..end];
+
+    let mut features: Vec<f32> = kept.iter().take(len).map(|&s| s as f32).collect();
+    let padded = len - features.len();
+    match options.padding {
+        AudioPadding::Zero => features.resize(len, 0.0),
+        AudioPadding::RepeatEdge => {
+            let edge = features.last().copied().unwrap_or(0.0);
+            features.resize(len, edge);
+        }
+        AudioPadding::NoiseFloor => {
+            let amplitude = noise_floor(kept, frame) * 3f32.sqrt();
+            // xorshift32 with a fixed seed, so the same recording always gives the same features
+            let mut state = 0x2545_f491_u32;
+            features.extend((0..padded).map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let unit = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                (unit * amplitude).round()
+            }));
+        }
+    }
+
+    let report = AudioReport {
+        input_samples: samples.len(),
+        trimmed_leading: start,
+        trimmed_trailing: samples.len() - end,
+        truncated: kept.len().saturating_sub(len),
+        padded,
+        padding: options.padding,
+    };
+    (features, report)
+}
+
+/// RMS of the quietest `frame`-sample frame, 0.0 for an empty recording
+fn noise_floor(samples: &[i16], frame: usize) -> f32 {
+    samples
+        .chunks(frame.max(1))
+        .map(|chunk| {
+            let sum: f64 = chunk.iter().map(|&s| (s as f64) * (s as f64)).sum();
+            (sum / chunk.len() as f64).sqrt() as f32
+        })
+        .min_by(f32::total_cmp)
+        .unwrap_or(0.0)
+}
+
+/// Convert interleaved PCM into the linked model's input features
+///
+/// The audio is mixed down to mono, resampled to `EI_CLASSIFIER_FREQUENCY`, and zero-padded or
+/// truncated to `EI_CLASSIFIER_RAW_SAMPLE_COUNT` samples.
+pub fn audio_features(
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<Vec<f32>, EimError> {
+    audio_features_with(samples, sample_rate, channels, &AudioOptions::default())
+        .map(|(features, _)| features)
+}
+
+/// Convert interleaved PCM into the linked model's input features, trimming and padding it
+/// according to `options`
+pub fn audio_features_with(
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+    options: &AudioOptions,
+) -> Result<(Vec<f32>, AudioReport), EimError> {
+    profile_scope!("preprocess_audio");
+    #[allow(clippy::unnecessary_cast)]
+    let frequency = EI_CLASSIFIER_FREQUENCY as u32;
+    if frequency == 0 {
+        return Err(EimError::InvalidOperation(
+            "The model has no sampling frequency, it doesn't take audio input".to_string(),
+        ));
+    }
+    if channels == 0 || sample_rate == 0 {
+        return Err(EimError::InvalidInput(format!(
+            "Invalid audio format: {} Hz, {} channels",
+            sample_rate, channels
+        )));
+    }
+
+    let mono = to_mono(samples, channels);
+    let resampled = resample(&mono, sample_rate, frequency);
+    // Measure the noise floor over 10 ms frames
+    let frame = (frequency / 100) as usize;
+    Ok(fit_window(
+        &resampled,
+        EI_CLASSIFIER_RAW_SAMPLE_COUNT,
+        frame,
+        options,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_silence_and_pads() {
+        let samples = [0, 1, 500, -800, 300, 2, 0];
+        let options = AudioOptions {
+            padding: AudioPadding::RepeatEdge,
+            trim_silence: Some(10),
+        };
+        let (features, report) = fit_window(&samples, 5, 2, &options);
+        assert_eq!(features, [500.0, -800.0, 300.0, 300.0, 300.0]);
+        assert_eq!(report.trimmed_leading, 2);
+        assert_eq!(report.trimmed_trailing, 2);
+        assert_eq!(report.padded, 2);
+
+        let (features, report) = fit_window(&samples, 3, 2, &AudioOptions::default());
+        assert_eq!(features, [0.0, 1.0, 500.0]);
+        assert_eq!(report.truncated, 4);
+    }
+
+    #[test]
+    fn noise_floor_padding_stays_quiet() {
+        let samples: Vec<i16> = (0..400)
+            .map(|i| if i < 100 { (i % 3) as i16 - 1 } else { 4000 })
+            .collect();
+        let options = AudioOptions {
+            padding: AudioPadding::NoiseFloor,
+            trim_silence: None,
+        };
+        let (features, report) = fit_window(&samples, 1000, 50, &options);
+        assert_eq!(report.padded, 600);
+        assert!(features[400..].iter().all(|v| v.abs() <= 2.0));
+        assert!(features[400..].iter().any(|&v| v != 0.0));
+    }
+}
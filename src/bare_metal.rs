@@ -0,0 +1,203 @@
+//! Classifier for bare-metal targets
+//!
+//! With the `no_std` feature the crate links the SDK built with TensorFlow Lite Micro for
+//! microcontrollers such as Cortex-M4F/M7 (`thumbv7em-none-eabihf`), where there is no standard
+//! library and often no allocator. This module is the API for those targets: results are copied
+//! into fixed-capacity [`heapless`] collections, labels borrow the SDK's static label strings,
+//! and timings are plain microsecond counts. There is no clock on bare metal, so timings are 0
+//! until the firmware sets a timer with [`crate::timing::set_timer`].
+//!
+//! ```ignore
+//! use edge_impulse_ffi_rs::bare_metal::Classifier;
+//!
+//! let mut classifier = Classifier::take().unwrap();
+//! let result = classifier.run(&features, false)?;
+//! for c in &result.classification {
+//!     defmt::info!("{}: {}", c.label, c.value);
+//! }
+//! ```
+
+use core::ffi::{c_char, CStr};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::bindings::*;
+use crate::error::{check_classifier, EdgeImpulseError};
+use crate::model_metadata::{EI_CLASSIFIER_LABEL_COUNT, EI_CLASSIFIER_MODEL_HASH};
+use crate::signal::Signal;
+
+/// Number of labels a result holds
+pub const MAX_LABELS: usize = EI_CLASSIFIER_LABEL_COUNT;
+
+/// Most bounding boxes a result holds; the SDK is told to keep no more detections than this
+pub const MAX_BOXES: usize = 16;
+
+/// Score of one label
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Classification {
+    pub label: &'static str,
+    pub value: f32,
+}
+
+/// A detected object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub label: &'static str,
+    pub value: f32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Result of one inference, without heap allocations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferenceResult {
+    pub classification: heapless::Vec<Classification, MAX_LABELS>,
+    /// The [`MAX_BOXES`] best detections
+    pub bounding_boxes: heapless::Vec<BoundingBox, MAX_BOXES>,
+    pub anomaly: f32,
+    /// Time spent in the DSP blocks, in microseconds
+    pub dsp_us: u64,
+    /// Time spent in the learning blocks, in microseconds
+    pub classification_us: u64,
+    /// Time spent in the anomaly block, in microseconds
+    pub anomaly_us: u64,
+}
+
+impl InferenceResult {
+    /// Copy a result of the SDK
+    ///
+    /// # Safety
+    ///
+    /// `result` must have been filled by the SDK, with its label and bounding box pointers still
+    /// valid.
+    pub unsafe fn from_raw(result: &ei_impulse_result_t) -> Self {
+        let mut output = Self {
+            anomaly: result.anomaly,
+            dsp_us: micros(result.timing.dsp_us),
+            classification_us: micros(result.timing.classification_us),
+            anomaly_us: micros(result.timing.anomaly_us),
+            ..Self::default()
+        };
+        let label_count = MAX_LABELS.min(result.classification.len());
+        for c in result.classification[..label_count]
+            .iter()
+            .filter(|c| !c.label.is_null())
+        {
+            // Can't overflow: there are at most MAX_LABELS labels
+            let _ = output.classification.push(Classification {
+                label: label_str(c.label),
+                value: c.value,
+            });
+        }
+        if !result.bounding_boxes.is_null() {
+            let boxes = core::slice::from_raw_parts(
+                result.bounding_boxes,
+                result.bounding_boxes_count as usize,
+            );
+            // The SDK marks unused slots with a zero score
+            for bb in boxes.iter().filter(|bb| bb.value > 0.0).take(MAX_BOXES) {
+                let _ = output.bounding_boxes.push(BoundingBox {
+                    label: label_str(bb.label),
+                    value: bb.value,
+                    x: bb.x,
+                    y: bb.y,
+                    width: bb.width,
+                    height: bb.height,
+                });
+            }
+        }
+        output
+    }
+}
+
+/// A `*_us` timing field in microseconds, whatever its integer type (see [`crate::timing`])
+fn micros<T: TryInto<u64>>(us: T) -> u64 {
+    us.try_into().unwrap_or(0)
+}
+
+/// A label of the SDK; labels are static strings in the model's generated code
+unsafe fn label_str(label: *const c_char) -> &'static str {
+    if label.is_null() {
+        return "";
+    }
+    CStr::from_ptr(label).to_str().unwrap_or("")
+}
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The classifier of the linked model
+///
+/// The SDK keeps one global state, so there is a single classifier, handed out by
+/// [`take`](Self::take) like a peripheral of a HAL.
+pub struct Classifier {
+    // The SDK's global state must not be touched from several contexts at once
+    _not_sync: PhantomData<*mut ()>,
+}
+
+impl Classifier {
+    /// Initialize the classifier, or `None` if it's already taken
+    ///
+    /// # Panics
+    ///
+    /// Panics if the linked library was built from another model than the metadata.
+    pub fn take() -> Option<Self> {
+        if TAKEN.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        let linked = unsafe { ei_ffi_model_hash() };
+        if linked != EI_CLASSIFIER_MODEL_HASH {
+            TAKEN.store(false, Ordering::Release);
+            panic!("the linked Edge Impulse library was built from another model than the crate's metadata");
+        }
+        unsafe {
+            ei_ffi_run_classifier_init();
+            ei_ffi_set_max_detections(MAX_BOXES as u32);
+        }
+        Some(Self {
+            _not_sync: PhantomData,
+        })
+    }
+
+    /// Run the impulse on raw input features
+    pub fn run(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceResult, EdgeImpulseError> {
+        let mut signal = Signal::from_slice(features)?;
+        let mut result = ei_impulse_result_t::default();
+        check_classifier(unsafe {
+            ei_ffi_run_classifier(signal.as_raw_mut(), &mut result, debug as i32)
+        })?;
+        Ok(unsafe { InferenceResult::from_raw(&result) })
+    }
+
+    /// Run only the learning and postprocessing blocks on precomputed DSP features
+    /// (`EI_CLASSIFIER_NN_INPUT_FRAME_SIZE` values)
+    pub fn run_features(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceResult, EdgeImpulseError> {
+        let mut result = ei_impulse_result_t::default();
+        check_classifier(unsafe {
+            ei_ffi_run_inference_features(
+                core::ptr::null_mut(),
+                features.as_ptr(),
+                features.len(),
+                &mut result,
+                debug as i32,
+            )
+        })?;
+        Ok(unsafe { InferenceResult::from_raw(&result) })
+    }
+}
+
+impl Drop for Classifier {
+    fn drop(&mut self) {
+        unsafe { ei_ffi_run_classifier_deinit() };
+        TAKEN.store(false, Ordering::Release);
+    }
+}
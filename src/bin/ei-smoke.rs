@@ -0,0 +1,120 @@
+//! Smoke test for device provisioning
+//!
+//! Initializes the linked model, checks its metadata and runs one inference on synthetic input,
+//! then prints a single JSON object with the outcome of every check. Exits with status 0 if all
+//! checks passed and 1 otherwise, so provisioning scripts can validate an image before it ships.
+//!
+//! Usage:
+//!   cargo run --features smoke --bin ei-smoke
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+use edge_impulse_ffi_rs::runner_api::InferenceResult;
+use edge_impulse_ffi_rs::scaling::{InputScaling, ScalingCheck};
+use edge_impulse_ffi_rs::{EimModel, ModelMetadata};
+use serde_json::{json, Value};
+
+struct Report {
+    checks: Vec<Value>,
+    passed: bool,
+}
+
+impl Report {
+    fn check(&mut self, name: &str, result: Result<String, String>) {
+        let passed = result.is_ok();
+        let detail = result.unwrap_or_else(|error| error);
+        self.checks
+            .push(json!({ "name": name, "pass": passed, "detail": detail }));
+        self.passed &= passed;
+    }
+}
+
+/// Input the model's first DSP block accepts without complaint: mid-gray pixels for image
+/// models, silence otherwise
+fn synthetic_features(len: usize) -> Vec<f32> {
+    let value = match InputScaling::model() {
+        InputScaling::PackedRgb => 0x80_80_80 as f32,
+        InputScaling::RawI16 | InputScaling::Raw => 0.0,
+    };
+    vec![value; len]
+}
+
+fn check_metadata(metadata: &ModelMetadata) -> Result<String, String> {
+    if metadata.dsp_input_frame_size == 0 {
+        return Err("model expects no input features".to_string());
+    }
+    if metadata.model_type == "classification" && metadata.labels.is_empty() {
+        return Err("classification model has no labels".to_string());
+    }
+    Ok(format!(
+        "{} model, {} input features, {} labels",
+        metadata.model_type,
+        metadata.dsp_input_frame_size,
+        metadata.labels.len()
+    ))
+}
+
+fn check_result(result: &InferenceResult) -> Result<String, String> {
+    let values: Vec<f32> = match result {
+        InferenceResult::Classification { classification, .. } => {
+            classification.values().copied().collect()
+        }
+        InferenceResult::Embedding { vector, .. } => vector.clone(),
+        InferenceResult::Regression { value } => vec![*value],
+        other => other.top_k(usize::MAX).iter().map(|(_, v)| *v).collect(),
+    };
+    match values.iter().find(|v| !v.is_finite()) {
+        Some(value) => Err(format!("model produced a non-finite value: {}", value)),
+        None => Ok(format!("{} output values", values.len())),
+    }
+}
+
+fn main() -> ExitCode {
+    let metadata = ModelMetadata::get();
+    let mut report = Report {
+        checks: Vec::new(),
+        passed: true,
+    };
+    let mut inference_ms = None;
+
+    report.check("metadata", check_metadata(metadata));
+
+    match EimModel::new() {
+        Ok(mut model) => {
+            report.check("init", Ok("classifier initialized".to_string()));
+            model.set_input_scaling_check(ScalingCheck::Off);
+
+            let features = synthetic_features(metadata.dsp_input_frame_size as usize);
+            let start = Instant::now();
+            let response = model.infer_slice(&features, None);
+            inference_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+            match response {
+                Ok(response) => {
+                    report.check("inference", Ok("synthetic inference succeeded".to_string()));
+                    report.check("output", check_result(&response.result));
+                }
+                Err(error) => report.check("inference", Err(error.to_string())),
+            }
+        }
+        Err(error) => report.check("init", Err(error.to_string())),
+    }
+
+    println!(
+        "{}",
+        json!({
+            "pass": report.passed,
+            "project_id": metadata.project_id,
+            "project_name": metadata.project_name,
+            "deploy_version": metadata.deploy_version,
+            "inference_ms": inference_ms,
+            "checks": report.checks,
+        })
+    );
+
+    if report.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,317 @@
+// Stand-in for the bindgen-generated bindings.rs, used by the `dummy` feature and on docs.rs.
+//
+// The types have the same names and fields as the real bindings, so the crate type-checks and
+// documents without a model. The functions are plain Rust with the same signatures: nothing is
+// linked, initialization does nothing and every inference fails with
+// EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE.
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct EI_IMPULSE_ERROR(pub ::core::ffi::c_int);
+impl EI_IMPULSE_ERROR {
+    pub const EI_IMPULSE_OK: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(0);
+    pub const EI_IMPULSE_ERROR_SHAPES_DONT_MATCH: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-1);
+    pub const EI_IMPULSE_CANCELED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-2);
+    pub const EI_IMPULSE_TFLITE_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-3);
+    pub const EI_IMPULSE_DSP_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-5);
+    pub const EI_IMPULSE_TFLITE_ARENA_ALLOC_FAILED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-6);
+    pub const EI_IMPULSE_CUBEAI_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-7);
+    pub const EI_IMPULSE_ALLOC_FAILED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-8);
+    pub const EI_IMPULSE_ONLY_SUPPORTED_FOR_IMAGES: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-9);
+    pub const EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-10);
+    pub const EI_IMPULSE_OUT_OF_MEMORY: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-11);
+    pub const EI_IMPULSE_INPUT_TENSOR_WAS_NULL: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-13);
+    pub const EI_IMPULSE_OUTPUT_TENSOR_WAS_NULL: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-14);
+    pub const EI_IMPULSE_SCORE_TENSOR_WAS_NULL: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-15);
+    pub const EI_IMPULSE_LABEL_TENSOR_WAS_NULL: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-16);
+    pub const EI_IMPULSE_TENSORRT_INIT_FAILED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-17);
+    pub const EI_IMPULSE_DRPAI_INIT_FAILED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-18);
+    pub const EI_IMPULSE_DRPAI_RUNTIME_FAILED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-19);
+    pub const EI_IMPULSE_DEPRECATED_MODEL: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-20);
+    pub const EI_IMPULSE_LAST_LAYER_NOT_AVAILABLE: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-21);
+    pub const EI_IMPULSE_INFERENCE_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-22);
+    pub const EI_IMPULSE_AKIDA_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-23);
+    pub const EI_IMPULSE_INVALID_SIZE: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-24);
+    pub const EI_IMPULSE_ONNX_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-25);
+    pub const EI_IMPULSE_MEMRYX_ERROR: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR(-26);
+}
+
+/// Zero-initialized value, like bindgen's `Default` for structs holding pointers
+macro_rules! zeroed_default {
+    ($($ty:ty),*) => {
+        $(impl Default for $ty {
+            fn default() -> Self {
+                let mut s = ::core::mem::MaybeUninit::<Self>::uninit();
+                unsafe {
+                    ::core::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+                    s.assume_init()
+                }
+            }
+        })*
+    };
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ei_signal_t {
+    /// `std::function` reading the features, opaque to Rust
+    pub get_data: [u64; 4usize],
+    pub total_length: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_feature_t {
+    pub matrix: *mut ::core::ffi::c_void,
+    pub blockId: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_impulse_handle_t {
+    _unused: [u8; 0],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_impulse_result_classification_t {
+    pub label: *const ::core::ffi::c_char,
+    pub value: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_impulse_result_bounding_box_t {
+    pub label: *const ::core::ffi::c_char,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub value: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ei_impulse_result_timing_t {
+    pub sampling: ::core::ffi::c_int,
+    pub dsp: ::core::ffi::c_int,
+    pub classification: ::core::ffi::c_int,
+    pub anomaly: ::core::ffi::c_int,
+    pub dsp_us: i64,
+    pub classification_us: i64,
+    pub anomaly_us: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ei_impulse_visual_ad_result_t {
+    pub mean_value: f32,
+    pub max_value: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_impulse_result_t {
+    pub bounding_boxes: *mut ei_impulse_result_bounding_box_t,
+    pub bounding_boxes_count: u32,
+    pub classification: [ei_impulse_result_classification_t; 3usize],
+    pub anomaly: f32,
+    pub visual_ad_grid_cells: *mut ei_impulse_result_bounding_box_t,
+    pub visual_ad_count: u32,
+    pub visual_ad_result: ei_impulse_visual_ad_result_t,
+    pub timing: ei_impulse_result_timing_t,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_ffi_tracked_object_t {
+    pub id: u32,
+    pub label: *const ::core::ffi::c_char,
+    pub value: f32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub type ei_ffi_log_callback_t =
+    ::core::option::Option<unsafe extern "C" fn(message: *const ::core::ffi::c_char)>;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ei_ffi_porting_hooks_t {
+    pub read_timer_us: ::core::option::Option<unsafe extern "C" fn() -> u64>,
+    pub sleep_ms: ::core::option::Option<unsafe extern "C" fn(time_ms: i32)>,
+    pub malloc_fn:
+        ::core::option::Option<unsafe extern "C" fn(size: usize) -> *mut ::core::ffi::c_void>,
+    pub calloc_fn: ::core::option::Option<
+        unsafe extern "C" fn(nitems: usize, size: usize) -> *mut ::core::ffi::c_void,
+    >,
+    pub free_fn: ::core::option::Option<unsafe extern "C" fn(ptr: *mut ::core::ffi::c_void)>,
+}
+
+pub type ei_ffi_alloc_failure_callback_t = ::core::option::Option<
+    unsafe extern "C" fn(
+        size: usize,
+        stage: ::core::ffi::c_int,
+        attempt: u32,
+    ) -> ::core::ffi::c_int,
+>;
+
+zeroed_default!(
+    ei_feature_t,
+    ei_impulse_handle_t,
+    ei_impulse_result_classification_t,
+    ei_impulse_result_bounding_box_t,
+    ei_impulse_result_t,
+    ei_ffi_tracked_object_t
+);
+
+/// Hash of the model these bindings were generated from
+pub const EI_FFI_MODEL_HASH: u64 = 0;
+
+const NOT_LINKED: EI_IMPULSE_ERROR = EI_IMPULSE_ERROR::EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE;
+
+pub unsafe fn ei_ffi_run_classifier_init() {}
+pub unsafe fn ei_ffi_run_classifier_deinit() {}
+pub unsafe fn ei_ffi_init_impulse(_handle: *mut ei_impulse_handle_t) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_run_classifier(
+    _signal: *mut ei_signal_t,
+    _result: *mut ei_impulse_result_t,
+    _debug: ::core::ffi::c_int,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_run_classifier_continuous(
+    _signal: *mut ei_signal_t,
+    _result: *mut ei_impulse_result_t,
+    _debug: ::core::ffi::c_int,
+    _enable_maf: ::core::ffi::c_int,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_run_inference(
+    _handle: *mut ei_impulse_handle_t,
+    _fmatrix: *mut ei_feature_t,
+    _result: *mut ei_impulse_result_t,
+    _debug: ::core::ffi::c_int,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_run_inference_features(
+    _handle: *mut ei_impulse_handle_t,
+    _features: *const f32,
+    _features_size: usize,
+    _result: *mut ei_impulse_result_t,
+    _debug: ::core::ffi::c_int,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_impulse_handle_new() -> *mut ei_impulse_handle_t {
+    ::core::ptr::null_mut()
+}
+pub unsafe fn ei_ffi_impulse_handle_free(_handle: *mut ei_impulse_handle_t) {}
+pub unsafe fn ei_ffi_run_classifier_with_handle(
+    _handle: *mut ei_impulse_handle_t,
+    _signal: *mut ei_signal_t,
+    _result: *mut ei_impulse_result_t,
+    _debug: ::core::ffi::c_int,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_signal_from_buffer(
+    _data: *const f32,
+    data_size: usize,
+    signal: *mut ei_signal_t,
+) -> EI_IMPULSE_ERROR {
+    (*signal).total_length = data_size;
+    EI_IMPULSE_ERROR::EI_IMPULSE_OK
+}
+pub unsafe fn ei_ffi_set_object_detection_threshold(
+    _block_id: u32,
+    _min_score: f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_anomaly_threshold(
+    _block_id: u32,
+    _min_anomaly_score: f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_object_tracking_threshold(
+    _block_id: u32,
+    _threshold: f32,
+    _keep_grace: u32,
+    _max_observations: u16,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_learn_block_threshold(
+    _block_id: u32,
+    _threshold: f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_get_learn_block_threshold(
+    _block_id: u32,
+    _threshold: *mut f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_nms_iou_threshold(
+    _block_id: u32,
+    _iou_threshold: f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_get_nms_iou_threshold(
+    _block_id: u32,
+    _iou_threshold: *mut f32,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_max_detections(_max_detections: u32) {}
+pub unsafe fn ei_ffi_get_max_detections() -> u32 {
+    0
+}
+pub unsafe fn ei_ffi_object_tracking_count(_result: *const ei_impulse_result_t) -> u32 {
+    0
+}
+pub unsafe fn ei_ffi_object_tracking_get(
+    _result: *const ei_impulse_result_t,
+    _index: u32,
+    _object: *mut ei_ffi_tracked_object_t,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_freeform_output_count(_result: *const ei_impulse_result_t) -> u32 {
+    0
+}
+pub unsafe fn ei_ffi_freeform_output_get(
+    _result: *const ei_impulse_result_t,
+    _index: u32,
+    _data: *mut *const f32,
+    _len: *mut usize,
+) -> EI_IMPULSE_ERROR {
+    NOT_LINKED
+}
+pub unsafe fn ei_ffi_set_log_callback(_callback: ei_ffi_log_callback_t) {}
+pub unsafe fn ei_ffi_set_cancel_flag(_flag: *const i32) {}
+pub unsafe fn ei_ffi_set_porting_hooks(_hooks: *const ei_ffi_porting_hooks_t) {}
+pub unsafe fn ei_ffi_set_timer_hook(
+    _read_timer_us: ::core::option::Option<unsafe extern "C" fn() -> u64>,
+) {
+}
+pub unsafe fn ei_ffi_clock_source() -> ::core::ffi::c_int {
+    0
+}
+pub unsafe fn ei_ffi_set_alloc_failure_callback(_callback: ei_ffi_alloc_failure_callback_t) {}
+pub unsafe fn ei_ffi_last_dsp_error() -> ::core::ffi::c_int {
+    0
+}
+pub unsafe fn ei_ffi_model_hash() -> u64 {
+    EI_FFI_MODEL_HASH
+}
@@ -0,0 +1,69 @@
+//! Cooperative cancellation
+//!
+//! Big models on slow hardware can take hundreds of milliseconds per inference. A
+//! [`CancellationToken`] lets another thread abort a classifier call that is already running:
+//! the SDK polls the token between processing steps, and the call fails with
+//! [`EdgeImpulseError::Canceled`](crate::EdgeImpulseError::Canceled) once it is canceled.
+//!
+//! The SDK only polls between steps (DSP blocks, learning blocks), so a single step such as the
+//! neural network itself still runs to completion.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+
+use crate::bindings::ei_ffi_set_cancel_flag;
+
+/// Shared flag that cancels the classifier calls made within [`run`](Self::run)
+///
+/// Clones share the same flag, so one clone can be handed to the thread that runs inference and
+/// another kept to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    // An `i32` rather than a `bool` so the glue can read it with a plain atomic load
+    canceled: Arc<AtomicI32>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not canceled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the running and all following classifier calls, until [`reset`](Self::reset)
+    pub fn cancel(&self) {
+        self.canceled.store(1, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Relaxed) != 0
+    }
+
+    /// Clear the flag so the token can be used for the next inference
+    pub fn reset(&self) {
+        self.canceled.store(0, Ordering::Relaxed);
+    }
+
+    /// Run `f` with this token watched by the classifier calls it makes on the current thread
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = ACTIVE_FLAG.with(|active| active.replace(self.canceled.as_ptr()));
+        let _restore = RestoreFlag(previous);
+        unsafe { ei_ffi_set_cancel_flag(self.canceled.as_ptr()) };
+        f()
+    }
+}
+
+thread_local! {
+    // Mirrors the glue's flag for this thread, so nested `run` calls restore the outer token
+    static ACTIVE_FLAG: Cell<*const i32> = const { Cell::new(std::ptr::null()) };
+}
+
+struct RestoreFlag(*const i32);
+
+impl Drop for RestoreFlag {
+    fn drop(&mut self) {
+        ACTIVE_FLAG.with(|active| active.set(self.0));
+        unsafe { ei_ffi_set_cancel_flag(self.0) };
+    }
+}
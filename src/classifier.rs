@@ -0,0 +1,592 @@
+//! Safe classifier handle
+//!
+//! [`Classifier`] owns the SDK's global classifier state: it initializes the classifier when
+//! created and deinitializes it when dropped. [`ImpulseHandle`] owns independent DSP and
+//! postprocessing state, so several pipelines can run the same model side by side.
+//!
+//! # Threading
+//!
+//! The C++ SDK does no locking of its own, so every call of a [`Classifier`] that touches the
+//! global classifier state holds one process-wide lock: classifiers (and `EimModel`s) on
+//! different threads take turns instead of racing. [`Classifier`] can be moved to another thread
+//! but not shared between threads (it is `Send` but not `Sync`). [`ImpulseHandle`]s are `Send` as
+//! well, but take the same lock while they run: the learning blocks' interpreter and tensor arena
+//! exist once per model, not once per handle. To share a model between threads, wrap it in
+//! [`SyncModel`](crate::runner_api::SyncModel).
+//!
+//! # Multiple instances
+//!
+//! The SDK's classifier state exists once per process. By default ([`InstancePolicy::Shared`])
+//! every [`Classifier`] (and every `EimModel`) uses that same state: the first one initializes
+//! it, later ones join without resetting it, and the last one to be dropped deinitializes it.
+//! With [`InstancePolicy::Exclusive`], creating a second classifier while one is alive fails with
+//! [`AlreadyInitialized`] instead.
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use crate::bindings::*;
+use crate::error::{check, EdgeImpulseError};
+use crate::feature_provider::{self, FeatureProvider, ProviderError};
+use crate::features::ModelFeatures;
+use crate::inference::{self, InferenceOutput};
+use crate::model_metadata::EI_CLASSIFIER_MODEL_HASH;
+use crate::quantization::QuantizationInfo;
+use crate::signal::Signal;
+use crate::thresholds::{Threshold, Thresholds, MODEL_THRESHOLDS};
+
+/// How classifiers created while another one is alive are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstancePolicy {
+    /// Share the native classifier state with the classifiers already alive
+    #[default]
+    Shared,
+    /// Only allow one classifier at a time
+    Exclusive,
+}
+
+static POLICY: OnceLock<InstancePolicy> = OnceLock::new();
+
+// Number of live classifiers using the native classifier state
+static INSTANCES: Mutex<usize> = Mutex::new(0);
+
+// Held by every call touching the native classifier state, whichever classifier makes it.
+// Taken after INSTANCES when both are needed.
+static NATIVE_STATE: Mutex<()> = Mutex::new(());
+
+/// Choose the [`InstancePolicy`] for this process
+///
+/// The policy is fixed once set, or once the first classifier is created (which uses
+/// [`InstancePolicy::Shared`] unless another policy was set). Returns the policy in effect if
+/// it is a different one.
+pub fn set_instance_policy(policy: InstancePolicy) -> Result<(), InstancePolicy> {
+    match *POLICY.get_or_init(|| policy) {
+        current if current == policy => Ok(()),
+        current => Err(current),
+    }
+}
+
+/// The [`InstancePolicy`] in effect
+pub fn instance_policy() -> InstancePolicy {
+    *POLICY.get_or_init(InstancePolicy::default)
+}
+
+/// Another classifier is alive and the policy is [`InstancePolicy::Exclusive`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyInitialized;
+
+impl fmt::Display for AlreadyInitialized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the Edge Impulse classifier is already initialized in this process")
+    }
+}
+
+impl std::error::Error for AlreadyInitialized {}
+
+/// A use of the native classifier state, which is initialized for the first one and
+/// deinitialized when the last one is dropped
+struct NativeInstance;
+
+impl NativeInstance {
+    fn acquire(check_policy: bool) -> Result<Self, AlreadyInitialized> {
+        let mut instances = INSTANCES.lock().unwrap_or_else(PoisonError::into_inner);
+        if *instances > 0 && check_policy && instance_policy() == InstancePolicy::Exclusive {
+            return Err(AlreadyInitialized);
+        }
+        if *instances == 0 {
+            check_linked_model();
+            // Fix the policy before the first initialization
+            instance_policy();
+            let _native = lock_native_state();
+            unsafe {
+                ei_ffi_run_classifier_init();
+            }
+        }
+        *instances += 1;
+        Ok(Self)
+    }
+
+    /// Lock the native classifier state for the duration of a call
+    fn lock(&self) -> MutexGuard<'static, ()> {
+        lock_native_state()
+    }
+}
+
+pub(crate) fn lock_native_state() -> MutexGuard<'static, ()> {
+    NATIVE_STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Drop for NativeInstance {
+    fn drop(&mut self) {
+        let mut instances = INSTANCES.lock().unwrap_or_else(PoisonError::into_inner);
+        *instances -= 1;
+        if *instances == 0 {
+            let _native = lock_native_state();
+            unsafe {
+                ei_ffi_run_classifier_deinit();
+            }
+        }
+    }
+}
+
+/// Panic if the linked library was built from another model than the generated metadata
+///
+/// The bindings and the metadata are checked against each other at compile time; the native
+/// library is built separately and can be left over from a previous model.
+fn check_linked_model() {
+    let linked = unsafe { ei_ffi_model_hash() };
+    assert_eq!(
+        linked, EI_CLASSIFIER_MODEL_HASH,
+        "the linked Edge Impulse library was built from another model than the crate's metadata \
+         (model hash {:#018x}, expected {:#018x}), run `cargo clean -p edge-impulse-ffi-rs` and rebuild",
+        linked, EI_CLASSIFIER_MODEL_HASH
+    );
+}
+
+/// Handle to the Edge Impulse classifier linked into this process
+pub struct Classifier {
+    instance: NativeInstance,
+    alloc_retry: Option<AllocRetry>,
+    // Calls take the native state lock one at a time, `&self` methods included: opt out of `Sync`
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl Classifier {
+    /// Initialize the classifier, or join the classifier state of the classifiers already alive
+    ///
+    /// # Panics
+    ///
+    /// Panics if another classifier is alive and the policy is [`InstancePolicy::Exclusive`];
+    /// use [`try_new`](Self::try_new) to handle that case. Also panics, like
+    /// [`try_new`](Self::try_new), if the linked library was built from another model.
+    pub fn new() -> Self {
+        Self::try_new().expect("failed to create classifier")
+    }
+
+    /// Initialize the classifier, failing if the [`InstancePolicy`] doesn't allow another one
+    ///
+    /// # Panics
+    ///
+    /// Panics if the linked native library was built from another model than the crate's
+    /// bindings and metadata, i.e. a stale build that would misread the model's results.
+    pub fn try_new() -> Result<Self, AlreadyInitialized> {
+        Ok(Self::with_instance(NativeInstance::acquire(true)?))
+    }
+
+    /// Another handle to the same classifier state, e.g. for a component of the same model,
+    /// regardless of the [`InstancePolicy`]
+    pub(crate) fn share(&self) -> Self {
+        Self::with_instance(
+            NativeInstance::acquire(false).expect("sharing is allowed by every policy"),
+        )
+    }
+
+    fn with_instance(instance: NativeInstance) -> Self {
+        Self {
+            instance,
+            alloc_retry: None,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Run inference on a buffer of features
+    pub fn run(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        self.retry_alloc(|| inference::run_classifier(features, debug))
+    }
+
+    /// Run inference on a buffer sized for the linked model
+    ///
+    /// Unlike [`run`](Self::run), the feature count is checked at compile time.
+    pub fn run_fixed(
+        &mut self,
+        features: &ModelFeatures,
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        self.retry_alloc(|| inference::run_classifier(features.as_slice(), debug))
+    }
+
+    /// Run inference on a signal borrowing the caller's features, without copying them
+    pub fn run_signal(
+        &mut self,
+        signal: &mut Signal<'_>,
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        self.retry_alloc(|| inference::run_classifier_signal(signal, debug))
+    }
+
+    /// Run the learning blocks on precomputed DSP features, skipping the SDK's DSP
+    ///
+    /// `features` is the output of the impulse's DSP blocks, concatenated in block order
+    /// (`EI_CLASSIFIER_NN_INPUT_FRAME_SIZE` values), e.g. read from a feature cache or computed
+    /// by a hardware DSP. See [`crate::feature_provider`].
+    pub fn run_features(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        self.retry_alloc(|| inference::run_inference(features, debug))
+    }
+
+    /// Run the learning blocks on the next features of a [`FeatureProvider`]
+    pub fn run_provider<P: FeatureProvider + ?Sized>(
+        &mut self,
+        provider: &mut P,
+        debug: bool,
+    ) -> Result<InferenceOutput, ProviderError<P::Error>> {
+        feature_provider::run_provided(provider, |features| self.run_features(features, debug))
+    }
+
+    /// Run continuous inference on one slice of a stream
+    ///
+    /// The SDK keeps the rest of the model window in an internal buffer, so `slice` must hold
+    /// exactly one slice (`EI_CLASSIFIER_SLICE_SIZE` raw samples). With `enable_maf` the SDK
+    /// smooths the classification scores with its moving average filter.
+    pub fn run_continuous(
+        &mut self,
+        slice: &[f32],
+        debug: bool,
+        enable_maf: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        let _native = self.instance.lock();
+        inference::run_classifier_continuous(slice, debug, enable_maf)
+    }
+
+    /// Retry [`run`](Self::run), [`run_fixed`](Self::run_fixed), [`run_signal`](Self::run_signal)
+    /// and [`run_features`](Self::run_features) calls whose postprocessing runs out of memory
+    /// (`None`, the default, returns the error)
+    ///
+    /// Continuous inference is never retried, since the slice is already in the feature buffer.
+    pub fn set_alloc_retry(&mut self, alloc_retry: Option<AllocRetry>) {
+        self.alloc_retry = alloc_retry;
+    }
+
+    /// The strategy set with [`set_alloc_retry`](Self::set_alloc_retry)
+    pub fn alloc_retry(&self) -> Option<AllocRetry> {
+        self.alloc_retry
+    }
+
+    fn retry_alloc<F>(&mut self, mut run: F) -> Result<InferenceOutput, EdgeImpulseError>
+    where
+        F: FnMut() -> Result<InferenceOutput, EdgeImpulseError>,
+    {
+        let _native = self.instance.lock();
+        let mut result = run();
+        let Some(retry) = self.alloc_retry else {
+            return result;
+        };
+        if !is_alloc_failure(&result) {
+            return result;
+        }
+        // Without object detection blocks there is no smaller batch to retry with
+        let Ok(thresholds) = read_thresholds() else {
+            return result;
+        };
+        let blocks = thresholds.object_detection_thresholds();
+        if blocks.is_empty() {
+            return result;
+        }
+
+        for attempt in 1..=retry.retries {
+            let raised = blocks.iter().all(|block| {
+                let min_score = retry.min_score(block.min_score, attempt);
+                set_learn_block_threshold(block.id as u32, min_score).is_ok()
+            });
+            if !raised {
+                break;
+            }
+            result = run();
+            if !is_alloc_failure(&result) {
+                break;
+            }
+        }
+        for block in &blocks {
+            let _ = set_learn_block_threshold(block.id as u32, block.min_score);
+        }
+        result
+    }
+
+    /// Scale and zero point of the model's input and output tensors
+    pub fn quantization_info(&self) -> QuantizationInfo {
+        QuantizationInfo::model()
+    }
+
+    /// Reinitialize the classifier, clearing the continuous feature buffer and moving average filter
+    ///
+    /// The SDK's state is deinitialized and initialized again. Its state exists once per process,
+    /// so with [`InstancePolicy::Shared`] this resets every classifier and `EimModel` alive,
+    /// including their continuous buffers and moving average filters. Use an [`ImpulseHandle`] for
+    /// state that can be reset on its own.
+    pub fn reset(&mut self) {
+        let _native = self.instance.lock();
+        unsafe {
+            ei_ffi_run_classifier_deinit();
+            ei_ffi_run_classifier_init();
+        }
+    }
+
+    /// Set the threshold of a learning block at runtime
+    ///
+    /// For object detection blocks this is the minimum confidence score, for anomaly (GMM)
+    /// blocks the anomaly threshold. K-means anomaly blocks have no threshold and fail with
+    /// [`EdgeImpulseError::UnsupportedInferencingEngine`], like blocks of unknown types.
+    pub fn set_learn_block_threshold(
+        &mut self,
+        block_id: u32,
+        value: f32,
+    ) -> Result<(), EdgeImpulseError> {
+        let _native = self.instance.lock();
+        set_learn_block_threshold(block_id, value)
+    }
+
+    /// Set the IoU above which non-max suppression merges overlapping boxes of a learning block
+    ///
+    /// Fails with [`EdgeImpulseError::UnsupportedInferencingEngine`] if the SDK the model was
+    /// exported with has no runtime NMS settings.
+    pub fn set_nms_iou_threshold(
+        &mut self,
+        block_id: u32,
+        iou_threshold: f32,
+    ) -> Result<(), EdgeImpulseError> {
+        let _native = self.instance.lock();
+        check(unsafe { ei_ffi_set_nms_iou_threshold(block_id, iou_threshold) })
+    }
+
+    /// Query the NMS IoU threshold currently active for a learning block
+    pub fn nms_iou_threshold(&self, block_id: u32) -> Result<f32, EdgeImpulseError> {
+        let _native = self.instance.lock();
+        let mut iou_threshold = 0.0f32;
+        check(unsafe { ei_ffi_get_nms_iou_threshold(block_id, &mut iou_threshold) })?;
+        Ok(iou_threshold)
+    }
+
+    /// Only return the `max_detections` highest scoring boxes per inference (`None` for all)
+    ///
+    /// The limit is process-wide: it applies to [`ImpulseHandle`]s as well.
+    pub fn set_max_detections(&mut self, max_detections: Option<u32>) {
+        unsafe { ei_ffi_set_max_detections(max_detections.unwrap_or(0)) }
+    }
+
+    /// The limit set with [`set_max_detections`](Self::set_max_detections)
+    pub fn max_detections(&self) -> Option<u32> {
+        match unsafe { ei_ffi_get_max_detections() } {
+            0 => None,
+            max_detections => Some(max_detections),
+        }
+    }
+
+    /// Query the thresholds currently active in the SDK
+    ///
+    /// Unlike [`crate::thresholds::get_model_thresholds`], which returns the values the model was
+    /// compiled with, this reads them back from the SDK and so reflects runtime changes.
+    pub fn thresholds(&self) -> Result<Thresholds, EdgeImpulseError> {
+        let _native = self.instance.lock();
+        read_thresholds()
+    }
+}
+
+// The threshold helpers below expect the native state lock to be held
+
+fn set_learn_block_threshold(block_id: u32, value: f32) -> Result<(), EdgeImpulseError> {
+    check(unsafe { ei_ffi_set_learn_block_threshold(block_id, value) })
+}
+
+fn read_thresholds() -> Result<Thresholds, EdgeImpulseError> {
+    let thresholds = MODEL_THRESHOLDS
+        .iter()
+        .map(|threshold| {
+            let mut min_score = 0.0f32;
+            check(unsafe {
+                ei_ffi_get_learn_block_threshold(threshold.id as u32, &mut min_score)
+            })?;
+            Ok(Threshold {
+                min_score,
+                ..threshold.clone()
+            })
+        })
+        .collect::<Result<Vec<_>, EdgeImpulseError>>()?;
+    Ok(Thresholds { thresholds })
+}
+
+/// How a [`Classifier`] retries calls whose postprocessing ran out of memory
+///
+/// Postprocessing allocates per candidate box, so each retry raises the minimum score of the
+/// model's object detection blocks by `threshold_step`, and fewer boxes are kept. The thresholds
+/// are restored afterwards. Models without object detection blocks aren't retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllocRetry {
+    /// Retries after the first failure
+    pub retries: u32,
+    /// Added to the minimum scores on each retry (capped at 1.0)
+    pub threshold_step: f32,
+}
+
+impl AllocRetry {
+    fn min_score(&self, min_score: f32, attempt: u32) -> f32 {
+        (min_score + self.threshold_step * attempt as f32).min(1.0)
+    }
+}
+
+impl Default for AllocRetry {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            threshold_step: 0.1,
+        }
+    }
+}
+
+/// Failures of the SDK's heap allocations, as opposed to the fixed size tensor arena
+fn is_alloc_failure(result: &Result<InferenceOutput, EdgeImpulseError>) -> bool {
+    matches!(
+        result,
+        Err(EdgeImpulseError::AllocFailed | EdgeImpulseError::OutOfMemory)
+    )
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Independent classifier state for the linked impulse
+///
+/// Every handle has its own DSP and postprocessing state, while the model, including the learning
+/// blocks' interpreter and tensor arena, is shared. Handles can be moved to other threads, so two
+/// threads can each run their own pipeline without going through the global [`Classifier`]; their
+/// inferences take turns on the process-wide lock.
+pub struct ImpulseHandle {
+    handle: NonNull<ei_impulse_handle_t>,
+}
+
+// The handle exclusively owns its DSP and postprocessing state, running it requires `&mut self`,
+// and the shared learning block state is only touched under the process-wide lock
+unsafe impl Send for ImpulseHandle {}
+
+impl ImpulseHandle {
+    /// Allocate and initialize a new handle
+    ///
+    /// # Panics
+    ///
+    /// Panics if the linked library was built from another model, see [`Classifier::try_new`].
+    pub fn new() -> Result<Self, EdgeImpulseError> {
+        check_linked_model();
+        let handle = NonNull::new(unsafe { ei_ffi_impulse_handle_new() })
+            .ok_or(EdgeImpulseError::OutOfMemory)?;
+        let handle = Self { handle };
+        let _native = lock_native_state();
+        check(unsafe { ei_ffi_init_impulse(handle.handle.as_ptr()) })?;
+        Ok(handle)
+    }
+
+    /// Run inference on a buffer of features using this handle's state
+    pub fn run(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        self.run_signal(&mut Signal::from_slice(features)?, debug)
+    }
+
+    /// Run inference on a signal borrowing the caller's features, using this handle's state
+    pub fn run_signal(
+        &mut self,
+        signal: &mut Signal<'_>,
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        let handle = self.handle.as_ptr();
+        let _native = lock_native_state();
+        inference::classify_with(signal, |signal, result| unsafe {
+            ei_ffi_run_classifier_with_handle(handle, signal, result, debug as i32)
+        })
+    }
+
+    /// Run the learning blocks on precomputed DSP features using this handle's state
+    ///
+    /// See [`Classifier::run_features`].
+    pub fn run_features(
+        &mut self,
+        features: &[f32],
+        debug: bool,
+    ) -> Result<InferenceOutput, EdgeImpulseError> {
+        let handle = self.handle.as_ptr();
+        let _native = lock_native_state();
+        inference::infer_with(features.len(), |result| unsafe {
+            ei_ffi_run_inference_features(
+                handle,
+                features.as_ptr(),
+                features.len(),
+                result,
+                debug as i32,
+            )
+        })
+    }
+
+    /// Run the learning blocks on the next features of a [`FeatureProvider`] using this
+    /// handle's state
+    pub fn run_provider<P: FeatureProvider + ?Sized>(
+        &mut self,
+        provider: &mut P,
+        debug: bool,
+    ) -> Result<InferenceOutput, ProviderError<P::Error>> {
+        feature_provider::run_provided(provider, |features| self.run_features(features, debug))
+    }
+}
+
+impl Drop for ImpulseHandle {
+    fn drop(&mut self) {
+        let _native = lock_native_state();
+        unsafe {
+            ei_ffi_impulse_handle_free(self.handle.as_ptr());
+        }
+    }
+}
+
+// ImpulseHandles need the linked model, the dummy model can't allocate them
+#[cfg(all(test, not(feature = "dummy")))]
+mod tests {
+    use super::*;
+    use crate::inference::{BoundingBox, Classification};
+    use crate::model_metadata::EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE;
+
+    /// The parts of a result that depend on the input only
+    fn scores(output: &InferenceOutput) -> (Vec<Classification>, Vec<BoundingBox>, f32) {
+        (
+            output.classification.clone(),
+            output.bounding_boxes.clone(),
+            output.anomaly,
+        )
+    }
+
+    #[test]
+    fn interleaved_handles_keep_their_own_state() {
+        let inputs = [
+            vec![0.0; EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE],
+            (0..EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE)
+                .map(|i| (i % 256) as f32)
+                .collect::<Vec<f32>>(),
+        ];
+        let expected: Vec<_> = inputs
+            .iter()
+            .map(|features| {
+                let mut fresh = ImpulseHandle::new().unwrap();
+                scores(&fresh.run(features, false).unwrap())
+            })
+            .collect();
+
+        // Alternating inferences on shared state would mix up the two inputs' results
+        let mut handles = [ImpulseHandle::new().unwrap(), ImpulseHandle::new().unwrap()];
+        for _ in 0..20 {
+            for ((handle, features), expected) in handles.iter_mut().zip(&inputs).zip(&expected) {
+                assert_eq!(&scores(&handle.run(features, false).unwrap()), expected);
+            }
+        }
+    }
+}
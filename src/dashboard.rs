@@ -0,0 +1,148 @@
+//! Live result dashboard over WebSocket
+//!
+//! [`Dashboard`] serves a small bundled HTML viewer and pushes every published
+//! [`InferenceResponse`] to the connected browsers as JSON, so detections from a headless
+//! device can be watched from a laptop during bring-up:
+//!
+//! ```no_run
+//! # use edge_impulse_ffi_rs::{dashboard::Dashboard, EimModel};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut model = EimModel::new()?;
+//! let dashboard = Dashboard::bind("0.0.0.0:4912", model.parameters()?)?;
+//! # let features = vec![0.0; 10];
+//! let response = model.infer(features, None)?;
+//! dashboard.publish(&response)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Browsing to `http://<device>:4912/` opens the viewer. Both the page and the WebSocket are
+//! served on the same port. The connection is unauthenticated and unencrypted; only bind it
+//! to networks you trust.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::{Message, WebSocket};
+
+use crate::runner_api::{InferenceResponse, ModelParameters};
+
+const VIEWER_HTML: &str = include_str!("dashboard.html");
+
+/// Largest HTTP request header accepted from a browser
+const MAX_REQUEST_LEN: usize = 8192;
+
+/// How long a slow client may block [`Dashboard::publish`] before it is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// WebSocket server pushing inference results to browsers
+///
+/// The listener runs on a background thread for as long as the process lives.
+pub struct Dashboard {
+    clients: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+    port: u16,
+}
+
+impl Dashboard {
+    /// Listen on `addr` and start accepting browsers
+    ///
+    /// `parameters` are sent to every browser when it connects, so the viewer knows the input
+    /// size and labels of the model.
+    pub fn bind<A: ToSocketAddrs>(addr: A, parameters: &ModelParameters) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let port = listener.local_addr()?.port();
+        let hello = serde_json::json!({
+            "type": "hello",
+            "parameters": serde_json::to_value(parameters)?,
+        })
+        .to_string();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = Arc::clone(&clients);
+        thread::Builder::new()
+            .name("ei-dashboard".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // A browser that fails the handshake or asks for something else is dropped
+                    if let Ok(Some(mut socket)) = handle_connection(stream) {
+                        if socket.send(Message::Text(hello.clone())).is_ok() {
+                            accepted.lock().unwrap().push(socket);
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { clients, port })
+    }
+
+    /// Port the dashboard listens on, e.g. when bound to port 0
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Number of connected browsers
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Send a result to every connected browser
+    ///
+    /// Browsers that disconnected or don't keep up are dropped. Returns the number of browsers
+    /// the result was sent to.
+    pub fn publish(&self, response: &InferenceResponse) -> io::Result<usize> {
+        let message = serde_json::json!({
+            "type": "result",
+            "response": serde_json::to_value(response)?,
+        })
+        .to_string();
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain_mut(|socket| socket.send(Message::Text(message.clone())).is_ok());
+        Ok(clients.len())
+    }
+}
+
+/// Serve the viewer page, or upgrade the connection to a WebSocket
+fn handle_connection(stream: TcpStream) -> io::Result<Option<WebSocket<TcpStream>>> {
+    stream.set_read_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    // Peek so the WebSocket handshake can still read the request itself
+    let mut buf = vec![0; MAX_REQUEST_LEN];
+    let mut header = String::new();
+    for _ in 0..100 {
+        let len = stream.peek(&mut buf)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        header = String::from_utf8_lossy(&buf[..len]).to_ascii_lowercase();
+        if header.contains("\r\n\r\n") || len == buf.len() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    if header.contains("upgrade: websocket") {
+        let socket = tungstenite::accept(stream)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        return Ok(Some(socket));
+    }
+
+    serve_viewer(stream)?;
+    Ok(None)
+}
+
+fn serve_viewer(mut stream: TcpStream) -> io::Result<()> {
+    // Consume the request we only peeked at
+    let mut request = [0; MAX_REQUEST_LEN];
+    let _ = stream.read(&mut request)?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        VIEWER_HTML.len(),
+        VIEWER_HTML
+    )?;
+    stream.flush()
+}
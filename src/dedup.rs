@@ -0,0 +1,179 @@
+//! Deduplication of detections from several inference sources
+//!
+//! Tiled inference, several cameras watching the same scene, or several models run on one frame
+//! all report the same object more than once. A [`Dedup`] merges those boxes consistently:
+//! boxes of the same label (or of any label, when class-agnostic) overlapping by more than an
+//! IoU threshold are one object, which is either the best box of the group (non-max
+//! suppression) or the confidence-weighted average of the group (box fusion). Boxes of every
+//! source must be in the same coordinates, e.g. mapped onto the full frame.
+//!
+//! ```ignore
+//! use edge_impulse_ffi_rs::dedup::{Dedup, MergeMethod};
+//!
+//! let dedup = Dedup::new(0.5).method(MergeMethod::Fuse);
+//! let objects = dedup.merge_results([&left_camera, &right_camera]);
+//! ```
+
+use crate::runner_api::{BoundingBox, InferenceResult};
+use crate::testing::iou;
+
+/// How a group of duplicate boxes becomes one box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MergeMethod {
+    /// Keep the box with the highest score (non-max suppression)
+    #[default]
+    Suppress,
+    /// Average the coordinates of the group weighted by score, with the highest score and the
+    /// label of the best box
+    Fuse,
+}
+
+/// Rules deciding which boxes are duplicates and how they're merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dedup {
+    /// IoU above which two boxes are the same object
+    pub iou_threshold: f32,
+    pub method: MergeMethod,
+    /// Merge overlapping boxes whatever their labels, for sources that disagree on the label
+    pub class_agnostic: bool,
+}
+
+impl Default for Dedup {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Dedup {
+    /// Per-label non-max suppression above `iou_threshold`
+    pub fn new(iou_threshold: f32) -> Self {
+        Self {
+            iou_threshold,
+            method: MergeMethod::Suppress,
+            class_agnostic: false,
+        }
+    }
+
+    pub fn method(mut self, method: MergeMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn class_agnostic(mut self, class_agnostic: bool) -> Self {
+        self.class_agnostic = class_agnostic;
+        self
+    }
+
+    /// Merge duplicate boxes, sorted by descending score
+    ///
+    /// Boxes are visited from the best score down; each one joins the group of the first kept
+    /// box it overlaps, or starts a group of its own.
+    pub fn merge(&self, boxes: impl IntoIterator<Item = BoundingBox>) -> Vec<BoundingBox> {
+        profile_scope!("merge_detections");
+        let mut boxes: Vec<BoundingBox> = boxes.into_iter().collect();
+        boxes.sort_by(|a, b| b.value.total_cmp(&a.value));
+        let mut groups: Vec<Vec<BoundingBox>> = Vec::new();
+        for bb in boxes {
+            let group = groups.iter_mut().find(|group| {
+                let best = &group[0];
+                (self.class_agnostic || best.label == bb.label)
+                    && iou(best, &bb) > self.iou_threshold
+            });
+            match group {
+                Some(group) if self.method == MergeMethod::Fuse => group.push(bb),
+                Some(_) => {}
+                None => groups.push(vec![bb]),
+            }
+        }
+        groups.into_iter().map(fuse).collect()
+    }
+
+    /// Merge the detections of several results, e.g. of cameras with overlapping views
+    ///
+    /// Results without bounding boxes contribute nothing.
+    pub fn merge_results<'a>(
+        &self,
+        results: impl IntoIterator<Item = &'a InferenceResult>,
+    ) -> Vec<BoundingBox> {
+        self.merge(results.into_iter().flat_map(|result| match result {
+            InferenceResult::ObjectDetection { bounding_boxes, .. }
+            | InferenceResult::ObjectTracking { bounding_boxes, .. } => bounding_boxes.clone(),
+            _ => Vec::new(),
+        }))
+    }
+}
+
+/// Confidence-weighted average of a group, best box first
+fn fuse(mut group: Vec<BoundingBox>) -> BoundingBox {
+    if group.len() == 1 {
+        return group.remove(0);
+    }
+    let total: f32 = group.iter().map(|bb| bb.value.max(f32::EPSILON)).sum();
+    let average = |edge: fn(&BoundingBox) -> i32| {
+        let sum: f32 = group
+            .iter()
+            .map(|bb| edge(bb) as f32 * bb.value.max(f32::EPSILON))
+            .sum();
+        (sum / total).round() as i32
+    };
+    let (left, top) = (average(|bb| bb.x), average(|bb| bb.y));
+    let (right, bottom) = (
+        average(|bb| bb.x + bb.width),
+        average(|bb| bb.y + bb.height),
+    );
+    let best = group.swap_remove(0);
+    BoundingBox {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+        ..best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bbox;
+
+    #[test]
+    fn fuses_duplicates_weighted_by_score() {
+        let boxes = vec![
+            bbox("car", 0.25, 4, 0, 20, 20),
+            bbox("car", 0.75, 0, 0, 20, 20),
+            bbox("car", 0.5, 100, 0, 20, 20),
+        ];
+        let suppressed = Dedup::new(0.5).merge(boxes.clone());
+        assert_eq!(
+            suppressed,
+            vec![
+                bbox("car", 0.75, 0, 0, 20, 20),
+                bbox("car", 0.5, 100, 0, 20, 20)
+            ]
+        );
+        let fused = Dedup::new(0.5).method(MergeMethod::Fuse).merge(boxes);
+        assert_eq!(
+            fused,
+            vec![
+                bbox("car", 0.75, 1, 0, 20, 20),
+                bbox("car", 0.5, 100, 0, 20, 20)
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_across_labels_when_class_agnostic() {
+        let boxes = vec![
+            bbox("car", 0.9, 0, 0, 20, 20),
+            bbox("truck", 0.6, 2, 0, 20, 20),
+        ];
+        assert_eq!(Dedup::new(0.5).merge(boxes.clone()).len(), 2);
+        assert_eq!(
+            Dedup::new(0.5).class_agnostic(true).merge(boxes),
+            vec![bbox("car", 0.9, 0, 0, 20, 20)]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Change events between consecutive results
+//!
+//! Event-driven applications (turn on the light when a person shows up, notify when the
+//! kettle stops boiling) care about what changed since the previous frame, not about every
+//! result. [`ResultDiff::compare`] turns two consecutive results into [`ChangeEvent`]s: labels
+//! whose score crossed a threshold, and detections that appeared or disappeared. Detections are
+//! matched by track ID when both results come from object tracking, and by label and IoU
+//! otherwise.
+
+use std::collections::HashMap;
+
+use crate::runner_api::{BoundingBox, InferenceResult, TrackedObject};
+use crate::testing::iou;
+
+/// Thresholds deciding what counts as a change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffThresholds {
+    /// Score a label has to reach to count as present
+    pub score: f32,
+    /// Minimum IoU for two boxes with the same label to be the same detection
+    pub iou: f32,
+}
+
+impl Default for DiffThresholds {
+    fn default() -> Self {
+        Self {
+            score: 0.5,
+            iou: 0.3,
+        }
+    }
+}
+
+/// A change between two consecutive results
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChangeEvent {
+    /// A label's score reached the threshold
+    LabelRose { label: String, score: f32 },
+    /// A label's score dropped below the threshold, or the label is gone
+    LabelFell { label: String, score: f32 },
+    /// A detection without a match in the previous result
+    DetectionAppeared {
+        bounding_box: BoundingBox,
+        /// Track ID, for object tracking results
+        track_id: Option<u32>,
+    },
+    /// A detection of the previous result without a match in the current one, as it was last
+    /// seen
+    DetectionLost {
+        bounding_box: BoundingBox,
+        /// Track ID, for object tracking results
+        track_id: Option<u32>,
+    },
+}
+
+/// Changes from one result to the next
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResultDiff {
+    /// Label events sorted by label, then lost detections, then new ones
+    pub events: Vec<ChangeEvent>,
+}
+
+impl ResultDiff {
+    /// Changes from `prev` to `current` with the default thresholds
+    pub fn compare(prev: &InferenceResult, current: &InferenceResult) -> Self {
+        Self::compare_with(prev, current, DiffThresholds::default())
+    }
+
+    /// Changes from `prev` to `current`
+    ///
+    /// Results of different kinds are compared by what they have in common: a label or
+    /// detection only one of them has appears or disappears.
+    pub fn compare_with(
+        prev: &InferenceResult,
+        current: &InferenceResult,
+        thresholds: DiffThresholds,
+    ) -> Self {
+        let mut events = label_events(scores(prev), scores(current), thresholds.score);
+        match (tracked(prev), tracked(current)) {
+            (Some(prev), Some(current)) => track_events(prev, current, &mut events),
+            _ => box_events(boxes(prev), boxes(current), thresholds.iou, &mut events),
+        }
+        Self { events }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+fn scores(result: &InferenceResult) -> Option<&HashMap<String, f32>> {
+    match result {
+        InferenceResult::Classification { classification, .. }
+        | InferenceResult::ObjectDetection { classification, .. }
+        | InferenceResult::ObjectTracking { classification, .. } => Some(classification),
+        _ => None,
+    }
+}
+
+fn boxes(result: &InferenceResult) -> &[BoundingBox] {
+    match result {
+        InferenceResult::ObjectDetection { bounding_boxes, .. }
+        | InferenceResult::ObjectTracking { bounding_boxes, .. } => bounding_boxes,
+        _ => &[],
+    }
+}
+
+fn tracked(result: &InferenceResult) -> Option<&[TrackedObject]> {
+    match result {
+        InferenceResult::ObjectTracking {
+            object_tracking, ..
+        } => Some(object_tracking),
+        _ => None,
+    }
+}
+
+fn label_events(
+    prev: Option<&HashMap<String, f32>>,
+    current: Option<&HashMap<String, f32>>,
+    threshold: f32,
+) -> Vec<ChangeEvent> {
+    let score = |scores: Option<&HashMap<String, f32>>, label: &str| {
+        scores.and_then(|scores| scores.get(label)).copied()
+    };
+    let mut labels: Vec<&String> = prev
+        .into_iter()
+        .chain(current)
+        .flat_map(|scores| scores.keys())
+        .collect();
+    labels.sort();
+    labels.dedup();
+
+    let mut events = Vec::new();
+    for label in labels {
+        let was_present = score(prev, label).is_some_and(|score| score >= threshold);
+        let current_score = score(current, label);
+        let is_present = current_score.is_some_and(|score| score >= threshold);
+        let label = label.clone();
+        let score = current_score.unwrap_or(0.0);
+        match (was_present, is_present) {
+            (false, true) => events.push(ChangeEvent::LabelRose { label, score }),
+            (true, false) => events.push(ChangeEvent::LabelFell { label, score }),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Match every previous box to the unused current box with the same label and the highest IoU
+fn box_events(
+    prev: &[BoundingBox],
+    current: &[BoundingBox],
+    min_iou: f32,
+    events: &mut Vec<ChangeEvent>,
+) {
+    let mut used = vec![false; current.len()];
+    for bb in prev {
+        let best = current
+            .iter()
+            .enumerate()
+            .filter(|(i, candidate)| !used[*i] && candidate.label == bb.label)
+            .map(|(i, candidate)| (i, iou(bb, candidate)))
+            .filter(|&(_, overlap)| overlap >= min_iou)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((i, _)) => used[i] = true,
+            None => events.push(ChangeEvent::DetectionLost {
+                bounding_box: bb.clone(),
+                track_id: None,
+            }),
+        }
+    }
+    for (bb, _) in current.iter().zip(&used).filter(|(_, &used)| !used) {
+        events.push(ChangeEvent::DetectionAppeared {
+            bounding_box: bb.clone(),
+            track_id: None,
+        });
+    }
+}
+
+fn track_events(prev: &[TrackedObject], current: &[TrackedObject], events: &mut Vec<ChangeEvent>) {
+    let as_box = |object: &TrackedObject| BoundingBox {
+        label: object.label.clone(),
+        value: object.value,
+        x: object.x,
+        y: object.y,
+        width: object.width,
+        height: object.height,
+    };
+    let missing_from =
+        |objects: &[TrackedObject], id: u32| !objects.iter().any(|object| object.object_id == id);
+    for object in prev
+        .iter()
+        .filter(|object| missing_from(current, object.object_id))
+    {
+        events.push(ChangeEvent::DetectionLost {
+            bounding_box: as_box(object),
+            track_id: Some(object.object_id),
+        });
+    }
+    for object in current
+        .iter()
+        .filter(|object| missing_from(prev, object.object_id))
+    {
+        events.push(ChangeEvent::DetectionAppeared {
+            bounding_box: as_box(object),
+            track_id: Some(object.object_id),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bbox;
+
+    fn detection(boxes: Vec<BoundingBox>) -> InferenceResult {
+        InferenceResult::ObjectDetection {
+            bounding_boxes: boxes,
+            classification: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn reports_threshold_crossings() {
+        let scores = |person: f32, kettle: f32| InferenceResult::Classification {
+            classification: HashMap::from([
+                ("person".to_string(), person),
+                ("kettle".to_string(), kettle),
+            ]),
+            anomaly: None,
+        };
+        let diff = ResultDiff::compare(&scores(0.2, 0.8), &scores(0.7, 0.3));
+        assert_eq!(
+            diff.events,
+            vec![
+                ChangeEvent::LabelFell {
+                    label: "kettle".to_string(),
+                    score: 0.3
+                },
+                ChangeEvent::LabelRose {
+                    label: "person".to_string(),
+                    score: 0.7
+                },
+            ]
+        );
+        assert!(ResultDiff::compare(&scores(0.6, 0.1), &scores(0.9, 0.4)).is_empty());
+    }
+
+    #[test]
+    fn matches_detections_by_iou() {
+        let prev = detection(vec![
+            bbox("cat", 0.9, 0, 0, 10, 10),
+            bbox("dog", 0.9, 50, 50, 10, 10),
+        ]);
+        let current = detection(vec![
+            bbox("cat", 0.9, 2, 1, 10, 10),
+            bbox("cat", 0.9, 80, 80, 10, 10),
+        ]);
+        let diff = ResultDiff::compare(&prev, &current);
+        assert_eq!(
+            diff.events,
+            vec![
+                ChangeEvent::DetectionLost {
+                    bounding_box: bbox("dog", 0.9, 50, 50, 10, 10),
+                    track_id: None
+                },
+                ChangeEvent::DetectionAppeared {
+                    bounding_box: bbox("cat", 0.9, 80, 80, 10, 10),
+                    track_id: None
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Errors returned by the Edge Impulse SDK
+//!
+//! [`EdgeImpulseError`] is a safe counterpart of the raw `EI_IMPULSE_ERROR` codes. Codes are
+//! converted by their numeric value, so the conversion keeps working with SDK versions that add
+//! or remove error codes; unknown codes map to [`EdgeImpulseError::Unknown`], and the build
+//! warns about them. The enum is `#[non_exhaustive]`, so matches in user code keep compiling
+//! when variants for new codes are added.
+//!
+//! DSP failures carry the `EIDSP_*` code the DSP block returned ([`DspErrorCode`]), which the
+//! SDK itself only prints.
+
+use core::fmt;
+
+use crate::bindings::{ei_ffi_last_dsp_error, EI_IMPULSE_ERROR};
+
+/// An error reported by the Edge Impulse SDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EdgeImpulseError {
+    /// The input shape doesn't match the model input (`EI_IMPULSE_ERROR_SHAPES_DONT_MATCH`)
+    ShapesDontMatch,
+    /// Inference was canceled (`EI_IMPULSE_CANCELED`)
+    Canceled,
+    /// TensorFlow Lite failed to run the model (`EI_IMPULSE_TFLITE_ERROR`)
+    TfliteError,
+    /// A DSP block failed to process the signal (`EI_IMPULSE_DSP_ERROR`), with the DSP error
+    /// code if it is known
+    DspError(Option<DspErrorCode>),
+    /// The TensorFlow Lite tensor arena could not be allocated (`EI_IMPULSE_TFLITE_ARENA_ALLOC_FAILED`)
+    TfliteArenaAllocFailed,
+    /// STM32 Cube.AI failed to run the model (`EI_IMPULSE_CUBEAI_ERROR`)
+    CubeAiError,
+    /// A memory allocation failed (`EI_IMPULSE_ALLOC_FAILED`)
+    AllocFailed,
+    /// The operation is only supported for image models (`EI_IMPULSE_ONLY_SUPPORTED_FOR_IMAGES`)
+    OnlySupportedForImages,
+    /// The model was built for an inferencing engine this build doesn't support (`EI_IMPULSE_UNSUPPORTED_INFERENCING_ENGINE`)
+    UnsupportedInferencingEngine,
+    /// The SDK ran out of memory (`EI_IMPULSE_OUT_OF_MEMORY`)
+    OutOfMemory,
+    /// The model's input tensor was null (`EI_IMPULSE_INPUT_TENSOR_WAS_NULL`)
+    InputTensorWasNull,
+    /// The model's output tensor was null (`EI_IMPULSE_OUTPUT_TENSOR_WAS_NULL`)
+    OutputTensorWasNull,
+    /// The object detection score tensor was null (`EI_IMPULSE_SCORE_TENSOR_WAS_NULL`)
+    ScoreTensorWasNull,
+    /// The object detection label tensor was null (`EI_IMPULSE_LABEL_TENSOR_WAS_NULL`)
+    LabelTensorWasNull,
+    /// TensorRT failed to initialize (`EI_IMPULSE_TENSORRT_INIT_FAILED`)
+    TensorRtInitFailed,
+    /// DRP-AI failed to initialize (`EI_IMPULSE_DRPAI_INIT_FAILED`)
+    DrpaiInitFailed,
+    /// DRP-AI failed while running the model (`EI_IMPULSE_DRPAI_RUNTIME_FAILED`)
+    DrpaiRuntimeFailed,
+    /// The model was exported with a deprecated SDK version (`EI_IMPULSE_DEPRECATED_MODEL`)
+    DeprecatedModel,
+    /// The model's last layer is not supported (`EI_IMPULSE_LAST_LAYER_NOT_AVAILABLE`)
+    LastLayerNotAvailable,
+    /// A learning or postprocessing block failed (`EI_IMPULSE_INFERENCE_ERROR`)
+    InferenceError,
+    /// BrainChip Akida failed to run the model (`EI_IMPULSE_AKIDA_ERROR`)
+    AkidaError,
+    /// The input has the wrong number of features (`EI_IMPULSE_INVALID_SIZE`)
+    InvalidSize,
+    /// ONNX Runtime failed to run the model (`EI_IMPULSE_ONNX_ERROR`)
+    OnnxError,
+    /// MemryX failed to run the model (`EI_IMPULSE_MEMRYX_ERROR`)
+    MemryxError,
+    /// An error code this crate doesn't know about
+    Unknown(i32),
+}
+
+impl EdgeImpulseError {
+    /// Convert a raw SDK error code, returning `None` for `EI_IMPULSE_OK` (0)
+    pub fn from_code(code: i32) -> Option<Self> {
+        let error = match code {
+            0 => return None,
+            -1 => Self::ShapesDontMatch,
+            -2 => Self::Canceled,
+            -3 => Self::TfliteError,
+            -5 => Self::DspError(None),
+            -6 => Self::TfliteArenaAllocFailed,
+            -7 => Self::CubeAiError,
+            -8 => Self::AllocFailed,
+            -9 => Self::OnlySupportedForImages,
+            -10 => Self::UnsupportedInferencingEngine,
+            -11 => Self::OutOfMemory,
+            -13 => Self::InputTensorWasNull,
+            -14 => Self::OutputTensorWasNull,
+            -15 => Self::ScoreTensorWasNull,
+            -16 => Self::LabelTensorWasNull,
+            -17 => Self::TensorRtInitFailed,
+            -18 => Self::DrpaiInitFailed,
+            -19 => Self::DrpaiRuntimeFailed,
+            -20 => Self::DeprecatedModel,
+            -21 => Self::LastLayerNotAvailable,
+            -22 => Self::InferenceError,
+            -23 => Self::AkidaError,
+            -24 => Self::InvalidSize,
+            -25 => Self::OnnxError,
+            -26 => Self::MemryxError,
+            other => Self::Unknown(other),
+        };
+        Some(error)
+    }
+
+    /// Raw SDK error code
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::ShapesDontMatch => -1,
+            Self::Canceled => -2,
+            Self::TfliteError => -3,
+            Self::DspError(_) => -5,
+            Self::TfliteArenaAllocFailed => -6,
+            Self::CubeAiError => -7,
+            Self::AllocFailed => -8,
+            Self::OnlySupportedForImages => -9,
+            Self::UnsupportedInferencingEngine => -10,
+            Self::OutOfMemory => -11,
+            Self::InputTensorWasNull => -13,
+            Self::OutputTensorWasNull => -14,
+            Self::ScoreTensorWasNull => -15,
+            Self::LabelTensorWasNull => -16,
+            Self::TensorRtInitFailed => -17,
+            Self::DrpaiInitFailed => -18,
+            Self::DrpaiRuntimeFailed => -19,
+            Self::DeprecatedModel => -20,
+            Self::LastLayerNotAvailable => -21,
+            Self::InferenceError => -22,
+            Self::AkidaError => -23,
+            Self::InvalidSize => -24,
+            Self::OnnxError => -25,
+            Self::MemryxError => -26,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    /// `EIDSP_*` code of a DSP failure, if known
+    pub fn dsp_code(&self) -> Option<DspErrorCode> {
+        match self {
+            Self::DspError(code) => *code,
+            _ => None,
+        }
+    }
+
+    /// Pipeline stage the error occurred in
+    pub fn stage(&self) -> &'static str {
+        match self {
+            Self::ShapesDontMatch | Self::InvalidSize | Self::OnlySupportedForImages => "input",
+            Self::DspError(_) => "dsp",
+            Self::TfliteArenaAllocFailed | Self::AllocFailed | Self::OutOfMemory => "allocation",
+            Self::UnsupportedInferencingEngine | Self::DeprecatedModel => "model",
+            Self::TensorRtInitFailed | Self::DrpaiInitFailed => "initialization",
+            Self::ScoreTensorWasNull | Self::LabelTensorWasNull | Self::LastLayerNotAvailable => {
+                "postprocessing"
+            }
+            Self::Canceled => "cancellation",
+            Self::Unknown(_) => "unknown",
+            Self::TfliteError
+            | Self::CubeAiError
+            | Self::InputTensorWasNull
+            | Self::OutputTensorWasNull
+            | Self::DrpaiRuntimeFailed
+            | Self::InferenceError
+            | Self::AkidaError
+            | Self::OnnxError
+            | Self::MemryxError => "inference",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::ShapesDontMatch => "input shape does not match the model input",
+            Self::Canceled => "inference was canceled",
+            Self::TfliteError => "TensorFlow Lite failed to run the model",
+            Self::DspError(_) => "DSP block failed to process the signal",
+            Self::TfliteArenaAllocFailed => "failed to allocate the TensorFlow Lite tensor arena",
+            Self::CubeAiError => "Cube.AI failed to run the model",
+            Self::AllocFailed => "memory allocation failed",
+            Self::OnlySupportedForImages => "operation is only supported for image models",
+            Self::UnsupportedInferencingEngine => {
+                "model requires an inferencing engine this build does not support"
+            }
+            Self::OutOfMemory => "out of memory",
+            Self::InputTensorWasNull => "model input tensor was null",
+            Self::OutputTensorWasNull => "model output tensor was null",
+            Self::ScoreTensorWasNull => "object detection score tensor was null",
+            Self::LabelTensorWasNull => "object detection label tensor was null",
+            Self::TensorRtInitFailed => "TensorRT failed to initialize",
+            Self::DrpaiInitFailed => "DRP-AI failed to initialize",
+            Self::DrpaiRuntimeFailed => "DRP-AI failed to run the model",
+            Self::DeprecatedModel => "model was exported with a deprecated SDK version",
+            Self::LastLayerNotAvailable => "model's last layer type is not supported",
+            Self::InferenceError => "a learning or postprocessing block failed",
+            Self::AkidaError => "Akida failed to run the model",
+            Self::InvalidSize => "input has the wrong number of features",
+            Self::OnnxError => "ONNX Runtime failed to run the model",
+            Self::MemryxError => "MemryX failed to run the model",
+            Self::Unknown(_) => "unknown SDK error",
+        }
+    }
+}
+
+impl fmt::Display for EdgeImpulseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} error: {} (code {})",
+            self.stage(),
+            self.description(),
+            self.code()
+        )?;
+        if let Some(dsp) = self.dsp_code() {
+            write!(f, ": {}", dsp)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for EdgeImpulseError {}
+
+impl From<EI_IMPULSE_ERROR> for EdgeImpulseError {
+    /// Convert a raw SDK error; `EI_IMPULSE_OK` is not an error and maps to `Unknown(0)`
+    fn from(code: EI_IMPULSE_ERROR) -> Self {
+        Self::from_code(code.0).unwrap_or(Self::Unknown(code.0))
+    }
+}
+
+/// Turn a raw SDK return code into a `Result`
+pub(crate) fn check(code: EI_IMPULSE_ERROR) -> Result<(), EdgeImpulseError> {
+    match EdgeImpulseError::from_code(code.0) {
+        None => Ok(()),
+        Some(error) => Err(error),
+    }
+}
+
+/// Like [`check`], for the return code of a classifier call; DSP errors get the DSP error code
+/// the SDK recorded for the call
+pub(crate) fn check_classifier(code: EI_IMPULSE_ERROR) -> Result<(), EdgeImpulseError> {
+    check(code).map_err(|error| match error {
+        EdgeImpulseError::DspError(None) => {
+            let dsp = unsafe { ei_ffi_last_dsp_error() };
+            EdgeImpulseError::DspError(DspErrorCode::from_code(dsp))
+        }
+        other => other,
+    })
+}
+
+/// An error code returned by a DSP block (`EIDSP_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DspErrorCode {
+    /// A buffer could not be allocated (`EIDSP_OUT_OF_MEM`)
+    OutOfMemory,
+    /// The signal length doesn't match what the block expects (`EIDSP_SIGNAL_SIZE_MISMATCH`)
+    SignalSizeMismatch,
+    /// A matrix has the wrong dimensions (`EIDSP_MATRIX_SIZE_MISMATCH`)
+    MatrixSizeMismatch,
+    /// The discrete cosine transform failed (`EIDSP_DCT_ERROR`)
+    DctError,
+    /// An input matrix was empty (`EIDSP_INPUT_MATRIX_EMPTY`)
+    InputMatrixEmpty,
+    /// A buffer has the wrong size (`EIDSP_BUFFER_SIZE_MISMATCH`)
+    BufferSizeMismatch,
+    /// A block parameter is invalid (`EIDSP_PARAMETER_INVALID`)
+    ParameterInvalid,
+    /// Streaming input was read at an unexpected offset (`EIDSP_UNEXPECTED_NEXT_OFFSET`)
+    UnexpectedNextOffset,
+    /// An index was out of bounds (`EIDSP_OUT_OF_BOUNDS`)
+    OutOfBounds,
+    /// The filter configuration is not supported (`EIDSP_UNSUPPORTED_FILTER_CONFIG`)
+    UnsupportedFilterConfig,
+    /// A value didn't fit in a narrower type (`EIDSP_NARROWING`)
+    Narrowing,
+    /// The DSP block version is not supported by this SDK (`EIDSP_BLOCK_VERSION_INCORRECT`)
+    BlockVersionIncorrect,
+    /// The operation is not supported (`EIDSP_NOT_SUPPORTED`)
+    NotSupported,
+    /// The operation requires CMSIS-DSP (`EIDSP_REQUIRES_CMSIS_DSP`)
+    RequiresCmsisDsp,
+    /// The FFT table for this length was not compiled in (`EIDSP_FFT_TABLE_NOT_LOADED`)
+    FftTableNotLoaded,
+    /// A model used by the DSP block failed (`EIDSP_INFERENCE_ERROR`)
+    InferenceError,
+    /// A DSP error code this crate doesn't know about
+    Unknown(i32),
+}
+
+impl DspErrorCode {
+    /// Convert a raw `EIDSP_*` code, returning `None` for `EIDSP_OK` (0)
+    pub fn from_code(code: i32) -> Option<Self> {
+        let error = match code {
+            0 => return None,
+            -1002 => Self::OutOfMemory,
+            -1003 => Self::SignalSizeMismatch,
+            -1004 => Self::MatrixSizeMismatch,
+            -1005 => Self::DctError,
+            -1006 => Self::InputMatrixEmpty,
+            -1007 => Self::BufferSizeMismatch,
+            -1008 => Self::ParameterInvalid,
+            -1011 => Self::UnexpectedNextOffset,
+            -1012 => Self::OutOfBounds,
+            -1013 => Self::UnsupportedFilterConfig,
+            -1014 => Self::Narrowing,
+            -1015 => Self::BlockVersionIncorrect,
+            -1016 => Self::NotSupported,
+            -1017 => Self::RequiresCmsisDsp,
+            -1018 => Self::FftTableNotLoaded,
+            -1019 => Self::InferenceError,
+            other => Self::Unknown(other),
+        };
+        Some(error)
+    }
+
+    /// Raw `EIDSP_*` code
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::OutOfMemory => -1002,
+            Self::SignalSizeMismatch => -1003,
+            Self::MatrixSizeMismatch => -1004,
+            Self::DctError => -1005,
+            Self::InputMatrixEmpty => -1006,
+            Self::BufferSizeMismatch => -1007,
+            Self::ParameterInvalid => -1008,
+            Self::UnexpectedNextOffset => -1011,
+            Self::OutOfBounds => -1012,
+            Self::UnsupportedFilterConfig => -1013,
+            Self::Narrowing => -1014,
+            Self::BlockVersionIncorrect => -1015,
+            Self::NotSupported => -1016,
+            Self::RequiresCmsisDsp => -1017,
+            Self::FftTableNotLoaded => -1018,
+            Self::InferenceError => -1019,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::OutOfMemory => "out of memory",
+            Self::SignalSizeMismatch => "signal size mismatch",
+            Self::MatrixSizeMismatch => "matrix size mismatch",
+            Self::DctError => "DCT failed",
+            Self::InputMatrixEmpty => "input matrix is empty",
+            Self::BufferSizeMismatch => "buffer size mismatch",
+            Self::ParameterInvalid => "invalid parameter",
+            Self::UnexpectedNextOffset => "unexpected read offset",
+            Self::OutOfBounds => "index out of bounds",
+            Self::UnsupportedFilterConfig => "unsupported filter configuration",
+            Self::Narrowing => "value does not fit the target type",
+            Self::BlockVersionIncorrect => "unsupported DSP block version",
+            Self::NotSupported => "operation not supported",
+            Self::RequiresCmsisDsp => "operation requires CMSIS-DSP",
+            Self::FftTableNotLoaded => "FFT table not loaded",
+            Self::InferenceError => "DSP inference failed",
+            Self::Unknown(_) => "unknown DSP error",
+        }
+    }
+}
+
+impl fmt::Display for DspErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (DSP code {})", self.description(), self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        assert_eq!(EdgeImpulseError::from_code(0), None);
+        for code in -40..0 {
+            let error = EdgeImpulseError::from_code(code).unwrap();
+            assert_eq!(error.code(), code);
+            assert_eq!(EdgeImpulseError::from(EI_IMPULSE_ERROR(code)), error);
+        }
+        assert_eq!(
+            EdgeImpulseError::from_code(-10),
+            Some(EdgeImpulseError::UnsupportedInferencingEngine)
+        );
+        assert_eq!(
+            EdgeImpulseError::from_code(-12),
+            Some(EdgeImpulseError::Unknown(-12))
+        );
+
+        assert_eq!(DspErrorCode::from_code(0), None);
+        for code in -1030..0 {
+            assert_eq!(DspErrorCode::from_code(code).unwrap().code(), code);
+        }
+    }
+
+    #[test]
+    fn dsp_errors_carry_their_code() {
+        assert_eq!(
+            DspErrorCode::from_code(-1003),
+            Some(DspErrorCode::SignalSizeMismatch)
+        );
+        assert_eq!(
+            DspErrorCode::from_code(-1009),
+            Some(DspErrorCode::Unknown(-1009))
+        );
+
+        let error = EdgeImpulseError::DspError(DspErrorCode::from_code(-1002));
+        assert_eq!(error.code(), -5);
+        assert_eq!(error.dsp_code(), Some(DspErrorCode::OutOfMemory));
+        assert_eq!(
+            error.to_string(),
+            "dsp error: DSP block failed to process the signal (code -5): out of memory (DSP code -1002)"
+        );
+        assert_eq!(EdgeImpulseError::TfliteError.dsp_code(), None);
+    }
+}
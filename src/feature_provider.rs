@@ -0,0 +1,168 @@
+//! Precomputed DSP features
+//!
+//! In heterogeneous systems the DSP stage often runs elsewhere: a hardware DSP or ISP, another
+//! process, or a cache of features computed earlier. A [`FeatureProvider`] supplies those
+//! features, and [`Classifier::run_provider`](crate::Classifier::run_provider) runs only the
+//! impulse's learning and postprocessing blocks on them (`run_inference` in the SDK), skipping
+//! the SDK's own DSP.
+//!
+//! Features are the output of every DSP block of the impulse, concatenated in block order, so
+//! [`FEATURE_COUNT`] (`EI_CLASSIFIER_NN_INPUT_FRAME_SIZE`) values per inference. They must be
+//! computed exactly like the DSP blocks the model was trained with.
+//!
+//! ```ignore
+//! use edge_impulse_ffi_rs::feature_provider::ReaderFeatures;
+//! use edge_impulse_ffi_rs::Classifier;
+//!
+//! // Features written by a DSP process as little-endian f32s
+//! let mut dsp = ReaderFeatures::new(std::fs::File::open("/tmp/features")?);
+//! let mut classifier = Classifier::new();
+//! let output = classifier.run_provider(&mut dsp, false)?;
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::error::EdgeImpulseError;
+use crate::model_metadata::EI_CLASSIFIER_NN_INPUT_FRAME_SIZE;
+
+/// Number of features the learning blocks take per inference
+pub const FEATURE_COUNT: usize = EI_CLASSIFIER_NN_INPUT_FRAME_SIZE;
+
+/// A source of precomputed DSP features
+///
+/// Closures `FnMut(&mut [f32]) -> Result<(), E>` are providers as well.
+pub trait FeatureProvider {
+    type Error: Error + Send + Sync + 'static;
+
+    /// Fill `features` ([`FEATURE_COUNT`] values) with the features of the next inference
+    fn provide(&mut self, features: &mut [f32]) -> Result<(), Self::Error>;
+}
+
+impl<F, E> FeatureProvider for F
+where
+    F: FnMut(&mut [f32]) -> Result<(), E>,
+    E: Error + Send + Sync + 'static,
+{
+    type Error = E;
+
+    fn provide(&mut self, features: &mut [f32]) -> Result<(), E> {
+        self(features)
+    }
+}
+
+/// Reads features as little-endian `f32`s from a stream, e.g. a pipe or socket of another process
+pub struct ReaderFeatures<R> {
+    reader: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> ReaderFeatures<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            bytes: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> FeatureProvider for ReaderFeatures<R> {
+    type Error = io::Error;
+
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] at the end of the stream
+    fn provide(&mut self, features: &mut [f32]) -> io::Result<()> {
+        self.bytes.resize(features.len() * 4, 0);
+        self.reader.read_exact(&mut self.bytes)?;
+        for (feature, bytes) in features.iter_mut().zip(self.bytes.chunks_exact(4)) {
+            *feature = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Ok(())
+    }
+}
+
+/// A provider failed, or the SDK failed on its features
+#[derive(Debug)]
+pub enum ProviderError<E> {
+    /// The provider couldn't supply features
+    Provider(E),
+    /// The learning or postprocessing blocks failed
+    Inference(EdgeImpulseError),
+}
+
+impl<E: fmt::Display> fmt::Display for ProviderError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Provider(error) => write!(f, "feature provider failed: {}", error),
+            Self::Inference(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ProviderError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Provider(error) => Some(error),
+            Self::Inference(error) => Some(error),
+        }
+    }
+}
+
+impl<E> From<EdgeImpulseError> for ProviderError<E> {
+    fn from(error: EdgeImpulseError) -> Self {
+        Self::Inference(error)
+    }
+}
+
+/// Fetch the next features from `provider` and run `run` on them
+pub(crate) fn run_provided<P, T, F>(provider: &mut P, run: F) -> Result<T, ProviderError<P::Error>>
+where
+    P: FeatureProvider + ?Sized,
+    F: FnOnce(&[f32]) -> Result<T, EdgeImpulseError>,
+{
+    let mut features = vec![0.0; FEATURE_COUNT];
+    provider
+        .provide(&mut features)
+        .map_err(ProviderError::Provider)?;
+    Ok(run(&features)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_little_endian_features() {
+        let bytes: Vec<u8> = [1.5f32, -2.0, 0.25]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let mut provider = ReaderFeatures::new(&bytes[..]);
+        let mut features = [0.0; 2];
+        provider.provide(&mut features).unwrap();
+        assert_eq!(features, [1.5, -2.0]);
+        let error = provider.provide(&mut features).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reports_provider_failures() {
+        let mut cache_miss = |_: &mut [f32]| Err(io::Error::other("cache miss"));
+        let result = run_provided(&mut cache_miss, |features| Ok(features.len()));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "feature provider failed: cache miss"
+        );
+
+        let mut zeros = |features: &mut [f32]| -> io::Result<()> {
+            features.fill(0.0);
+            Ok(())
+        };
+        let result = run_provided(&mut zeros, |features| Ok(features.len()));
+        assert_eq!(result.unwrap(), FEATURE_COUNT);
+    }
+}
@@ -0,0 +1,97 @@
+//! Fixed-size feature buffers
+//!
+//! [`Features<N>`] holds exactly `N` features. [`ModelFeatures`] is sized for the linked model
+//! (`EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE`), and
+//! [`Classifier::run_fixed`](crate::Classifier::run_fixed) only accepts that size, so passing a
+//! buffer built for another model is a compile error rather than a runtime failure.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::model_metadata::EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE;
+use crate::runner_api::EimError;
+
+/// Features sized for the linked model's input
+pub type ModelFeatures = Features<EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE>;
+
+/// A heap-allocated buffer of exactly `N` features
+#[derive(Debug, Clone, PartialEq)]
+pub struct Features<const N: usize> {
+    // Boxed so that large inputs (e.g. images) don't live on the stack
+    data: Box<[f32; N]>,
+}
+
+impl<const N: usize> Features<N> {
+    /// Number of features in the buffer
+    pub const LEN: usize = N;
+
+    /// Create a buffer with every feature set to 0.0
+    pub fn zeroed() -> Self {
+        Self {
+            data: vec![0.0; N]
+                .into_boxed_slice()
+                .try_into()
+                .expect("vec has exactly N elements"),
+        }
+    }
+
+    /// Wrap an already boxed array
+    pub fn from_boxed(data: Box<[f32; N]>) -> Self {
+        Self { data }
+    }
+
+    /// The features as a slice
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data[..]
+    }
+}
+
+impl<const N: usize> Default for Features<N> {
+    fn default() -> Self {
+        Self::zeroed()
+    }
+}
+
+impl<const N: usize> From<[f32; N]> for Features<N> {
+    fn from(data: [f32; N]) -> Self {
+        Self {
+            data: Box::new(data),
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<Vec<f32>> for Features<N> {
+    type Error = EimError;
+
+    /// Convert a `Vec` of runtime length, failing if it doesn't hold exactly `N` features
+    fn try_from(features: Vec<f32>) -> Result<Self, Self::Error> {
+        let len = features.len();
+        features
+            .into_boxed_slice()
+            .try_into()
+            .map(Self::from_boxed)
+            .map_err(|_| EimError::InvalidInput(format!("Expected {} features, got {}", N, len)))
+    }
+}
+
+impl<const N: usize> TryFrom<&[f32]> for Features<N> {
+    type Error = EimError;
+
+    /// Copy a slice of runtime length, failing if it doesn't hold exactly `N` features
+    fn try_from(features: &[f32]) -> Result<Self, Self::Error> {
+        Self::try_from(features.to_vec())
+    }
+}
+
+impl<const N: usize> Deref for Features<N> {
+    type Target = [f32; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<const N: usize> DerefMut for Features<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
@@ -0,0 +1,184 @@
+//! FOMO centroid post-processing
+//!
+//! FOMO models classify a grid of cells (by default each cell covers 8x8 input pixels) rather
+//! than predicting boxes. The SDK reports every group of activated cells as a bounding box, so
+//! one object can show up as several touching boxes. Edge Impulse Studio draws FOMO output as
+//! one centroid per object instead; [`centroids`] does the same, merging touching boxes of the
+//! same label and reporting the grid cells each object covers.
+
+use crate::inference::BoundingBox;
+
+/// Input pixels covered by one FOMO output cell with the default 1/8 output resolution
+pub const DEFAULT_CELL_SIZE: u32 = 8;
+
+/// An object found by a FOMO model
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Centroid {
+    /// Class label
+    pub label: String,
+    /// Highest confidence of the merged boxes
+    pub value: f32,
+    /// X coordinate of the centroid, in input pixels
+    pub x: u32,
+    /// Y coordinate of the centroid, in input pixels
+    pub y: u32,
+    /// Grid column of the cell containing the centroid
+    pub column: u32,
+    /// Grid row of the cell containing the centroid
+    pub row: u32,
+    /// Number of activated grid cells that make up the object
+    pub cell_count: u32,
+    /// Smallest box containing all merged boxes
+    pub bounds: BoundingBox,
+}
+
+/// Merge touching boxes of the same label into centroids
+///
+/// Boxes are snapped to the grid of `cell_size` pixel cells first; boxes whose cells touch
+/// (including diagonally) belong to the same object. The centroid is the center of the merged
+/// boxes, weighted by their area. Centroids are returned in the order of their first box.
+///
+/// # Panics
+///
+/// Panics if `cell_size` is 0.
+pub fn centroids(boxes: &[BoundingBox], cell_size: u32) -> Vec<Centroid> {
+    assert!(cell_size > 0, "cell_size must be at least 1");
+    let cells: Vec<CellRect> = boxes.iter().map(|bb| CellRect::of(bb, cell_size)).collect();
+
+    // Union-find over boxes, joining touching boxes with the same label
+    let mut parent: Vec<usize> = (0..boxes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for a in 0..boxes.len() {
+        for b in a + 1..boxes.len() {
+            if boxes[a].label == boxes[b].label && cells[a].touches(&cells[b]) {
+                let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                parent[ra.max(rb)] = ra.min(rb);
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..boxes.len() {
+        let r = root(&mut parent, i);
+        match groups.iter_mut().find(|(group_root, _)| *group_root == r) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((r, vec![i])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, members)| merge(boxes, &cells, &members, cell_size))
+        .collect()
+}
+
+fn merge(boxes: &[BoundingBox], cells: &[CellRect], members: &[usize], cell_size: u32) -> Centroid {
+    let first = &boxes[members[0]];
+    let mut value = 0.0f32;
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    let (mut sum_x, mut sum_y, mut total_area) = (0.0f64, 0.0f64, 0.0f64);
+    let mut cell_count = 0;
+    for &i in members {
+        let bb = &boxes[i];
+        value = value.max(bb.value);
+        left = left.min(bb.x);
+        top = top.min(bb.y);
+        right = right.max(bb.x + bb.width);
+        bottom = bottom.max(bb.y + bb.height);
+        // Zero-sized boxes still count, as a single point
+        let area = ((bb.width as f64) * (bb.height as f64)).max(1.0);
+        sum_x += (bb.x as f64 + bb.width as f64 / 2.0) * area;
+        sum_y += (bb.y as f64 + bb.height as f64 / 2.0) * area;
+        total_area += area;
+        cell_count += cells[i].count();
+    }
+    let x = (sum_x / total_area).round() as u32;
+    let y = (sum_y / total_area).round() as u32;
+
+    Centroid {
+        label: first.label.clone(),
+        value,
+        x,
+        y,
+        column: x / cell_size,
+        row: y / cell_size,
+        cell_count,
+        bounds: BoundingBox {
+            label: first.label.clone(),
+            value,
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        },
+    }
+}
+
+/// Grid cells covered by a box, end exclusive
+struct CellRect {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+}
+
+impl CellRect {
+    fn of(bb: &BoundingBox, cell_size: u32) -> Self {
+        let left = bb.x / cell_size;
+        let top = bb.y / cell_size;
+        Self {
+            left,
+            top,
+            right: (bb.x + bb.width).div_ceil(cell_size).max(left + 1),
+            bottom: (bb.y + bb.height).div_ceil(cell_size).max(top + 1),
+        }
+    }
+
+    fn touches(&self, other: &CellRect) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.top <= other.bottom
+            && other.top <= self.bottom
+    }
+
+    fn count(&self) -> u32 {
+        (self.right - self.left) * (self.bottom - self.top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bbox;
+
+    #[test]
+    fn merges_touching_boxes_of_the_same_label() {
+        let boxes = [
+            bbox("bolt", 0.8, 0, 0, 8, 8),
+            bbox("nut", 0.9, 8, 0, 8, 8),
+            bbox("bolt", 0.6, 8, 8, 8, 8),
+            bbox("bolt", 0.7, 40, 40, 16, 8),
+        ];
+        let centroids = centroids(&boxes, DEFAULT_CELL_SIZE);
+        assert_eq!(centroids.len(), 3);
+
+        let first = &centroids[0];
+        assert_eq!(first.label, "bolt");
+        assert_eq!(first.value, 0.8);
+        assert_eq!((first.x, first.y), (8, 8));
+        assert_eq!(first.cell_count, 2);
+        assert_eq!((first.bounds.width, first.bounds.height), (16, 16));
+
+        assert_eq!(centroids[1].label, "nut");
+        let last = &centroids[2];
+        assert_eq!((last.x, last.y, last.column, last.row), (48, 44, 6, 5));
+        assert_eq!(last.cell_count, 2);
+    }
+}
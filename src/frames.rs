@@ -0,0 +1,177 @@
+//! Multi-frame feature assembly
+//!
+//! Image models trained with `EI_CLASSIFIER_INPUT_FRAMES > 1` take several consecutive frames
+//! as a single input. [`FrameStacker`] keeps the most recent frames and assembles them, oldest
+//! first, into the feature buffer the model expects.
+
+use std::collections::VecDeque;
+
+use crate::model_metadata::{
+    EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE, EI_CLASSIFIER_INPUT_FRAMES,
+    EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME,
+};
+use crate::runner_api::EimError;
+
+/// Order in which stacked frames are laid out in the feature buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameLayout {
+    /// Frames are concatenated: all features of frame 0, then all features of frame 1, ...
+    Planar,
+    /// Frames are interleaved per feature: feature 0 of every frame, then feature 1, ...
+    Interleaved,
+}
+
+impl FrameLayout {
+    /// Detect the layout the linked model expects
+    ///
+    /// Models that treat the stacked frames as axes of one raw sample
+    /// (`EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME == EI_CLASSIFIER_INPUT_FRAMES`) expect interleaved
+    /// features, all others expect frames one after another.
+    pub fn detect() -> Self {
+        let frames = EI_CLASSIFIER_INPUT_FRAMES;
+        if frames > 1 && EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME == frames {
+            FrameLayout::Interleaved
+        } else {
+            FrameLayout::Planar
+        }
+    }
+}
+
+/// Keeps the most recent frames and assembles them into a model input
+#[derive(Debug, Clone)]
+pub struct FrameStacker {
+    frame_size: usize,
+    frame_count: usize,
+    layout: FrameLayout,
+    frames: VecDeque<Vec<f32>>,
+}
+
+impl FrameStacker {
+    /// Create a frame stacker for the linked model
+    pub fn new() -> Self {
+        #[allow(clippy::unnecessary_min_or_max)]
+        let frame_count = EI_CLASSIFIER_INPUT_FRAMES.max(1);
+        Self::with_layout(
+            EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE / frame_count,
+            frame_count,
+            FrameLayout::detect(),
+        )
+    }
+
+    /// Create a frame stacker for `frame_count` frames of `frame_size` features each
+    pub fn with_layout(frame_size: usize, frame_count: usize, layout: FrameLayout) -> Self {
+        let frame_count = frame_count.max(1);
+        Self {
+            frame_size,
+            frame_count,
+            layout,
+            frames: VecDeque::with_capacity(frame_count),
+        }
+    }
+
+    /// Number of features in a single frame
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Number of frames stacked into one model input
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Layout of the assembled features
+    pub fn layout(&self) -> FrameLayout {
+        self.layout
+    }
+
+    /// Add a frame, dropping the oldest one once `frame_count` frames are buffered
+    pub fn push_frame(&mut self, frame: &[f32]) -> Result<(), EimError> {
+        if frame.len() != self.frame_size {
+            return Err(EimError::InvalidInput(format!(
+                "Expected a frame of {} features, got {}",
+                self.frame_size,
+                frame.len()
+            )));
+        }
+        if self.frames.len() == self.frame_count {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame.to_vec());
+        Ok(())
+    }
+
+    /// Whether enough frames have been pushed to assemble a model input
+    pub fn is_ready(&self) -> bool {
+        self.frames.len() == self.frame_count
+    }
+
+    /// Assemble the buffered frames, oldest first, or `None` if not enough frames were pushed
+    pub fn features(&self) -> Option<Vec<f32>> {
+        if !self.is_ready() {
+            return None;
+        }
+        let mut features = Vec::with_capacity(self.frame_size * self.frame_count);
+        match self.layout {
+            FrameLayout::Planar => {
+                for frame in &self.frames {
+                    features.extend_from_slice(frame);
+                }
+            }
+            FrameLayout::Interleaved => {
+                for i in 0..self.frame_size {
+                    features.extend(self.frames.iter().map(|frame| frame[i]));
+                }
+            }
+        }
+        Some(features)
+    }
+
+    /// Discard all buffered frames
+    pub fn reset(&mut self) {
+        self.frames.clear();
+    }
+}
+
+impl Default for FrameStacker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planar_concatenates_frames_oldest_first() {
+        let mut stacker = FrameStacker::with_layout(3, 2, FrameLayout::Planar);
+        stacker.push_frame(&[1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(stacker.features(), None);
+        stacker.push_frame(&[4.0, 5.0, 6.0]).unwrap();
+        assert_eq!(stacker.features(), Some(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+    }
+
+    #[test]
+    fn interleaved_groups_features_by_index() {
+        let mut stacker = FrameStacker::with_layout(3, 2, FrameLayout::Interleaved);
+        stacker.push_frame(&[1.0, 2.0, 3.0]).unwrap();
+        stacker.push_frame(&[4.0, 5.0, 6.0]).unwrap();
+        assert_eq!(stacker.features(), Some(vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]));
+    }
+
+    #[test]
+    fn drops_oldest_frame_when_full() {
+        let mut stacker = FrameStacker::with_layout(2, 2, FrameLayout::Planar);
+        stacker.push_frame(&[1.0, 1.0]).unwrap();
+        stacker.push_frame(&[2.0, 2.0]).unwrap();
+        stacker.push_frame(&[3.0, 3.0]).unwrap();
+        assert_eq!(stacker.features(), Some(vec![2.0, 2.0, 3.0, 3.0]));
+    }
+
+    #[test]
+    fn rejects_wrong_frame_size() {
+        let mut stacker = FrameStacker::with_layout(2, 2, FrameLayout::Planar);
+        assert!(stacker.push_frame(&[1.0]).is_err());
+        assert!(!stacker.is_ready());
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-label event gating
+//!
+//! Alarms built on a classifier shouldn't fire on a single noisy window, nor fire again for
+//! every window of the same event. [`EventGate`] applies the usual hysteresis to a stream of
+//! scores: a label has to stay at or above its threshold for a minimum duration before its
+//! event fires, fires once per sustained detection, and is then ignored for a refractory period.
+//!
+//! Timestamps are positions in the stream, e.g. the end of each audio window, so gating works
+//! the same on live input and on recordings.
+//!
+//! ```ignore
+//! use std::time::Duration;
+//! use edge_impulse_ffi_rs::gating::{EventGate, GateRule};
+//!
+//! let mut gate = EventGate::new().rule(
+//!     "glass_break",
+//!     GateRule::new(0.8)
+//!         .min_duration(Duration::from_millis(200))
+//!         .refractory(Duration::from_secs(5)),
+//! );
+//! for event in gate.update_output(&output, position) {
+//!     println!("{} at {:?}", event.label, event.fired_at);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::inference::InferenceOutput;
+use crate::runner_api::InferenceResult;
+
+/// When the event of a label fires
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateRule {
+    /// Score the label has to reach
+    pub threshold: f32,
+    /// How long the score has to stay at or above the threshold before the event fires
+    pub min_duration: Duration,
+    /// Time after an event during which the label can't fire again
+    pub refractory: Duration,
+}
+
+impl GateRule {
+    /// Fire as soon as the score reaches `threshold`, without a refractory period
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            min_duration: Duration::ZERO,
+            refractory: Duration::ZERO,
+        }
+    }
+
+    pub fn min_duration(mut self, min_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self
+    }
+
+    pub fn refractory(mut self, refractory: Duration) -> Self {
+        self.refractory = refractory;
+        self
+    }
+}
+
+/// An event that passed its label's gate
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GatedEvent {
+    pub label: String,
+    /// When the score reached the threshold
+    pub start: Duration,
+    /// When the score had stayed above the threshold for the minimum duration
+    pub fired_at: Duration,
+    /// Score at `fired_at`
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LabelState {
+    /// Start of the current run of scores at or above the threshold
+    above_since: Option<Duration>,
+    /// The current run already fired
+    fired: bool,
+    /// End of the refractory period of the last event
+    quiet_until: Option<Duration>,
+}
+
+/// Minimum-duration and refractory gating of label events
+///
+/// Labels without a rule never fire, and labels missing from an update count as scored 0.
+#[derive(Debug, Clone, Default)]
+pub struct EventGate {
+    rules: HashMap<String, GateRule>,
+    states: HashMap<String, LabelState>,
+}
+
+impl EventGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gate `label` with `rule`, replacing its previous rule
+    pub fn rule(mut self, label: impl Into<String>, rule: GateRule) -> Self {
+        self.set_rule(label, rule);
+        self
+    }
+
+    pub fn set_rule(&mut self, label: impl Into<String>, rule: GateRule) {
+        self.rules.insert(label.into(), rule);
+    }
+
+    /// Feed the scores at stream position `now` and return the events that fire, sorted by
+    /// label
+    ///
+    /// Positions must not go backwards.
+    pub fn update<'a>(
+        &mut self,
+        scores: impl IntoIterator<Item = (&'a str, f32)>,
+        now: Duration,
+    ) -> Vec<GatedEvent> {
+        let scores: HashMap<&str, f32> = scores.into_iter().collect();
+        let mut events = Vec::new();
+        for (label, rule) in &self.rules {
+            let score = scores.get(label.as_str()).copied().unwrap_or(0.0);
+            let state = self.states.entry(label.clone()).or_default();
+            if score < rule.threshold {
+                state.above_since = None;
+                state.fired = false;
+                continue;
+            }
+            let start = *state.above_since.get_or_insert(now);
+            let quiet = state.quiet_until.is_some_and(|until| now < until);
+            if state.fired || quiet || now - start < rule.min_duration {
+                continue;
+            }
+            state.fired = true;
+            state.quiet_until = Some(now + rule.refractory);
+            events.push(GatedEvent {
+                label: label.clone(),
+                start,
+                fired_at: now,
+                score,
+            });
+        }
+        events.sort_by(|a, b| a.label.cmp(&b.label));
+        events
+    }
+
+    /// Feed the classification scores of an inference at stream position `now`
+    pub fn update_output(&mut self, output: &InferenceOutput, now: Duration) -> Vec<GatedEvent> {
+        self.update(
+            output
+                .classification
+                .iter()
+                .map(|c| (c.label.as_str(), c.value)),
+            now,
+        )
+    }
+
+    /// Feed the classification scores of a runner result at stream position `now`; results
+    /// without scores count as all labels scored 0
+    pub fn update_result(&mut self, result: &InferenceResult, now: Duration) -> Vec<GatedEvent> {
+        match result {
+            InferenceResult::Classification { classification, .. }
+            | InferenceResult::ObjectDetection { classification, .. }
+            | InferenceResult::ObjectTracking { classification, .. } => self.update(
+                classification
+                    .iter()
+                    .map(|(label, &score)| (label.as_str(), score)),
+                now,
+            ),
+            _ => self.update([], now),
+        }
+    }
+
+    /// Forget the state of every label, e.g. when the stream restarts
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_after_min_duration_and_waits_out_refractory() {
+        let mut gate = EventGate::new().rule(
+            "alarm",
+            GateRule::new(0.8)
+                .min_duration(Duration::from_millis(500))
+                .refractory(Duration::from_secs(2)),
+        );
+        let ms = Duration::from_millis;
+        let mut fired = Vec::new();
+        // A single noisy window, a sustained alarm, a dip inside the refractory period, and a
+        // second alarm after it
+        let scores = [
+            0.9, 0.1, 0.9, 0.85, 0.9, 0.95, 0.2, 0.9, 0.9, 0.9, 0.1, 0.9, 0.9, 0.9,
+        ];
+        for (i, &score) in scores.iter().enumerate() {
+            let now = ms(250 * i as u64);
+            for event in gate.update([("alarm", score), ("noise", 1.0)], now) {
+                fired.push((event.start, event.fired_at));
+            }
+        }
+        assert_eq!(fired, vec![(ms(500), ms(1000)), (ms(2750), ms(3250))]);
+    }
+}
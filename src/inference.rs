@@ -0,0 +1,466 @@
+//! Owned inference results
+//!
+//! The raw `ei_impulse_result_t` filled in by the C++ SDK holds `*const c_char` labels and
+//! pointers to bounding box and anomaly grid arrays owned by the SDK. Those pointers are only
+//! valid until the next inference call, so the types in this module copy everything out of
+//! the C struct immediately after `ei_ffi_run_classifier` returns. The resulting values can
+//! outlive the C struct and be sent across threads.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::time::Duration;
+
+use crate::bindings::*;
+use crate::error::{check_classifier, EdgeImpulseError};
+use crate::journal;
+use crate::logging;
+use crate::model_metadata::{
+    EI_CLASSIFIER_HAS_ANOMALY, EI_CLASSIFIER_IS_VISUAL_ANOMALY, EI_CLASSIFIER_LABEL_COUNT,
+    EI_CLASSIFIER_OUTPUT_QUANTIZED,
+};
+use crate::quantization::QuantizationInfo;
+use crate::signal::Signal;
+use crate::timing;
+
+/// A single classification score
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Classification {
+    /// Class label
+    pub label: String,
+    /// Confidence score (0.0 - 1.0)
+    pub value: f32,
+}
+
+/// A bounding box from object detection, or a cell of the visual anomaly grid
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingBox {
+    /// Class label
+    pub label: String,
+    /// Confidence (object detection) or anomaly score (visual anomaly grid)
+    pub value: f32,
+    /// X coordinate of the top-left corner, in input pixels
+    pub x: u32,
+    /// Y coordinate of the top-left corner, in input pixels
+    pub y: u32,
+    /// Width in input pixels
+    pub width: u32,
+    /// Height in input pixels
+    pub height: u32,
+}
+
+/// An object followed across frames by the object tracking postprocessing block
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedObject {
+    /// Track ID, stable for as long as the SDK keeps following the object
+    pub id: u32,
+    /// Class label
+    pub label: String,
+    /// Confidence score
+    pub value: f32,
+    /// X coordinate of the top-left corner, in input pixels
+    pub x: u32,
+    /// Y coordinate of the top-left corner, in input pixels
+    pub y: u32,
+    /// Width in input pixels
+    pub width: u32,
+    /// Height in input pixels
+    pub height: u32,
+}
+
+/// Visual anomaly detection (e.g. FOMO-AD) output
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VisualAnomaly {
+    /// Grid cells reported by the SDK, with their anomaly scores
+    pub grid: Vec<BoundingBox>,
+    /// Mean anomaly value over the whole grid
+    pub mean_value: f32,
+    /// Maximum anomaly value over the whole grid
+    pub max_value: f32,
+}
+
+/// Element type of the model's output tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputDType {
+    /// 32-bit float (also reported for float16 models, which the SDK runs in float32)
+    F32,
+    /// Quantized int8, see [`QuantizationInfo`]
+    I8,
+}
+
+impl OutputDType {
+    /// Output type of the linked model
+    pub const fn model() -> Self {
+        if EI_CLASSIFIER_OUTPUT_QUANTIZED {
+            OutputDType::I8
+        } else {
+            OutputDType::F32
+        }
+    }
+}
+
+/// Raw output vector of a freeform learning block, e.g. an embedding
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embedding {
+    /// Output values, dequantized by the SDK
+    pub vector: Vec<f32>,
+    /// Element type of the output tensor the values were read from
+    pub dtype: OutputDType,
+}
+
+impl Embedding {
+    /// The values as stored in an int8 output tensor, `None` for float outputs
+    pub fn quantized(&self) -> Option<Vec<i8>> {
+        if self.dtype != OutputDType::I8 {
+            return None;
+        }
+        let output = QuantizationInfo::model().output;
+        Some(self.vector.iter().map(|&v| output.quantize(v)).collect())
+    }
+
+    /// The values in half precision, e.g. to store many embeddings compactly
+    #[cfg(feature = "half")]
+    pub fn to_f16(&self) -> Vec<half::f16> {
+        self.vector
+            .iter()
+            .map(|&v| half::f16::from_f32(v))
+            .collect()
+    }
+}
+
+/// Time spent in each stage of the impulse, from the SDK's microsecond timers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timing {
+    /// Time spent in the DSP blocks
+    pub dsp: Duration,
+    /// Time spent running the learning blocks
+    pub classification: Duration,
+    /// Time spent in the anomaly block
+    pub anomaly: Duration,
+}
+
+impl Timing {
+    /// Convert the raw timing struct, using its `*_us` fields (see [`timing`](crate::timing))
+    pub fn from_raw(timing: &ei_impulse_result_timing_t) -> Self {
+        Self {
+            dsp: timing::from_micros(timing.dsp_us),
+            classification: timing::from_micros(timing.classification_us),
+            anomaly: timing::from_micros(timing.anomaly_us),
+        }
+    }
+
+    /// Total time spent in the impulse
+    pub fn total(&self) -> Duration {
+        self.dsp + self.classification + self.anomaly
+    }
+
+    /// Inferences per second if every inference took as long as this one, `None` if the SDK
+    /// reported no time at all
+    pub fn fps_estimate(&self) -> Option<f64> {
+        let total = self.total().as_secs_f64();
+        (total > 0.0).then(|| 1.0 / total)
+    }
+}
+
+impl From<&ei_impulse_result_timing_t> for Timing {
+    fn from(timing: &ei_impulse_result_timing_t) -> Self {
+        Self::from_raw(timing)
+    }
+}
+
+/// Owned copy of an `ei_impulse_result_t`
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InferenceOutput {
+    /// Classification scores, one entry per model label
+    pub classification: Vec<Classification>,
+    /// Object detection bounding boxes
+    pub bounding_boxes: Vec<BoundingBox>,
+    /// Tracked objects (empty unless the model has object tracking enabled)
+    pub object_tracking: Vec<TrackedObject>,
+    /// Anomaly score (0.0 when the model has no anomaly block, see
+    /// [`anomaly_score`](Self::anomaly_score))
+    pub anomaly: f32,
+    /// Visual anomaly results, for visual anomaly (FOMO-AD) models
+    pub visual_anomaly: Option<VisualAnomaly>,
+    /// Output vectors of freeform learning blocks (empty unless the model outputs embeddings)
+    pub embeddings: Vec<Embedding>,
+    /// Timing information reported by the SDK
+    pub timing: Timing,
+}
+
+impl InferenceOutput {
+    /// Copy all results out of a raw result struct
+    ///
+    /// # Safety
+    ///
+    /// `result` must have been filled in by the SDK (or zero-initialized): every non-null label
+    /// must point to a valid NUL-terminated string, and `bounding_boxes` / `visual_ad_grid_cells`
+    /// must point to at least `bounding_boxes_count` / `visual_ad_count` elements.
+    pub unsafe fn from_raw(result: &ei_impulse_result_t) -> Self {
+        let classification = classifications(result);
+
+        let bounding_boxes = copy_boxes(result.bounding_boxes, result.bounding_boxes_count);
+
+        // Mean and max are meaningful even when no grid cell crossed the threshold
+        let visual_anomaly = if EI_CLASSIFIER_IS_VISUAL_ANOMALY {
+            Some(VisualAnomaly {
+                grid: copy_boxes(result.visual_ad_grid_cells, result.visual_ad_count),
+                mean_value: result.visual_ad_result.mean_value,
+                max_value: result.visual_ad_result.max_value,
+            })
+        } else {
+            None
+        };
+
+        Self {
+            classification,
+            bounding_boxes,
+            object_tracking: copy_tracked_objects(result),
+            anomaly: result.anomaly,
+            visual_anomaly,
+            embeddings: copy_embeddings(result),
+            timing: Timing::from_raw(&result.timing),
+        }
+    }
+
+    /// Check the code returned by a raw `ei_ffi_run_classifier*` call and copy its result
+    ///
+    /// Errors are mapped like those of [`Classifier::run`](crate::Classifier::run), including the
+    /// DSP error code. On success this is the `From<&ei_impulse_result_t>` conversion, with the
+    /// same requirements on `result`.
+    pub fn from_raw_result(
+        code: EI_IMPULSE_ERROR,
+        result: &ei_impulse_result_t,
+    ) -> Result<Self, EdgeImpulseError> {
+        check_classifier(code)?;
+        Ok(Self::from(result))
+    }
+
+    /// Anomaly score, `None` if the model has no anomaly block
+    pub fn anomaly_score(&self) -> Option<f32> {
+        (EI_CLASSIFIER_HAS_ANOMALY != 0).then_some(self.anomaly)
+    }
+}
+
+/// Copy a result filled in by a raw `ei_ffi_run_classifier*` call, see
+/// [`InferenceOutput::from_raw`]
+///
+/// Hands a result obtained through the raw bindings over to the safe types in one call.
+///
+/// # Safety requirements
+///
+/// The conversion follows the struct's pointers, which Rust can't check, so `result` must come
+/// straight from the SDK (or be zero-initialized, e.g. `ei_impulse_result_t::default()`):
+///
+/// - every non-null label must point to a valid NUL-terminated string,
+/// - `bounding_boxes` and `visual_ad_grid_cells` must be null or point to at least
+///   `bounding_boxes_count` and `visual_ad_count` elements,
+/// - the labels and arrays belong to the SDK and are only valid until its next inference or
+///   deinitialization, so convert the result before the next call,
+/// - the fields are read as the linked model fills them in, e.g. the visual anomaly fields only
+///   for visual anomaly models, so the result must come from this crate's model and not from
+///   an extra model of [`crate::models`].
+///
+/// Setting the raw pointers to anything else is undefined behavior.
+impl From<&ei_impulse_result_t> for InferenceOutput {
+    fn from(result: &ei_impulse_result_t) -> Self {
+        // SAFETY: the requirements are the caller's, as documented above
+        unsafe { Self::from_raw(result) }
+    }
+}
+
+/// Classification entries of a raw result, exactly one per model label
+///
+/// `ei_impulse_result_t::classification` is a fixed-size array that can be longer than the
+/// model's label count; only the first `EI_CLASSIFIER_LABEL_COUNT` entries are filled in.
+pub fn classification_entries(
+    result: &ei_impulse_result_t,
+) -> &[ei_impulse_result_classification_t] {
+    let len = EI_CLASSIFIER_LABEL_COUNT.min(result.classification.len());
+    &result.classification[..len]
+}
+
+/// Copy the classification scores out of a raw result, in label order
+///
+/// Entries without a label (e.g. for object detection models) are skipped.
+///
+/// # Safety
+///
+/// Every non-null label in the first `EI_CLASSIFIER_LABEL_COUNT` entries must point to a valid
+/// NUL-terminated string.
+pub unsafe fn classifications(result: &ei_impulse_result_t) -> Vec<Classification> {
+    classification_entries(result)
+        .iter()
+        .filter(|c| !c.label.is_null())
+        .map(|c| Classification {
+            label: label_to_string(c.label),
+            value: c.value,
+        })
+        .collect()
+}
+
+/// Run the classifier on a buffer of features and copy the results out of the C struct
+///
+/// The features must match the model's expected input (`EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE`).
+pub(crate) fn run_classifier(
+    features: &[f32],
+    debug: bool,
+) -> Result<InferenceOutput, EdgeImpulseError> {
+    run_classifier_signal(&mut Signal::from_slice(features)?, debug)
+}
+
+/// Run the classifier on a signal borrowing the caller's features
+pub(crate) fn run_classifier_signal(
+    signal: &mut Signal<'_>,
+    debug: bool,
+) -> Result<InferenceOutput, EdgeImpulseError> {
+    classify_with(signal, |signal, result| unsafe {
+        ei_ffi_run_classifier(signal, result, debug as i32)
+    })
+}
+
+/// Feed one slice of a continuous stream to `run_classifier_continuous` and copy the results
+///
+/// The SDK keeps the rest of the model window in an internal buffer, so `features` must hold
+/// exactly one slice (`EI_CLASSIFIER_SLICE_SIZE` raw samples). With `enable_maf` the SDK
+/// smooths the classification scores with its moving average filter.
+pub(crate) fn run_classifier_continuous(
+    features: &[f32],
+    debug: bool,
+    enable_maf: bool,
+) -> Result<InferenceOutput, EdgeImpulseError> {
+    classify_with(
+        &mut Signal::from_slice(features)?,
+        |signal, result| unsafe {
+            ei_ffi_run_classifier_continuous(signal, result, debug as i32, enable_maf as i32)
+        },
+    )
+}
+
+/// Run the learning blocks on precomputed DSP features and copy the results out of the C struct
+///
+/// `features` is the output of the impulse's DSP blocks, concatenated in block order
+/// (`EI_CLASSIFIER_NN_INPUT_FRAME_SIZE` values), e.g. read from a feature cache or computed by a
+/// hardware DSP. The SDK's own DSP doesn't run. See [`crate::feature_provider`].
+pub(crate) fn run_inference(
+    features: &[f32],
+    debug: bool,
+) -> Result<InferenceOutput, EdgeImpulseError> {
+    infer_with(features.len(), |result| unsafe {
+        ei_ffi_run_inference_features(
+            std::ptr::null_mut(),
+            features.as_ptr(),
+            features.len(),
+            result,
+            debug as i32,
+        )
+    })
+}
+
+/// Run `run` on a signal and copy the results out of the C struct
+pub(crate) fn classify_with<F>(
+    signal: &mut Signal<'_>,
+    run: F,
+) -> Result<InferenceOutput, EdgeImpulseError>
+where
+    F: FnOnce(&mut ei_signal_t, &mut ei_impulse_result_t) -> EI_IMPULSE_ERROR,
+{
+    let input_len = signal.len();
+    infer_with(input_len, |result| run(signal.as_raw_mut(), result))
+}
+
+/// Run `run` on `input_len` features and copy the results out of the C struct
+pub(crate) fn infer_with<F>(input_len: usize, run: F) -> Result<InferenceOutput, EdgeImpulseError>
+where
+    F: FnOnce(&mut ei_impulse_result_t) -> EI_IMPULSE_ERROR,
+{
+    let mut result = ei_impulse_result_t::default();
+    let code = {
+        profile_scope!("sdk_inference");
+        run(&mut result)
+    };
+    logging::flush_native_log();
+    check_classifier(code).inspect_err(|error| journal::record(error, input_len))?;
+
+    profile_scope!("copy_result");
+    Ok(unsafe { InferenceOutput::from_raw(&result) })
+}
+
+pub(crate) unsafe fn label_to_string(label: *const c_char) -> String {
+    if label.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(label).to_string_lossy().into_owned()
+}
+
+unsafe fn copy_boxes(
+    boxes: *const ei_impulse_result_bounding_box_t,
+    count: u32,
+) -> Vec<BoundingBox> {
+    if boxes.is_null() || count == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(boxes, count as usize)
+        .iter()
+        // The SDK marks unused slots with a zero score
+        .filter(|bb| bb.value > 0.0)
+        .map(|bb| BoundingBox {
+            label: label_to_string(bb.label),
+            value: bb.value,
+            x: bb.x,
+            y: bb.y,
+            width: bb.width,
+            height: bb.height,
+        })
+        .collect()
+}
+
+unsafe fn copy_tracked_objects(result: &ei_impulse_result_t) -> Vec<TrackedObject> {
+    let count = ei_ffi_object_tracking_count(result);
+    (0..count)
+        .filter_map(|index| {
+            let mut object = ei_ffi_tracked_object_t::default();
+            if ei_ffi_object_tracking_get(result, index, &mut object)
+                != EI_IMPULSE_ERROR::EI_IMPULSE_OK
+            {
+                return None;
+            }
+            Some(TrackedObject {
+                id: object.id,
+                label: label_to_string(object.label),
+                value: object.value,
+                x: object.x,
+                y: object.y,
+                width: object.width,
+                height: object.height,
+            })
+        })
+        .collect()
+}
+
+unsafe fn copy_embeddings(result: &ei_impulse_result_t) -> Vec<Embedding> {
+    let count = ei_ffi_freeform_output_count(result);
+    (0..count)
+        .filter_map(|index| {
+            let mut data: *const f32 = std::ptr::null();
+            let mut len = 0;
+            if ei_ffi_freeform_output_get(result, index, &mut data, &mut len)
+                != EI_IMPULSE_ERROR::EI_IMPULSE_OK
+                || data.is_null()
+            {
+                return None;
+            }
+            Some(Embedding {
+                vector: std::slice::from_raw_parts(data, len).to_vec(),
+                dtype: OutputDType::model(),
+            })
+        })
+        .collect()
+}
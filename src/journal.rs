@@ -0,0 +1,108 @@
+//! Journal of recent inference errors
+//!
+//! Every failed classifier call is recorded in a bounded in-memory ring buffer, so headless
+//! devices can report why classifications have been failing without keeping verbose logging
+//! on. Read it with [`error_journal`].
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use crate::error::EdgeImpulseError;
+
+/// Number of errors kept by default
+pub const DEFAULT_ERROR_JOURNAL_CAPACITY: usize = 32;
+
+/// A failed classifier call
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// Error returned by the SDK
+    pub error: EdgeImpulseError,
+    /// Raw `EI_IMPULSE_ERROR` code
+    pub code: i32,
+    /// Pipeline stage the error occurred in, see [`EdgeImpulseError::stage`]
+    pub stage: &'static str,
+    /// When the call failed
+    pub timestamp: SystemTime,
+    /// Number of features passed to the call
+    pub input_len: usize,
+}
+
+/// Recorded errors, oldest first
+pub fn error_journal() -> Vec<JournalEntry> {
+    lock_journal().entries.iter().cloned().collect()
+}
+
+/// Remove all recorded errors
+pub fn clear_error_journal() {
+    lock_journal().entries.clear();
+}
+
+/// Change how many errors are kept, dropping the oldest ones if needed
+///
+/// A capacity of 0 disables the journal.
+pub fn set_error_journal_capacity(capacity: usize) {
+    let mut journal = lock_journal();
+    journal.capacity = capacity;
+    let excess = journal.entries.len().saturating_sub(capacity);
+    journal.entries.drain(..excess);
+}
+
+/// Record a failed classifier call
+pub(crate) fn record(error: &EdgeImpulseError, input_len: usize) {
+    let mut journal = lock_journal();
+    if journal.capacity == 0 {
+        return;
+    }
+    if journal.entries.len() == journal.capacity {
+        journal.entries.pop_front();
+    }
+    journal.entries.push_back(JournalEntry {
+        error: *error,
+        code: error.code(),
+        stage: error.stage(),
+        timestamp: SystemTime::now(),
+        input_len,
+    });
+}
+
+struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+}
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal {
+    entries: VecDeque::new(),
+    capacity: DEFAULT_ERROR_JOURNAL_CAPACITY,
+});
+
+fn lock_journal() -> MutexGuard<'static, Journal> {
+    // Entries are only pushed and popped, so the journal is usable after a panic
+    JOURNAL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_errors() {
+        set_error_journal_capacity(2);
+        clear_error_journal();
+        record(&EdgeImpulseError::InvalidSize, 10);
+        record(&EdgeImpulseError::DspError(None), 20);
+        record(&EdgeImpulseError::TfliteArenaAllocFailed, 30);
+
+        let journal = error_journal();
+        assert_eq!(journal.len(), 2);
+        assert_eq!(journal[0].code, -5);
+        assert_eq!(journal[0].stage, "dsp");
+        assert_eq!(journal[1].error, EdgeImpulseError::TfliteArenaAllocFailed);
+        assert_eq!(journal[1].input_len, 30);
+
+        set_error_journal_capacity(0);
+        record(&EdgeImpulseError::InvalidSize, 10);
+        assert!(error_journal().is_empty());
+        set_error_journal_capacity(DEFAULT_ERROR_JOURNAL_CAPACITY);
+    }
+}
@@ -2,11 +2,183 @@
 //!
 //! This crate provides safe Rust bindings for the Edge Impulse C++ SDK,
 //! allowing you to run inference on trained models from Rust applications.
+//!
+//! [`bindings`], [`model_metadata`] and [`thresholds`] are generated from the linked model and
+//! are not semver-stable: their items can change whenever the model changes. Libraries that
+//! build on this crate should use [`stable`], whose types are the same for every model.
+//!
+//! With the `no_std` feature the crate builds without the standard library for bare-metal
+//! targets, and only the bindings, the metadata, [`error`], [`signal`], [`timing`] and
+//! `bare_metal` are available.
+
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
+
+// These modules are generated into OUT_DIR by build.rs (unstable, they follow the model)
+#[allow(
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    clippy::missing_safety_doc
+)]
+pub mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+pub mod model_metadata {
+    include!(concat!(env!("OUT_DIR"), "/model_metadata.rs"));
+}
+#[cfg(not(feature = "no_std"))]
+pub mod thresholds {
+    include!(concat!(env!("OUT_DIR"), "/thresholds.rs"));
+}
+
+// Bindings and metadata are generated from the same model in one build script run; different
+// hashes mean one of them is left over from a previous model
+const _: () = assert!(
+    bindings::EI_FFI_MODEL_HASH == model_metadata::EI_CLASSIFIER_MODEL_HASH,
+    "bindings.rs and model_metadata.rs come from different models, run `cargo clean -p edge-impulse-ffi-rs`"
+);
 
-// These modules are auto-generated by the build.rs scripts
-pub mod bindings;
-pub mod model_metadata;
-pub mod thresholds;
+// Errors reported by the SDK
+pub mod error;
+
+// Borrowed input signals
+pub mod signal;
+
+// Units and clock of the SDK's timings
+pub mod timing;
+
+// Heapless classifier for bare-metal targets
+#[cfg(feature = "no_std")]
+pub mod bare_metal;
 
 // Re-export the bindings for convenience
 pub use bindings::*;
+pub use error::{DspErrorCode, EdgeImpulseError};
+pub use signal::Signal;
+
+/// Items that need the standard library, left out with the `no_std` feature
+macro_rules! std_only {
+    ($($item:item)*) => {
+        $(
+            #[cfg(not(feature = "no_std"))]
+            $item
+        )*
+    };
+}
+
+/// Profiler scope until the end of the enclosing block, with the `profiling` feature
+#[cfg(not(feature = "no_std"))]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        profiling::scope!($name);
+    };
+}
+
+std_only! {
+    // Extra models from EI_MODEL_<NAME>, with their own bindings and classifiers
+    pub mod models;
+
+    // Model metadata as a single value
+    pub mod metadata;
+
+    // Quantization parameters of the model's tensors
+    pub mod quantization;
+
+    // Safe, owned result types
+    pub mod inference;
+
+    // Safe classifier handle
+    pub mod classifier;
+
+    // Cooperative cancellation of running inference
+    pub mod cancel;
+
+    // Native debug output sinks
+    pub mod logging;
+
+    // Timer, sleep and allocator hooks for the SDK's porting layer
+    pub mod porting;
+
+    // Journal of recent inference errors
+    pub mod journal;
+
+    // Audio preprocessing
+    pub mod audio;
+
+    // Fixed-size feature buffers
+    pub mod features;
+
+    // Precomputed DSP features from outside the SDK
+    pub mod feature_provider;
+
+    // Image preprocessing
+    pub mod preprocessing;
+
+    // Tiled inference on large images
+    pub mod tiling;
+
+    // Input scaling checks
+    pub mod scaling;
+
+    // FOMO centroid post-processing
+    pub mod fomo;
+
+    // Export of detection timelines
+    pub mod timeline;
+
+    // Multi-frame feature assembly
+    pub mod frames;
+
+    // Similarity search over embeddings
+    pub mod similarity;
+
+    // Live result dashboard
+    #[cfg(feature = "ws")]
+    pub mod dashboard;
+
+    // Runner-compatible model API
+    pub mod runner_api;
+
+    // Model-independent facade for libraries
+    pub mod stable;
+
+    // Tolerant result comparison for tests
+    pub mod testing;
+
+    // Change events between consecutive results
+    pub mod diff;
+
+    // Minimum-duration and refractory gating of label events
+    pub mod gating;
+
+    // Merging of duplicate detections across tiles and sources
+    pub mod dedup;
+
+    pub use cancel::CancellationToken;
+    pub use classifier::{
+        instance_policy, set_instance_policy, AllocRetry, AlreadyInitialized, Classifier,
+        ImpulseHandle, InstancePolicy,
+    };
+    pub use dedup::{Dedup, MergeMethod};
+    pub use diff::{ChangeEvent, DiffThresholds, ResultDiff};
+    pub use feature_provider::{FeatureProvider, ProviderError, ReaderFeatures};
+    pub use features::{Features, ModelFeatures};
+    pub use gating::{EventGate, GateRule, GatedEvent};
+    pub use frames::{FrameLayout, FrameStacker};
+    pub use inference::{
+        BoundingBox, Classification, Embedding, InferenceOutput, OutputDType, Timing, TrackedObject,
+        VisualAnomaly,
+    };
+    pub use journal::{clear_error_journal, error_journal, set_error_journal_capacity, JournalEntry};
+    pub use logging::{last_native_logs, set_native_log_callback, set_native_log_sink, NativeLogSink};
+    pub use metadata::ModelMetadata;
+    pub use preprocessing::{features_from_rgb_bytes, features_from_rgba_bytes, ResizeMode};
+    pub use quantization::{QuantizationInfo, TensorQuantization};
+    #[cfg(feature = "serde")]
+    pub use runner_api::DeploymentConfig;
+    pub use runner_api::{EimError, EimModel, EimModelBuilder, ModelParameters, SyncModel};
+    pub use scaling::{InputScaling, ScalingCheck, ScalingMismatch};
+    pub use similarity::{CentroidSet, Metric};
+    pub use tiling::Tiling;
+}
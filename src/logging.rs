@@ -0,0 +1,353 @@
+//! Native debug output sinks
+//!
+//! The C++ SDK prints its debug output (e.g. with `debug = true`) through `ei_printf`, which
+//! writes to stdout by default. Deployments without an attached console can route that output
+//! to a rotating log file or keep the most recent lines in memory with
+//! [`set_native_log_sink`], and read them back with [`last_native_logs`]. Applications that
+//! have their own logging can receive every line with [`set_native_log_callback`].
+
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+use crate::bindings::ei_ffi_set_log_callback;
+
+/// Where the SDK's debug output goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum NativeLogSink {
+    /// Print to stdout, like the C++ SDK does by default
+    Stdout,
+    /// Append to a file, rotating it once it grows past `max_bytes`
+    ///
+    /// Rotated files are renamed to `<path>.1`, `<path>.2`, ... and at most `max_files` of them
+    /// are kept.
+    File {
+        /// Log file path
+        path: PathBuf,
+        /// Size at which the file is rotated
+        max_bytes: u64,
+        /// Number of rotated files to keep
+        max_files: usize,
+    },
+    /// Keep the last `capacity` lines in memory, retrievable with [`last_native_logs`]
+    RingBuffer {
+        /// Number of lines to keep
+        capacity: usize,
+    },
+}
+
+/// Route the SDK's debug output to `sink`
+///
+/// Replaces the previous sink, discarding any lines buffered in memory.
+pub fn set_native_log_sink(sink: NativeLogSink) -> io::Result<()> {
+    let output = match sink {
+        NativeLogSink::Stdout => None,
+        NativeLogSink::File {
+            path,
+            max_bytes,
+            max_files,
+        } => Some(Output::File(RotatingFile::open(
+            path, max_bytes, max_files,
+        )?)),
+        NativeLogSink::RingBuffer { capacity } => Some(Output::RingBuffer {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }),
+    };
+
+    install(output);
+    Ok(())
+}
+
+/// Send every line of SDK debug output to `callback`
+///
+/// Replaces the sink set with [`set_native_log_sink`]. The callback runs on the thread that
+/// runs inference, one call at a time, and may change the sink. Lines it logs itself are
+/// dropped rather than passed back to it, and a panic in it drops the rest of its lines.
+///
+/// The callback must not call into the classifier: it runs while inference holds the
+/// process-wide classifier lock, so running inference from it deadlocks.
+pub fn set_native_log_callback<F>(callback: F)
+where
+    F: Fn(&str) + Send + 'static,
+{
+    install(Some(Output::Callback(Arc::new(Mutex::new(Box::new(
+        callback,
+    ))))));
+}
+
+/// Boxed callback passed to [`set_native_log_callback`]
+pub(crate) type LogCallback = Box<dyn Fn(&str) + Send>;
+
+fn install(output: Option<Output>) {
+    let mut state = lock_state();
+    let callback = output.as_ref().map(|_| on_native_log as _);
+    *state = output.map(|output| LogState {
+        output,
+        partial: HashMap::new(),
+    });
+    unsafe {
+        ei_ffi_set_log_callback(callback);
+    }
+}
+
+/// Lines of SDK debug output kept by the [`NativeLogSink::RingBuffer`] sink, oldest first
+///
+/// Includes the trailing lines the SDK hasn't finished yet. Empty for other sinks.
+pub fn last_native_logs() -> Vec<String> {
+    let state = lock_state();
+    match state.as_ref() {
+        Some(LogState {
+            output: Output::RingBuffer { lines, .. },
+            partial,
+        }) => {
+            let mut logs: Vec<String> = lines.iter().cloned().collect();
+            logs.extend(partial.values().cloned());
+            logs
+        }
+        _ => Vec::new(),
+    }
+}
+
+struct LogState {
+    output: Output,
+    // The SDK prints lines in several chunks, so collect them until the newline arrives, per
+    // thread since impulse handles on different threads interleave their output
+    partial: HashMap<ThreadId, String>,
+}
+
+enum Output {
+    // Called outside the state lock, so it has a lock of its own
+    Callback(Arc<Mutex<LogCallback>>),
+    File(RotatingFile),
+    RingBuffer {
+        lines: VecDeque<String>,
+        capacity: usize,
+    },
+}
+
+impl Output {
+    /// Write `lines`, or hand them back for the callback once the state lock is released
+    fn write_lines(&mut self, new_lines: Vec<String>) -> Option<Pending> {
+        match self {
+            Output::Callback(callback) => {
+                return Some(Pending {
+                    callback: Arc::clone(callback),
+                    lines: new_lines,
+                })
+            }
+            // There is nobody to report write errors to from inside the SDK
+            Output::File(file) => {
+                for line in &new_lines {
+                    let _ = file.write_line(line);
+                }
+            }
+            Output::RingBuffer { lines, capacity } => {
+                for line in new_lines.into_iter().filter(|_| *capacity > 0) {
+                    if lines.len() == *capacity {
+                        lines.pop_front();
+                    }
+                    lines.push_back(line);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Lines waiting for the log callback
+struct Pending {
+    callback: Arc<Mutex<LogCallback>>,
+    lines: Vec<String>,
+}
+
+thread_local! {
+    // Set while this thread runs the log callback
+    static IN_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Pending {
+    /// Call the callback with the state lock released, so it can log or change the sink
+    fn deliver(self) {
+        // Lines logged by the callback itself would call it again
+        if IN_CALLBACK.with(Cell::get) {
+            return;
+        }
+        let _guard = CallbackGuard::enter();
+        // Unwinding into C++ is undefined behaviour, a panicking callback loses its lines
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            let callback = self.callback.lock().unwrap_or_else(PoisonError::into_inner);
+            for line in &self.lines {
+                callback(line);
+            }
+        }));
+    }
+}
+
+/// Marks this thread as running the log callback until dropped
+struct CallbackGuard;
+
+impl CallbackGuard {
+    fn enter() -> Self {
+        IN_CALLBACK.with(|flag| flag.set(true));
+        Self
+    }
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        IN_CALLBACK.with(|flag| flag.set(false));
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+static STATE: Mutex<Option<LogState>> = Mutex::new(None);
+
+fn lock_state() -> MutexGuard<'static, Option<LogState>> {
+    // A panic while logging leaves the state usable, so recover from poisoning
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Pass on the line the SDK hasn't finished, once a classifier call has returned
+///
+/// Some debug output (e.g. the raw features) doesn't end with a newline, and would otherwise
+/// only reach the sink with the output of the next inference.
+pub(crate) fn flush_native_log() {
+    let pending = lock_state().as_mut().and_then(|state| {
+        let line = state.partial.remove(&thread::current().id())?;
+        state.output.write_lines(vec![line])
+    });
+    if let Some(pending) = pending {
+        pending.deliver();
+    }
+}
+
+/// Write a line of debug output from the Rust side to the same sink as the SDK's output
+///
+/// Dropped without a sink or callback: the crate doesn't write to the application's stdout.
+pub(crate) fn log_line(line: &str) {
+    let pending = lock_state()
+        .as_mut()
+        .and_then(|state| state.output.write_lines(vec![line.to_string()]));
+    if let Some(pending) = pending {
+        pending.deliver();
+    }
+}
+
+unsafe extern "C" fn on_native_log(message: *const c_char) {
+    if message.is_null() {
+        return;
+    }
+    let message = CStr::from_ptr(message).to_string_lossy();
+
+    let pending = {
+        let mut state = lock_state();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+        let thread = thread::current().id();
+        let partial = state.partial.entry(thread).or_default();
+        partial.push_str(&message);
+        let mut lines = Vec::new();
+        while let Some(end) = partial.find('\n') {
+            lines.push(partial[..end].trim_end_matches('\r').to_string());
+            partial.drain(..=end);
+        }
+        if partial.is_empty() {
+            state.partial.remove(&thread);
+        }
+        if lines.is_empty() {
+            return;
+        }
+        state.output.write_lines(lines)
+    };
+    if let Some(pending) = pending {
+        pending.deliver();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_can_change_the_sink() {
+        set_native_log_callback(|line| {
+            log_line("logged from the callback");
+            if line == "switch" {
+                set_native_log_sink(NativeLogSink::RingBuffer { capacity: 2 }).unwrap();
+            }
+        });
+        log_line("switch");
+        log_line("after");
+        assert_eq!(last_native_logs(), vec!["after".to_string()]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Model metadata as a value
+//!
+//! [`model_metadata`](crate::model_metadata) exposes the model's `model_metadata.h` as loose
+//! constants, whose names and types follow the header. [`ModelMetadata`] collects the commonly
+//! needed ones into a single struct that can be passed around, logged or serialized.
+
+use std::sync::OnceLock;
+
+use crate::model_metadata::*;
+use crate::preprocessing::ResizeMode;
+use crate::runner_api::{ModelThreshold, RunnerHelloHasAnomaly, SensorType};
+use crate::thresholds::MODEL_THRESHOLDS;
+
+/// Metadata of the linked model
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModelMetadata {
+    /// Studio project ID
+    pub project_id: u32,
+    /// Owner of the Studio project
+    pub project_owner: String,
+    /// Name of the Studio project
+    pub project_name: String,
+    /// License of the Studio project, if `ei-fetch` recorded one when downloading the model
+    pub project_license: Option<String>,
+    /// Deployment version the model was exported from
+    pub deploy_version: u32,
+    /// Model type ("classification", "regression", "object_detection" or "visual_anomaly")
+    pub model_type: String,
+    /// Type of sensor the model was trained on
+    pub sensor: SensorType,
+    /// Inferencing engine the model was compiled for (`EI_CLASSIFIER_INFERENCING_ENGINE`)
+    pub inferencing_engine: u32,
+    /// Number of features the DSP blocks take (`EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE`)
+    pub dsp_input_frame_size: u32,
+    /// Number of features the learning block takes (`EI_CLASSIFIER_NN_INPUT_FRAME_SIZE`)
+    pub nn_input_frame_size: u32,
+    /// Number of raw samples in one window
+    pub raw_sample_count: u32,
+    /// Number of axes per raw sample
+    pub raw_samples_per_frame: u32,
+    /// Names of the axes, in the order they're interleaved in the input
+    pub axis_names: Vec<String>,
+    /// Image input width (0 for non-image models)
+    pub input_width: u32,
+    /// Image input height (0 for non-image models)
+    pub input_height: u32,
+    /// Number of stacked image frames
+    pub input_frames: u32,
+    /// How images are fitted to the input size
+    pub resize_mode: ResizeMode,
+    /// Sampling frequency in Hz
+    pub frequency: f32,
+    /// Interval between raw samples in ms
+    pub interval_ms: f32,
+    /// Number of raw samples per slice in continuous mode
+    pub slice_size: u32,
+    /// Number of slices in one model window in continuous mode
+    pub slices_per_model_window: u32,
+    /// Class labels, in the order of the classification output
+    pub labels: Vec<String>,
+    /// Type of anomaly block
+    pub has_anomaly: RunnerHelloHasAnomaly,
+    /// Whether object tracking postprocessing is enabled
+    pub has_object_tracking: bool,
+    /// Thresholds of the learning blocks, as exported (see [`crate::thresholds`])
+    pub thresholds: Vec<ModelThreshold>,
+}
+
+impl ModelMetadata {
+    /// Metadata of the linked model, built from [`crate::model_metadata`] on first use
+    pub fn get() -> &'static ModelMetadata {
+        static METADATA: OnceLock<ModelMetadata> = OnceLock::new();
+        METADATA.get_or_init(Self::from_constants)
+    }
+
+    /// License of the Studio project the model comes from, e.g. `"Apache-2.0"`
+    ///
+    /// Products embedding someone else's (public) project can show it together with
+    /// [`project_owner`](Self::project_owner) to meet attribution requirements. `None` when the
+    /// model wasn't downloaded with `ei-fetch` or the project has no license set.
+    pub fn license(&self) -> Option<&str> {
+        self.project_license.as_deref()
+    }
+
+    // The constants' types follow the model, so some casts are no-ops for some models
+    #[allow(clippy::unnecessary_cast)]
+    fn from_constants() -> Self {
+        let model_type = if EI_CLASSIFIER_IS_VISUAL_ANOMALY {
+            "visual_anomaly"
+        } else if EI_CLASSIFIER_OBJECT_DETECTION != 0 {
+            "object_detection"
+        } else if EI_CLASSIFIER_IS_REGRESSION {
+            "regression"
+        } else {
+            "classification"
+        };
+
+        Self {
+            project_id: EI_CLASSIFIER_PROJECT_ID as u32,
+            project_owner: EI_CLASSIFIER_PROJECT_OWNER.to_string(),
+            project_name: EI_CLASSIFIER_PROJECT_NAME.to_string(),
+            project_license: EI_CLASSIFIER_PROJECT_LICENSE.map(str::to_string),
+            deploy_version: EI_CLASSIFIER_PROJECT_DEPLOY_VERSION as u32,
+            model_type: model_type.to_string(),
+            sensor: SensorType::from(EI_CLASSIFIER_SENSOR),
+            inferencing_engine: EI_CLASSIFIER_INFERENCING_ENGINE as u32,
+            dsp_input_frame_size: EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE as u32,
+            nn_input_frame_size: EI_CLASSIFIER_NN_INPUT_FRAME_SIZE as u32,
+            raw_sample_count: EI_CLASSIFIER_RAW_SAMPLE_COUNT as u32,
+            raw_samples_per_frame: EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME as u32,
+            axis_names: EI_CLASSIFIER_AXIS_NAMES
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            input_width: EI_CLASSIFIER_INPUT_WIDTH as u32,
+            input_height: EI_CLASSIFIER_INPUT_HEIGHT as u32,
+            input_frames: EI_CLASSIFIER_INPUT_FRAMES as u32,
+            resize_mode: ResizeMode::model(),
+            frequency: EI_CLASSIFIER_FREQUENCY as f32,
+            interval_ms: EI_CLASSIFIER_INTERVAL_MS as f32,
+            slice_size: EI_CLASSIFIER_SLICE_SIZE as u32,
+            slices_per_model_window: EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW as u32,
+            labels: EI_CLASSIFIER_LABELS.iter().map(|l| l.to_string()).collect(),
+            has_anomaly: RunnerHelloHasAnomaly::from(EI_CLASSIFIER_HAS_ANOMALY as u32),
+            has_object_tracking: EI_CLASSIFIER_OBJECT_TRACKING_ENABLED != 0,
+            thresholds: MODEL_THRESHOLDS.iter().map(ModelThreshold::from).collect(),
+        }
+    }
+}
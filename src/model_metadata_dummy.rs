@@ -0,0 +1,95 @@
+// Stand-in for the generated model_metadata.rs, used by the `dummy` feature and on docs.rs.
+// Describes a small 3-axis accelerometer classification model; nothing is linked against it.
+
+pub const EI_CLASSIFIER_PROJECT_ID: usize = 0;
+pub const EI_CLASSIFIER_PROJECT_OWNER: &str = "Edge Impulse";
+pub const EI_CLASSIFIER_PROJECT_NAME: &str = "dummy";
+pub const EI_CLASSIFIER_PROJECT_DEPLOY_VERSION: usize = 0;
+
+pub const EI_CLASSIFIER_SENSOR_UNKNOWN: i32 = -1;
+pub const EI_CLASSIFIER_SENSOR_MICROPHONE: usize = 1;
+pub const EI_CLASSIFIER_SENSOR_ACCELEROMETER: usize = 2;
+pub const EI_CLASSIFIER_SENSOR_CAMERA: usize = 3;
+pub const EI_CLASSIFIER_SENSOR_9DOF: usize = 4;
+pub const EI_CLASSIFIER_SENSOR_ENVIRONMENTAL: usize = 5;
+pub const EI_CLASSIFIER_SENSOR_FUSION: usize = 6;
+pub const EI_CLASSIFIER_SENSOR: i32 = 2;
+
+pub const EI_CLASSIFIER_TFLITE: usize = 3;
+pub const EI_CLASSIFIER_INFERENCING_ENGINE: usize = 3;
+
+pub const EI_CLASSIFIER_NN_INPUT_FRAME_SIZE: usize = 33;
+pub const EI_CLASSIFIER_RAW_SAMPLE_COUNT: usize = 125;
+pub const EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME: usize = 3;
+pub const EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE: usize = 375;
+pub const EI_CLASSIFIER_INPUT_WIDTH: usize = 0;
+pub const EI_CLASSIFIER_INPUT_HEIGHT: usize = 0;
+pub const EI_CLASSIFIER_INPUT_FRAMES: usize = 0;
+pub const EI_CLASSIFIER_INTERVAL_MS: f32 = 16.0;
+pub const EI_CLASSIFIER_LABEL_COUNT: usize = 3;
+pub const EI_CLASSIFIER_HAS_ANOMALY: usize = 0;
+pub const EI_CLASSIFIER_FREQUENCY: f32 = 62.5;
+pub const EI_CLASSIFIER_HAS_VISUAL_ANOMALY: usize = 1;
+pub const EI_CLASSIFIER_OBJECT_DETECTION: usize = 0;
+pub const EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW: usize = 4;
+pub const EI_CLASSIFIER_SLICE_SIZE: usize = 31;
+
+pub const EI_ANOMALY_TYPE_UNKNOWN: usize = 0;
+pub const EI_ANOMALY_TYPE_KMEANS: usize = 1;
+pub const EI_ANOMALY_TYPE_GMM: usize = 2;
+pub const EI_ANOMALY_TYPE_VISUAL_GMM: usize = 3;
+
+pub const EI_CLASSIFIER_RESIZE_NONE: usize = 0;
+pub const EI_CLASSIFIER_RESIZE_FIT_SHORTEST: usize = 1;
+pub const EI_CLASSIFIER_RESIZE_FIT_LONGEST: usize = 2;
+pub const EI_CLASSIFIER_RESIZE_SQUASH: usize = 3;
+pub const EI_CLASSIFIER_RESIZE_MODE: usize = EI_CLASSIFIER_RESIZE_NONE;
+pub const EI_CLASSIFIER_LAST_LAYER_YOLOV5: usize = 0;
+pub const EI_CLASSIFIER_OBJECT_TRACKING_ENABLED: usize = 0;
+
+pub const EI_CLASSIFIER_TFLITE_INPUT_DATATYPE: usize = 1;
+pub const EI_CLASSIFIER_TFLITE_INPUT_QUANTIZED: usize = 0;
+pub const EI_CLASSIFIER_TFLITE_INPUT_SCALE: f32 = 1.0;
+pub const EI_CLASSIFIER_TFLITE_INPUT_ZEROPOINT: i32 = 0;
+pub const EI_CLASSIFIER_TFLITE_OUTPUT_DATATYPE: usize = 1;
+pub const EI_CLASSIFIER_TFLITE_OUTPUT_QUANTIZED: usize = 0;
+pub const EI_CLASSIFIER_TFLITE_OUTPUT_SCALE: f32 = 1.0;
+pub const EI_CLASSIFIER_TFLITE_OUTPUT_ZEROPOINT: i32 = 0;
+
+/// License of the Studio project, as recorded by ei-fetch (`None` if unknown)
+pub const EI_CLASSIFIER_PROJECT_LICENSE: Option<&str> = None;
+
+/// Whether the model's input tensor is quantized
+pub const EI_CLASSIFIER_INPUT_QUANTIZED: bool = false;
+/// Scale of the input tensor (1.0 when it isn't quantized)
+pub const EI_CLASSIFIER_INPUT_SCALE: f32 = 1.0;
+/// Zero point of the input tensor (0 when it isn't quantized)
+pub const EI_CLASSIFIER_INPUT_ZERO_POINT: i32 = 0;
+
+/// Whether the model's output tensor is quantized
+pub const EI_CLASSIFIER_OUTPUT_QUANTIZED: bool = false;
+/// Scale of the output tensor (1.0 when it isn't quantized)
+pub const EI_CLASSIFIER_OUTPUT_SCALE: f32 = 1.0;
+/// Zero point of the output tensor (0 when it isn't quantized)
+pub const EI_CLASSIFIER_OUTPUT_ZERO_POINT: i32 = 0;
+
+/// Class labels, in the order of the model's classification output
+pub const EI_CLASSIFIER_LABELS: &[&str] = &["idle", "circle", "wave"];
+
+/// Names of the raw input axes, in the order the model expects them
+pub const EI_CLASSIFIER_AXIS_NAMES: &[&str] = &["accX", "accY", "accZ"];
+
+/// Types of the impulse's DSP blocks (e.g. "image", "mfcc"), in order
+pub const EI_CLASSIFIER_DSP_BLOCKS: &[&str] = &["spectral-analysis"];
+
+/// Scale of the raw features the first DSP block expects: "packed_rgb", "raw_i16" or "raw"
+pub const EI_CLASSIFIER_INPUT_SCALING: &str = "raw";
+
+/// Whether the model's learning block is a regression block with a single output value
+pub const EI_CLASSIFIER_IS_REGRESSION: bool = false;
+
+/// Whether the model's anomaly block is a visual anomaly (FOMO-AD) block
+pub const EI_CLASSIFIER_IS_VISUAL_ANOMALY: bool = false;
+
+/// Hash of the model this metadata was generated from, see `EI_FFI_MODEL_HASH`
+pub const EI_CLASSIFIER_MODEL_HASH: u64 = 0;
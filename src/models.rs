@@ -0,0 +1,258 @@
+//! Extra models compiled into the same binary
+//!
+//! Every `EI_MODEL_<NAME>` variable set at build time adds a model next to the one in
+//! `model/`, e.g. a keyword spotting model next to an image model. Each gets a module here,
+//! named after `<NAME>` in lower case, with the model's own `bindings` and `model_metadata`
+//! and a `Classifier`:
+//!
+//! ```ignore
+//! use edge_impulse_ffi_rs::models::{camera, wake_word};
+//!
+//! let mut wake_word = wake_word::Classifier::new();
+//! let mut camera = camera::Classifier::new();
+//! let heard = wake_word.run(&audio, false)?;
+//! let seen = camera.run(&pixels, false)?;
+//! ```
+//!
+//! Extra models are built with TensorFlow Lite Micro into their own static library, with every
+//! symbol hidden except their entry points, which are renamed to `ei_ffi_<name>_*`. Their SDK
+//! copies therefore can't clash with each other or with the main model. The rest of the crate
+//! (logging, porting hooks, cancellation, thresholds, [`runner_api`](crate::runner_api)) drives
+//! the main model only.
+//!
+//! With the `dummy` feature this module holds a single stand-in model, `dummy`, whose
+//! inference always fails like the main dummy model's.
+
+/// Modules of an extra model generated by build.rs into `OUT_DIR/models/<name>/`
+macro_rules! extra_model {
+    ($name:literal) => {
+        /// Bindings of this model, linked to its renamed `ei_ffi_<name>_*` entry points
+        #[allow(
+            non_camel_case_types,
+            non_snake_case,
+            non_upper_case_globals,
+            dead_code,
+            clippy::missing_safety_doc
+        )]
+        pub mod bindings {
+            include!(concat!(env!("OUT_DIR"), "/models/", $name, "/bindings.rs"));
+        }
+
+        /// Metadata constants of this model
+        pub mod model_metadata {
+            include!(concat!(
+                env!("OUT_DIR"),
+                "/models/",
+                $name,
+                "/model_metadata.rs"
+            ));
+        }
+
+        const _: () = assert!(
+            bindings::EI_FFI_MODEL_HASH == model_metadata::EI_CLASSIFIER_MODEL_HASH,
+            "the bindings and metadata of an extra model come from different models, run `cargo clean -p edge-impulse-ffi-rs`"
+        );
+
+        static ALIVE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+        /// Handle to this model's classifier
+        ///
+        /// The SDK keeps one global state per model, so only one classifier of a model can be
+        /// alive at a time. Classifiers of different models are independent.
+        pub struct Classifier {
+            // The SDK's global state must not be touched from several threads at once
+            _not_sync: std::marker::PhantomData<std::cell::Cell<()>>,
+        }
+
+        impl Classifier {
+            /// Initialize the classifier
+            ///
+            /// # Panics
+            ///
+            /// Panics if a classifier of this model is already alive, see
+            /// [`try_new`](Self::try_new).
+            #[allow(clippy::new_without_default)]
+            pub fn new() -> Self {
+                Self::try_new().expect("failed to create classifier")
+            }
+
+            /// Initialize the classifier, failing if one of this model is already alive
+            ///
+            /// # Panics
+            ///
+            /// Panics if the linked library was built from another model than the metadata.
+            pub fn try_new() -> Result<Self, $crate::classifier::AlreadyInitialized> {
+                use std::sync::atomic::Ordering;
+                if ALIVE.swap(true, Ordering::AcqRel) {
+                    return Err($crate::classifier::AlreadyInitialized);
+                }
+                let linked = unsafe { bindings::ei_ffi_model_hash() };
+                if linked != model_metadata::EI_CLASSIFIER_MODEL_HASH {
+                    ALIVE.store(false, Ordering::Release);
+                    panic!(
+                        "the linked library of model '{}' was built from another model (model hash {:#018x}, expected {:#018x}), run `cargo clean -p edge-impulse-ffi-rs` and rebuild",
+                        $name,
+                        linked,
+                        model_metadata::EI_CLASSIFIER_MODEL_HASH
+                    );
+                }
+                unsafe { bindings::ei_ffi_run_classifier_init() };
+                Ok(Self {
+                    _not_sync: std::marker::PhantomData,
+                })
+            }
+
+            /// Run inference on a buffer of features
+            ///
+            /// The features must match this model's input
+            /// (`model_metadata::EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE`). Read the anomaly score
+            /// from `anomaly`: [`InferenceOutput::anomaly_score`] looks at the main model.
+            ///
+            /// [`InferenceOutput::anomaly_score`]: crate::InferenceOutput::anomaly_score
+            pub fn run(
+                &mut self,
+                features: &[f32],
+                debug: bool,
+            ) -> Result<$crate::InferenceOutput, $crate::EdgeImpulseError> {
+                let mut signal = bindings::ei_signal_t::default();
+                check(unsafe {
+                    bindings::ei_ffi_signal_from_buffer(
+                        features.as_ptr(),
+                        features.len(),
+                        &mut signal,
+                    )
+                })?;
+                let mut result = bindings::ei_impulse_result_t::default();
+                check(unsafe {
+                    bindings::ei_ffi_run_classifier(&mut signal, &mut result, debug as i32)
+                })?;
+                Ok(unsafe { output_from_raw(&result) })
+            }
+        }
+
+        impl Drop for Classifier {
+            fn drop(&mut self) {
+                unsafe { bindings::ei_ffi_run_classifier_deinit() };
+                ALIVE.store(false, std::sync::atomic::Ordering::Release);
+            }
+        }
+
+        /// Map this model's error codes to the crate's errors, with this model's DSP error
+        fn check(code: bindings::EI_IMPULSE_ERROR) -> Result<(), $crate::EdgeImpulseError> {
+            $crate::error::check($crate::bindings::EI_IMPULSE_ERROR(code.0)).map_err(|error| {
+                match error {
+                    $crate::EdgeImpulseError::DspError(None) => {
+                        let dsp = unsafe { bindings::ei_ffi_last_dsp_error() };
+                        $crate::EdgeImpulseError::DspError($crate::DspErrorCode::from_code(dsp))
+                    }
+                    other => other,
+                }
+            })
+        }
+
+        /// Copy a result of this model, see [`InferenceOutput::from_raw`]
+        ///
+        /// [`InferenceOutput::from_raw`]: crate::InferenceOutput::from_raw
+        unsafe fn output_from_raw(result: &bindings::ei_impulse_result_t) -> $crate::InferenceOutput {
+            use $crate::inference::{
+                label_to_string, BoundingBox, Classification, Embedding, OutputDType,
+                TrackedObject, Timing, VisualAnomaly,
+            };
+
+            let boxes = |boxes: *const bindings::ei_impulse_result_bounding_box_t, count: u32| {
+                if boxes.is_null() || count == 0 {
+                    return Vec::new();
+                }
+                std::slice::from_raw_parts(boxes, count as usize)
+                    .iter()
+                    // The SDK marks unused slots with a zero score
+                    .filter(|bb| bb.value > 0.0)
+                    .map(|bb| BoundingBox {
+                        label: label_to_string(bb.label),
+                        value: bb.value,
+                        x: bb.x,
+                        y: bb.y,
+                        width: bb.width,
+                        height: bb.height,
+                    })
+                    .collect()
+            };
+
+            let label_count =
+                (model_metadata::EI_CLASSIFIER_LABEL_COUNT as usize).min(result.classification.len());
+            let classification = result.classification[..label_count]
+                .iter()
+                .filter(|c| !c.label.is_null())
+                .map(|c| Classification {
+                    label: label_to_string(c.label),
+                    value: c.value,
+                })
+                .collect();
+
+            let object_tracking = (0..bindings::ei_ffi_object_tracking_count(result))
+                .filter_map(|index| {
+                    let mut object = bindings::ei_ffi_tracked_object_t::default();
+                    if bindings::ei_ffi_object_tracking_get(result, index, &mut object).0 != 0 {
+                        return None;
+                    }
+                    Some(TrackedObject {
+                        id: object.id,
+                        label: label_to_string(object.label),
+                        value: object.value,
+                        x: object.x,
+                        y: object.y,
+                        width: object.width,
+                        height: object.height,
+                    })
+                })
+                .collect();
+
+            let dtype = if model_metadata::EI_CLASSIFIER_OUTPUT_QUANTIZED {
+                OutputDType::I8
+            } else {
+                OutputDType::F32
+            };
+            let embeddings = (0..bindings::ei_ffi_freeform_output_count(result))
+                .filter_map(|index| {
+                    let mut data: *const f32 = std::ptr::null();
+                    let mut len = 0;
+                    if bindings::ei_ffi_freeform_output_get(result, index, &mut data, &mut len).0
+                        != 0
+                        || data.is_null()
+                    {
+                        return None;
+                    }
+                    Some(Embedding {
+                        vector: std::slice::from_raw_parts(data, len).to_vec(),
+                        dtype,
+                    })
+                })
+                .collect();
+
+            // Mean and max are meaningful even when no grid cell crossed the threshold
+            let visual_anomaly = model_metadata::EI_CLASSIFIER_IS_VISUAL_ANOMALY.then(|| {
+                VisualAnomaly {
+                    grid: boxes(result.visual_ad_grid_cells, result.visual_ad_count),
+                    mean_value: result.visual_ad_result.mean_value,
+                    max_value: result.visual_ad_result.max_value,
+                }
+            });
+
+            $crate::InferenceOutput {
+                classification,
+                bounding_boxes: boxes(result.bounding_boxes, result.bounding_boxes_count),
+                object_tracking,
+                anomaly: result.anomaly,
+                visual_anomaly,
+                embeddings,
+                timing: Timing {
+                    dsp: $crate::timing::from_micros(result.timing.dsp_us),
+                    classification: $crate::timing::from_micros(result.timing.classification_us),
+                    anomaly: $crate::timing::from_micros(result.timing.anomaly_us),
+                },
+            }
+        }
+    };
+}
+
+include!(concat!(env!("OUT_DIR"), "/models.rs"));
@@ -0,0 +1,315 @@
+//! Porting layer hooks
+//!
+//! The C++ SDK reads the time, sleeps and allocates memory through its porting layer
+//! (`ei_read_timer_ms`, `ei_sleep`, `ei_malloc`, ...), which uses the POSIX implementation in
+//! this crate. [`set_porting_hooks`] replaces any of these functions, e.g. to use an RTOS tick
+//! counter or an instrumented allocator. Hooks are plain `extern "C"` functions, since the SDK
+//! calls them from C++:
+//!
+//! ```no_run
+//! use edge_impulse_ffi_rs::porting::{set_porting_hooks, PortingHooks};
+//!
+//! unsafe extern "C" fn read_timer_us() -> u64 {
+//!     // e.g. the RTOS tick counter
+//!     0
+//! }
+//!
+//! unsafe {
+//!     set_porting_hooks(PortingHooks {
+//!         read_timer_us: Some(read_timer_us),
+//!         ..Default::default()
+//!     });
+//! }
+//! ```
+//!
+//! Allocations the porting layer can't satisfy make the SDK fail with an opaque
+//! [`EdgeImpulseError::AllocFailed`] or [`EdgeImpulseError::TfliteArenaAllocFailed`].
+//! [`set_alloc_failure_hook`] reports each of them with its size and the classifier call it was
+//! made from, and can free memory and have the allocation retried. With the `fallback-heap`
+//! feature, [`PortingHooks::with_fallback_heap`] serves allocations `malloc` can't satisfy from
+//! memory reserved up front.
+//!
+//! [`EdgeImpulseError::AllocFailed`]: crate::EdgeImpulseError::AllocFailed
+//! [`EdgeImpulseError::TfliteArenaAllocFailed`]: crate::EdgeImpulseError::TfliteArenaAllocFailed
+
+#[cfg(feature = "fallback-heap")]
+use std::alloc::Layout;
+use std::ffi::c_void;
+#[cfg(feature = "fallback-heap")]
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "fallback-heap")]
+use std::ptr::{self, NonNull};
+#[cfg(feature = "fallback-heap")]
+use std::sync::{Mutex, MutexGuard};
+use std::sync::{PoisonError, RwLock};
+
+#[cfg(feature = "fallback-heap")]
+use linked_list_allocator::Heap;
+
+use crate::bindings::{
+    ei_ffi_porting_hooks_t, ei_ffi_set_alloc_failure_callback, ei_ffi_set_porting_hooks,
+};
+
+/// Replacements for the SDK's porting functions, `None` keeps the POSIX implementation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PortingHooks {
+    /// Monotonic time in microseconds; `ei_read_timer_ms` is derived from it
+    pub read_timer_us: Option<unsafe extern "C" fn() -> u64>,
+    /// Block for the given number of milliseconds (`ei_sleep`)
+    pub sleep_ms: Option<unsafe extern "C" fn(i32)>,
+    /// Allocate memory (`ei_malloc`)
+    pub malloc: Option<unsafe extern "C" fn(usize) -> *mut c_void>,
+    /// Allocate zeroed memory (`ei_calloc`); without it, `malloc` is used and the memory zeroed
+    pub calloc: Option<unsafe extern "C" fn(usize, usize) -> *mut c_void>,
+    /// Free memory from `malloc` or `calloc` (`ei_free`)
+    pub free: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+#[cfg(feature = "fallback-heap")]
+impl PortingHooks {
+    /// Serve SDK allocations that `malloc` can't satisfy from `memory`
+    ///
+    /// Replaces the allocation hooks with the system allocator, falling back to a first-fit heap
+    /// in `memory` (e.g. a buffer reserved at startup) when it returns NULL. Failed allocations
+    /// still reach the [`set_alloc_failure_hook`] once the fallback heap is exhausted too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fallback heap was already set up.
+    pub fn with_fallback_heap(mut self, memory: &'static mut [MaybeUninit<u8>]) -> Self {
+        let mut heap = lock_fallback_heap();
+        assert_eq!(heap.size(), 0, "the fallback heap is already set up");
+        heap.init_from_slice(memory);
+        self.malloc = Some(fallback_malloc);
+        self.calloc = Some(fallback_calloc);
+        self.free = Some(fallback_free);
+        self
+    }
+}
+
+/// Route the SDK's porting functions to `hooks`
+///
+/// Replaces all previously set hooks.
+///
+/// # Panics
+///
+/// Panics if an allocation hook is set without `free`, or `free` without an allocation hook.
+///
+/// # Safety
+///
+/// The SDK must not hold any memory while the allocator changes, since memory must be freed by
+/// the allocator it came from: set allocation hooks before the first
+/// [`Classifier`](crate::Classifier), `EimModel` or [`ImpulseHandle`](crate::ImpulseHandle) is
+/// created, and only reset them once all of them are dropped. The hooks are called from any
+/// thread that runs inference, and must not call back into the SDK.
+pub unsafe fn set_porting_hooks(hooks: PortingHooks) {
+    assert_eq!(
+        hooks.malloc.is_some() || hooks.calloc.is_some(),
+        hooks.free.is_some(),
+        "allocation hooks need a matching free hook"
+    );
+    let raw = ei_ffi_porting_hooks_t {
+        read_timer_us: hooks.read_timer_us,
+        sleep_ms: hooks.sleep_ms,
+        malloc_fn: hooks.malloc,
+        calloc_fn: hooks.calloc,
+        free_fn: hooks.free,
+    };
+    ei_ffi_set_porting_hooks(&raw);
+}
+
+/// Restore the POSIX implementation of all porting functions
+///
+/// # Safety
+///
+/// See [`set_porting_hooks`].
+pub unsafe fn reset_porting_hooks() {
+    ei_ffi_set_porting_hooks(std::ptr::null());
+}
+
+/// Alignment of fallback heap allocations, as `malloc` guarantees on 64-bit targets, and size of
+/// the header in front of them that records the block's size
+#[cfg(feature = "fallback-heap")]
+const FALLBACK_ALIGN: usize = 16;
+
+#[cfg(feature = "fallback-heap")]
+static FALLBACK_HEAP: Mutex<Heap> = Mutex::new(Heap::empty());
+
+#[cfg(feature = "fallback-heap")]
+fn lock_fallback_heap() -> MutexGuard<'static, Heap> {
+    FALLBACK_HEAP.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Allocate `size` bytes from the fallback heap, NULL once it's exhausted
+#[cfg(feature = "fallback-heap")]
+fn fallback_alloc(size: usize) -> *mut c_void {
+    let Some(layout) = size
+        .checked_add(FALLBACK_ALIGN)
+        .and_then(|total| Layout::from_size_align(total, FALLBACK_ALIGN).ok())
+    else {
+        return ptr::null_mut();
+    };
+    match lock_fallback_heap().allocate_first_fit(layout) {
+        Ok(block) => unsafe {
+            block.as_ptr().cast::<usize>().write(layout.size());
+            block.as_ptr().add(FALLBACK_ALIGN).cast()
+        },
+        Err(()) => ptr::null_mut(),
+    }
+}
+
+#[cfg(feature = "fallback-heap")]
+unsafe extern "C" fn fallback_malloc(size: usize) -> *mut c_void {
+    let ptr = libc::malloc(size);
+    if ptr.is_null() && size != 0 {
+        return fallback_alloc(size);
+    }
+    ptr
+}
+
+#[cfg(feature = "fallback-heap")]
+unsafe extern "C" fn fallback_calloc(nitems: usize, size: usize) -> *mut c_void {
+    let ptr = libc::calloc(nitems, size);
+    let Some(size) = nitems.checked_mul(size).filter(|&size| size != 0) else {
+        return ptr;
+    };
+    if !ptr.is_null() {
+        return ptr;
+    }
+    let ptr = fallback_alloc(size);
+    if !ptr.is_null() {
+        ptr.cast::<u8>().write_bytes(0, size);
+    }
+    ptr
+}
+
+#[cfg(feature = "fallback-heap")]
+unsafe extern "C" fn fallback_free(ptr: *mut c_void) {
+    let mut heap = lock_fallback_heap();
+    let ptr = ptr.cast::<u8>();
+    if ptr < heap.bottom() || ptr >= heap.top() {
+        drop(heap);
+        return libc::free(ptr.cast());
+    }
+    let block = ptr.sub(FALLBACK_ALIGN);
+    let layout = Layout::from_size_align_unchecked(block.cast::<usize>().read(), FALLBACK_ALIGN);
+    heap.deallocate(NonNull::new_unchecked(block), layout);
+}
+
+/// Classifier call an allocation was made from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AllocStage {
+    /// Initialization of the classifier or an impulse handle, e.g. the tensor arena
+    Init,
+    /// A full classifier call: DSP, learning blocks and postprocessing
+    Classify,
+    /// Learning blocks and postprocessing on precomputed features
+    Inference,
+    /// Outside of any classifier call
+    Other,
+}
+
+impl AllocStage {
+    fn from_raw(stage: c_int) -> Self {
+        match stage {
+            1 => AllocStage::Init,
+            2 => AllocStage::Classify,
+            3 => AllocStage::Inference,
+            _ => AllocStage::Other,
+        }
+    }
+}
+
+/// An allocation of the SDK that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocFailure {
+    /// Requested size in bytes
+    pub size: usize,
+    /// Classifier call the allocation was made from
+    pub stage: AllocStage,
+    /// Number of times the allocation was already retried
+    pub attempt: u32,
+}
+
+type AllocFailureHook = Box<dyn Fn(&AllocFailure) -> bool + Send + Sync>;
+
+static ALLOC_FAILURE_HOOK: RwLock<Option<AllocFailureHook>> = RwLock::new(None);
+
+/// Call `hook` whenever an allocation of the SDK's porting layer fails
+///
+/// Returning `true` retries the allocation, e.g. after the hook dropped caches; returning
+/// `false` lets it fail, so the classifier call returns an allocation error. The hook runs on the
+/// thread that runs inference, from inside the allocator: it must not allocate through the SDK
+/// or call back into it, and has to give up eventually.
+pub fn set_alloc_failure_hook<F>(hook: F)
+where
+    F: Fn(&AllocFailure) -> bool + Send + Sync + 'static,
+{
+    *ALLOC_FAILURE_HOOK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(Box::new(hook));
+    unsafe { ei_ffi_set_alloc_failure_callback(Some(on_alloc_failure)) }
+}
+
+/// Remove the hook set with [`set_alloc_failure_hook`], failed allocations fail right away
+pub fn clear_alloc_failure_hook() {
+    unsafe { ei_ffi_set_alloc_failure_callback(None) }
+    *ALLOC_FAILURE_HOOK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+unsafe extern "C" fn on_alloc_failure(size: usize, stage: c_int, attempt: u32) -> c_int {
+    let failure = AllocFailure {
+        size,
+        stage: AllocStage::from_raw(stage),
+        attempt,
+    };
+    // Unwinding into C++ is undefined behaviour, a panicking hook lets the allocation fail
+    let retry = panic::catch_unwind(AssertUnwindSafe(|| {
+        let hook = ALLOC_FAILURE_HOOK
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        hook.as_ref().is_some_and(|hook| hook(&failure))
+    }));
+    c_int::from(retry.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alloc_failure_hook_decides_retries() {
+        set_alloc_failure_hook(|failure| {
+            failure.stage == AllocStage::Classify && failure.attempt < 2
+        });
+        unsafe {
+            assert_eq!(on_alloc_failure(4096, 2, 0), 1);
+            assert_eq!(on_alloc_failure(4096, 2, 2), 0);
+            assert_eq!(on_alloc_failure(4096, 1, 0), 0);
+        }
+        clear_alloc_failure_hook();
+        assert_eq!(unsafe { on_alloc_failure(4096, 2, 0) }, 0);
+    }
+
+    #[cfg(feature = "fallback-heap")]
+    #[test]
+    fn fallback_heap_serves_and_frees_allocations() {
+        let memory = Box::leak(vec![MaybeUninit::uninit(); 4096].into_boxed_slice());
+        let hooks = PortingHooks::default().with_fallback_heap(memory);
+        let free = hooks.free.unwrap();
+
+        let block = fallback_alloc(1000);
+        assert!(!block.is_null());
+        assert_eq!(block as usize % FALLBACK_ALIGN, 0);
+        assert!(fallback_alloc(4096).is_null());
+        unsafe { free(block) };
+        assert_eq!(lock_fallback_heap().used(), 0);
+
+        // Memory from the system allocator goes back to it
+        unsafe { free(fallback_malloc(64)) };
+    }
+}
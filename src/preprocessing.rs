@@ -0,0 +1,483 @@
+//! Image preprocessing
+//!
+//! Image models expect a `EI_CLASSIFIER_INPUT_WIDTH` x `EI_CLASSIFIER_INPUT_HEIGHT` frame,
+//! resized the way the impulse was trained (`EI_CLASSIFIER_RESIZE_MODE`), with every pixel
+//! packed as `0xRRGGBB` into one `f32` feature. Grayscale models take the same packed RGB
+//! values and convert them in the SDK.
+//!
+//! With the `image` feature, [`resize_image`] and [`image_to_features`] perform these steps
+//! for an [`image::DynamicImage`], and [`EimModel::infer_image`](crate::EimModel::infer_image)
+//! runs inference on one directly.
+//!
+//! Frames that are already decoded in memory (e.g. from V4L2 or FFmpeg) can skip the `image`
+//! crate: [`features_from_rgb_bytes`] and [`features_from_rgba_bytes`] resize raw pixel rows
+//! with bilinear sampling and pack them without any extra dependency.
+
+use crate::metadata::ModelMetadata;
+use crate::model_metadata::{
+    EI_CLASSIFIER_INPUT_HEIGHT, EI_CLASSIFIER_INPUT_WIDTH, EI_CLASSIFIER_RESIZE_FIT_LONGEST,
+    EI_CLASSIFIER_RESIZE_FIT_SHORTEST, EI_CLASSIFIER_RESIZE_MODE, EI_CLASSIFIER_RESIZE_SQUASH,
+};
+use crate::runner_api::{EimError, SensorType};
+
+/// How an image is fitted to the model's input size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResizeMode {
+    /// No resizing; the center of the image is cropped to the input size
+    None,
+    /// Resize so the shortest side fits, then crop the center
+    FitShortest,
+    /// Resize so the longest side fits, then pad with black
+    FitLongest,
+    /// Resize to the input size, ignoring the aspect ratio
+    Squash,
+}
+
+impl ResizeMode {
+    /// Resize mode the linked model was trained with
+    pub fn model() -> Self {
+        Self::from_metadata(EI_CLASSIFIER_RESIZE_MODE)
+    }
+
+    /// Name used by the runner's model parameters ("none", "fit-short", "fit-long", "squash")
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResizeMode::None => "none",
+            ResizeMode::FitShortest => "fit-short",
+            ResizeMode::FitLongest => "fit-long",
+            ResizeMode::Squash => "squash",
+        }
+    }
+
+    /// Convert an `EI_CLASSIFIER_RESIZE_*` value
+    pub fn from_metadata(mode: usize) -> Self {
+        match mode {
+            m if m == EI_CLASSIFIER_RESIZE_FIT_SHORTEST => ResizeMode::FitShortest,
+            m if m == EI_CLASSIFIER_RESIZE_FIT_LONGEST => ResizeMode::FitLongest,
+            m if m == EI_CLASSIFIER_RESIZE_SQUASH => ResizeMode::Squash,
+            _ => ResizeMode::None,
+        }
+    }
+}
+
+/// Resize an RGB888 frame for the linked model and pack it into features
+///
+/// `stride` is the number of bytes per row, at least `width * 3`.
+pub fn features_from_rgb_bytes(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Result<Vec<f32>, EimError> {
+    RawFrame::new(bytes, width, height, stride, 3)?.model_features()
+}
+
+/// Resize an RGBA8888 frame for the linked model and pack it into features
+///
+/// The alpha channel is ignored. `stride` is the number of bytes per row, at least `width * 4`.
+pub fn features_from_rgba_bytes(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Result<Vec<f32>, EimError> {
+    RawFrame::new(bytes, width, height, stride, 4)?.model_features()
+}
+
+/// Explain a feature count that doesn't match a camera model's input, `None` for other models
+///
+/// Passing decoded RGB(A) bytes or one feature per channel is the most common mistake with
+/// image models, and a bare count mismatch doesn't say what went wrong.
+pub(crate) fn camera_input_mismatch(metadata: &ModelMetadata, len: usize) -> Option<String> {
+    if metadata.sensor != SensorType::Camera || metadata.input_width == 0 {
+        return None;
+    }
+    let frames = metadata.input_frames.max(1) as usize;
+    let pixels = metadata.input_width as usize * metadata.input_height as usize * frames;
+    let hint = if len == pixels * 3 {
+        "this looks like one feature per R, G and B byte, but every pixel is a single feature packed as 0xRRGGBB"
+    } else if len == pixels * 4 {
+        "this looks like one feature per R, G, B and A byte, but every pixel is a single feature packed as 0xRRGGBB"
+    } else {
+        "the model takes one feature per pixel, packed as 0xRRGGBB"
+    };
+    Some(format!(
+        "Expected {} features for a {}x{} camera model{}, got {}: {}. Convert frames with \
+         features_from_rgb_bytes or features_from_rgba_bytes (or EimModel::infer_image with the \
+         `image` feature), which also resize them to the input size",
+        metadata.dsp_input_frame_size,
+        metadata.input_width,
+        metadata.input_height,
+        if frames > 1 {
+            format!(" ({} frames)", frames)
+        } else {
+            String::new()
+        },
+        len,
+        hint
+    ))
+}
+
+/// A decoded frame borrowed from the caller, one row every `stride` bytes
+pub(crate) struct RawFrame<'a> {
+    bytes: &'a [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    bytes_per_pixel: usize,
+}
+
+impl<'a> RawFrame<'a> {
+    pub(crate) fn new(
+        bytes: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        bytes_per_pixel: usize,
+    ) -> Result<Self, EimError> {
+        let (width, height) = (width as usize, height as usize);
+        if width == 0 || height == 0 {
+            return Err(EimError::InvalidInput(format!(
+                "Frame must not be empty, got {}x{}",
+                width, height
+            )));
+        }
+        if stride < width * bytes_per_pixel {
+            return Err(EimError::InvalidInput(format!(
+                "Stride of {} bytes is too small for {} pixels of {} bytes",
+                stride, width, bytes_per_pixel
+            )));
+        }
+        let required = stride * (height - 1) + width * bytes_per_pixel;
+        if bytes.len() < required {
+            return Err(EimError::InvalidInput(format!(
+                "Expected at least {} bytes for a {}x{} frame, got {}",
+                required,
+                width,
+                height,
+                bytes.len()
+            )));
+        }
+        Ok(Self {
+            bytes,
+            width,
+            height,
+            stride,
+            bytes_per_pixel,
+        })
+    }
+
+    /// The `width` x `height` pixels at `x`, `y`, which must lie inside the frame
+    pub(crate) fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> RawFrame<'a> {
+        debug_assert!(x + width <= self.width && y + height <= self.height);
+        RawFrame {
+            bytes: &self.bytes[y * self.stride + x * self.bytes_per_pixel..],
+            width,
+            height,
+            stride: self.stride,
+            bytes_per_pixel: self.bytes_per_pixel,
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    // The types of the metadata constants depend on the model, so the casts may be no-ops
+    #[allow(clippy::unnecessary_cast)]
+    pub(crate) fn model_features(&self) -> Result<Vec<f32>, EimError> {
+        profile_scope!("preprocess_frame");
+        let width = EI_CLASSIFIER_INPUT_WIDTH as usize;
+        let height = EI_CLASSIFIER_INPUT_HEIGHT as usize;
+        if width == 0 || height == 0 {
+            return Err(EimError::InvalidOperation(
+                "The model has no image input".to_string(),
+            ));
+        }
+        Ok(self.features(width, height, ResizeMode::model()))
+    }
+
+    /// Fit the frame to `width` x `height` pixels like [`resize_image`] and pack every pixel
+    fn features(&self, width: usize, height: usize, mode: ResizeMode) -> Vec<f32> {
+        let Placement {
+            scaled_w,
+            scaled_h,
+            offset_x,
+            offset_y,
+            scale_x,
+            scale_y,
+        } = Placement::new(self.width, self.height, width, height, mode);
+
+        let mut features = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sy = y as i64 - offset_y;
+            for x in 0..width {
+                let sx = x as i64 - offset_x;
+                if sx < 0 || sy < 0 || sx >= scaled_w as i64 || sy >= scaled_h as i64 {
+                    // Padding is black
+                    features.push(0.0);
+                    continue;
+                }
+                let [r, g, b] = self.sample(
+                    (sx as f32 + 0.5) * scale_x - 0.5,
+                    (sy as f32 + 0.5) * scale_y - 0.5,
+                );
+                features.push(((r << 16) | (g << 8) | b) as f32);
+            }
+        }
+        features
+    }
+
+    /// Bilinear sample at source coordinates, clamped to the frame
+    fn sample(&self, x: f32, y: f32) -> [u32; 3] {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+        let mut rgb = [0; 3];
+        for (channel, value) in rgb.iter_mut().enumerate() {
+            let top = lerp(
+                self.channel(x0, y0, channel),
+                self.channel(x1, y0, channel),
+                fx,
+            );
+            let bottom = lerp(
+                self.channel(x0, y1, channel),
+                self.channel(x1, y1, channel),
+                fx,
+            );
+            *value = lerp(top, bottom, fy).round() as u32;
+        }
+        rgb
+    }
+
+    fn channel(&self, x: usize, y: usize, channel: usize) -> f32 {
+        self.bytes[y * self.stride + x * self.bytes_per_pixel + channel] as f32
+    }
+}
+
+/// Where a frame ends up on a `width` x `height` input after resizing with a [`ResizeMode`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Placement {
+    scaled_w: usize,
+    scaled_h: usize,
+    /// Offset of the scaled frame on the input, negative when it's cropped
+    offset_x: i64,
+    offset_y: i64,
+    /// Frame pixels per input pixel
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl Placement {
+    pub(crate) fn new(
+        frame_w: usize,
+        frame_h: usize,
+        width: usize,
+        height: usize,
+        mode: ResizeMode,
+    ) -> Self {
+        let (w, h) = (frame_w as f32, frame_h as f32);
+        let width_ratio = width as f32 / w;
+        let height_ratio = height as f32 / h;
+        let (scaled_w, scaled_h) = match mode {
+            ResizeMode::Squash => (width, height),
+            ResizeMode::None => (frame_w, frame_h),
+            ResizeMode::FitShortest | ResizeMode::FitLongest => {
+                let factor = if mode == ResizeMode::FitShortest {
+                    width_ratio.max(height_ratio)
+                } else {
+                    width_ratio.min(height_ratio)
+                };
+                (
+                    ((w * factor).round() as usize).max(1),
+                    ((h * factor).round() as usize).max(1),
+                )
+            }
+        };
+        Self {
+            scaled_w,
+            scaled_h,
+            offset_x: (width as i64 - scaled_w as i64) / 2,
+            offset_y: (height as i64 - scaled_h as i64) / 2,
+            scale_x: w / scaled_w as f32,
+            scale_y: h / scaled_h as f32,
+        }
+    }
+
+    /// Frame coordinates of a point on the input
+    pub(crate) fn frame_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.offset_x as f32) * self.scale_x,
+            (y - self.offset_y as f32) * self.scale_y,
+        )
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(feature = "image")]
+pub use self::image_support::*;
+
+#[cfg(feature = "image")]
+mod image_support {
+    use image::imageops::{self, FilterType};
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    use super::ResizeMode;
+    use crate::model_metadata::{EI_CLASSIFIER_INPUT_HEIGHT, EI_CLASSIFIER_INPUT_WIDTH};
+
+    /// Fit an image to `width` x `height` pixels using `mode`
+    pub fn resize_image(
+        image: &DynamicImage,
+        width: u32,
+        height: u32,
+        mode: ResizeMode,
+    ) -> RgbImage {
+        let (w, h) = image.dimensions();
+        if (w, h) == (width, height) {
+            return image.to_rgb8();
+        }
+
+        let scaled = |factor: f32| {
+            let resize_w = ((w as f32 * factor).round() as u32).max(1);
+            let resize_h = ((h as f32 * factor).round() as u32).max(1);
+            image
+                .resize_exact(resize_w, resize_h, FilterType::Triangle)
+                .to_rgb8()
+        };
+        let width_ratio = width as f32 / w as f32;
+        let height_ratio = height as f32 / h as f32;
+
+        match mode {
+            ResizeMode::Squash => image
+                .resize_exact(width, height, FilterType::Triangle)
+                .to_rgb8(),
+            ResizeMode::FitShortest => {
+                center(&scaled(width_ratio.max(height_ratio)), width, height)
+            }
+            ResizeMode::FitLongest => center(&scaled(width_ratio.min(height_ratio)), width, height),
+            ResizeMode::None => center(&image.to_rgb8(), width, height),
+        }
+    }
+
+    /// Pack every pixel as `0xRRGGBB` into one feature, row by row
+    pub fn image_to_features(image: &RgbImage) -> Vec<f32> {
+        image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0;
+                (((r as u32) << 16) | ((g as u32) << 8) | b as u32) as f32
+            })
+            .collect()
+    }
+
+    /// Resize an image for the linked model and pack it into features
+    pub fn image_features(image: &DynamicImage) -> Vec<f32> {
+        profile_scope!("preprocess_image");
+        let resized = resize_image(
+            image,
+            EI_CLASSIFIER_INPUT_WIDTH as u32,
+            EI_CLASSIFIER_INPUT_HEIGHT as u32,
+            ResizeMode::model(),
+        );
+        image_to_features(&resized)
+    }
+
+    /// Place `image` in the middle of a black `width` x `height` canvas, cropping what doesn't fit
+    fn center(image: &RgbImage, width: u32, height: u32) -> RgbImage {
+        let mut out = RgbImage::new(width, height);
+        let x = (width as i64 - image.width() as i64) / 2;
+        let y = (height as i64 - image.height() as i64) / 2;
+        imageops::replace(&mut out, image, x, y);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packed(r: u32, g: u32, b: u32) -> f32 {
+        ((r << 16) | (g << 8) | b) as f32
+    }
+
+    #[test]
+    fn packs_rows_with_padding() {
+        // 2x2 RGBA frame with 4 bytes of padding per row
+        let bytes = [
+            255, 0, 0, 255, 0, 255, 0, 255, 9, 9, 9, 9, //
+            0, 0, 255, 255, 1, 2, 3, 255, 9, 9, 9, 9,
+        ];
+        let frame = RawFrame::new(&bytes, 2, 2, 12, 4).unwrap();
+        assert_eq!(
+            frame.features(2, 2, ResizeMode::None),
+            vec![
+                packed(255, 0, 0),
+                packed(0, 255, 0),
+                packed(0, 0, 255),
+                packed(1, 2, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn pads_and_crops_to_the_input_size() {
+        let bytes = [10, 20, 30, 40, 50, 60];
+        let frame = RawFrame::new(&bytes, 2, 1, 6, 3).unwrap();
+        // Fitting the longest side pads the missing row with black
+        assert_eq!(
+            frame.features(2, 2, ResizeMode::FitLongest),
+            vec![packed(10, 20, 30), packed(40, 50, 60), 0.0, 0.0]
+        );
+        // Without resizing the center is cropped
+        assert_eq!(
+            frame.features(1, 1, ResizeMode::None),
+            vec![packed(10, 20, 30)]
+        );
+    }
+
+    #[test]
+    fn maps_input_points_back_to_the_frame() {
+        // A 200x100 frame fitted into a 100x100 input is halved and padded by 25 rows
+        let placement = Placement::new(200, 100, 100, 100, ResizeMode::FitLongest);
+        assert_eq!(placement.frame_point(10.0, 35.0), (20.0, 20.0));
+        let placement = Placement::new(200, 100, 100, 100, ResizeMode::Squash);
+        assert_eq!(placement.frame_point(50.0, 50.0), (100.0, 50.0));
+    }
+
+    #[test]
+    fn explains_camera_input_mismatches() {
+        let mut metadata = ModelMetadata::get().clone();
+        metadata.sensor = SensorType::Camera;
+        metadata.input_width = 4;
+        metadata.input_height = 2;
+        metadata.input_frames = 1;
+        metadata.dsp_input_frame_size = 8;
+
+        let rgb = camera_input_mismatch(&metadata, 24).unwrap();
+        assert!(rgb.starts_with("Expected 8 features for a 4x2 camera model, got 24"));
+        assert!(rgb.contains("R, G and B"));
+        assert!(camera_input_mismatch(&metadata, 32)
+            .unwrap()
+            .contains("R, G, B and A"));
+
+        metadata.sensor = SensorType::Microphone;
+        assert_eq!(camera_input_mismatch(&metadata, 24), None);
+    }
+
+    #[test]
+    fn rejects_short_buffers() {
+        assert!(RawFrame::new(&[0; 11], 2, 2, 6, 3).is_err());
+        assert!(RawFrame::new(&[0; 12], 2, 2, 5, 3).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! Quantization parameters of the model's tensors
+//!
+//! Int8 models store real values `r` as `q = r / scale + zero_point`. [`QuantizationInfo`]
+//! reports the scale and zero point of the input and output tensors, so data that is already
+//! quantized (or raw int8 outputs) can be converted correctly.
+
+use crate::model_metadata::{
+    EI_CLASSIFIER_INPUT_QUANTIZED, EI_CLASSIFIER_INPUT_SCALE, EI_CLASSIFIER_INPUT_ZERO_POINT,
+    EI_CLASSIFIER_OUTPUT_QUANTIZED, EI_CLASSIFIER_OUTPUT_SCALE, EI_CLASSIFIER_OUTPUT_ZERO_POINT,
+};
+
+/// Quantization of a single tensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TensorQuantization {
+    /// Whether the tensor holds quantized (int8) values
+    pub quantized: bool,
+    /// Size of one quantization step (1.0 for float tensors)
+    pub scale: f32,
+    /// Quantized value representing 0.0 (0 for float tensors)
+    pub zero_point: i32,
+}
+
+impl TensorQuantization {
+    /// Convert a real value to its quantized int8 representation, saturating at the int8 range
+    pub fn quantize(&self, value: f32) -> i8 {
+        let quantized = (value / self.scale).round() + self.zero_point as f32;
+        quantized.clamp(i8::MIN as f32, i8::MAX as f32) as i8
+    }
+
+    /// Convert a quantized int8 value back to a real value
+    pub fn dequantize(&self, value: i8) -> f32 {
+        (value as i32 - self.zero_point) as f32 * self.scale
+    }
+}
+
+/// Quantization of the model's input and output tensors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizationInfo {
+    /// Input tensor quantization
+    pub input: TensorQuantization,
+    /// Output tensor quantization
+    pub output: TensorQuantization,
+}
+
+impl QuantizationInfo {
+    /// Quantization parameters of the linked model
+    pub const fn model() -> Self {
+        Self {
+            input: TensorQuantization {
+                quantized: EI_CLASSIFIER_INPUT_QUANTIZED,
+                scale: EI_CLASSIFIER_INPUT_SCALE,
+                zero_point: EI_CLASSIFIER_INPUT_ZERO_POINT,
+            },
+            output: TensorQuantization {
+                quantized: EI_CLASSIFIER_OUTPUT_QUANTIZED,
+                scale: EI_CLASSIFIER_OUTPUT_SCALE,
+                zero_point: EI_CLASSIFIER_OUTPUT_ZERO_POINT,
+            },
+        }
+    }
+}
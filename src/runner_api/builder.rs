@@ -0,0 +1,163 @@
+//! Model configuration
+//!
+//! [`EimModelBuilder`] configures an [`EimModel`] in one place: SDK debug output, continuous
+//! classification, learning block thresholds and non-max suppression are all applied when the
+//! model is built.
+
+use crate::audio::AudioOptions;
+use crate::classifier::Classifier;
+use crate::logging::{set_native_log_callback, LogCallback};
+use crate::model_metadata::EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW;
+use crate::scaling::ScalingCheck;
+
+use super::{ContinuousClassifier, EimError, EimModel};
+
+/// Builder for [`EimModel`], created with [`EimModel::builder`]
+#[derive(Default)]
+pub struct EimModelBuilder {
+    debug: bool,
+    debug_callback: Option<LogCallback>,
+    continuous: bool,
+    slices_per_window: Option<usize>,
+    maf_window: Option<usize>,
+    maf_labels: Option<Vec<String>>,
+    thresholds: Vec<(u32, f32)>,
+    nms_iou_thresholds: Vec<(u32, f32)>,
+    max_detections: Option<u32>,
+    scaling_check: ScalingCheck,
+    audio_options: AudioOptions,
+}
+
+impl EimModelBuilder {
+    /// Print SDK debug output on every inference (can be overridden per call)
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Send SDK debug output to `callback` instead of stdout
+    ///
+    /// The SDK's output is process-wide, so this replaces any sink set with
+    /// [`set_native_log_sink`](crate::logging::set_native_log_sink).
+    pub fn debug_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.debug_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Enable continuous classification with [`EimModel::classify_continuous`]
+    pub fn continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+
+    /// Split the model window into `slices` slices in continuous mode
+    ///
+    /// Defaults to `EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW`, see [`ContinuousClassifier`].
+    pub fn slices_per_window(mut self, slices: usize) -> Self {
+        self.slices_per_window = Some(slices);
+        self
+    }
+
+    /// Average continuous results over the last `window` results
+    ///
+    /// See [`ContinuousClassifier::set_maf_window`].
+    pub fn maf_window(mut self, window: usize) -> Self {
+        self.maf_window = Some(window);
+        self
+    }
+
+    /// Only average the scores of `labels` in continuous mode
+    ///
+    /// See [`ContinuousClassifier::set_maf_labels`].
+    pub fn maf_labels<I, S>(mut self, labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.maf_labels = Some(labels.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Report continuous results without any moving average filter
+    pub fn disable_maf(mut self) -> Self {
+        self.maf_window = Some(1);
+        self.maf_labels = None;
+        self
+    }
+
+    /// Set the threshold of a learning block when the model is built
+    ///
+    /// See [`EimModel::set_learn_block_threshold`].
+    pub fn threshold(mut self, block_id: u32, value: f32) -> Self {
+        self.thresholds.push((block_id, value));
+        self
+    }
+
+    /// Set the NMS IoU threshold of an object detection block when the model is built
+    ///
+    /// See [`EimModel::set_nms_iou_threshold`].
+    pub fn nms_iou_threshold(mut self, block_id: u32, iou_threshold: f32) -> Self {
+        self.nms_iou_thresholds.push((block_id, iou_threshold));
+        self
+    }
+
+    /// Only return the `max_detections` highest scoring boxes per inference
+    pub fn max_detections(mut self, max_detections: u32) -> Self {
+        self.max_detections = Some(max_detections);
+        self
+    }
+
+    /// Choose what happens when features are on another scale than the model expects
+    ///
+    /// See [`EimModel::set_input_scaling_check`].
+    pub fn input_scaling_check(mut self, check: ScalingCheck) -> Self {
+        self.scaling_check = check;
+        self
+    }
+
+    /// Choose how audio is padded and trimmed by [`EimModel::infer_audio`]
+    pub fn audio_options(mut self, options: AudioOptions) -> Self {
+        self.audio_options = options;
+        self
+    }
+
+    /// Initialize the model with this configuration
+    ///
+    /// Fails with [`EimError::AlreadyInitialized`] if another model is alive and the
+    /// [`InstancePolicy`](crate::classifier::InstancePolicy) is `Exclusive`.
+    pub fn build(self) -> Result<EimModel, EimError> {
+        let classifier = Classifier::try_new()?;
+        let continuous = if self.continuous {
+            let slices = self
+                .slices_per_window
+                .unwrap_or(EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW);
+            let mut classifier = ContinuousClassifier::with_classifier(classifier.share(), slices)?;
+            classifier.set_debug(self.debug);
+            classifier.set_maf_window(self.maf_window);
+            classifier.set_maf_labels(self.maf_labels);
+            Some(classifier)
+        } else {
+            None
+        };
+
+        let mut model = EimModel::from_parts(classifier, self.debug, continuous);
+        for (block_id, value) in self.thresholds {
+            model.set_learn_block_threshold(block_id, value)?;
+        }
+        for (block_id, iou_threshold) in self.nms_iou_thresholds {
+            model.set_nms_iou_threshold(block_id, iou_threshold)?;
+        }
+        if let Some(max_detections) = self.max_detections {
+            model.set_max_detections(Some(max_detections));
+        }
+        model.set_input_scaling_check(self.scaling_check);
+        model.set_audio_options(self.audio_options);
+        if let Some(callback) = self.debug_callback {
+            set_native_log_callback(callback);
+        }
+        Ok(model)
+    }
+}
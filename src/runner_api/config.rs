@@ -0,0 +1,251 @@
+//! Deployment configuration files
+//!
+//! [`DeploymentConfig`] describes everything [`EimModelBuilder`] can configure (debug output,
+//! continuous mode, smoothing, thresholds, non-max suppression) plus the native log sink, so
+//! devices in the field can be tuned by shipping a new config file instead of a new binary.
+//!
+//! Every field is optional. With the `toml` or `yaml` feature the config can be loaded straight
+//! from a file; otherwise deserialize it with any serde format:
+//!
+//! ```toml
+//! debug = false
+//! max_detections = 20
+//! log = { type = "ring_buffer", capacity = 200 }
+//!
+//! [continuous]
+//! slices_per_window = 4
+//! maf_window = 6
+//! maf_labels = ["alarm"]
+//!
+//! [[thresholds]]
+//! block_id = 5
+//! value = 0.6
+//!
+//! [[nms]]
+//! block_id = 5
+//! iou_threshold = 0.3
+//!
+//! [[events]]
+//! label = "alarm"
+//! threshold = 0.8
+//! min_duration_ms = 300
+//! refractory_ms = 5000
+//! ```
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gating::{EventGate, GateRule};
+
+use crate::logging::{set_native_log_sink, NativeLogSink};
+use crate::model_metadata::EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW;
+
+use super::{EimError, EimModel, EimModelBuilder};
+
+/// Model and runtime settings of a deployment
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeploymentConfig {
+    /// Print SDK debug output on every inference
+    pub debug: bool,
+    /// Enable continuous classification, see [`EimModelBuilder::continuous`]
+    pub continuous: Option<ContinuousConfig>,
+    /// Learning block thresholds, see [`EimModel::set_learn_block_threshold`]
+    pub thresholds: Vec<ThresholdConfig>,
+    /// Non-max suppression of object detection blocks, see [`EimModel::set_nms_iou_threshold`]
+    pub nms: Vec<NmsConfig>,
+    /// Highest scoring boxes returned per inference, see [`EimModel::set_max_detections`]
+    pub max_detections: Option<u32>,
+    /// Where the SDK's debug output goes, see [`set_native_log_sink`]
+    pub log: Option<NativeLogSink>,
+    /// Gating of label events, see [`event_gate`](Self::event_gate)
+    pub events: Vec<EventConfig>,
+}
+
+/// Continuous classification and smoothing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContinuousConfig {
+    /// Slices per model window, defaults to `EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW`
+    pub slices_per_window: Option<usize>,
+    /// Length of the moving average filter
+    pub maf_window: Option<usize>,
+    /// Only smooth these labels
+    pub maf_labels: Option<Vec<String>>,
+    /// Report results without any moving average filter
+    pub disable_maf: bool,
+}
+
+/// Threshold of a learning block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdConfig {
+    /// Learning block ID, see [`crate::thresholds`]
+    pub block_id: u32,
+    /// Minimum score or anomaly threshold
+    pub value: f32,
+}
+
+/// NMS IoU threshold of an object detection block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NmsConfig {
+    /// Learning block ID, see [`crate::thresholds`]
+    pub block_id: u32,
+    /// IoU above which overlapping boxes are merged
+    pub iou_threshold: f32,
+}
+
+/// Gate of a label's events, see [`GateRule`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventConfig {
+    pub label: String,
+    /// Score the label has to reach
+    pub threshold: f32,
+    /// How long the score has to stay at or above the threshold, in milliseconds
+    #[serde(default)]
+    pub min_duration_ms: u64,
+    /// Time after an event during which the label can't fire again, in milliseconds
+    #[serde(default)]
+    pub refractory_ms: u64,
+}
+
+impl DeploymentConfig {
+    /// Parse a TOML config
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(config: &str) -> Result<Self, EimError> {
+        toml::from_str(config)
+            .map_err(|e| EimError::InvalidInput(format!("Invalid deployment config: {}", e)))
+    }
+
+    /// Parse a YAML config
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(config: &str) -> Result<Self, EimError> {
+        serde_yaml::from_str(config)
+            .map_err(|e| EimError::InvalidInput(format!("Invalid deployment config: {}", e)))
+    }
+
+    /// Load a config file, as TOML (`.toml`) or YAML (`.yaml`, `.yml`) depending on its extension
+    #[cfg(any(feature = "toml", feature = "yaml"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, EimError> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path).map_err(|e| {
+            EimError::InvalidInput(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&config),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml_str(&config),
+            _ => Err(EimError::InvalidInput(format!(
+                "Unsupported deployment config format: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// A builder with every model setting of this config applied
+    ///
+    /// The log sink is not part of the builder; [`build`](Self::build) sets it as well.
+    pub fn builder(&self) -> EimModelBuilder {
+        let mut builder = EimModel::builder().debug(self.debug);
+        if let Some(continuous) = &self.continuous {
+            builder = builder.continuous(true);
+            if let Some(slices) = continuous.slices_per_window {
+                builder = builder.slices_per_window(slices);
+            }
+            if let Some(window) = continuous.maf_window {
+                builder = builder.maf_window(window);
+            }
+            if let Some(labels) = &continuous.maf_labels {
+                builder = builder.maf_labels(labels.iter().cloned());
+            }
+            if continuous.disable_maf {
+                builder = builder.disable_maf();
+            }
+        }
+        for threshold in &self.thresholds {
+            builder = builder.threshold(threshold.block_id, threshold.value);
+        }
+        for nms in &self.nms {
+            builder = builder.nms_iou_threshold(nms.block_id, nms.iou_threshold);
+        }
+        if let Some(max_detections) = self.max_detections {
+            builder = builder.max_detections(max_detections);
+        }
+        builder
+    }
+
+    /// An event gate with the rules of [`events`](Self::events)
+    pub fn event_gate(&self) -> EventGate {
+        let mut gate = EventGate::new();
+        for event in &self.events {
+            gate.set_rule(
+                event.label.clone(),
+                GateRule::new(event.threshold)
+                    .min_duration(Duration::from_millis(event.min_duration_ms))
+                    .refractory(Duration::from_millis(event.refractory_ms)),
+            );
+        }
+        gate
+    }
+
+    /// Set the log sink and build the model
+    pub fn build(&self) -> Result<EimModel, EimError> {
+        self.set_log_sink()?;
+        self.builder().build()
+    }
+
+    /// Apply this config to a running model, e.g. after the config file changed
+    ///
+    /// Debug output, thresholds, NMS, the detection limit, the moving average filter and the
+    /// log sink change in place; blocks the config doesn't list keep their current thresholds.
+    /// Continuous mode can't be switched on or off, and changing `slices_per_window` discards
+    /// the samples buffered so far.
+    pub fn apply(&self, model: &mut EimModel) -> Result<(), EimError> {
+        match (&self.continuous, model.continuous_mut()) {
+            (Some(config), Some(continuous)) => {
+                let slices = config
+                    .slices_per_window
+                    .unwrap_or(EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW);
+                if slices != continuous.slices_per_window() {
+                    continuous.set_slices_per_window(slices)?;
+                }
+                if config.disable_maf {
+                    continuous.set_maf_window(Some(1));
+                    continuous.set_maf_labels(None);
+                } else {
+                    continuous.set_maf_window(config.maf_window);
+                    continuous.set_maf_labels(config.maf_labels.clone());
+                }
+            }
+            (None, None) => {}
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(EimError::InvalidOperation(
+                    "Continuous mode can't be switched on or off in a running model".to_string(),
+                ))
+            }
+        }
+        model.set_debug(self.debug);
+        for threshold in &self.thresholds {
+            model.set_learn_block_threshold(threshold.block_id, threshold.value)?;
+        }
+        for nms in &self.nms {
+            model.set_nms_iou_threshold(nms.block_id, nms.iou_threshold)?;
+        }
+        if let Some(max_detections) = self.max_detections {
+            model.set_max_detections(Some(max_detections));
+        }
+        self.set_log_sink()
+    }
+
+    fn set_log_sink(&self) -> Result<(), EimError> {
+        if let Some(sink) = &self.log {
+            set_native_log_sink(sink.clone())
+                .map_err(|e| EimError::InvalidInput(format!("Failed to open log sink: {}", e)))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+//! Continuous classification
+//!
+//! [`ContinuousClassifier`] classifies a stream of samples one slice at a time. With the
+//! model's default number of slices per window (`EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW`) each
+//! slice is passed straight to the SDK's `run_classifier_continuous`, which keeps the model
+//! window in its own feature buffer and smooths the results with a moving average filter, exactly
+//! like the C++ SDK.
+//!
+//! The SDK's buffer is sized at build time, so other slice counts are handled by keeping a
+//! rolling window in Rust and classifying the whole window on every slice. More slices mean
+//! lower latency at the cost of more inferences.
+//!
+//! The SDK's moving average filter always averages every label over the last
+//! `EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW / 2` results. [`ContinuousClassifier::set_maf_window`]
+//! and [`ContinuousClassifier::set_maf_labels`] replace it with a filter of any length, limited
+//! to some labels, which also works with custom slice counts.
+
+use std::collections::VecDeque;
+
+use crate::classifier::Classifier;
+use crate::inference::InferenceOutput;
+use crate::model_metadata::{
+    EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE, EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME,
+    EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW,
+};
+
+use super::EimError;
+
+/// Classifies a stream of samples, one slice at a time
+pub struct ContinuousClassifier {
+    classifier: Classifier,
+    debug: bool,
+    slices_per_window: usize,
+    window: Vec<f32>,
+    filled: usize,
+    maf_window: Option<usize>,
+    maf_labels: Option<Vec<String>>,
+    maf: Option<MovingAverageFilter>,
+}
+
+impl ContinuousClassifier {
+    /// Create a continuous classifier with the model's default number of slices per window
+    pub fn new() -> Result<Self, EimError> {
+        Self::with_slices_per_window(EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW)
+    }
+
+    /// Create a continuous classifier that splits the model window into `slices` slices
+    pub fn with_slices_per_window(slices: usize) -> Result<Self, EimError> {
+        Self::with_classifier(Classifier::try_new()?, slices)
+    }
+
+    /// Create a continuous classifier running on `classifier`
+    pub(crate) fn with_classifier(classifier: Classifier, slices: usize) -> Result<Self, EimError> {
+        validate_slices_per_window(slices)?;
+        Ok(Self {
+            classifier,
+            debug: false,
+            slices_per_window: slices,
+            window: vec![0.0; window_size()],
+            filled: 0,
+            maf_window: None,
+            maf_labels: None,
+            maf: None,
+        })
+    }
+
+    /// Print SDK debug output on every inference
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Number of slices the model window is split into
+    pub fn slices_per_window(&self) -> usize {
+        self.slices_per_window
+    }
+
+    /// Change the number of slices per model window
+    ///
+    /// The window length must be divisible into `slices` whole frames, so `slices` must divide
+    /// the number of raw samples in the window. Buffered samples are discarded.
+    pub fn set_slices_per_window(&mut self, slices: usize) -> Result<(), EimError> {
+        validate_slices_per_window(slices)?;
+        self.slices_per_window = slices;
+        self.reset();
+        Ok(())
+    }
+
+    /// Average classification scores over the last `window` results instead of using the SDK's
+    /// moving average filter
+    ///
+    /// `None` restores the default: the SDK's filter with the default slice count, and no
+    /// smoothing with custom slice counts. A window of 0 or 1 disables smoothing.
+    pub fn set_maf_window(&mut self, window: Option<usize>) {
+        self.maf_window = window;
+        self.update_maf();
+    }
+
+    /// Length of the moving average filter set with [`set_maf_window`](Self::set_maf_window)
+    pub fn maf_window(&self) -> Option<usize> {
+        self.maf_window
+    }
+
+    /// Only smooth the scores of `labels`, other labels are reported as classified
+    ///
+    /// Without a window set with [`set_maf_window`](Self::set_maf_window), the filter averages
+    /// the same number of results as the SDK's. `None` smooths all labels again.
+    pub fn set_maf_labels(&mut self, labels: Option<Vec<String>>) {
+        self.maf_labels = labels;
+        self.update_maf();
+    }
+
+    /// Labels set with [`set_maf_labels`](Self::set_maf_labels)
+    pub fn maf_labels(&self) -> Option<&[String]> {
+        self.maf_labels.as_deref()
+    }
+
+    /// Number of features expected by [`classify_slice`](Self::classify_slice)
+    pub fn slice_size(&self) -> usize {
+        window_size() / self.slices_per_window
+    }
+
+    /// Discard all buffered samples, including the SDK's feature buffer and moving average filter
+    ///
+    /// See [`Classifier::reset`]: the SDK's state is shared by every model alive.
+    pub fn reset(&mut self) {
+        self.classifier.reset();
+        self.window.iter_mut().for_each(|v| *v = 0.0);
+        self.filled = 0;
+        if let Some(maf) = &mut self.maf {
+            maf.reset();
+        }
+    }
+
+    /// Add a slice of samples and classify the current window
+    ///
+    /// With the default slice count this returns a result for every slice, like the C++
+    /// `run_classifier_continuous` (the SDK zero-fills the window until it has seen enough
+    /// slices). With a custom slice count it returns `None` until a whole window was received.
+    pub fn classify_slice(&mut self, slice: &[f32]) -> Result<Option<InferenceOutput>, EimError> {
+        let slice_size = self.slice_size();
+        if slice.len() != slice_size {
+            return Err(EimError::InvalidInput(format!(
+                "Expected a slice of {} features, got {}",
+                slice_size,
+                slice.len()
+            )));
+        }
+
+        if self.slices_per_window == EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW {
+            let output = self
+                .classifier
+                .run_continuous(slice, self.debug, self.maf.is_none())
+                .map_err(|error| EimError::impulse("run_classifier_continuous", error))?;
+            return Ok(Some(self.smooth(output)));
+        }
+
+        // Shift the window left by one slice and append the new samples
+        self.window.copy_within(slice_size.., 0);
+        let start = self.window.len() - slice_size;
+        self.window[start..].copy_from_slice(slice);
+        self.filled = (self.filled + slice_size).min(self.window.len());
+
+        if self.filled < self.window.len() {
+            return Ok(None);
+        }
+
+        let output = self
+            .classifier
+            .run(&self.window, self.debug)
+            .map_err(|error| EimError::impulse("run_classifier", error))?;
+        Ok(Some(self.smooth(output)))
+    }
+
+    /// Replace the SDK's filter with ours if the default was changed
+    fn update_maf(&mut self) {
+        self.maf = if self.maf_window.is_none() && self.maf_labels.is_none() {
+            None
+        } else {
+            let window = self
+                .maf_window
+                .unwrap_or(EI_CLASSIFIER_SLICES_PER_MODEL_WINDOW / 2);
+            Some(MovingAverageFilter::new(
+                window.max(1),
+                self.maf_labels.clone(),
+            ))
+        };
+    }
+
+    fn smooth(&mut self, mut output: InferenceOutput) -> InferenceOutput {
+        if let Some(maf) = &mut self.maf {
+            maf.apply(&mut output);
+        }
+        output
+    }
+}
+
+/// Averages the scores of `labels` (all labels if `None`) over the last `window` results
+struct MovingAverageFilter {
+    window: usize,
+    labels: Option<Vec<String>>,
+    history: Vec<VecDeque<f32>>,
+}
+
+impl MovingAverageFilter {
+    fn new(window: usize, labels: Option<Vec<String>>) -> Self {
+        Self {
+            window,
+            labels,
+            history: Vec::new(),
+        }
+    }
+
+    fn apply(&mut self, output: &mut InferenceOutput) {
+        self.history
+            .resize_with(output.classification.len(), VecDeque::new);
+        for (classification, history) in output.classification.iter_mut().zip(&mut self.history) {
+            if let Some(labels) = &self.labels {
+                if !labels.contains(&classification.label) {
+                    continue;
+                }
+            }
+            if history.len() == self.window {
+                history.pop_front();
+            }
+            history.push_back(classification.value);
+            classification.value = history.iter().sum::<f32>() / history.len() as f32;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+fn window_size() -> usize {
+    EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE
+}
+
+fn validate_slices_per_window(slices: usize) -> Result<(), EimError> {
+    let frame_size = EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME.max(1);
+    let frames = window_size() / frame_size;
+    if slices == 0 || slices > frames || !frames.is_multiple_of(slices) {
+        return Err(EimError::InvalidInput(format!(
+            "slices_per_model_window must divide the {} frames of the model window, got {}",
+            frames, slices
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::Classification;
+
+    #[test]
+    fn slice_counts_must_divide_the_window() {
+        let frames = window_size() / EI_CLASSIFIER_RAW_SAMPLES_PER_FRAME.max(1);
+        assert!(validate_slices_per_window(1).is_ok());
+        assert!(validate_slices_per_window(frames).is_ok());
+        assert!(validate_slices_per_window(0).is_err());
+        assert!(validate_slices_per_window(frames + 1).is_err());
+        if let Some(slices) = (2..frames).find(|slices| !frames.is_multiple_of(*slices)) {
+            assert!(validate_slices_per_window(slices).is_err());
+        }
+    }
+
+    fn output(scores: &[(&str, f32)]) -> InferenceOutput {
+        InferenceOutput {
+            classification: scores
+                .iter()
+                .map(|&(label, value)| Classification {
+                    label: label.to_string(),
+                    value,
+                })
+                .collect(),
+            ..InferenceOutput::default()
+        }
+    }
+
+    #[test]
+    fn moving_average_smooths_the_selected_labels() {
+        let mut maf = MovingAverageFilter::new(2, Some(vec!["yes".to_string()]));
+        let scores = |output: InferenceOutput| -> Vec<f32> {
+            output.classification.iter().map(|c| c.value).collect()
+        };
+
+        let mut first = output(&[("yes", 1.0), ("no", 0.0)]);
+        maf.apply(&mut first);
+        assert_eq!(scores(first), [1.0, 0.0]);
+        let mut second = output(&[("yes", 0.0), ("no", 1.0)]);
+        maf.apply(&mut second);
+        assert_eq!(scores(second), [0.5, 1.0]);
+        // The window only holds the last two scores
+        let mut third = output(&[("yes", 0.0), ("no", 1.0)]);
+        maf.apply(&mut third);
+        assert_eq!(scores(third), [0.0, 1.0]);
+
+        maf.reset();
+        let mut after_reset = output(&[("yes", 0.5), ("no", 0.0)]);
+        maf.apply(&mut after_reset);
+        assert_eq!(scores(after_reset), [0.5, 0.0]);
+    }
+}
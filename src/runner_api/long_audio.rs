@@ -0,0 +1,331 @@
+//! Classification of long recordings
+//!
+//! [`EimModel::classify_long_audio`] slides the model window over a recording of any length,
+//! classifies every window and turns the scores into a timeline of [`Detection`]s: runs of
+//! consecutive windows whose top label scores above a threshold. The scores of each detection,
+//! and of the whole recording, are aggregated with the chosen [`Aggregation`].
+
+use std::collections::HashMap;
+#[cfg(feature = "wav")]
+use std::path::Path;
+use std::time::Duration;
+
+use crate::audio::{self, AudioOptions};
+use crate::model_metadata::{EI_CLASSIFIER_FREQUENCY, EI_CLASSIFIER_RAW_SAMPLE_COUNT};
+
+use super::{EimError, EimModel, InferenceResult};
+
+/// How the scores of several windows are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregation {
+    /// Highest score of any window
+    #[default]
+    Max,
+    /// Mean score over the windows
+    Mean,
+    /// Fraction of the windows in which the label scored highest
+    Vote,
+}
+
+/// Settings of [`EimModel::classify_long_audio`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LongAudioOptions {
+    /// Distance between the starts of two windows; `None` for half the model window
+    pub hop: Option<Duration>,
+    /// How window scores are combined into detection and recording scores
+    pub aggregation: Aggregation,
+    /// Minimum score of the top label for a window to be part of a detection
+    pub threshold: f32,
+    /// Labels that never start a detection, e.g. `"noise"` or `"background"`
+    pub ignore_labels: Vec<String>,
+    /// Padding of the last window, see [`AudioOptions`]
+    pub audio: AudioOptions,
+}
+
+impl Default for LongAudioOptions {
+    fn default() -> Self {
+        Self {
+            hop: None,
+            aggregation: Aggregation::Max,
+            threshold: 0.5,
+            ignore_labels: Vec::new(),
+            audio: AudioOptions::default(),
+        }
+    }
+}
+
+/// Scores of one window of the recording
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowScores {
+    /// Start of the window in the recording
+    pub start: Duration,
+    /// End of the window in the recording
+    pub end: Duration,
+    /// Score of every label
+    pub scores: HashMap<String, f32>,
+}
+
+impl WindowScores {
+    /// Highest scoring label, ties broken by label
+    pub fn top(&self) -> Option<(&str, f32)> {
+        self.scores
+            .iter()
+            .map(|(label, &value)| (label.as_str(), value))
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+    }
+}
+
+/// A run of consecutive windows with the same top label
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Detection {
+    /// Detected label
+    pub label: String,
+    /// Start of the first window
+    pub start: Duration,
+    /// End of the last window
+    pub end: Duration,
+    /// Aggregated score of the label over the windows
+    pub score: f32,
+    /// Number of windows in the detection
+    pub windows: usize,
+}
+
+/// Result of [`EimModel::classify_long_audio`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LongAudioResult {
+    /// Length of the recording
+    pub duration: Duration,
+    /// Scores of every window, in order
+    pub windows: Vec<WindowScores>,
+    /// Detections, in order
+    pub detections: Vec<Detection>,
+    /// Aggregated score of every label over the whole recording
+    pub summary: HashMap<String, f32>,
+}
+
+impl EimModel {
+    /// Classify a WAV file of any length, see [`classify_long_samples`](Self::classify_long_samples)
+    ///
+    /// 8, 16, 24 and 32-bit integer and 32-bit float files are supported.
+    #[cfg(feature = "wav")]
+    pub fn classify_long_audio<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &LongAudioOptions,
+    ) -> Result<LongAudioResult, EimError> {
+        let path = path.as_ref();
+        let read_error =
+            |error: hound::Error| EimError::InvalidInput(format!("{}: {}", path.display(), error));
+        let mut reader = hound::WavReader::open(path).map_err(read_error)?;
+        let spec = reader.spec();
+        let samples: Vec<i16> = match spec.sample_format {
+            hound::SampleFormat::Int => {
+                let shift = spec.bits_per_sample as i32 - 16;
+                reader
+                    .samples::<i32>()
+                    .map(|s| {
+                        s.map(|s| match shift {
+                            0 => s as i16,
+                            shift if shift > 0 => (s >> shift) as i16,
+                            shift => (s << -shift) as i16,
+                        })
+                    })
+                    .collect::<Result<_, _>>()
+            }
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16))
+                .collect::<Result<_, _>>(),
+        }
+        .map_err(read_error)?;
+        self.classify_long_samples(&samples, spec.sample_rate, spec.channels, options)
+    }
+
+    /// Classify a recording of any length, given as interleaved 16-bit PCM
+    ///
+    /// The recording is mixed down and resampled like in [`infer_audio`](Self::infer_audio),
+    /// then classified one model window at a time, starting a new window every `options.hop`.
+    /// Only classification models are supported.
+    pub fn classify_long_samples(
+        &mut self,
+        samples: &[i16],
+        sample_rate: u32,
+        channels: u16,
+        options: &LongAudioOptions,
+    ) -> Result<LongAudioResult, EimError> {
+        #[allow(clippy::unnecessary_cast)]
+        let frequency = EI_CLASSIFIER_FREQUENCY as u32;
+        if frequency == 0 {
+            return Err(EimError::InvalidOperation(
+                "The model has no sampling frequency, it doesn't take audio input".to_string(),
+            ));
+        }
+        if channels == 0 || sample_rate == 0 {
+            return Err(EimError::InvalidInput(format!(
+                "Invalid audio format: {} Hz, {} channels",
+                sample_rate, channels
+            )));
+        }
+
+        let window = EI_CLASSIFIER_RAW_SAMPLE_COUNT;
+        let hop = match options.hop {
+            Some(hop) => (hop.as_secs_f64() * frequency as f64).round() as usize,
+            None => window / 2,
+        };
+        if hop == 0 {
+            return Err(EimError::InvalidInput(
+                "The hop must be at least one sample long".to_string(),
+            ));
+        }
+
+        let mono = audio::to_mono(samples, channels);
+        let resampled = audio::resample(&mono, sample_rate, frequency);
+        let at = |sample: usize| Duration::from_secs_f64(sample as f64 / frequency as f64);
+
+        let mut windows = Vec::new();
+        let mut start = 0;
+        loop {
+            let end = (start + window).min(resampled.len());
+            let (features, _) = audio::fit_window(
+                &resampled[start..end],
+                window,
+                (frequency / 100) as usize,
+                &options.audio,
+            );
+            let scores = match self.infer_slice(&features, None)?.result {
+                InferenceResult::Classification { classification, .. } => classification,
+                _ => {
+                    return Err(EimError::InvalidOperation(
+                        "Long recordings can only be classified by classification models"
+                            .to_string(),
+                    ))
+                }
+            };
+            windows.push(WindowScores {
+                start: at(start),
+                end: at(start + window),
+                scores,
+            });
+            if end == resampled.len() {
+                break;
+            }
+            start += hop;
+        }
+
+        Ok(LongAudioResult {
+            duration: at(resampled.len()),
+            detections: detections(&windows, options),
+            summary: aggregate(&windows, options.aggregation),
+            windows,
+        })
+    }
+}
+
+/// Group consecutive windows with the same top label scoring at least `options.threshold`
+pub fn detections(windows: &[WindowScores], options: &LongAudioOptions) -> Vec<Detection> {
+    let mut detections = Vec::new();
+    let mut run: Option<(&str, usize)> = None;
+    let mut close = |label: &str, first: usize, last: usize| {
+        let run = &windows[first..=last];
+        detections.push(Detection {
+            label: label.to_string(),
+            start: run[0].start,
+            end: run[run.len() - 1].end,
+            score: aggregate(run, options.aggregation)
+                .get(label)
+                .copied()
+                .unwrap_or(0.0),
+            windows: run.len(),
+        });
+    };
+
+    for (i, window) in windows.iter().enumerate() {
+        let top = window
+            .top()
+            .filter(|(label, value)| {
+                *value >= options.threshold && !options.ignore_labels.iter().any(|l| l == label)
+            })
+            .map(|(label, _)| label);
+        match (run, top) {
+            (Some((label, _)), Some(top)) if label == top => {}
+            (Some((label, first)), _) => {
+                close(label, first, i - 1);
+                run = top.map(|top| (top, i));
+            }
+            (None, _) => run = top.map(|top| (top, i)),
+        }
+    }
+    if let Some((label, first)) = run {
+        close(label, first, windows.len() - 1);
+    }
+    detections
+}
+
+/// Combine the scores of every label over `windows`
+pub fn aggregate(windows: &[WindowScores], aggregation: Aggregation) -> HashMap<String, f32> {
+    let mut combined: HashMap<String, f32> = HashMap::new();
+    for window in windows {
+        let top = window.top().map(|(label, _)| label);
+        for (label, &value) in &window.scores {
+            let entry = combined.entry(label.clone()).or_insert(0.0);
+            match aggregation {
+                Aggregation::Max => *entry = entry.max(value),
+                Aggregation::Mean => *entry += value,
+                Aggregation::Vote => {
+                    if top == Some(label.as_str()) {
+                        *entry += 1.0;
+                    }
+                }
+            }
+        }
+    }
+    if aggregation != Aggregation::Max && !windows.is_empty() {
+        for value in combined.values_mut() {
+            *value /= windows.len() as f32;
+        }
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(second: u64, scores: &[(&str, f32)]) -> WindowScores {
+        WindowScores {
+            start: Duration::from_secs(second),
+            end: Duration::from_secs(second + 1),
+            scores: scores.iter().map(|&(l, v)| (l.to_string(), v)).collect(),
+        }
+    }
+
+    #[test]
+    fn builds_timeline_from_consecutive_windows() {
+        let windows = [
+            window(0, &[("dog", 0.9), ("noise", 0.1)]),
+            window(1, &[("dog", 0.7), ("noise", 0.3)]),
+            window(2, &[("dog", 0.2), ("noise", 0.8)]),
+            window(3, &[("dog", 0.6), ("noise", 0.4)]),
+        ];
+        let options = LongAudioOptions {
+            aggregation: Aggregation::Mean,
+            ignore_labels: vec!["noise".to_string()],
+            ..Default::default()
+        };
+        let detections = detections(&windows, &options);
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].label, "dog");
+        assert_eq!(detections[0].start, Duration::from_secs(0));
+        assert_eq!(detections[0].end, Duration::from_secs(2));
+        assert!((detections[0].score - 0.8).abs() < 1e-6);
+        assert_eq!(detections[1].windows, 1);
+
+        let votes = aggregate(&windows, Aggregation::Vote);
+        assert_eq!(votes["dog"], 0.75);
+        assert_eq!(votes["noise"], 0.25);
+    }
+}
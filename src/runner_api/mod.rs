@@ -0,0 +1,526 @@
+//! Runner-compatible model API
+//!
+//! [`EimModel`] exposes the same shape of API as `edge-impulse-runner-rs`, but runs the impulse
+//! in-process through the FFI bindings instead of talking to an `.eim` binary over a socket.
+//!
+//! Methods that only make sense for the socket-based runner (model paths, socket paths) are
+//! kept as documented no-ops so ported code compiles unchanged.
+
+pub mod builder;
+#[cfg(feature = "serde")]
+pub mod config;
+pub mod continuous;
+pub mod long_audio;
+pub mod sync;
+pub mod types;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::audio::AudioOptions;
+use crate::cancel::CancellationToken;
+use crate::classifier::{AllocRetry, Classifier};
+use crate::inference::{self, InferenceOutput};
+use crate::metadata::ModelMetadata;
+use crate::model_metadata::*;
+use crate::preprocessing::camera_input_mismatch;
+use crate::scaling::{InputScaling, ScalingCheck, ScalingMismatch};
+use crate::tiling::Tiling;
+
+pub use builder::EimModelBuilder;
+#[cfg(feature = "serde")]
+pub use config::DeploymentConfig;
+pub use continuous::ContinuousClassifier;
+pub use long_audio::{Aggregation, Detection, LongAudioOptions, LongAudioResult, WindowScores};
+pub use sync::SyncModel;
+pub use types::*;
+
+/// An Edge Impulse model linked into the current process
+///
+/// `EimModel` is `Send` but not `Sync`; use [`SyncModel`] to share it between threads.
+pub struct EimModel {
+    classifier: Classifier,
+    parameters: ModelParameters,
+    debug: bool,
+    next_id: u32,
+    continuous: Option<ContinuousClassifier>,
+    cancellation: Option<CancellationToken>,
+    scaling_check: ScalingCheck,
+    scaling_mismatch: Option<ScalingMismatch>,
+    audio_options: AudioOptions,
+}
+
+impl EimModel {
+    /// Initialize the classifier
+    pub fn new() -> Result<Self, EimError> {
+        Self::builder().build()
+    }
+
+    /// Initialize the classifier, optionally printing SDK debug output on every inference
+    pub fn new_with_debug(debug: bool) -> Result<Self, EimError> {
+        Self::builder().debug(debug).build()
+    }
+
+    /// Configure a model: debug output, continuous mode, thresholds, ...
+    pub fn builder() -> EimModelBuilder {
+        EimModelBuilder::default()
+    }
+
+    fn from_parts(
+        classifier: Classifier,
+        debug: bool,
+        continuous: Option<ContinuousClassifier>,
+    ) -> Self {
+        let mut parameters = model_parameters();
+        parameters.use_continuous_mode = continuous.is_some();
+        Self {
+            classifier,
+            parameters,
+            debug,
+            next_id: 1,
+            continuous,
+            cancellation: None,
+            scaling_check: ScalingCheck::default(),
+            scaling_mismatch: None,
+            audio_options: AudioOptions::default(),
+        }
+    }
+
+    /// Initialize the classifier, accepting the arguments of the runner's socket constructor
+    ///
+    /// In FFI mode the model is linked into the binary and no socket is used, so both paths are
+    /// ignored.
+    pub fn new_with_socket<P: AsRef<Path>, S: AsRef<Path>>(
+        _path: P,
+        _socket_path: S,
+    ) -> Result<Self, EimError> {
+        Self::new()
+    }
+
+    /// Parameters of the linked model
+    pub fn parameters(&self) -> Result<&ModelParameters, EimError> {
+        Ok(&self.parameters)
+    }
+
+    /// Studio project and deployment version the model was exported from
+    pub fn project_info(&self) -> Result<ProjectInfo, EimError> {
+        let metadata = ModelMetadata::get();
+        Ok(ProjectInfo {
+            id: metadata.project_id,
+            owner: metadata.project_owner.clone(),
+            name: metadata.project_name.clone(),
+            deploy_version: metadata.deploy_version,
+        })
+    }
+
+    /// Path to the `.eim` model file
+    ///
+    /// Always `None` in FFI mode, the model is linked into the binary.
+    pub fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Path to the runner's Unix socket
+    ///
+    /// Always `None` in FFI mode, there is no runner process to talk to.
+    pub fn socket_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Register a callback for debug messages
+    ///
+    /// There are no runner socket messages in FFI mode, so the callback receives the SDK's debug
+    /// output instead, one line at a time (see [`EimModelBuilder::debug_callback`]). The SDK
+    /// only prints it for inferences run with `debug` enabled.
+    pub fn set_debug_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        crate::logging::set_native_log_callback(callback);
+    }
+
+    /// Run inference on a buffer of features
+    ///
+    /// `debug` overrides the debug flag the model was created with.
+    pub fn infer(
+        &mut self,
+        features: Vec<f32>,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        self.infer_slice(&features, debug)
+    }
+
+    /// Run inference on borrowed features
+    ///
+    /// Same as [`infer`](Self::infer), but the SDK reads the features straight from `features`,
+    /// so callers that already hold the input in a buffer don't need to copy it into a `Vec`.
+    pub fn infer_slice(
+        &mut self,
+        features: &[f32],
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        profile_scope!("EimModel::infer");
+        if features.len() != EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE {
+            return Err(EimError::InvalidInput(
+                camera_input_mismatch(ModelMetadata::get(), features.len()).unwrap_or_else(|| {
+                    format!(
+                        "Expected {} features, got {}",
+                        EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE,
+                        features.len()
+                    )
+                }),
+            ));
+        }
+        self.check_scaling(features)?;
+
+        let debug = debug.unwrap_or(self.debug);
+        let classifier = &mut self.classifier;
+        let output = match &self.cancellation {
+            Some(token) => token.run(|| classifier.run(features, debug)),
+            None => classifier.run(features, debug),
+        }
+        .map_err(|error| EimError::impulse("run_classifier", error))?;
+
+        profile_scope!("postprocess");
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(InferenceResponse {
+            success: true,
+            id,
+            result: convert_inference_result(&output),
+        })
+    }
+
+    /// Run inference on an image
+    ///
+    /// The image is resized and cropped according to `EI_CLASSIFIER_RESIZE_MODE` and packed into
+    /// the model's input features, see [`crate::preprocessing`].
+    #[cfg(feature = "image")]
+    pub fn infer_image(
+        &mut self,
+        image: &image::DynamicImage,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        let features = crate::preprocessing::image_features(image);
+        self.infer_slice(&features, debug)
+    }
+
+    /// Detect objects in a large RGB888 frame tile by tile, see [`crate::tiling`]
+    ///
+    /// The result is an object detection result with the merged boxes in frame coordinates and
+    /// no classification scores.
+    pub fn infer_tiled_rgb(
+        &mut self,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        tiling: &Tiling,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        let bounding_boxes =
+            crate::tiling::detect_tiled_rgb(bytes, width, height, stride, tiling, |features| {
+                match self.infer_slice(features, debug)?.result {
+                    InferenceResult::ObjectDetection { bounding_boxes, .. }
+                    | InferenceResult::ObjectTracking { bounding_boxes, .. } => Ok(bounding_boxes),
+                    _ => Err(EimError::InvalidOperation(
+                        "Tiled inference needs an object detection model".to_string(),
+                    )),
+                }
+            })?;
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(InferenceResponse {
+            success: true,
+            id,
+            result: InferenceResult::ObjectDetection {
+                bounding_boxes,
+                classification: HashMap::new(),
+            },
+        })
+    }
+
+    /// Detect objects in a large image tile by tile, see [`crate::tiling`]
+    #[cfg(feature = "image")]
+    pub fn infer_image_tiled(
+        &mut self,
+        image: &image::DynamicImage,
+        tiling: &Tiling,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        let rgb = image.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        self.infer_tiled_rgb(
+            rgb.as_raw(),
+            width,
+            height,
+            width as usize * 3,
+            tiling,
+            debug,
+        )
+    }
+
+    /// Run inference on raw 16-bit PCM audio
+    ///
+    /// `samples` are interleaved if `channels > 1`. The audio is mixed down to mono, resampled to
+    /// the model's frequency and trimmed, padded or truncated to the model window according to
+    /// the [audio options](Self::set_audio_options), see [`crate::audio`]. With debug output
+    /// enabled, what was done to the audio is logged to the sink or callback of the SDK's output
+    /// (see [`crate::logging`]), if one is set.
+    pub fn infer_audio(
+        &mut self,
+        samples: &[i16],
+        sample_rate: u32,
+        channels: u16,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        let (features, report) =
+            crate::audio::audio_features_with(samples, sample_rate, channels, &self.audio_options)?;
+        if debug.unwrap_or(self.debug) {
+            crate::logging::log_line(&report.to_string());
+        }
+        self.infer_slice(&features, debug)
+    }
+
+    /// Add a slice of samples and classify the current window
+    ///
+    /// Requires a model built with [`EimModelBuilder::continuous`]; see
+    /// [`ContinuousClassifier::classify_slice`].
+    pub fn classify_continuous(
+        &mut self,
+        slice: &[f32],
+    ) -> Result<Option<InferenceResponse>, EimError> {
+        let continuous = self.continuous.as_mut().ok_or_else(|| {
+            EimError::InvalidOperation(
+                "Continuous mode is not enabled, build the model with continuous(true)".to_string(),
+            )
+        })?;
+        let output = match &self.cancellation {
+            Some(token) => token.run(|| continuous.classify_slice(slice))?,
+            None => continuous.classify_slice(slice)?,
+        };
+        let Some(output) = output else {
+            return Ok(None);
+        };
+
+        profile_scope!("postprocess");
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(Some(InferenceResponse {
+            success: true,
+            id,
+            result: convert_inference_result(&output),
+        }))
+    }
+
+    /// Set the threshold of a learning block at runtime
+    ///
+    /// For object detection blocks this is the minimum confidence score, for anomaly (GMM)
+    /// blocks the anomaly threshold. Block IDs are listed in [`crate::thresholds`].
+    pub fn set_learn_block_threshold(&mut self, id: u32, value: f32) -> Result<(), EimError> {
+        self.classifier
+            .set_learn_block_threshold(id, value)
+            .map_err(|error| EimError::impulse("set_learn_block_threshold", error))?;
+        self.parameters.update_threshold(id, value);
+        Ok(())
+    }
+
+    /// Set the IoU (0.0 to 1.0) above which non-max suppression merges overlapping boxes
+    ///
+    /// Lower values suppress more boxes, which helps dense detection models that report the
+    /// same object several times. Block IDs are listed in [`crate::thresholds`].
+    pub fn set_nms_iou_threshold(&mut self, id: u32, iou_threshold: f32) -> Result<(), EimError> {
+        if !(0.0..=1.0).contains(&iou_threshold) {
+            return Err(EimError::InvalidInput(format!(
+                "NMS IoU threshold must be between 0.0 and 1.0, got {}",
+                iou_threshold
+            )));
+        }
+        self.classifier
+            .set_nms_iou_threshold(id, iou_threshold)
+            .map_err(|error| EimError::impulse("set_nms_iou_threshold", error))
+    }
+
+    /// Only return the `max_detections` highest scoring boxes per inference (`None` for all)
+    pub fn set_max_detections(&mut self, max_detections: Option<u32>) {
+        self.classifier.set_max_detections(max_detections);
+    }
+
+    /// Retry inference that runs out of memory in postprocessing with fewer boxes, see
+    /// [`Classifier::set_alloc_retry`]
+    pub fn set_alloc_retry(&mut self, alloc_retry: Option<AllocRetry>) {
+        self.classifier.set_alloc_retry(alloc_retry);
+    }
+
+    /// Choose how [`infer_audio`](Self::infer_audio) pads and trims recordings
+    pub fn set_audio_options(&mut self, options: AudioOptions) {
+        self.audio_options = options;
+    }
+
+    /// Choose what happens when features are on another scale than the model expects
+    ///
+    /// See [`crate::scaling`]; the default logs a warning for the first mismatch.
+    pub fn set_input_scaling_check(&mut self, check: ScalingCheck) {
+        self.scaling_check = check;
+    }
+
+    /// The first mismatch found by [`ScalingCheck::Warn`], `None` while the features looked fine
+    pub fn scaling_mismatch(&self) -> Option<&ScalingMismatch> {
+        self.scaling_mismatch.as_ref()
+    }
+
+    fn check_scaling(&mut self, features: &[f32]) -> Result<(), EimError> {
+        if self.scaling_check == ScalingCheck::Off {
+            return Ok(());
+        }
+        let Err(mismatch) = InputScaling::model().check(features) else {
+            return Ok(());
+        };
+        if self.scaling_check == ScalingCheck::Error {
+            return Err(EimError::InvalidInput(mismatch.to_string()));
+        }
+        if self.scaling_mismatch.is_none() {
+            crate::logging::log_line(&format!("edge-impulse-ffi-rs: warning: {}", mismatch));
+            self.scaling_mismatch = Some(mismatch);
+        }
+        Ok(())
+    }
+
+    /// Watch `token` during inference, so another thread can abort a running call
+    ///
+    /// A canceled call fails with [`EdgeImpulseError::Canceled`](crate::EdgeImpulseError::Canceled)
+    /// and the token stays canceled until it is [reset](CancellationToken::reset).
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Access the underlying classifier, e.g. to query the active thresholds
+    pub fn classifier(&self) -> &Classifier {
+        &self.classifier
+    }
+
+    /// Access the continuous classifier, e.g. to change the moving average filter while running
+    ///
+    /// `None` unless the model was built with [`EimModelBuilder::continuous`].
+    pub fn continuous_mut(&mut self) -> Option<&mut ContinuousClassifier> {
+        self.continuous.as_mut()
+    }
+
+    /// Print SDK debug output on every inference, unless overridden per call
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+        if let Some(continuous) = &mut self.continuous {
+            continuous.set_debug(debug);
+        }
+    }
+}
+
+/// Convert owned SDK results into the runner result type
+pub fn convert_inference_result(output: &InferenceOutput) -> InferenceResult {
+    let classification: HashMap<String, f32> = output
+        .classification
+        .iter()
+        .map(|c| (c.label.clone(), c.value))
+        .collect();
+
+    if let Some(visual_anomaly) = &output.visual_anomaly {
+        return InferenceResult::VisualAnomaly {
+            visual_anomaly_grid: visual_anomaly.grid.iter().map(convert_box).collect(),
+            visual_anomaly_max: visual_anomaly.max_value,
+            visual_anomaly_mean: visual_anomaly.mean_value,
+            anomaly: output.anomaly,
+        };
+    }
+
+    if let Some(embedding) = output.embeddings.first() {
+        return InferenceResult::Embedding {
+            vector: embedding.vector.clone(),
+            dtype: embedding.dtype,
+        };
+    }
+
+    if EI_CLASSIFIER_IS_REGRESSION {
+        if let Some(result) = output.classification.first() {
+            return InferenceResult::Regression {
+                value: result.value,
+            };
+        }
+    }
+
+    if EI_CLASSIFIER_OBJECT_TRACKING_ENABLED != 0 {
+        return InferenceResult::ObjectTracking {
+            object_tracking: output
+                .object_tracking
+                .iter()
+                .map(|object| TrackedObject {
+                    object_id: object.id,
+                    label: object.label.clone(),
+                    value: object.value,
+                    x: object.x as i32,
+                    y: object.y as i32,
+                    width: object.width as i32,
+                    height: object.height as i32,
+                })
+                .collect(),
+            bounding_boxes: output.bounding_boxes.iter().map(convert_box).collect(),
+            classification,
+        };
+    }
+
+    if EI_CLASSIFIER_OBJECT_DETECTION != 0 {
+        return InferenceResult::ObjectDetection {
+            bounding_boxes: output.bounding_boxes.iter().map(convert_box).collect(),
+            classification,
+        };
+    }
+
+    InferenceResult::Classification {
+        classification,
+        anomaly: output.anomaly_score(),
+    }
+}
+
+/// Build the runner's model parameters from the generated metadata
+// The types of the metadata constants depend on the model, so some casts may be no-ops
+#[allow(clippy::unnecessary_cast)]
+fn model_parameters() -> ModelParameters {
+    let metadata = ModelMetadata::get();
+    let pixels = metadata.input_width * metadata.input_height * metadata.input_frames.max(1);
+
+    ModelParameters {
+        axis_count: metadata.raw_samples_per_frame,
+        axis_names: metadata.axis_names.clone(),
+        frequency: metadata.frequency,
+        has_anomaly: metadata.has_anomaly,
+        has_object_tracking: metadata.has_object_tracking,
+        image_channel_count: metadata
+            .nn_input_frame_size
+            .checked_div(pixels)
+            .unwrap_or(0),
+        image_input_frames: metadata.input_frames,
+        image_resize_mode: metadata.resize_mode.name().to_string(),
+        image_input_height: metadata.input_height,
+        image_input_width: metadata.input_width,
+        inferencing_engine: metadata.inferencing_engine,
+        input_features_count: metadata.nn_input_frame_size,
+        interval_ms: metadata.interval_ms,
+        label_count: EI_CLASSIFIER_LABEL_COUNT as u32,
+        labels: metadata.labels.clone(),
+        model_type: metadata.model_type.clone(),
+        sensor: EI_CLASSIFIER_SENSOR,
+        slice_size: metadata.slice_size,
+        thresholds: metadata.thresholds.clone(),
+        use_continuous_mode: false,
+    }
+}
+
+fn convert_box(bb: &inference::BoundingBox) -> BoundingBox {
+    BoundingBox {
+        label: bb.label.clone(),
+        value: bb.value,
+        x: bb.x as i32,
+        y: bb.y as i32,
+        width: bb.width as i32,
+        height: bb.height as i32,
+    }
+}
@@ -0,0 +1,119 @@
+//! Thread-safe model wrapper
+//!
+//! [`EimModel`] is `Send` but not `Sync`, as the C++ SDK keeps global state without any locking.
+//! [`SyncModel`] serializes all access through a mutex, so a single model can be shared between
+//! threads behind an `Arc`. Separate models on other threads don't need it: their SDK calls
+//! already take turns on the process-wide lock of [`crate::classifier`].
+
+use std::sync::{Mutex, MutexGuard};
+
+use super::{EimError, EimModel, InferenceResponse, ModelParameters};
+use crate::inference::InferenceOutput;
+
+/// An [`EimModel`] that can be shared between threads
+pub struct SyncModel {
+    model: Mutex<EimModel>,
+    parameters: ModelParameters,
+}
+
+impl SyncModel {
+    /// Wrap a model
+    pub fn new(model: EimModel) -> Self {
+        let parameters = model
+            .parameters()
+            .cloned()
+            .expect("model parameters are always available in FFI mode");
+        Self {
+            model: Mutex::new(model),
+            parameters,
+        }
+    }
+
+    /// Run inference, waiting for any inference running on another thread to finish first
+    pub fn infer(
+        &self,
+        features: Vec<f32>,
+        debug: Option<bool>,
+    ) -> Result<InferenceResponse, EimError> {
+        self.lock().infer(features, debug)
+    }
+
+    /// Set the threshold of a learning block at runtime
+    pub fn set_learn_block_threshold(&self, id: u32, value: f32) -> Result<(), EimError> {
+        self.lock().set_learn_block_threshold(id, value)
+    }
+
+    /// Parameters of the linked model (doesn't need the lock)
+    pub fn parameters(&self) -> &ModelParameters {
+        &self.parameters
+    }
+
+    /// Lock the model for a sequence of calls that must not be interleaved with other threads
+    pub fn lock(&self) -> MutexGuard<'_, EimModel> {
+        // A panic during inference leaves no Rust-side state half-updated, so recover the model
+        self.model.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Unwrap the model
+    pub fn into_inner(self) -> EimModel {
+        self.model.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl From<EimModel> for SyncModel {
+    fn from(model: EimModel) -> Self {
+        Self::new(model)
+    }
+}
+
+// Compile-time checks of the threading contract documented in `crate::classifier`
+#[allow(dead_code)]
+fn assert_thread_safety() {
+    fn send<T: Send>() {}
+    fn sync<T: Sync>() {}
+    send::<EimModel>();
+    send::<SyncModel>();
+    sync::<SyncModel>();
+    send::<crate::classifier::ImpulseHandle>();
+    send::<InferenceOutput>();
+    sync::<InferenceOutput>();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::classifier::lock_native_state;
+    use crate::model_metadata::EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE;
+
+    #[test]
+    fn separate_models_never_infer_at_the_same_time() {
+        let shared = SyncModel::new(EimModel::new().unwrap());
+        let mut other = EimModel::new().unwrap();
+
+        // Stand in for an inference of `shared` that hasn't returned yet
+        let _shared = shared.lock();
+        let native = lock_native_state();
+        let done = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let _ = other.infer(vec![0.0; EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE], None);
+                done.store(true, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(100));
+        assert!(
+            !done.load(Ordering::SeqCst),
+            "the other model reached the SDK during an inference"
+        );
+
+        drop(native);
+        worker.join().unwrap();
+        assert!(done.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,468 @@
+//! Types shared with the Edge Impulse runner API
+//!
+//! These mirror the types used by `edge-impulse-runner-rs` so that code written against the
+//! EIM runner can be ported to the FFI backend with minimal changes.
+
+use std::collections::HashMap;
+
+use crate::classifier::AlreadyInitialized;
+use crate::error::EdgeImpulseError;
+use crate::fomo::{self, Centroid};
+use crate::inference::{self, OutputDType};
+
+/// Errors returned by [`EimModel`](super::EimModel)
+#[derive(Debug, thiserror::Error)]
+pub enum EimError {
+    /// An SDK call failed; `source` keeps the original `EI_IMPULSE_ERROR` code
+    #[error("{call} failed: {source}")]
+    Impulse {
+        /// The SDK call that failed (e.g. `run_classifier`)
+        call: &'static str,
+        /// Error returned by the SDK
+        source: EdgeImpulseError,
+    },
+    /// The input passed to the model is invalid (e.g. wrong number of features)
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    /// The requested operation is not supported by this model
+    #[error("Invalid operation: {0}")]
+    InvalidOperation(String),
+    /// Another model is alive and the [`InstancePolicy`](crate::classifier::InstancePolicy) is
+    /// `Exclusive`
+    #[error(transparent)]
+    AlreadyInitialized(#[from] AlreadyInitialized),
+}
+
+impl EimError {
+    /// Wrap an error returned by the SDK call `call`
+    pub fn impulse(call: &'static str, source: EdgeImpulseError) -> Self {
+        EimError::Impulse { call, source }
+    }
+
+    /// The SDK error, if this error came from the SDK
+    ///
+    /// Lets callers branch on specific failures, e.g. `Some(EdgeImpulseError::TfliteArenaAllocFailed)`.
+    pub fn impulse_error(&self) -> Option<EdgeImpulseError> {
+        match self {
+            EimError::Impulse { source, .. } => Some(*source),
+            _ => None,
+        }
+    }
+}
+
+impl From<EdgeImpulseError> for EimError {
+    fn from(error: EdgeImpulseError) -> Self {
+        EimError::impulse("Edge Impulse SDK call", error)
+    }
+}
+
+/// Response of a single inference call
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InferenceResponse {
+    /// Whether inference succeeded
+    pub success: bool,
+    /// Sequential ID of this inference
+    pub id: u32,
+    /// Inference results
+    pub result: InferenceResult,
+}
+
+/// Inference results, depending on the model type
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InferenceResult {
+    /// Classification results (label -> score)
+    Classification {
+        classification: HashMap<String, f32>,
+        /// Score of the KMeans or GMM anomaly block, `None` if the model has none
+        anomaly: Option<f32>,
+    },
+    /// Object detection results
+    ObjectDetection {
+        bounding_boxes: Vec<BoundingBox>,
+        classification: HashMap<String, f32>,
+    },
+    /// Object detection results with track IDs from object tracking
+    ObjectTracking {
+        object_tracking: Vec<TrackedObject>,
+        bounding_boxes: Vec<BoundingBox>,
+        classification: HashMap<String, f32>,
+    },
+    /// Visual anomaly detection results
+    VisualAnomaly {
+        visual_anomaly_grid: Vec<BoundingBox>,
+        visual_anomaly_max: f32,
+        visual_anomaly_mean: f32,
+        anomaly: f32,
+    },
+    /// Output vector of a model that produces embeddings instead of classes
+    Embedding {
+        vector: Vec<f32>,
+        dtype: OutputDType,
+    },
+    /// Predicted value of a regression model
+    Regression { value: f32 },
+}
+
+impl InferenceResult {
+    /// Anomaly score of the result, from a KMeans/GMM anomaly block or the visual anomaly block
+    pub fn anomaly(&self) -> Option<f32> {
+        match self {
+            InferenceResult::Classification { anomaly, .. } => *anomaly,
+            InferenceResult::VisualAnomaly { anomaly, .. } => Some(*anomaly),
+            _ => None,
+        }
+    }
+
+    /// The `n` highest scores, highest first
+    ///
+    /// Classification results rank their labels, detection results their bounding boxes (a
+    /// label can appear several times) and visual anomaly results their grid cells. Embeddings
+    /// and regression values have no scores.
+    pub fn top_k(&self, n: usize) -> Vec<(&str, f32)> {
+        let mut scores: Vec<(&str, f32)> = match self {
+            InferenceResult::Classification { classification, .. } => classification
+                .iter()
+                .map(|(label, &value)| (label.as_str(), value))
+                .collect(),
+            InferenceResult::ObjectDetection { bounding_boxes, .. }
+            | InferenceResult::ObjectTracking { bounding_boxes, .. }
+            | InferenceResult::VisualAnomaly {
+                visual_anomaly_grid: bounding_boxes,
+                ..
+            } => bounding_boxes
+                .iter()
+                .map(|bb| (bb.label.as_str(), bb.value))
+                .collect(),
+            InferenceResult::Embedding { .. } | InferenceResult::Regression { .. } => Vec::new(),
+        };
+        // Ties are broken by label so the order doesn't depend on the HashMap
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        scores.truncate(n);
+        scores
+    }
+
+    /// Detected objects as FOMO centroids, merging touching boxes of the same label
+    ///
+    /// `cell_size` is the number of input pixels per FOMO output cell, see
+    /// [`crate::fomo::centroids`]. Empty for results without bounding boxes.
+    pub fn fomo_centroids(&self, cell_size: u32) -> Vec<Centroid> {
+        let bounding_boxes = match self {
+            InferenceResult::ObjectDetection { bounding_boxes, .. }
+            | InferenceResult::ObjectTracking { bounding_boxes, .. } => bounding_boxes,
+            _ => return Vec::new(),
+        };
+        let boxes: Vec<inference::BoundingBox> = bounding_boxes
+            .iter()
+            .map(|bb| inference::BoundingBox {
+                label: bb.label.clone(),
+                value: bb.value,
+                x: bb.x.max(0) as u32,
+                y: bb.y.max(0) as u32,
+                width: bb.width.max(0) as u32,
+                height: bb.height.max(0) as u32,
+            })
+            .collect();
+        fomo::centroids(&boxes, cell_size)
+    }
+
+    /// A copy without the classification scores, bounding boxes and tracked objects scoring
+    /// below `min`
+    ///
+    /// Visual anomaly grid cells, embeddings and regression values are kept as they are, their
+    /// values aren't confidences.
+    pub fn filter_confidence(&self, min: f32) -> Self {
+        let keep_scores = |classification: &HashMap<String, f32>| {
+            classification
+                .iter()
+                .filter(|(_, &value)| value >= min)
+                .map(|(label, &value)| (label.clone(), value))
+                .collect()
+        };
+        let keep_boxes = |bounding_boxes: &[BoundingBox]| {
+            bounding_boxes
+                .iter()
+                .filter(|bb| bb.value >= min)
+                .cloned()
+                .collect()
+        };
+        match self {
+            InferenceResult::Classification {
+                classification,
+                anomaly,
+            } => InferenceResult::Classification {
+                classification: keep_scores(classification),
+                anomaly: *anomaly,
+            },
+            InferenceResult::ObjectDetection {
+                bounding_boxes,
+                classification,
+            } => InferenceResult::ObjectDetection {
+                bounding_boxes: keep_boxes(bounding_boxes),
+                classification: keep_scores(classification),
+            },
+            InferenceResult::ObjectTracking {
+                object_tracking,
+                bounding_boxes,
+                classification,
+            } => InferenceResult::ObjectTracking {
+                object_tracking: object_tracking
+                    .iter()
+                    .filter(|object| object.value >= min)
+                    .cloned()
+                    .collect(),
+                bounding_boxes: keep_boxes(bounding_boxes),
+                classification: keep_scores(classification),
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// A bounding box from object detection or a visual anomaly grid cell
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingBox {
+    /// Class label
+    pub label: String,
+    /// Confidence or anomaly score
+    pub value: f32,
+    /// X coordinate of the top-left corner
+    pub x: i32,
+    /// Y coordinate of the top-left corner
+    pub y: i32,
+    /// Width of the box
+    pub width: i32,
+    /// Height of the box
+    pub height: i32,
+}
+
+/// An object followed across frames by object tracking
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedObject {
+    /// Track ID, stable for as long as the object is tracked
+    pub object_id: u32,
+    /// Class label
+    pub label: String,
+    /// Confidence score
+    pub value: f32,
+    /// X coordinate of the top-left corner
+    pub x: i32,
+    /// Y coordinate of the top-left corner
+    pub y: i32,
+    /// Width of the box
+    pub width: i32,
+    /// Height of the box
+    pub height: i32,
+}
+
+/// Type of anomaly block in the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RunnerHelloHasAnomaly {
+    None = 0,
+    KMeans = 1,
+    GMM = 2,
+    VisualGMM = 3,
+}
+
+/// Type of sensor a model was trained on (`EI_CLASSIFIER_SENSOR`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SensorType {
+    Unknown = -1,
+    Microphone = 1,
+    Accelerometer = 2,
+    Camera = 3,
+    /// 9DOF positional sensor (accelerometer, gyroscope and magnetometer)
+    Positional = 4,
+    Environmental = 5,
+    /// Several sensors fused into one input, see [`ModelParameters::axis_names`]
+    Fusion = 6,
+}
+
+impl From<i32> for SensorType {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Self::Microphone,
+            2 => Self::Accelerometer,
+            3 => Self::Camera,
+            4 => Self::Positional,
+            5 => Self::Environmental,
+            6 => Self::Fusion,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl From<u32> for RunnerHelloHasAnomaly {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::KMeans,
+            2 => Self::GMM,
+            3 => Self::VisualGMM,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Studio project the model was exported from, as reported by the runner's hello message
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProjectInfo {
+    /// Project ID
+    pub id: u32,
+    /// Project owner (user or organization name)
+    pub owner: String,
+    /// Project name
+    pub name: String,
+    /// Deployment version the model was exported from
+    pub deploy_version: u32,
+}
+
+/// Model parameters, as reported by the runner's hello message
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModelParameters {
+    /// Number of axes per raw sample
+    pub axis_count: u32,
+    /// Names of the axes, in the order they're interleaved in the input (e.g. `accX`)
+    pub axis_names: Vec<String>,
+    /// Sampling frequency in Hz
+    pub frequency: f32,
+    /// Type of anomaly block
+    pub has_anomaly: RunnerHelloHasAnomaly,
+    /// Whether object tracking postprocessing is enabled
+    pub has_object_tracking: bool,
+    /// Number of channels of image input (1 for grayscale, 3 for RGB)
+    pub image_channel_count: u32,
+    /// How images are fitted to the input size ("none", "fit-short", "fit-long" or "squash")
+    pub image_resize_mode: String,
+    /// Number of stacked image frames
+    pub image_input_frames: u32,
+    /// Image input height
+    pub image_input_height: u32,
+    /// Image input width
+    pub image_input_width: u32,
+    /// Inferencing engine the model was compiled for
+    pub inferencing_engine: u32,
+    /// Number of input features
+    pub input_features_count: u32,
+    /// Interval between raw samples in ms
+    pub interval_ms: f32,
+    /// Number of labels
+    pub label_count: u32,
+    /// Class labels
+    pub labels: Vec<String>,
+    /// Model type ("classification", "regression", "object_detection" or "visual_anomaly")
+    pub model_type: String,
+    /// Sensor type, see [`ModelParameters::sensor_type`]
+    pub sensor: i32,
+    /// Number of raw samples per slice in continuous mode
+    pub slice_size: u32,
+    /// Thresholds of the learning blocks
+    pub thresholds: Vec<ModelThreshold>,
+    /// Whether the model runs in continuous mode
+    pub use_continuous_mode: bool,
+}
+
+/// Threshold of a learning block, see [`crate::thresholds`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModelThreshold {
+    /// Minimum confidence score of an object detection block
+    ObjectDetection { id: u32, min_score: f32 },
+    /// Anomaly threshold of a (visual) anomaly block
+    AnomalyGMM { id: u32, min_anomaly_score: f32 },
+    /// Threshold of a block of another type
+    Unknown { id: u32, unknown: f32 },
+}
+
+impl ModelThreshold {
+    /// Learning block ID
+    pub fn id(&self) -> u32 {
+        match *self {
+            ModelThreshold::ObjectDetection { id, .. }
+            | ModelThreshold::AnomalyGMM { id, .. }
+            | ModelThreshold::Unknown { id, .. } => id,
+        }
+    }
+
+    /// Threshold value
+    pub fn value(&self) -> f32 {
+        match *self {
+            ModelThreshold::ObjectDetection { min_score, .. } => min_score,
+            ModelThreshold::AnomalyGMM {
+                min_anomaly_score, ..
+            } => min_anomaly_score,
+            ModelThreshold::Unknown { unknown, .. } => unknown,
+        }
+    }
+
+    fn set_value(&mut self, value: f32) {
+        match self {
+            ModelThreshold::ObjectDetection { min_score, .. } => *min_score = value,
+            ModelThreshold::AnomalyGMM {
+                min_anomaly_score, ..
+            } => *min_anomaly_score = value,
+            ModelThreshold::Unknown { unknown, .. } => *unknown = value,
+        }
+    }
+}
+
+impl From<&crate::thresholds::Threshold> for ModelThreshold {
+    fn from(threshold: &crate::thresholds::Threshold) -> Self {
+        let id = threshold.id as u32;
+        match threshold.threshold_type {
+            "object_detection" => ModelThreshold::ObjectDetection {
+                id,
+                min_score: threshold.min_score,
+            },
+            "visual_anomaly" => ModelThreshold::AnomalyGMM {
+                id,
+                min_anomaly_score: threshold.min_score,
+            },
+            _ => ModelThreshold::Unknown {
+                id,
+                unknown: threshold.min_score,
+            },
+        }
+    }
+}
+
+impl ModelParameters {
+    /// Type of sensor the model was trained on
+    pub fn sensor_type(&self) -> SensorType {
+        SensorType::from(self.sensor)
+    }
+
+    /// Check that input axes are ordered the way the model expects, e.g. before interleaving
+    /// the readings of several sensors for a sensor fusion model
+    pub fn validate_axes<S: AsRef<str>>(&self, axes: &[S]) -> Result<(), EimError> {
+        let matches = axes.len() == self.axis_names.len()
+            && axes
+                .iter()
+                .zip(&self.axis_names)
+                .all(|(axis, expected)| axis.as_ref() == expected);
+        if matches {
+            return Ok(());
+        }
+        Err(EimError::InvalidInput(format!(
+            "Expected axes [{}], got [{}]",
+            self.axis_names.join(", "),
+            axes.iter()
+                .map(|axis| axis.as_ref())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+
+    /// Record a threshold changed at runtime
+    pub(crate) fn update_threshold(&mut self, id: u32, value: f32) {
+        if let Some(threshold) = self.thresholds.iter_mut().find(|t| t.id() == id) {
+            threshold.set_value(value);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Input scaling checks
+//!
+//! DSP blocks expect their raw features on a specific scale: image blocks take pixels packed as
+//! `0xRRGGBB`, audio blocks take raw 16-bit samples. Features on another scale (pixels
+//! normalized to 0–1, audio normalized to -1–1) don't fail, they just classify badly.
+//! [`InputScaling::check`] spots the common mix-ups so they can be reported instead.
+//!
+//! The scaling is taken from the impulse's first DSP block at build time
+//! (`EI_CLASSIFIER_INPUT_SCALING`).
+
+use std::fmt;
+
+use crate::model_metadata::EI_CLASSIFIER_INPUT_SCALING;
+
+/// Scale of the raw features a model expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputScaling {
+    /// Pixels packed as `0xRRGGBB`, i.e. 0 to 16777215 (image blocks)
+    PackedRgb,
+    /// Raw 16-bit samples, -32768 to 32767 (audio blocks)
+    RawI16,
+    /// Values as they come from the sensor; not checked
+    Raw,
+}
+
+impl InputScaling {
+    /// The scaling expected by the linked model
+    pub fn model() -> Self {
+        match EI_CLASSIFIER_INPUT_SCALING {
+            "packed_rgb" => InputScaling::PackedRgb,
+            "raw_i16" => InputScaling::RawI16,
+            _ => InputScaling::Raw,
+        }
+    }
+
+    /// Check that `features` are plausible for this scaling
+    ///
+    /// Only flags features that are clearly on another scale, e.g. image features that are all
+    /// between 0 and 1; all-zero input passes.
+    pub fn check(&self, features: &[f32]) -> Result<(), ScalingMismatch> {
+        let (min, max) = features
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        if features.is_empty() || (min == 0.0 && max == 0.0) {
+            return Ok(());
+        }
+
+        let found = match self {
+            InputScaling::PackedRgb if min < 0.0 => "negative values, e.g. standardized pixels",
+            InputScaling::PackedRgb if max <= 1.0 => {
+                "values between 0 and 1, e.g. normalized pixels"
+            }
+            InputScaling::PackedRgb if max <= 255.0 => {
+                "values between 0 and 255, e.g. one channel per feature"
+            }
+            InputScaling::PackedRgb if max > 16_777_215.0 => "values above 0xFFFFFF",
+            InputScaling::RawI16 if min >= -1.0 && max <= 1.0 => {
+                "values between -1 and 1, e.g. normalized audio"
+            }
+            InputScaling::RawI16 if min < -32_768.0 || max > 32_767.0 => {
+                "values outside the 16-bit range"
+            }
+            _ => return Ok(()),
+        };
+        Err(ScalingMismatch {
+            expected: *self,
+            found,
+            min,
+            max,
+        })
+    }
+}
+
+impl fmt::Display for InputScaling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputScaling::PackedRgb => "pixels packed as 0xRRGGBB",
+            InputScaling::RawI16 => "raw 16-bit samples (-32768 to 32767)",
+            InputScaling::Raw => "raw sensor values",
+        })
+    }
+}
+
+/// What [`EimModel`](crate::EimModel) does with features on another scale than the model expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingCheck {
+    /// Don't check the features
+    Off,
+    /// Log a warning with the SDK's debug output (see [`crate::logging`]) and keep the mismatch
+    /// for [`EimModel::scaling_mismatch`](crate::EimModel::scaling_mismatch), once per model
+    #[default]
+    Warn,
+    /// Fail the inference with [`EimError::InvalidInput`](crate::EimError::InvalidInput)
+    Error,
+}
+
+/// Features that are on another scale than the model expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalingMismatch {
+    /// Scaling the model expects
+    pub expected: InputScaling,
+    /// What the features look like
+    pub found: &'static str,
+    /// Smallest feature
+    pub min: f32,
+    /// Largest feature
+    pub max: f32,
+}
+
+impl fmt::Display for ScalingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the model expects {}, but the features are {} (min {}, max {})",
+            self.expected, self.found, self.min, self.max
+        )
+    }
+}
+
+impl std::error::Error for ScalingMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_features_on_another_scale() {
+        let image = InputScaling::PackedRgb;
+        assert!(image
+            .check(&[0.0, 0x203040 as f32, 0xFFFFFF as f32])
+            .is_ok());
+        assert!(image.check(&[0.0; 4]).is_ok());
+        assert!(image.check(&[0.1, 0.5, 1.0]).is_err());
+        assert!(image.check(&[12.0, 255.0]).is_err());
+
+        let audio = InputScaling::RawI16;
+        assert!(audio.check(&[-1200.0, 30.0, 800.0]).is_ok());
+        let mismatch = audio.check(&[-0.25, 0.5]).unwrap_err();
+        assert_eq!(mismatch.max, 0.5);
+
+        assert!(InputScaling::Raw.check(&[1e9]).is_ok());
+    }
+}
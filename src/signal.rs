@@ -0,0 +1,53 @@
+//! Borrowed input signals
+//!
+//! The SDK reads its input through a `signal_t`, which pulls features on demand instead of
+//! owning a buffer. [`Signal`] wraps a `signal_t` that reads straight from a borrowed `&[f32]`,
+//! so large inputs (e.g. megapixel images) are classified without copying them. The borrow
+//! keeps the slice alive and unchanged for as long as the signal exists.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::bindings::*;
+use crate::error::{check, EdgeImpulseError};
+
+/// An SDK input signal reading from a borrowed slice of features
+pub struct Signal<'a> {
+    raw: ei_signal_t,
+    len: usize,
+    _data: PhantomData<&'a [f32]>,
+}
+
+impl<'a> Signal<'a> {
+    /// Create a signal that reads from `data` without copying it
+    pub fn from_slice(data: &'a [f32]) -> Result<Self, EdgeImpulseError> {
+        let mut raw = ei_signal_t::default();
+        check(unsafe { ei_ffi_signal_from_buffer(data.as_ptr(), data.len(), &mut raw) })?;
+        Ok(Self {
+            raw,
+            len: data.len(),
+            _data: PhantomData,
+        })
+    }
+
+    /// Number of features in the signal
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the signal has no features
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Raw `signal_t` to pass to the SDK
+    pub(crate) fn as_raw_mut(&mut self) -> &mut ei_signal_t {
+        &mut self.raw
+    }
+}
+
+impl fmt::Debug for Signal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signal").field("len", &self.len).finish()
+    }
+}
@@ -0,0 +1,201 @@
+//! Similarity search over embeddings
+//!
+//! Verification-style impulses (voice or face ID) output an [`Embedding`](crate::Embedding)
+//! rather than class scores. [`CentroidSet`] holds one reference vector per enrolled identity
+//! and finds the closest ones to a new embedding, using cosine similarity or L2 distance.
+
+use crate::runner_api::EimError;
+
+/// How vectors are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Cosine similarity, from -1.0 to 1.0; higher is closer
+    Cosine,
+    /// Euclidean distance; lower is closer
+    L2,
+}
+
+impl Metric {
+    /// Compare two vectors of the same length
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vectors have different lengths.
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => cosine_similarity(a, b),
+            Metric::L2 => l2_distance(a, b),
+        }
+    }
+}
+
+/// Cosine similarity of two vectors, 0.0 if either of them is all zeros
+///
+/// # Panics
+///
+/// Panics if the vectors have different lengths.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = norm(a) * norm(b);
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+/// Euclidean distance between two vectors
+///
+/// # Panics
+///
+/// Panics if the vectors have different lengths.
+pub fn l2_distance(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// A centroid matched by [`CentroidSet::top_k`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    /// Label the centroid was added with
+    pub label: &'a str,
+    /// Position of the centroid in the set
+    pub index: usize,
+    /// Similarity or distance to the query, depending on the [`Metric`]
+    pub score: f32,
+}
+
+/// Labeled reference vectors, e.g. the averaged embeddings of enrolled speakers
+#[derive(Debug, Clone, Default)]
+pub struct CentroidSet {
+    labels: Vec<String>,
+    vectors: Vec<Vec<f32>>,
+}
+
+impl CentroidSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a centroid; all centroids must have the same length
+    pub fn add(&mut self, label: impl Into<String>, vector: Vec<f32>) -> Result<(), EimError> {
+        self.check_len(&vector)?;
+        self.labels.push(label.into());
+        self.vectors.push(vector);
+        Ok(())
+    }
+
+    /// Add the mean of `vectors` as one centroid, e.g. from several enrollment samples
+    pub fn add_mean<V: AsRef<[f32]>>(
+        &mut self,
+        label: impl Into<String>,
+        vectors: &[V],
+    ) -> Result<(), EimError> {
+        let Some(first) = vectors.first() else {
+            return Err(EimError::InvalidInput(
+                "At least one vector is needed to compute a centroid".to_string(),
+            ));
+        };
+        let mut mean = vec![0.0; first.as_ref().len()];
+        for vector in vectors {
+            let vector = vector.as_ref();
+            if vector.len() != mean.len() {
+                return Err(EimError::InvalidInput(format!(
+                    "Expected vectors of {} values, got {}",
+                    mean.len(),
+                    vector.len()
+                )));
+            }
+            mean.iter_mut().zip(vector).for_each(|(m, v)| *m += v);
+        }
+        mean.iter_mut().for_each(|m| *m /= vectors.len() as f32);
+        self.add(label, mean)
+    }
+
+    /// Number of centroids
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Whether the set has no centroids
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// The `k` centroids closest to `query`, closest first
+    pub fn top_k(
+        &self,
+        query: &[f32],
+        k: usize,
+        metric: Metric,
+    ) -> Result<Vec<Match<'_>>, EimError> {
+        self.check_len(query)?;
+        let mut matches: Vec<Match<'_>> = self
+            .vectors
+            .iter()
+            .enumerate()
+            .map(|(index, vector)| Match {
+                label: &self.labels[index],
+                index,
+                score: metric.score(query, vector),
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            let ordering = a.score.total_cmp(&b.score);
+            match metric {
+                Metric::Cosine => ordering.reverse(),
+                Metric::L2 => ordering,
+            }
+        });
+        matches.truncate(k);
+        Ok(matches)
+    }
+
+    /// The centroid closest to `query`, `None` if the set is empty
+    pub fn nearest(&self, query: &[f32], metric: Metric) -> Result<Option<Match<'_>>, EimError> {
+        Ok(self.top_k(query, 1, metric)?.into_iter().next())
+    }
+
+    fn check_len(&self, vector: &[f32]) -> Result<(), EimError> {
+        match self.vectors.first() {
+            Some(first) if first.len() != vector.len() => Err(EimError::InvalidInput(format!(
+                "Expected vectors of {} values, got {}",
+                first.len(),
+                vector.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_centroids_by_metric() {
+        let mut set = CentroidSet::new();
+        set.add("alice", vec![1.0, 0.0]).unwrap();
+        set.add("bob", vec![0.0, 1.0]).unwrap();
+        set.add_mean("carol", &[[1.0, 1.0], [3.0, 3.0]]).unwrap();
+        assert!(set.add("dave", vec![1.0]).is_err());
+
+        let cosine = set.top_k(&[0.9, 0.1], 2, Metric::Cosine).unwrap();
+        assert_eq!(cosine[0].label, "alice");
+        assert_eq!(cosine[1].label, "carol");
+
+        let nearest = set.nearest(&[2.0, 2.0], Metric::L2).unwrap().unwrap();
+        assert_eq!(nearest.label, "carol");
+        assert_eq!(nearest.score, 0.0);
+    }
+}
@@ -0,0 +1,43 @@
+//! Semver-stable facade
+//!
+//! [`bindings`](crate::bindings) and [`model_metadata`](crate::model_metadata) are generated
+//! from the linked model, so their types, fields and constant types can change whenever the
+//! model or SDK changes. Libraries building on this crate should only use the items re-exported
+//! here: none of them expose generated types in their fields, and their signatures stay the
+//! same for every model.
+//!
+//! The raw conversions on these types (`Timing::from_raw`, `From<&ei_impulse_result_timing_t>`,
+//! `InferenceOutput::from_raw`, `From<&ei_impulse_result_t>`,
+//! `InferenceOutput::from_raw_result`, `From<EI_IMPULSE_ERROR>`) take generated types and are not
+//! covered by this guarantee.
+
+pub use crate::cancel::CancellationToken;
+pub use crate::classifier::{
+    instance_policy, set_instance_policy, AlreadyInitialized, Classifier, ImpulseHandle,
+    InstancePolicy,
+};
+pub use crate::error::{DspErrorCode, EdgeImpulseError};
+pub use crate::features::Features;
+pub use crate::frames::{FrameLayout, FrameStacker};
+pub use crate::inference::{
+    BoundingBox, Classification, Embedding, InferenceOutput, OutputDType, Timing, TrackedObject,
+    VisualAnomaly,
+};
+pub use crate::journal::{
+    clear_error_journal, error_journal, set_error_journal_capacity, JournalEntry,
+};
+pub use crate::logging::{
+    last_native_logs, set_native_log_callback, set_native_log_sink, NativeLogSink,
+};
+pub use crate::metadata::ModelMetadata;
+pub use crate::porting::{reset_porting_hooks, set_porting_hooks, PortingHooks};
+pub use crate::preprocessing::{features_from_rgb_bytes, features_from_rgba_bytes, ResizeMode};
+pub use crate::quantization::{QuantizationInfo, TensorQuantization};
+pub use crate::runner_api::{
+    ContinuousClassifier, EimError, EimModel, EimModelBuilder, InferenceResponse, InferenceResult,
+    ModelParameters, ModelThreshold, ProjectInfo, RunnerHelloHasAnomaly, SensorType, SyncModel,
+};
+pub use crate::scaling::{InputScaling, ScalingCheck, ScalingMismatch};
+pub use crate::signal::Signal;
+pub use crate::similarity::{cosine_similarity, l2_distance, CentroidSet, Match, Metric};
+pub use crate::timing::{clock_source, set_timer, ClockSource};
@@ -0,0 +1,415 @@
+//! Tolerant comparison of inference results, for tests
+//!
+//! Results from the same model differ slightly between builds (TFLite vs EON, float vs int8,
+//! different compilers), so comparing them with `==` is too strict. [`assert_results_close`]
+//! compares classification scores and anomaly scores within a tolerance, and matches bounding
+//! boxes by label and IoU instead of by exact coordinates.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::runner_api::{BoundingBox, InferenceResult};
+
+/// How far two results may differ and still be considered equal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Maximum absolute difference between two scores (classification, box confidence,
+    /// regression value or embedding element)
+    pub score: f32,
+    /// Minimum IoU for two bounding boxes with the same label to be considered the same box
+    pub iou: f32,
+    /// Maximum absolute difference between two anomaly scores
+    pub anomaly: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            score: 0.01,
+            iou: 0.5,
+            anomaly: 0.05,
+        }
+    }
+}
+
+/// A difference between two results that is larger than the [`Tolerance`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch(pub String);
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Intersection over union of two boxes, 0.0 if they don't overlap
+pub fn iou(a: &BoundingBox, b: &BoundingBox) -> f32 {
+    let left = a.x.max(b.x);
+    let top = a.y.max(b.y);
+    let right = (a.x + a.width).min(b.x + b.width);
+    let bottom = (a.y + a.height).min(b.y + b.height);
+    if right <= left || bottom <= top {
+        return 0.0;
+    }
+    let intersection = ((right - left) as f32) * ((bottom - top) as f32);
+    let area = |bb: &BoundingBox| (bb.width as f32) * (bb.height as f32);
+    intersection / (area(a) + area(b) - intersection)
+}
+
+/// Every difference between `expected` and `actual` that is larger than `tol`
+///
+/// Results of different kinds (e.g. classification vs object detection) are a single mismatch.
+pub fn compare_results(
+    expected: &InferenceResult,
+    actual: &InferenceResult,
+    tol: Tolerance,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    match (expected, actual) {
+        (
+            InferenceResult::Classification {
+                classification: expected_scores,
+                anomaly: expected_anomaly,
+            },
+            InferenceResult::Classification {
+                classification: actual_scores,
+                anomaly: actual_anomaly,
+            },
+        ) => {
+            compare_scores(expected_scores, actual_scores, tol, &mut mismatches);
+            compare_anomaly(*expected_anomaly, *actual_anomaly, tol, &mut mismatches);
+        }
+        (
+            InferenceResult::ObjectDetection {
+                bounding_boxes: expected_boxes,
+                classification: expected_scores,
+            },
+            InferenceResult::ObjectDetection {
+                bounding_boxes: actual_boxes,
+                classification: actual_scores,
+            },
+        )
+        | (
+            InferenceResult::ObjectTracking {
+                bounding_boxes: expected_boxes,
+                classification: expected_scores,
+                ..
+            },
+            InferenceResult::ObjectTracking {
+                bounding_boxes: actual_boxes,
+                classification: actual_scores,
+                ..
+            },
+        ) => {
+            compare_scores(expected_scores, actual_scores, tol, &mut mismatches);
+            compare_boxes(
+                expected_boxes,
+                actual_boxes,
+                tol,
+                tol.score,
+                &mut mismatches,
+            );
+        }
+        (
+            InferenceResult::VisualAnomaly {
+                visual_anomaly_grid: expected_grid,
+                visual_anomaly_max: expected_max,
+                visual_anomaly_mean: expected_mean,
+                anomaly: expected_anomaly,
+            },
+            InferenceResult::VisualAnomaly {
+                visual_anomaly_grid: actual_grid,
+                visual_anomaly_max: actual_max,
+                visual_anomaly_mean: actual_mean,
+                anomaly: actual_anomaly,
+            },
+        ) => {
+            compare_anomaly(
+                Some(*expected_anomaly),
+                Some(*actual_anomaly),
+                tol,
+                &mut mismatches,
+            );
+            compare_value(
+                "visual anomaly max",
+                *expected_max,
+                *actual_max,
+                tol.anomaly,
+                &mut mismatches,
+            );
+            compare_value(
+                "visual anomaly mean",
+                *expected_mean,
+                *actual_mean,
+                tol.anomaly,
+                &mut mismatches,
+            );
+            compare_boxes(
+                expected_grid,
+                actual_grid,
+                tol,
+                tol.anomaly,
+                &mut mismatches,
+            );
+        }
+        (
+            InferenceResult::Embedding {
+                vector: expected_vector,
+                ..
+            },
+            InferenceResult::Embedding {
+                vector: actual_vector,
+                ..
+            },
+        ) => {
+            if expected_vector.len() != actual_vector.len() {
+                mismatches.push(Mismatch(format!(
+                    "embedding length: expected {}, got {}",
+                    expected_vector.len(),
+                    actual_vector.len()
+                )));
+            } else {
+                for (i, (e, a)) in expected_vector.iter().zip(actual_vector).enumerate() {
+                    compare_value(
+                        &format!("embedding[{}]", i),
+                        *e,
+                        *a,
+                        tol.score,
+                        &mut mismatches,
+                    );
+                }
+            }
+        }
+        (
+            InferenceResult::Regression { value: expected },
+            InferenceResult::Regression { value: actual },
+        ) => compare_value(
+            "regression value",
+            *expected,
+            *actual,
+            tol.score,
+            &mut mismatches,
+        ),
+        _ => mismatches.push(Mismatch(format!(
+            "result kind: expected {}, got {}",
+            kind(expected),
+            kind(actual)
+        ))),
+    }
+    mismatches
+}
+
+/// Panic with every mismatch if `actual` differs from `expected` by more than `tol`
+///
+/// # Panics
+///
+/// Panics if [`compare_results`] finds any mismatch.
+#[track_caller]
+pub fn assert_results_close(expected: &InferenceResult, actual: &InferenceResult, tol: Tolerance) {
+    let mismatches = compare_results(expected, actual, tol);
+    if !mismatches.is_empty() {
+        let lines: Vec<String> = mismatches.iter().map(|m| format!("  {}", m)).collect();
+        panic!("results differ:\n{}", lines.join("\n"));
+    }
+}
+
+fn kind(result: &InferenceResult) -> &'static str {
+    match result {
+        InferenceResult::Classification { .. } => "classification",
+        InferenceResult::ObjectDetection { .. } => "object detection",
+        InferenceResult::ObjectTracking { .. } => "object tracking",
+        InferenceResult::VisualAnomaly { .. } => "visual anomaly",
+        InferenceResult::Embedding { .. } => "embedding",
+        InferenceResult::Regression { .. } => "regression",
+    }
+}
+
+fn compare_value(what: &str, expected: f32, actual: f32, tol: f32, mismatches: &mut Vec<Mismatch>) {
+    if (expected - actual).abs() > tol {
+        mismatches.push(Mismatch(format!(
+            "{}: expected {}, got {} (tolerance {})",
+            what, expected, actual, tol
+        )));
+    }
+}
+
+fn compare_scores(
+    expected: &HashMap<String, f32>,
+    actual: &HashMap<String, f32>,
+    tol: Tolerance,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let mut labels: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+    labels.sort();
+    labels.dedup();
+    for label in labels {
+        match (expected.get(label), actual.get(label)) {
+            (Some(&e), Some(&a)) => compare_value(
+                &format!("score of {:?}", label),
+                e,
+                a,
+                tol.score,
+                mismatches,
+            ),
+            (Some(_), None) => mismatches.push(Mismatch(format!("missing label {:?}", label))),
+            (None, _) => mismatches.push(Mismatch(format!("unexpected label {:?}", label))),
+        }
+    }
+}
+
+fn compare_anomaly(
+    expected: Option<f32>,
+    actual: Option<f32>,
+    tol: Tolerance,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    match (expected, actual) {
+        (Some(e), Some(a)) => compare_value("anomaly score", e, a, tol.anomaly, mismatches),
+        (None, None) => {}
+        _ => mismatches.push(Mismatch(format!(
+            "anomaly score: expected {:?}, got {:?}",
+            expected, actual
+        ))),
+    }
+}
+
+/// Match every expected box to the unused actual box with the same label and the highest IoU
+fn compare_boxes(
+    expected: &[BoundingBox],
+    actual: &[BoundingBox],
+    tol: Tolerance,
+    value_tol: f32,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let mut used = vec![false; actual.len()];
+    for bb in expected {
+        let best = actual
+            .iter()
+            .enumerate()
+            .filter(|(i, candidate)| !used[*i] && candidate.label == bb.label)
+            .map(|(i, candidate)| (i, iou(bb, candidate)))
+            .filter(|&(_, overlap)| overlap >= tol.iou)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((i, _)) => {
+                used[i] = true;
+                compare_value(
+                    &format!("value of {:?} box at ({}, {})", bb.label, bb.x, bb.y),
+                    bb.value,
+                    actual[i].value,
+                    value_tol,
+                    mismatches,
+                );
+            }
+            None => mismatches.push(Mismatch(format!(
+                "no {:?} box matching ({}, {}, {}x{}) with IoU >= {}",
+                bb.label, bb.x, bb.y, bb.width, bb.height, tol.iou
+            ))),
+        }
+    }
+    for (bb, _) in actual.iter().zip(&used).filter(|(_, &used)| !used) {
+        mismatches.push(Mismatch(format!(
+            "unexpected {:?} box at ({}, {}, {}x{})",
+            bb.label, bb.x, bb.y, bb.width, bb.height
+        )));
+    }
+}
+
+/// Bounding box types that [`bbox`] builds
+#[cfg(test)]
+pub(crate) trait TestBox {
+    fn from_parts(label: &str, value: f32, x: i32, y: i32, width: i32, height: i32) -> Self;
+}
+
+#[cfg(test)]
+impl TestBox for BoundingBox {
+    fn from_parts(label: &str, value: f32, x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            label: label.to_string(),
+            value,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+#[cfg(test)]
+impl TestBox for crate::inference::BoundingBox {
+    fn from_parts(label: &str, value: f32, x: i32, y: i32, width: i32, height: i32) -> Self {
+        let coordinate = |value: i32| u32::try_from(value).expect("negative coordinate");
+        Self {
+            label: label.to_string(),
+            value,
+            x: coordinate(x),
+            y: coordinate(y),
+            width: coordinate(width),
+            height: coordinate(height),
+        }
+    }
+}
+
+/// A `width` × `height` box with its top-left corner at (`x`, `y`), for unit tests of either
+/// bounding box type
+#[cfg(test)]
+pub(crate) fn bbox<B: TestBox>(
+    label: &str,
+    value: f32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> B {
+    B::from_parts(label, value, x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_boxes_by_iou_and_scores_within_tolerance() {
+        let expected = InferenceResult::ObjectDetection {
+            bounding_boxes: vec![
+                bbox("cat", 0.9, 0, 0, 10, 10),
+                bbox("dog", 0.8, 50, 50, 10, 10),
+            ],
+            classification: HashMap::new(),
+        };
+        let actual = InferenceResult::ObjectDetection {
+            bounding_boxes: vec![
+                bbox("dog", 0.805, 51, 50, 10, 10),
+                bbox("cat", 0.895, 1, 1, 10, 10),
+            ],
+            classification: HashMap::new(),
+        };
+        assert_results_close(&expected, &actual, Tolerance::default());
+
+        let shifted = InferenceResult::ObjectDetection {
+            bounding_boxes: vec![
+                bbox("cat", 0.9, 0, 0, 10, 10),
+                bbox("dog", 0.8, 58, 58, 10, 10),
+            ],
+            classification: HashMap::new(),
+        };
+        let mismatches = compare_results(&expected, &shifted, Tolerance::default());
+        assert_eq!(mismatches.len(), 2);
+    }
+
+    #[test]
+    fn reports_score_and_anomaly_differences() {
+        let expected = InferenceResult::Classification {
+            classification: HashMap::from([("idle".to_string(), 0.7), ("wave".to_string(), 0.3)]),
+            anomaly: Some(-0.2),
+        };
+        let actual = InferenceResult::Classification {
+            classification: HashMap::from([("idle".to_string(), 0.6), ("wave".to_string(), 0.3)]),
+            anomaly: Some(0.5),
+        };
+        let mismatches = compare_results(&expected, &actual, Tolerance::default());
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].0.contains("idle"));
+        assert!(mismatches[1].0.contains("anomaly"));
+    }
+}
@@ -0,0 +1,51 @@
+// Stand-in for the generated thresholds.rs, used by the `dummy` feature and on docs.rs.
+
+/// Represents a threshold configuration for a specific block
+#[derive(Debug, Clone)]
+pub struct Threshold {
+    /// Block ID for this threshold
+    pub id: usize,
+    /// Minimum score threshold
+    pub min_score: f32,
+    /// Type of threshold (e.g., "object_detection")
+    pub threshold_type: &'static str,
+}
+
+/// Collection of all thresholds in the model
+#[derive(Debug, Clone)]
+pub struct Thresholds {
+    /// List of all thresholds in the model
+    pub thresholds: Vec<Threshold>,
+}
+
+impl Thresholds {
+    /// Get all object detection thresholds
+    pub fn object_detection_thresholds(&self) -> Vec<&Threshold> {
+        self.thresholds
+            .iter()
+            .filter(|t| t.threshold_type == "object_detection")
+            .collect()
+    }
+
+    /// Get threshold for a specific block ID
+    pub fn get_threshold(&self, block_id: usize) -> Option<&Threshold> {
+        self.thresholds.iter().find(|t| t.id == block_id)
+    }
+
+    /// Get the default object detection threshold (first one found)
+    pub fn default_object_detection_threshold(&self) -> Option<f32> {
+        self.object_detection_thresholds()
+            .first()
+            .map(|t| t.min_score)
+    }
+}
+
+/// All thresholds in the model
+pub const MODEL_THRESHOLDS: &[Threshold] = &[];
+
+/// Get all thresholds in the model
+pub fn get_model_thresholds() -> Thresholds {
+    Thresholds {
+        thresholds: MODEL_THRESHOLDS.to_vec(),
+    }
+}
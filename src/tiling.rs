@@ -0,0 +1,237 @@
+//! Tiled inference on large images
+//!
+//! Object detection models take small inputs (e.g. 320x320), so small objects in a
+//! high-resolution frame shrink to a few pixels or vanish when the frame is resized. Tiling
+//! splits the frame into overlapping tiles of about the model's input size, runs detection on
+//! every tile, maps the boxes back to frame coordinates and merges the duplicates found by
+//! neighbouring tiles with a [`Dedup`], per-label non-max suppression by default. Optionally the whole frame is
+//! detected as well, a two-level image pyramid that keeps objects larger than a tile.
+//!
+//! ```ignore
+//! use edge_impulse_ffi_rs::tiling::Tiling;
+//!
+//! let response = model.infer_tiled_rgb(&frame, 3840, 2160, 3840 * 3, &Tiling::model(), None)?;
+//! ```
+
+use crate::dedup::Dedup;
+use crate::model_metadata::{EI_CLASSIFIER_INPUT_HEIGHT, EI_CLASSIFIER_INPUT_WIDTH};
+use crate::preprocessing::{Placement, RawFrame, ResizeMode};
+use crate::runner_api::{BoundingBox, EimError};
+
+/// How a frame is split into tiles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tiling {
+    /// Tile size in frame pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Fraction of a tile shared with its neighbours, so objects on a border are whole in one
+    /// of them
+    pub overlap: f32,
+    /// Also detect on the whole frame, for objects larger than a tile
+    pub full_frame: bool,
+    /// How the duplicates found by several tiles are merged
+    pub dedup: Dedup,
+}
+
+impl Tiling {
+    /// Tiles of `tile_width` x `tile_height` pixels with 20% overlap and a full-frame pass
+    pub fn new(tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            tile_width,
+            tile_height,
+            overlap: 0.2,
+            full_frame: true,
+            dedup: Dedup::new(0.5),
+        }
+    }
+
+    /// Tiles of the model's input size, so tiles aren't scaled down
+    // The types of the metadata constants depend on the model, so the casts may be no-ops
+    #[allow(clippy::unnecessary_cast)]
+    pub fn model() -> Self {
+        Self::new(
+            EI_CLASSIFIER_INPUT_WIDTH as u32,
+            EI_CLASSIFIER_INPUT_HEIGHT as u32,
+        )
+    }
+
+    pub fn overlap(mut self, overlap: f32) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn full_frame(mut self, full_frame: bool) -> Self {
+        self.full_frame = full_frame;
+        self
+    }
+
+    /// IoU above which boxes are duplicates
+    pub fn iou_threshold(mut self, iou_threshold: f32) -> Self {
+        self.dedup.iou_threshold = iou_threshold;
+        self
+    }
+
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Tiles covering a `width` x `height` frame, row by row
+    ///
+    /// Tiles are clipped to frames smaller than a tile, and the last tile of a row or column is
+    /// moved back to end at the frame's edge.
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<Tile> {
+        let xs = positions(width, self.tile_width, self.overlap);
+        let ys = positions(height, self.tile_height, self.overlap);
+        ys.iter()
+            .flat_map(|&y| {
+                xs.iter().map(move |&x| Tile {
+                    x,
+                    y,
+                    width: self.tile_width.min(width),
+                    height: self.tile_height.min(height),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A region of the frame, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Start of every tile along an axis of `length` pixels
+fn positions(length: u32, tile: u32, overlap: f32) -> Vec<u32> {
+    if length <= tile {
+        return vec![0];
+    }
+    let step = ((tile as f32 * (1.0 - overlap.clamp(0.0, 0.9))).round() as u32).max(1);
+    let last = length - tile;
+    let mut positions: Vec<u32> = (0..last).step_by(step as usize).collect();
+    positions.push(last);
+    positions
+}
+
+/// Detect objects in an RGB888 frame tile by tile with `detect`, which runs the model on the
+/// features of one tile and returns boxes in model input coordinates
+///
+/// `stride` is the number of bytes per row, at least `width * 3`. Tiles are resized to the
+/// model's input with its resize mode. The result holds the merged boxes in frame coordinates,
+/// sorted by descending score.
+pub fn detect_tiled_rgb<F>(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    tiling: &Tiling,
+    mut detect: F,
+) -> Result<Vec<BoundingBox>, EimError>
+where
+    F: FnMut(&[f32]) -> Result<Vec<BoundingBox>, EimError>,
+{
+    profile_scope!("tiled_detection");
+    if tiling.tile_width == 0 || tiling.tile_height == 0 {
+        return Err(EimError::InvalidInput(format!(
+            "Tile size must not be empty, got {}x{}",
+            tiling.tile_width, tiling.tile_height
+        )));
+    }
+    let frame = RawFrame::new(bytes, width, height, stride, 3)?;
+    let mut regions = tiling.tiles(width, height);
+    if tiling.full_frame && regions.len() > 1 {
+        regions.push(Tile {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+    }
+
+    let mut boxes = Vec::new();
+    for tile in regions {
+        let crop = frame.crop(
+            tile.x as usize,
+            tile.y as usize,
+            tile.width as usize,
+            tile.height as usize,
+        );
+        let detections = detect(&crop.model_features()?)?;
+        boxes.extend(detections.iter().filter_map(|bb| to_frame(bb, &crop, tile)));
+    }
+    Ok(tiling.dedup.merge(boxes))
+}
+
+/// A box in model input coordinates mapped onto `tile`, clipped to it; `None` if nothing is
+/// left
+// The types of the metadata constants depend on the model, so the casts may be no-ops
+#[allow(clippy::unnecessary_cast)]
+fn to_frame(bb: &BoundingBox, crop: &RawFrame, tile: Tile) -> Option<BoundingBox> {
+    let placement = Placement::new(
+        crop.width(),
+        crop.height(),
+        EI_CLASSIFIER_INPUT_WIDTH as usize,
+        EI_CLASSIFIER_INPUT_HEIGHT as usize,
+        ResizeMode::model(),
+    );
+    let (left, top) = placement.frame_point(bb.x as f32, bb.y as f32);
+    let (right, bottom) =
+        placement.frame_point((bb.x + bb.width) as f32, (bb.y + bb.height) as f32);
+    let clip = |value: f32, size: u32| value.round().clamp(0.0, size as f32) as i32;
+    let (left, top) = (clip(left, tile.width), clip(top, tile.height));
+    let (right, bottom) = (clip(right, tile.width), clip(bottom, tile.height));
+    (right > left && bottom > top).then(|| BoundingBox {
+        label: bb.label.clone(),
+        value: bb.value,
+        x: tile.x as i32 + left,
+        y: tile.y as i32 + top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Greedy non-max suppression per label: keep the best box and drop every box of the same
+/// label overlapping a kept one by more than `iou_threshold` (see [`Dedup`])
+pub fn merge_detections(boxes: Vec<BoundingBox>, iou_threshold: f32) -> Vec<BoundingBox> {
+    Dedup::new(iou_threshold).merge(boxes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bbox;
+
+    #[test]
+    fn covers_the_frame_with_overlapping_tiles() {
+        let tiles = Tiling::new(100, 100).overlap(0.2).tiles(250, 90);
+        let xs: Vec<u32> = tiles.iter().map(|tile| tile.x).collect();
+        assert_eq!(xs, vec![0, 80, 150]);
+        assert!(tiles.iter().all(|tile| tile.y == 0 && tile.height == 90));
+        assert_eq!(Tiling::new(100, 100).tiles(100, 100).len(), 1);
+    }
+
+    #[test]
+    fn merges_duplicates_per_label() {
+        let merged = merge_detections(
+            vec![
+                bbox("car", 0.6, 102, 50, 20, 20),
+                bbox("car", 0.9, 100, 50, 20, 20),
+                bbox("person", 0.7, 100, 50, 20, 20),
+                bbox("car", 0.8, 300, 50, 20, 20),
+            ],
+            0.5,
+        );
+        assert_eq!(
+            merged,
+            vec![
+                bbox("car", 0.9, 100, 50, 20, 20),
+                bbox("car", 0.8, 300, 50, 20, 20),
+                bbox("person", 0.7, 100, 50, 20, 20),
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Export of detection timelines
+//!
+//! Writes [`Detection`]s, e.g. from [`EimModel::classify_long_audio`] or built from the results
+//! of consecutive video frames, in formats that review tools read directly: Audacity label
+//! tracks, SRT subtitles to play alongside a video, and JSON lines for scripts.
+//!
+//! [`EimModel::classify_long_audio`]: crate::runner_api::EimModel::classify_long_audio
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::runner_api::{Detection, LongAudioResult};
+
+/// Format of an exported timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineFormat {
+    /// Audacity label track: `start<TAB>end<TAB>label` per line, times in seconds
+    AudacityLabels,
+    /// SubRip subtitles, one cue per detection showing the label and score
+    Srt,
+    /// One JSON object per line with `label`, `start`, `end` (in seconds), `score` and `windows`
+    JsonLines,
+}
+
+impl TimelineFormat {
+    /// Format matching the extension of `path`: `.txt` for Audacity labels, `.srt`, and
+    /// `.jsonl` or `.ndjson` for JSON lines
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "txt" => Some(TimelineFormat::AudacityLabels),
+            "srt" => Some(TimelineFormat::Srt),
+            "jsonl" | "ndjson" => Some(TimelineFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// Write `detections` to `writer` in `format`
+pub fn write_timeline<W: Write>(
+    mut writer: W,
+    detections: &[Detection],
+    format: TimelineFormat,
+) -> io::Result<()> {
+    for (i, detection) in detections.iter().enumerate() {
+        match format {
+            TimelineFormat::AudacityLabels => writeln!(
+                writer,
+                "{:.6}\t{:.6}\t{}",
+                detection.start.as_secs_f64(),
+                detection.end.as_secs_f64(),
+                // Tabs and newlines would start a new field or label
+                detection.label.replace(['\t', '\n', '\r'], " ")
+            )?,
+            TimelineFormat::Srt => writeln!(
+                writer,
+                "{}\n{} --> {}\n{} ({:.2})\n",
+                i + 1,
+                srt_time(detection.start),
+                srt_time(detection.end),
+                detection.label,
+                detection.score
+            )?,
+            TimelineFormat::JsonLines => writeln!(
+                writer,
+                "{{\"label\":{},\"start\":{},\"end\":{},\"score\":{},\"windows\":{}}}",
+                json_string(&detection.label),
+                detection.start.as_secs_f64(),
+                detection.end.as_secs_f64(),
+                json_number(detection.score),
+                detection.windows
+            )?,
+        }
+    }
+    writer.flush()
+}
+
+/// Write `detections` to a file, in the format matching its extension
+pub fn save_timeline<P: AsRef<Path>>(path: P, detections: &[Detection]) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = TimelineFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{}: unknown timeline format, use .txt, .srt or .jsonl",
+                path.display()
+            ),
+        )
+    })?;
+    let file = std::fs::File::create(path)?;
+    write_timeline(io::BufWriter::new(file), detections, format)
+}
+
+impl LongAudioResult {
+    /// Write the detections to `writer` in `format`, see [`write_timeline`]
+    pub fn write_timeline<W: Write>(&self, writer: W, format: TimelineFormat) -> io::Result<()> {
+        write_timeline(writer, &self.detections, format)
+    }
+}
+
+/// `HH:MM:SS,mmm`
+fn srt_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// JSON has no NaN or infinity
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detections() -> Vec<Detection> {
+        vec![
+            Detection {
+                label: "dog".to_string(),
+                start: Duration::from_millis(500),
+                end: Duration::from_millis(2_250),
+                score: 0.875,
+                windows: 3,
+            },
+            Detection {
+                label: "door \"slam\"".to_string(),
+                start: Duration::from_secs(3_725),
+                end: Duration::from_millis(3_726_001),
+                score: 0.5,
+                windows: 1,
+            },
+        ]
+    }
+
+    fn export(format: TimelineFormat) -> String {
+        let mut out = Vec::new();
+        write_timeline(&mut out, &detections(), format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_every_format() {
+        assert_eq!(
+            export(TimelineFormat::AudacityLabels),
+            "0.500000\t2.250000\tdog\n3725.000000\t3726.001000\tdoor \"slam\"\n"
+        );
+        assert_eq!(
+            export(TimelineFormat::Srt),
+            "1\n00:00:00,500 --> 00:00:02,250\ndog (0.88)\n\n\
+             2\n01:02:05,000 --> 01:02:06,001\ndoor \"slam\" (0.50)\n\n"
+        );
+        assert_eq!(
+            export(TimelineFormat::JsonLines).lines().nth(1).unwrap(),
+            r#"{"label":"door \"slam\"","start":3725,"end":3726.001,"score":0.5,"windows":1}"#
+        );
+        assert_eq!(
+            TimelineFormat::from_path("events.SRT"),
+            Some(TimelineFormat::Srt)
+        );
+        assert_eq!(TimelineFormat::from_path("events.csv"), None);
+    }
+}
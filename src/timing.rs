@@ -0,0 +1,94 @@
+//! Units and clock of the SDK's timings
+//!
+//! The SDK times every stage of the impulse with its porting timer, `ei_read_timer_us`, and
+//! reports the results in `ei_impulse_result_timing_t` twice: in microseconds (`dsp_us`,
+//! `classification_us`, `anomaly_us`) and rounded down to whole milliseconds (`dsp`,
+//! `classification`, `anomaly`). The integer types of these fields depend on the SDK version,
+//! so [`from_micros`] and [`from_millis`] take any of them.
+//!
+//! By default the timer is `std::chrono::steady_clock`, which is monotonic and unaffected by
+//! changes to the system time. [`set_timer`] replaces it, e.g. with a hardware timer or an RTOS
+//! tick counter; bare-metal builds have no clock until one is set, and report all timings as 0.
+//!
+//! ```no_run
+//! use edge_impulse_ffi_rs::timing::{clock_source, set_timer, ClockSource};
+//!
+//! unsafe extern "C" fn cycle_counter_us() -> u64 {
+//!     // e.g. DWT->CYCCNT / (SystemCoreClock / 1_000_000)
+//!     0
+//! }
+//!
+//! unsafe { set_timer(Some(cycle_counter_us)) };
+//! assert_eq!(clock_source(), ClockSource::Custom);
+//! ```
+
+use core::time::Duration;
+
+use crate::bindings::{ei_ffi_clock_source, ei_ffi_set_timer_hook};
+
+/// Unit of the `*_us` timing fields and of the porting timer
+pub const MICROS_UNIT: Duration = Duration::from_micros(1);
+
+/// Unit of the millisecond timing fields (`dsp`, `classification`, `anomaly`)
+pub const MILLIS_UNIT: Duration = Duration::from_millis(1);
+
+/// Microseconds per millisecond, the ratio between the two kinds of fields
+pub const MICROS_PER_MILLI: u64 = 1000;
+
+/// Duration of a `*_us` timing field, of any integer type; negative values are 0
+pub fn from_micros<T: TryInto<u64>>(us: T) -> Duration {
+    Duration::from_micros(us.try_into().unwrap_or(0))
+}
+
+/// Duration of a millisecond timing field, of any integer type; negative values are 0
+pub fn from_millis<T: TryInto<u64>>(ms: T) -> Duration {
+    Duration::from_millis(ms.try_into().unwrap_or(0))
+}
+
+/// Clock behind the SDK's timings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClockSource {
+    /// `std::chrono::steady_clock`, the default with an operating system
+    Steady,
+    /// A timer set with [`set_timer`] or the `read_timer_us` porting hook
+    Custom,
+    /// No clock, on bare-metal targets without a timer: all timings are 0
+    None,
+}
+
+/// The clock the SDK currently reads its timings from
+pub fn clock_source() -> ClockSource {
+    match unsafe { ei_ffi_clock_source() } {
+        0 => ClockSource::Steady,
+        1 => ClockSource::Custom,
+        _ => ClockSource::None,
+    }
+}
+
+/// Time the SDK with `read_timer_us`, a monotonic clock in microseconds; `None` restores the
+/// default clock
+///
+/// Unlike `porting::set_porting_hooks`, this leaves the other porting hooks alone. Changing the
+/// timer while a classifier call runs skews that call's timings.
+///
+/// # Safety
+///
+/// `read_timer_us` is called from any thread that runs inference, and must not call back into
+/// the SDK.
+pub unsafe fn set_timer(read_timer_us: Option<unsafe extern "C" fn() -> u64>) {
+    ei_ffi_set_timer_hook(read_timer_us);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_fields_of_any_integer_type() {
+        assert_eq!(from_micros(1500i64), Duration::from_micros(1500));
+        assert_eq!(from_micros(1500u64), 1500 * MICROS_UNIT);
+        assert_eq!(from_micros(-1i64), Duration::ZERO);
+        assert_eq!(from_millis(3i32), 3 * MILLIS_UNIT);
+        assert_eq!(MILLIS_UNIT.as_micros(), u128::from(MICROS_PER_MILLI));
+    }
+}
@@ -0,0 +1,58 @@
+//! Runtime thresholds of the linked model's learning blocks
+//!
+//! Meant to run against a model with a K-means anomaly block (the other tests pass on any model):
+//!
+//! ```sh
+//! cargo test --test learn_block_thresholds
+//! ```
+//!
+//! K-means blocks have no threshold, so the SDK must refuse to read or write one instead of
+//! treating their config as another block type's.
+#![cfg(not(feature = "dummy"))]
+
+use edge_impulse_ffi_rs::model_metadata::{EI_ANOMALY_TYPE_KMEANS, EI_CLASSIFIER_HAS_ANOMALY};
+use edge_impulse_ffi_rs::thresholds::MODEL_THRESHOLDS;
+use edge_impulse_ffi_rs::{Classifier, EdgeImpulseError};
+
+/// Block IDs scanned for learning blocks that aren't in `MODEL_THRESHOLDS`
+const BLOCK_IDS: std::ops::Range<u32> = 0..4096;
+
+#[test]
+fn reads_the_thresholds_of_every_block() {
+    let classifier = Classifier::new();
+    let thresholds = classifier.thresholds().unwrap();
+    assert_eq!(thresholds.thresholds.len(), MODEL_THRESHOLDS.len());
+}
+
+#[test]
+fn rejects_thresholds_of_kmeans_blocks() {
+    if EI_CLASSIFIER_HAS_ANOMALY != EI_ANOMALY_TYPE_KMEANS {
+        eprintln!("skipped: the linked model has no K-means anomaly block");
+        return;
+    }
+    let mut classifier = Classifier::new();
+    let kmeans_blocks: Vec<u32> = BLOCK_IDS
+        .filter(|&id| !MODEL_THRESHOLDS.iter().any(|t| t.id as u32 == id))
+        .filter(|&id| {
+            matches!(
+                classifier.set_learn_block_threshold(id, 0.5),
+                Err(EdgeImpulseError::UnsupportedInferencingEngine)
+            )
+        })
+        .collect();
+    assert!(
+        !kmeans_blocks.is_empty(),
+        "the K-means block accepted a threshold"
+    );
+
+    // Object detection thresholds still round-trip next to the K-means block
+    for threshold in MODEL_THRESHOLDS {
+        let id = threshold.id as u32;
+        classifier.set_learn_block_threshold(id, 0.25).unwrap();
+        let read = classifier.thresholds().unwrap();
+        assert_eq!(read.get_threshold(threshold.id).unwrap().min_score, 0.25);
+        classifier
+            .set_learn_block_threshold(id, threshold.min_score)
+            .unwrap();
+    }
+}
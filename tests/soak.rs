@@ -0,0 +1,120 @@
+//! Long-running memory-leak regression tests
+//!
+//! Opt-in, as they run tens of thousands of inferences against the linked model:
+//!
+//! ```sh
+//! cargo test --release --features soak-tests --test soak -- --nocapture
+//! ```
+//!
+//! `EI_SOAK_ITERATIONS` overrides the number of inferences. Resident memory is sampled after a
+//! warm-up phase (so arenas and lazily allocated buffers are in place) and again at the end; any
+//! growth beyond a small tolerance means some path allocates per call without freeing.
+#![cfg(feature = "soak-tests")]
+
+use std::sync::Mutex;
+
+use edge_impulse_ffi_rs::model_metadata::EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE;
+use edge_impulse_ffi_rs::{Classifier, ImpulseHandle};
+
+const DEFAULT_ITERATIONS: usize = 20_000;
+const WARMUP_ITERATIONS: usize = 500;
+/// Allowed resident memory growth after warm-up
+const RSS_TOLERANCE_BYTES: usize = 2 * 1024 * 1024;
+
+/// RSS is process-wide, so the soak tests must not run concurrently
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn iterations() -> usize {
+    std::env::var("EI_SOAK_ITERATIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS)
+}
+
+/// Current resident set size in bytes
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size as usize)
+}
+
+/// Peak resident set size in bytes (growth of the peak still reveals leaks)
+#[cfg(target_os = "macos")]
+fn resident_bytes() -> Option<usize> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    Some(usage.ru_maxrss as usize)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn resident_bytes() -> Option<usize> {
+    None
+}
+
+/// Run `step` for the warm-up and soak phases and assert resident memory stays flat
+fn assert_stable_rss(name: &str, mut step: impl FnMut(usize)) {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let total = iterations();
+    for i in 0..WARMUP_ITERATIONS.min(total) {
+        step(i);
+    }
+    let Some(before) = resident_bytes() else {
+        eprintln!(
+            "{}: resident memory not available on this platform, only checking for crashes",
+            name
+        );
+        for i in WARMUP_ITERATIONS..total {
+            step(i);
+        }
+        return;
+    };
+
+    for i in WARMUP_ITERATIONS..total {
+        step(i);
+    }
+    let after = resident_bytes().expect("resident memory became unavailable");
+
+    let growth = after.saturating_sub(before);
+    eprintln!(
+        "{}: {} iterations, RSS {} KiB -> {} KiB (+{} KiB)",
+        name,
+        total,
+        before / 1024,
+        after / 1024,
+        growth / 1024
+    );
+    assert!(
+        growth <= RSS_TOLERANCE_BYTES,
+        "{}: resident memory grew by {} KiB over {} iterations",
+        name,
+        growth / 1024,
+        total - WARMUP_ITERATIONS
+    );
+}
+
+#[test]
+fn classifier_does_not_leak() {
+    let features = vec![0.0f32; EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE];
+    let mut classifier = Classifier::new();
+    assert_stable_rss("classifier", |i| {
+        classifier
+            .run(&features, false)
+            .unwrap_or_else(|e| panic!("inference {} failed: {}", i, e));
+    });
+}
+
+#[test]
+fn impulse_handles_do_not_leak() {
+    let features = vec![0.0f32; EI_CLASSIFIER_DSP_INPUT_FRAME_SIZE];
+    assert_stable_rss("impulse handle", |i| {
+        let mut handle =
+            ImpulseHandle::new().unwrap_or_else(|e| panic!("handle {} failed: {}", i, e));
+        handle
+            .run(&features, false)
+            .unwrap_or_else(|e| panic!("inference {} failed: {}", i, e));
+    });
+}